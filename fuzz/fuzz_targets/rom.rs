@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+use padme_core::{Rom, System};
+
+/// Smallest rom that can be loaded
+const MIN_ROM_SIZE: usize = 32 * 1024;
+/// Number of frames executed for each input
+const FRAMES: usize = 4;
+
+fuzz_target!(|data: &[u8]| {
+    let mut bin = data.to_vec();
+    if bin.len() < MIN_ROM_SIZE {
+        bin.resize(MIN_ROM_SIZE, 0);
+    }
+    // Unsupported cartridges are rejected with an error
+    if let Ok(rom) = Rom::load(bin) {
+        let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+        for _ in 0..FRAMES {
+            emu.update_frame();
+        }
+    }
+});
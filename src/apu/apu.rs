@@ -1,7 +1,7 @@
 use crate::cpu::CLOCK_SPEED;
 use crate::region::*;
 
-use super::{Channel1, Channel2, Channel3, Channel4};
+use super::{ApuState, Channel1, Channel2, Channel3, Channel4};
 use super::modulation::*;
 
 pub const AUDIO_SAMPLE_RATE: u32        = 48000; // Hz
@@ -17,8 +17,27 @@ const DEFAULT_REG_DMG_NR50: u8          = 0x77;
 const DEFAULT_REG_DMG_NR51: u8          = 0xF3;
 const DEFAULT_REG_DMG_NR52: u8          = 0xF1;
 
+/// Maximum number of ticks waiting to be synthesized in fast mode (~1 frame)
+const FAST_MODE_MAX_PENDING_TICKS: u32  = CLOCK_SPEED / 60;
+
+/// How samples are produced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioMode {
+    /// Channels are stepped on every tick
+    Accurate,
+    /// Ticks are accumulated and the samples are synthesized in batch, usually once per frame
+    /// The System synthesizes the pending ticks before an instruction that may access the sound registers,
+    /// so the samples match the accurate mode
+    Fast,
+}
+
 pub trait AudioSpeaker {
     fn set_samples(&mut self, left: f32, right: f32);
+    /// Same as set_samples with the cycle the samples were produced at, counted since the system was created
+    /// This can be implemented to align samples with frames (see System::cycle_count)
+    fn set_samples_at(&mut self, left: f32, right: f32, _cycle: u64) {
+        self.set_samples(left, right);
+    }
 }
 
 pub struct Apu {
@@ -57,6 +76,20 @@ pub struct Apu {
     channel_3: Channel3,
     /// Sound Channel 4 - Noise
     channel_4: Channel4,
+    /// Sample production mode
+    mode: AudioMode,
+    /// Ticks not synthesized yet in fast mode
+    pending_ticks: u32,
+    /// Number of ticks between 2 samples
+    sample_period: u32,
+    /// Ticks since the last sample
+    sample_ticks: u32,
+    /// Number of samples sent to the speaker (wraps around)
+    samples: u32,
+    /// Number of ticks synthesized
+    cycles: u64,
+    /// Factors applied to the left and right samples after mixing
+    gain: (f32, f32),
 }
 
 impl Apu {
@@ -71,9 +104,42 @@ impl Apu {
             channel_2: Channel2::new(),
             channel_3: Channel3::new(),
             channel_4: Channel4::new(),
+            mode: AudioMode::Accurate,
+            pending_ticks: 0,
+            sample_period: SAMPLE_PERIOD,
+            sample_ticks: 0,
+            samples: 0,
+            cycles: 0,
+            gain: (1.0, 1.0),
         }
     }
 
+    /// Restore the registers, frame sequencer and channels to their power on values
+    /// The sample mode, speed and gain are kept, pending ticks must be flushed in fast mode
+    pub fn reset(&mut self) {
+        self.reg_nr50 = DEFAULT_REG_DMG_NR50;
+        self.reg_nr51 = DEFAULT_REG_DMG_NR51;
+        self.reg_nr52 = DEFAULT_REG_DMG_NR52;
+        self.ticks = 0;
+        self.fs_step = 0;
+        self.channel_1 = Channel1::new();
+        self.channel_2 = Channel2::new();
+        self.channel_3 = Channel3::new();
+        self.channel_4 = Channel4::new();
+        self.pending_ticks = 0;
+    }
+
+    /// Clear NR10 - NR51 without going through the register writes side effects
+    /// The length counters and wave ram are kept like on DMG
+    fn power_off(&mut self) {
+        self.reg_nr50 = 0;
+        self.reg_nr51 = 0;
+        self.channel_1.power_off();
+        self.channel_2.power_off();
+        self.channel_3.power_off();
+        self.channel_4.power_off();
+    }
+
     #[inline]
     fn is_enabled(&self) -> bool {
         (self.reg_nr52 >> 7) != 0
@@ -90,7 +156,7 @@ impl Apu {
     }
 
     fn handle_fs_step(&mut self) {
-        let is_length_period = (self.fs_step % 2) == 0;
+        let is_length_period = self.fs_step.is_multiple_of(2);
         self.channel_1.set_half_length_period(is_length_period);
         self.channel_2.set_half_length_period(is_length_period);
         self.channel_3.set_half_length_period(is_length_period);
@@ -147,10 +213,131 @@ impl Apu {
         (sample * volume) / 4.0
     }
 
+    /// Retrieve a snapshot of the registers and channels
+    pub fn state(&self) -> ApuState {
+        ApuState {
+            enabled: self.is_enabled(),
+            nr50: self.reg_nr50,
+            nr51: self.reg_nr51,
+            frame_sequencer_step: self.fs_step,
+            channels: [
+                self.channel_1.state(),
+                self.channel_2.state(),
+                self.channel_3.state(),
+                self.channel_4.state(),
+            ],
+            wave_ram: self.channel_3.wave_ram(),
+            noise_lfsr: self.channel_4.lfsr(),
+        }
+    }
+
+    /// Seed the noise generator, pending ticks must be flushed in fast mode
+    pub fn set_noise_lfsr(&mut self, value: u16) {
+        self.channel_4.set_lfsr(value);
+    }
+
+    /// Select how samples are produced (default = Accurate)
+    pub fn set_mode<AS: AudioSpeaker>(&mut self, mode: AudioMode, speaker: &mut AS) {
+        self.flush(speaker);
+        self.mode = mode;
+    }
+
+    /// Retrieve how samples are produced
+    pub fn mode(&self) -> AudioMode {
+        self.mode
+    }
+
+    /// Produce samples at the audio sample rate when the emulation runs at a given speed
+    /// e.g. at 2x, twice as many ticks are emulated per second, so a sample is produced every 2 periods
+    pub fn set_speed(&mut self, speed: f32) {
+        self.sample_period = ((SAMPLE_PERIOD as f32 * speed) as u32).max(1);
+        self.sample_ticks %= self.sample_period;
+    }
+
+    /// Retrieve the number of ticks between 2 samples at the current speed
+    pub fn sample_period(&self) -> u32 {
+        self.sample_period
+    }
+
+    /// Scale the left and right samples, pending ticks must be flushed in fast mode
+    pub fn set_gain(&mut self, left: f32, right: f32) {
+        self.gain = (left, right);
+    }
+
+    pub fn gain(&self) -> (f32, f32) {
+        self.gain
+    }
+
+    /// Retrieve the number of samples produced so far (wraps around)
+    /// In fast mode, this includes the samples that the next flush will synthesize
+    pub fn sample_count(&self) -> u32 {
+        let pending = (self.sample_ticks + self.pending_ticks) / self.sample_period;
+        self.samples.wrapping_add(pending)
+    }
+
+    /// Retrieve the number of ticks since the creation
+    /// In fast mode, this includes the ticks that the next flush will synthesize
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles + self.pending_ticks as u64
+    }
+
+    /// Output the samples sent to the speaker
+    fn output_samples<AS: AudioSpeaker>(&mut self, speaker: &mut AS) {
+        let left_volume = self.volume_left();
+        let right_volume = self.volume_right();
+
+        let s02 = self.mix_channels(0x10, left_volume);
+        let s01 = self.mix_channels(0x01, right_volume);
+
+        speaker.set_samples_at(s02 * self.gain.0, s01 * self.gain.1, self.cycles);
+        self.samples = self.samples.wrapping_add(1);
+    }
+
+    /// Synthesize all the pending ticks in fast mode
+    /// This must be done before the registers are read or written, the pending ticks would use their new values
+    /// Channels are only stepped up to the next frame sequencer step or the next sample
+    pub fn flush<AS: AudioSpeaker>(&mut self, speaker: &mut AS) {
+        while self.pending_ticks > 0 {
+            let to_fs = FRAME_SEQUENCER_PERIOD - self.ticks % FRAME_SEQUENCER_PERIOD;
+            let to_sample = self.sample_period - self.sample_ticks;
+            let ticks = self.pending_ticks.min(to_fs).min(to_sample);
+
+            self.channel_3.age_fetch(ticks);
+
+            self.channel_1.step_by(ticks);
+            self.channel_2.step_by(ticks);
+            self.channel_3.step_by(ticks);
+            self.channel_4.step_by(ticks);
+
+            self.ticks = self.ticks.wrapping_add(ticks);
+            self.cycles += ticks as u64;
+            self.sample_ticks += ticks;
+            self.pending_ticks -= ticks;
+
+            if self.ticks.is_multiple_of(FRAME_SEQUENCER_PERIOD) {
+                self.handle_fs_step();
+            }
+            if self.sample_ticks == self.sample_period {
+                self.sample_ticks = 0;
+                self.output_samples(speaker);
+            }
+        }
+    }
+
     pub fn step<AS: AudioSpeaker>(&mut self, speaker: &mut AS) {
+        if self.mode == AudioMode::Fast {
+            self.pending_ticks += 1;
+            if self.pending_ticks >= FAST_MODE_MAX_PENDING_TICKS {
+                self.flush(speaker);
+            }
+            return;
+        }
+
         self.ticks = self.ticks.wrapping_add(1);
+        self.cycles += 1;
+        self.sample_ticks += 1;
 
-        self.channel_3.wave_just_read = false;
+        self.channel_3.age_fetch(1);
 
         self.channel_1.step();
         self.channel_2.step();
@@ -158,21 +345,15 @@ impl Apu {
         self.channel_4.step();
 
         // Every 8192 T-cycles, the frame sequencer is stepped
-        if self.ticks % FRAME_SEQUENCER_PERIOD == 0 {
+        if self.ticks.is_multiple_of(FRAME_SEQUENCER_PERIOD) {
             self.handle_fs_step();
         }
 
         // Every sample period, we can send the current sample to the speaker
         // It's up to the speaker to store an audio buffer and play it a regular interval
-        if self.ticks % SAMPLE_PERIOD == 0 {
-
-            let left_volume = self.volume_left();
-            let right_volume = self.volume_right();
-
-            let s02 = self.mix_channels(0x10, left_volume);
-            let s01 = self.mix_channels(0x01, right_volume);
-
-            speaker.set_samples(s02, s01);
+        if self.sample_ticks == self.sample_period {
+            self.sample_ticks = 0;
+            self.output_samples(speaker);
         }
     }
 }
@@ -222,13 +403,16 @@ impl MemoryRegion for Apu {
     }
 
     fn write(&mut self, address: u16, value: u8) {
-        if !self.is_enabled() && !(WAVE_PATTERN_RAM_START..=WAVE_PATTERN_RAM_START).contains(&address)
-            && address != REG_NR11_ADDR
-            && address != REG_NR21_ADDR
-            && address != REG_NR31_ADDR
-            && address != REG_NR41_ADDR
-            && address != REG_NR52_ADDR {
-                return;
+        if !self.is_enabled() {
+            // Only the length counters (DMG), wave ram and NR52 can be written while powered off
+            match address {
+                REG_NR11_ADDR => return self.channel_1.write_length(value),
+                REG_NR21_ADDR => return self.channel_2.write_length(value),
+                REG_NR31_ADDR => return self.channel_3.write_length(value),
+                REG_NR41_ADDR => return self.channel_4.write_length(value),
+                WAVE_PATTERN_RAM_START..=WAVE_PATTERN_RAM_END | REG_NR52_ADDR => (),
+                _ => return,
+            }
         }
         match address {
             REG_NR10_ADDR |
@@ -262,27 +446,327 @@ impl MemoryRegion for Apu {
             REG_NR51_ADDR => self.reg_nr51 = value,
             REG_NR52_ADDR => {
                 let enabled = is_set!(value, 0b1000_0000);
-                let len_ch1 = self.channel_1.length_counter();
-                let len_ch2 = self.channel_2.length_counter();
-                let len_ch3 = self.channel_3.length_counter();
-                let len_ch4 = self.channel_4.length_counter();
 
                 if enabled && !self.is_enabled() {
                     self.fs_step = 0;
                 } else if !enabled && self.is_enabled() {
-                    for addr in REG_NR10_ADDR..REG_NR52_ADDR {
-                        self.write(addr, 0x00);
-                    }
+                    self.power_off();
                 }
-                // restore old counters
-                self.channel_1.set_length_counter(len_ch1);
-                self.channel_2.set_length_counter(len_ch2);
-                self.channel_3.set_length_counter(len_ch3);
-                self.channel_4.set_length_counter(len_ch4);
-
                 self.reg_nr52 = value & 0x80
             },
             _ => (),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+
+    const SAMPLE_COUNT: usize = 2048;
+
+    struct TestSpeaker {
+        samples: [(f32, f32); SAMPLE_COUNT],
+        count: usize,
+    }
+
+    impl AudioSpeaker for TestSpeaker {
+        fn set_samples(&mut self, left: f32, right: f32) {
+            if self.count < SAMPLE_COUNT {
+                self.samples[self.count] = (left, right);
+                self.count += 1;
+            }
+        }
+    }
+
+    fn new_speaker() -> TestSpeaker {
+        TestSpeaker { samples: [(0.0, 0.0); SAMPLE_COUNT], count: 0 }
+    }
+
+    /// Tone on channel 2 with a decreasing envelope & noise on channel 4
+    fn new_apu() -> Apu {
+        let mut apu = Apu::new();
+        apu.write(REG_NR51_ADDR, 0xFF);
+        apu.write(REG_NR21_ADDR, 0x80);
+        apu.write(REG_NR22_ADDR, 0xF1);
+        apu.write(REG_NR23_ADDR, 0x40);
+        apu.write(REG_NR24_ADDR, 0x87);
+        apu.write(REG_NR42_ADDR, 0xA2);
+        apu.write(REG_NR43_ADDR, 0x21);
+        apu.write(REG_NR44_ADDR, 0x80);
+        apu
+    }
+
+    #[test]
+    fn it_synthesizes_the_same_samples_in_fast_mode() {
+        let ticks = SAMPLE_PERIOD * SAMPLE_COUNT as u32;
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        let mut fast_apu = new_apu();
+        let mut fast_speaker = new_speaker();
+        fast_apu.set_mode(AudioMode::Fast, &mut fast_speaker);
+
+        for _ in 0..ticks {
+            apu.step(&mut speaker);
+            fast_apu.step(&mut fast_speaker);
+        }
+        // Samples waiting to be synthesized are already counted
+        assert_eq!(fast_apu.sample_count(), apu.sample_count());
+        fast_apu.flush(&mut fast_speaker);
+
+        assert_eq!(speaker.count, SAMPLE_COUNT);
+        assert_eq!(fast_speaker.count, SAMPLE_COUNT);
+        assert!(speaker.samples.iter().any(|(left, _)| *left != speaker.samples[0].0));
+        assert!(speaker.samples == fast_speaker.samples);
+    }
+
+    #[test]
+    fn it_synthesizes_the_same_samples_in_fast_mode_with_writes_mid_run() {
+        // Retrigger channel 2 at another frequency, move it to the left, then power the APU off
+        let writes = [(REG_NR23_ADDR, 0x80), (REG_NR24_ADDR, 0x86), (REG_NR51_ADDR, 0xDD), (REG_NR52_ADDR, 0x00)];
+        let ticks = SAMPLE_PERIOD * (SAMPLE_COUNT / (writes.len() + 1)) as u32;
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        let mut fast_apu = new_apu();
+        let mut fast_speaker = new_speaker();
+        fast_apu.set_mode(AudioMode::Fast, &mut fast_speaker);
+
+        for (address, value) in writes {
+            for _ in 0..ticks {
+                apu.step(&mut speaker);
+                fast_apu.step(&mut fast_speaker);
+            }
+            // Done by the System before an instruction accessing the sound registers
+            fast_apu.flush(&mut fast_speaker);
+            assert_eq!(fast_apu.read(REG_NR52_ADDR), apu.read(REG_NR52_ADDR));
+            apu.write(address, value);
+            fast_apu.write(address, value);
+        }
+        for _ in 0..ticks {
+            apu.step(&mut speaker);
+            fast_apu.step(&mut fast_speaker);
+        }
+        fast_apu.flush(&mut fast_speaker);
+
+        assert_eq!(fast_speaker.count, speaker.count);
+        assert!(speaker.samples == fast_speaker.samples);
+    }
+
+    #[test]
+    fn it_produces_the_same_noise_from_the_same_seed() {
+        let mut speakers = [new_speaker(), new_speaker(), new_speaker()];
+        for (speaker, seed) in speakers.iter_mut().zip([0x1234, 0x1234, 0x4321]) {
+            let mut apu = new_apu();
+            // Only keep the noise channel
+            apu.write(REG_NR51_ADDR, 0x88);
+            apu.set_noise_lfsr(seed);
+            assert_eq!(apu.state().noise_lfsr, seed);
+            for _ in 0..(SAMPLE_PERIOD * SAMPLE_COUNT as u32) {
+                apu.step(speaker);
+            }
+        }
+
+        assert!(speakers[0].samples == speakers[1].samples);
+        assert!(speakers[0].samples != speakers[2].samples);
+    }
+
+    #[test]
+    fn it_applies_the_gain_after_mixing() {
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        let mut gain_apu = new_apu();
+        let mut gain_speaker = new_speaker();
+        gain_apu.set_gain(0.5, 0.0);
+
+        for _ in 0..(SAMPLE_PERIOD * SAMPLE_COUNT as u32) {
+            apu.step(&mut speaker);
+            gain_apu.step(&mut gain_speaker);
+        }
+
+        assert!(speaker.samples.iter().any(|(left, _)| *left != 0.0));
+        for ((left, _), (gain_left, gain_right)) in speaker.samples.iter().zip(gain_speaker.samples.iter()) {
+            assert_eq!(left * 0.5, *gain_left);
+            assert_eq!(*gain_right, 0.0);
+        }
+    }
+
+    #[test]
+    fn it_timestamps_samples_in_both_modes() {
+        struct TimedSpeaker {
+            cycles: [u64; 16],
+            count: usize,
+        }
+
+        impl AudioSpeaker for TimedSpeaker {
+            fn set_samples(&mut self, _left: f32, _right: f32) {
+                unreachable!();
+            }
+
+            fn set_samples_at(&mut self, _left: f32, _right: f32, cycle: u64) {
+                self.cycles[self.count] = cycle;
+                self.count += 1;
+            }
+        }
+
+        let ticks = SAMPLE_PERIOD * 16;
+        let mut apu = new_apu();
+        let mut speaker = TimedSpeaker { cycles: [0; 16], count: 0 };
+        let mut fast_apu = new_apu();
+        let mut fast_speaker = TimedSpeaker { cycles: [0; 16], count: 0 };
+        fast_apu.set_mode(AudioMode::Fast, &mut fast_speaker);
+
+        for _ in 0..ticks {
+            apu.step(&mut speaker);
+            fast_apu.step(&mut fast_speaker);
+        }
+        assert_eq!(fast_apu.cycle_count(), ticks as u64);
+        fast_apu.flush(&mut fast_speaker);
+
+        assert_eq!(speaker.cycles[0], SAMPLE_PERIOD as u64);
+        assert_eq!(speaker.cycles[15], ticks as u64);
+        assert_eq!(speaker.cycles, fast_speaker.cycles);
+    }
+
+    #[test]
+    fn it_silences_channels_on_reset() {
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        for _ in 0..(SAMPLE_PERIOD * 100) {
+            apu.step(&mut speaker);
+        }
+        assert_ne!(apu.state(), Apu::new().state());
+
+        apu.reset();
+        assert_eq!(apu.state(), Apu::new().state());
+        assert_eq!(apu.read(REG_NR52_ADDR), 0xF0);
+
+        speaker.count = 0;
+        for _ in 0..(SAMPLE_PERIOD * 100) {
+            apu.step(&mut speaker);
+        }
+        assert!(speaker.samples[..100].iter().all(|sample| *sample == speaker.samples[0]));
+    }
+
+    #[test]
+    fn it_clears_registers_on_power_off() {
+        let mut apu = new_apu();
+        apu.write(REG_NR11_ADDR, 0b1000_0000 | 20);
+        apu.write(REG_NR12_ADDR, 0xF3);
+        apu.write(REG_NR14_ADDR, 0x87);
+        apu.write(REG_NR50_ADDR, 0x77);
+
+        apu.write(REG_NR52_ADDR, 0x00);
+        assert_eq!(apu.read(REG_NR52_ADDR), 0x70);
+        assert_eq!(apu.read(REG_NR11_ADDR), 0x3F);
+        assert_eq!(apu.read(REG_NR12_ADDR), 0x00);
+        assert_eq!(apu.read(REG_NR50_ADDR), 0x00);
+        assert_eq!(apu.state().channels[0].length_counter, 44);
+
+        // Only the length and wave ram are writable while powered off
+        apu.write(REG_NR12_ADDR, 0xF3);
+        apu.write(REG_NR21_ADDR, 0b1100_0000 | 60);
+        apu.write(WAVE_PATTERN_RAM_END, 0x42);
+        assert_eq!(apu.read(REG_NR12_ADDR), 0x00);
+        assert_eq!(apu.read(REG_NR21_ADDR), 0x3F);
+        assert_eq!(apu.state().channels[1].length_counter, 4);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_END), 0x42);
+
+        apu.write(REG_NR52_ADDR, 0x80);
+        assert_eq!(apu.read(REG_NR52_ADDR), 0xF0);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_END), 0x42);
+    }
+
+    #[test]
+    fn it_reports_the_remaining_length_in_time() {
+        let mut apu = new_apu();
+        apu.write(REG_NR11_ADDR, 0b1000_0000 | 20);
+        apu.write(REG_NR12_ADDR, 0xF3);
+        apu.write(REG_NR14_ADDR, 0x87);
+        assert_eq!(apu.state().channels[0].length_remaining, None);
+
+        // 44 clocks of 1/256s
+        apu.write(REG_NR14_ADDR, 0xC7);
+        let channel = apu.state().channels[0];
+        assert_eq!(channel.length_counter, 44);
+        assert_eq!(channel.length_remaining, Some(Duration::from_micros(171_875)));
+        assert_eq!(channel.frequency, 131_072);
+    }
+
+    #[test]
+    fn it_clocks_length_on_trigger_in_the_first_half_period() {
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        // The next frame sequencer step does not clock the length
+        for _ in 0..FRAME_SEQUENCER_PERIOD {
+            apu.step(&mut speaker);
+        }
+
+        // The DAC is off: the length is still clocked and reloaded but the channel stays off
+        apu.write(REG_NR12_ADDR, 0x00);
+        apu.write(REG_NR11_ADDR, 63);
+        apu.write(REG_NR14_ADDR, 0xC0);
+        assert!(!apu.state().channels[0].enabled);
+        assert_eq!(apu.state().channels[0].length_counter, 63);
+
+        // Enabling the length clocks it down to 0 and disables the channel
+        apu.write(REG_NR30_ADDR, 0x80);
+        apu.write(REG_NR31_ADDR, 0xFF);
+        apu.write(REG_NR34_ADDR, 0x80);
+        assert!(apu.state().channels[2].enabled);
+        apu.write(REG_NR34_ADDR, 0x40);
+        assert!(!apu.state().channels[2].enabled);
+
+        // Triggering reloads the maximum minus the extra clock
+        apu.write(REG_NR34_ADDR, 0xC0);
+        assert!(apu.state().channels[2].enabled);
+        assert_eq!(apu.state().channels[2].length_counter, 255);
+    }
+
+    #[test]
+    fn it_only_reaches_wave_ram_right_after_a_fetch() {
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        for (i, address) in (WAVE_PATTERN_RAM_START..=WAVE_PATTERN_RAM_END).enumerate() {
+            apu.write(address, i as u8);
+        }
+        apu.write(REG_NR30_ADDR, 0x80);
+        apu.write(REG_NR33_ADDR, 0x00);
+        // 512 ticks period, the first fetch is delayed by the trigger
+        apu.write(REG_NR34_ADDR, 0x87);
+
+        for _ in 0..517 {
+            apu.step(&mut speaker);
+        }
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_START + 5), 0xFF);
+
+        // Sample 1 is fetched from the first byte, whatever the address is
+        apu.step(&mut speaker);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_START + 5), 0x00);
+        apu.step(&mut speaker);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_START + 5), 0x00);
+        apu.step(&mut speaker);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_START + 5), 0xFF);
+
+        // Writes also land in the byte being fetched, the second one for sample 2
+        for _ in 0..510 {
+            apu.step(&mut speaker);
+        }
+        apu.write(WAVE_PATTERN_RAM_START, 0x42);
+        assert_eq!(apu.read(WAVE_PATTERN_RAM_START + 1), 0x42);
+    }
+
+    #[test]
+    fn it_produces_samples_at_the_same_rate_at_any_speed() {
+        let mut apu = new_apu();
+        let mut speaker = new_speaker();
+        apu.set_speed(4.0);
+
+        // 4x more ticks are emulated in the same amount of time
+        for _ in 0..(SAMPLE_PERIOD * 4 * 100) {
+            apu.step(&mut speaker);
+        }
+        assert_eq!(speaker.count, 100);
+    }
+}
@@ -1,5 +1,8 @@
+use crate::cpu::CLOCK_SPEED;
 use crate::region::*;
 
+use super::state::{ChannelState, length_remaining};
+use super::channel_core::ChannelCore;
 use super::modulation::*;
 
 //
@@ -11,8 +14,34 @@ const DEFAULT_REG_DMG_NR32: u8          = 0x9F;
 const DEFAULT_REG_DMG_NR33: u8          = 0xFF;
 const DEFAULT_REG_DMG_NR34: u8          = 0xBF;
 
+/// Ticks (3 APU cycles) between a trigger and the first period of the channel
+const TRIGGER_DELAY: u32                = 6;
+/// Ticks (1 APU cycle) during which the CPU can access the wave ram byte just fetched
+const ACCESS_WINDOW: u8                 = 2;
+
+/// Position in wave ram
+pub struct WaveTable {
+    /// Wave cursor position in ram
+    cursor: u8,
+    /// Last wave ram byte fetched, it is not refilled on trigger
+    sample_buffer: u8,
+    /// Ticks since the last wave ram fetch
+    fetch_age: u8,
+}
+
+impl Default for WaveTable {
+    fn default() -> Self {
+        Self {
+            cursor: 0,
+            sample_buffer: 0,
+            fetch_age: ACCESS_WINDOW,
+        }
+    }
+}
+
 pub struct Channel3 {
-    enabled: bool,
+    /// Length and wave ram position state
+    core: ChannelCore<(), (), WaveTable>,
     /// Bit 7  : Sound Channel 3 Off  (0=Stop, 1=Playback) (Read/Write)
     reg_nr30: u8,
     /// Bit 7-0: Sound length (Write only) (t1: 0 - 255)
@@ -29,38 +58,20 @@ pub struct Channel3 {
     /// Bit 6  : Counter/consecutive selection (Read/Write)
     /// Bit 2-0: Frequency's higher 3 bits (x) (Write Only)
     reg_nr34: u8,
-    /// Length counter between 0 and 64
-    length_counter: u16,
-    /// Length period is half
-    length_half_period: bool,
-    /// Frequency timer between 4 and 8192
-    frequency_timer: u16,
-    /// Wave cursor position in ram
-    wave_cursor: u8,
     /// Wave ram as 32 x 4bits
     wave_ram: [u8; 16],
-    /// Current wave sample taken from the wave ram
-    current_wave_sample: u8,
-    /// DMG needs can only reads wave after a few apu cycles
-    pub wave_just_read: bool,
 }
 
 impl Channel3 {
     pub fn new() -> Self {
         Self {
-            enabled: false,
+            core: ChannelCore::new(256),
             reg_nr30: DEFAULT_REG_DMG_NR30,
             reg_nr31: DEFAULT_REG_DMG_NR31,
             reg_nr32: DEFAULT_REG_DMG_NR32,
             reg_nr33: DEFAULT_REG_DMG_NR33,
             reg_nr34: DEFAULT_REG_DMG_NR34,
-            length_counter: 256,
-            length_half_period: false,
-            frequency_timer: 4,
-            wave_cursor: 0,
             wave_ram: [0; 16],
-            current_wave_sample: 0,
-            wave_just_read: false,
         }
     }
 
@@ -68,35 +79,87 @@ impl Channel3 {
     fn output_level(&self) -> u8 {
         (self.reg_nr32 >> 5) & 0b0000_0011
     }
+
+    /// Clear all the registers without side effects when the APU is powered off
+    /// Wave RAM and the length counter are kept
+    pub fn power_off(&mut self) {
+        self.core.power_off();
+        self.reg_nr30 = 0;
+        self.reg_nr31 = 0;
+        self.reg_nr32 = 0;
+        self.reg_nr33 = 0;
+        self.reg_nr34 = 0;
+    }
+
+    pub fn state(&self) -> ChannelState {
+        ChannelState {
+            enabled: self.core.enabled,
+            dac_enabled: self.is_dac_enabled(),
+            frequency: CLOCK_SPEED / (64 * (0x800 - self.frequency())),
+            volume: match self.output_level() {
+                0 => 0,
+                level => 0xF >> (level - 1),
+            },
+            length_counter: self.core.length_counter,
+            length_enabled: self.is_length_enabled(),
+            length_remaining: length_remaining(self.core.enabled, self.is_length_enabled(), self.core.length_counter),
+            registers: [self.reg_nr30, self.reg_nr31, self.reg_nr32, self.reg_nr33, self.reg_nr34],
+        }
+    }
+
+    /// Age the last wave ram fetch by the given number of ticks
+    pub fn age_fetch(&mut self, ticks: u32) {
+        let wave = &mut self.core.wave;
+        wave.fetch_age = (wave.fetch_age as u32 + ticks).min(ACCESS_WINDOW as u32) as u8;
+    }
+
+    /// While the channel plays, the CPU only reaches the byte being fetched and only
+    /// within the APU cycle of the fetch
+    #[inline]
+    fn wave_ram_index(&self, address: u16) -> Option<usize> {
+        if !self.core.enabled {
+            Some((address - WAVE_PATTERN_RAM_START) as usize)
+        } else if self.core.wave.fetch_age < ACCESS_WINDOW {
+            Some((self.core.wave.cursor / 2) as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve the wave pattern ram
+    pub fn wave_ram(&self) -> [u8; 16] {
+        self.wave_ram
+    }
 }
 
-impl Channel for Channel3 {
-    fn is_enabled(&self) -> bool {
-        self.enabled
+impl HasCore for Channel3 {
+    type Envelope = ();
+    type Sweep = ();
+    type Wave = WaveTable;
+
+    fn core(&self) -> &ChannelCore<(), (), WaveTable> {
+        &self.core
     }
 
-    fn set_enabled(&mut self, enabled: bool) {
-        self.enabled = enabled;
+    fn core_mut(&mut self) -> &mut ChannelCore<(), (), WaveTable> {
+        &mut self.core
     }
+}
 
+impl Channel for Channel3 {
     fn is_dac_enabled(&self) -> bool {
         is_set!(self.reg_nr30, 0b1000_0000)
     }
 
     fn trigger(&mut self) {
         if self.is_dac_enabled() {
-            self.enabled = true;
-        }
-        if self.length_counter == 0 {
-            self.set_length_counter(256);
-            if self.is_length_enabled() && self.length_half_period {
-                self.length_counter -= 1;
-            }
+            self.core.enabled = true;
         }
+        self.reload_length();
+        // The position goes back to 0 but the sample buffer keeps the last fetched byte
+        // until the first period ends and sample 1 is fetched
         self.reset_frequency_timer();
-        // Not my proudest hack, it just seems that adding 6 ticks helps syncing
-        // wave_just_read to be set at the right time
-        self.frequency_timer += 6;
+        self.core.frequency_timer += TRIGGER_DELAY;
         self.reset_wave();
     }
 }
@@ -106,14 +169,6 @@ impl Clock for Channel3 {
         (((self.reg_nr34 & 0b0000_0111) as u32) << 8) | (self.reg_nr33 as u32)
     }
 
-    fn frequency_timer(&self) -> u32 {
-        self.frequency_timer as u32
-    }
-
-    fn set_frequency_timer(&mut self, value: u32) {
-        self.frequency_timer = value as u16;
-    }
-
     fn reset_frequency_timer(&mut self) {
         let timer = (0x800 - self.frequency()) * 2;
         self.set_frequency_timer(timer);
@@ -121,36 +176,27 @@ impl Clock for Channel3 {
 }
 
 impl LengthModulation for Channel3 {
+    const MAX_LENGTH: u16 = 256;
+
     fn is_length_enabled(&self) -> bool {
         is_set!(self.reg_nr34, 0b0100_0000)
     }
-
-    fn length_counter(&self) -> u16 {
-        self.length_counter
-    }
-
-    fn set_length_counter(&mut self, value: u16) {
-        self.length_counter = value;
-    }
-
-    fn set_half_length_period(&mut self, half: bool) {
-        self.length_half_period = half;
-    }
 }
 
 impl WaveModulation for Channel3 {
     fn wave_cursor(&self) -> u8 {
-        self.wave_cursor
+        self.core.wave.cursor
     }
 
     fn set_wave_cursor(&mut self, value: u8) {
-        self.wave_cursor = value;
+        self.core.wave.cursor = value;
     }
 
     fn inc_wave_cursor(&mut self) {
-        self.wave_cursor = (self.wave_cursor + 1) % 32;
-        self.current_wave_sample = self.wave_ram[(self.wave_cursor / 2) as usize];
-        self.wave_just_read = true;
+        let wave = &mut self.core.wave;
+        wave.cursor = (wave.cursor + 1) % 32;
+        wave.sample_buffer = self.wave_ram[(wave.cursor / 2) as usize];
+        wave.fetch_age = 0;
     }
 
     fn wave_duty(&self) -> u8 {
@@ -158,17 +204,19 @@ impl WaveModulation for Channel3 {
     }
 
     fn wave_sample(&self) -> u8 {
-        if self.wave_cursor & 0x1 == 0x1 {
-            self.current_wave_sample >> 4
+        // Upper nibble first
+        let wave = &self.core.wave;
+        if wave.cursor & 0x1 == 0x0 {
+            wave.sample_buffer >> 4
         } else {
-            self.current_wave_sample & 0xF
+            wave.sample_buffer & 0xF
         }
     }
 }
 
 impl DigitalAmplitude for Channel3 {
     fn digital_amplitude(&self) -> u8 {
-        let sample = self.wave_sample() as u8;
+        let sample = self.wave_sample();
         let volume_shift = match self.output_level() {
             0x00 => 4,
             0x01 => 0,
@@ -190,14 +238,9 @@ impl MemoryRegion for Channel3 {
             REG_NR33_ADDR => 0xFF,
             REG_NR34_ADDR => self.reg_nr34 | 0b1011_1111,
             WAVE_PATTERN_RAM_START..=WAVE_PATTERN_RAM_END => {
-                if self.enabled {
-                    if !self.wave_just_read {
-                        0xFF
-                    } else {
-                        self.wave_ram[(self.wave_cursor / 2) as usize]
-                    }
-                } else {
-                    self.wave_ram[(address - WAVE_PATTERN_RAM_START) as usize]
+                match self.wave_ram_index(address) {
+                    Some(index) => self.wave_ram[index],
+                    None => 0xFF,
                 }
             },
             _ => unreachable!(),
@@ -209,40 +252,26 @@ impl MemoryRegion for Channel3 {
             REG_NR30_ADDR => {
                 self.reg_nr30 = value;
                 if !self.is_dac_enabled() {
-                    self.enabled = false;
+                    self.core.enabled = false;
                 }
             },
             REG_NR31_ADDR => {
-                self.set_length_counter(256 - (value as u16));
+                self.write_length(value);
                 self.reg_nr31 = value;
             },
             REG_NR32_ADDR => self.reg_nr32 = value,
             REG_NR33_ADDR => self.reg_nr33 = value,
             REG_NR34_ADDR => {
-                let trigger = is_set!(value, 0b1000_0000);
-                let length_enabled = is_set!(value, 0b0100_0000);
-
-                if self.length_half_period && !self.is_length_enabled() && length_enabled && self.length_counter > 0 {
-                    self.length_counter -= 1;
-
-                    if self.length_counter == 0 {
-                        self.enabled = false;
-                    }
-                }
-
+                self.enable_length(is_set!(value, 0b0100_0000));
                 self.reg_nr34 = value;
                 // trigger a channel restart
-                if trigger {
+                if is_set!(value, 0b1000_0000) {
                     self.trigger();
                 }
             },
             WAVE_PATTERN_RAM_START..=WAVE_PATTERN_RAM_END => {
-                if self.enabled {
-                    if self.wave_just_read {
-                        self.wave_ram[(self.wave_cursor / 2) as usize] = value
-                    }
-                } else {
-                    self.wave_ram[(address - WAVE_PATTERN_RAM_START) as usize] = value
+                if let Some(index) = self.wave_ram_index(address) {
+                    self.wave_ram[index] = value;
                 }
             },
             _ => unreachable!(),
@@ -1,5 +1,8 @@
+use crate::cpu::CLOCK_SPEED;
 use crate::region::*;
 
+use super::state::{ChannelState, length_remaining};
+use super::channel_core::{ChannelCore, Envelope};
 use super::modulation::*;
 
 //
@@ -10,8 +13,28 @@ const DEFAULT_REG_DMG_NR42: u8          = 0x00;
 const DEFAULT_REG_DMG_NR43: u8          = 0xFF;
 const DEFAULT_REG_DMG_NR44: u8          = 0xBF;
 
+/// Noise generator state
+#[derive(Default)]
+pub struct Noise {
+    /// Linear feedback shift register (15 bits)
+    lfsr: u16,
+}
+
+impl Noise {
+    /// Shift the LFSR once
+    fn clock(&mut self, width_mode: bool) {
+        let x = (self.lfsr & 0b01) ^ ((self.lfsr >> 1) & 0b01);
+        self.lfsr = (self.lfsr >> 1) | (x << 14);
+        if width_mode {
+            // Set bit 6
+            self.lfsr = (self.lfsr & !(1 << 6)) | (x << 6);
+        }
+    }
+}
+
 pub struct Channel4 {
-    enabled: bool,
+    /// Length, envelope and noise state
+    core: ChannelCore<Envelope, (), Noise>,
     /// Bit 5-0: Sound length
     reg_nr41: u8,
     /// Bit 7-4: Initial Volume of envelope (0-0Fh) (0=No Sound)
@@ -26,34 +49,19 @@ pub struct Channel4 {
     /// Bit 7  : Initial (1=Restart Sound)
     /// Bit 6  : Counter/consecutive selection (Read/Write)
     reg_nr44: u8,
-    /// Volume between 0x0 and 0xF
-    current_volume: u8,
-    /// Envelope Period timer
-    envelope_timer: u8,
-    /// Frequency timer between 4 and 8192
-    frequency_timer: u32,
-    /// Length counter between 0 and 64
-    length_counter: u8,
-    /// Length period is half
-    length_half_period: bool,
-    /// Linear feedback shift register (15 bits)
-    lfsr: u16,
 }
 
 impl Channel4 {
     pub fn new() -> Self {
         Self {
-            enabled: false,
+            core: ChannelCore {
+                envelope: Envelope::new(DEFAULT_REG_DMG_NR42),
+                ..ChannelCore::new(64)
+            },
             reg_nr41: DEFAULT_REG_DMG_NR41,
             reg_nr42: DEFAULT_REG_DMG_NR42,
             reg_nr43: DEFAULT_REG_DMG_NR43,
             reg_nr44: DEFAULT_REG_DMG_NR44,
-            current_volume: DEFAULT_REG_DMG_NR42 >> 4,
-            envelope_timer: DEFAULT_REG_DMG_NR42 & 0b111,
-            frequency_timer: 4,
-            length_counter: 64,
-            length_half_period: false,
-            lfsr: 0,
         }
     }
 
@@ -71,33 +79,58 @@ impl Channel4 {
     fn divisor_code(&self) -> u8 {
         self.reg_nr43 & 0b0000_0111
     }
+
+    /// Clear all the registers without side effects when the APU is powered off
+    /// The length counter is kept
+    pub fn power_off(&mut self) {
+        self.core.power_off();
+        self.reg_nr41 = 0;
+        self.reg_nr42 = 0;
+        self.reg_nr43 = 0;
+        self.reg_nr44 = 0;
+    }
+
+    pub fn state(&self) -> ChannelState {
+        ChannelState {
+            enabled: self.core.enabled,
+            dac_enabled: self.is_dac_enabled(),
+            frequency: CLOCK_SPEED / self.frequency(),
+            volume: self.core.envelope.volume,
+            length_counter: self.core.length_counter,
+            length_enabled: self.is_length_enabled(),
+            length_remaining: length_remaining(self.core.enabled, self.is_length_enabled(), self.core.length_counter),
+            registers: [0xFF, self.reg_nr41, self.reg_nr42, self.reg_nr43, self.reg_nr44],
+        }
+    }
 }
 
-impl Channel for Channel4 {
-    fn is_enabled(&self) -> bool {
-        self.enabled
+impl HasCore for Channel4 {
+    type Envelope = Envelope;
+    type Sweep = ();
+    type Wave = Noise;
+
+    fn core(&self) -> &ChannelCore<Envelope, (), Noise> {
+        &self.core
     }
 
-    fn set_enabled(&mut self, enabled: bool) {
-        self.enabled = enabled;
+    fn core_mut(&mut self) -> &mut ChannelCore<Envelope, (), Noise> {
+        &mut self.core
     }
+}
 
+impl Channel for Channel4 {
     fn is_dac_enabled(&self) -> bool {
         (self.reg_nr42 & 0b1111_1000) != 0
     }
 
     fn trigger(&mut self) {
         if self.is_dac_enabled() {
-            self.enabled = true;
-        }
-        if self.length_counter == 0 {
-            self.set_length_counter(64);
-            if self.is_length_enabled() && self.length_half_period {
-                self.length_counter -= 1;
-            }
+            self.core.enabled = true;
         }
+        self.reload_length();
+        self.reset_frequency_timer();
         self.reset_envelope();
-        self.lfsr = 0x7fff;
+        self.core.wave.lfsr = 0x7fff;
     }
 }
 
@@ -123,40 +156,57 @@ impl Clock for Channel4 {
         divisor << shift
     }
 
-    fn frequency_timer(&self) -> u32 {
-        self.frequency_timer
+    fn reset_frequency_timer(&mut self) {
+        let new_frequency = self.frequency();
+        self.set_frequency_timer(new_frequency);
+    }
+}
+
+impl Channel4 {
+    /// Retrieve the linear feedback shift register (15 bits)
+    pub fn lfsr(&self) -> u16 {
+        self.core.wave.lfsr
     }
 
-    fn set_frequency_timer(&mut self, value: u32) {
-        self.frequency_timer = value;
+    /// Replace the linear feedback shift register, it is set to 0x7FFF again on trigger
+    pub fn set_lfsr(&mut self, value: u16) {
+        self.core.wave.lfsr = value & 0x7FFF;
     }
 
-    fn reset_frequency_timer(&mut self) {
-        let new_frequency = self.frequency();
-        self.set_frequency_timer(new_frequency);
+    /// Shift the LFSR once
+    fn clock_lfsr(&mut self) {
+        let width_mode = self.is_width_mode_set();
+        self.core.wave.clock(width_mode);
     }
 }
 
 impl Step for Channel4 {
     fn step(&mut self) {
-        if self.frequency_timer == 0 {
+        if self.core.frequency_timer == 0 {
             // frequency is 11 bits large = 0x7FF
             self.reset_frequency_timer();
-            let x = (self.lfsr & 0b01) ^ ((self.lfsr >> 1) & 0b01);
-            self.lfsr = (self.lfsr >> 1) | (x << 14);
-            if self.is_width_mode_set() {
-                // Set bit 6
-                self.lfsr = (self.lfsr & !(1 << 6)) | (x << 6);
-            }
+            self.clock_lfsr();
         }
 
-        self.frequency_timer -= 1;
+        self.core.frequency_timer -= 1;
+    }
+
+    fn step_by(&mut self, ticks: u32) {
+        let mut ticks = ticks;
+
+        while ticks > self.core.frequency_timer {
+            ticks -= self.core.frequency_timer + 1;
+            self.reset_frequency_timer();
+            self.clock_lfsr();
+            self.core.frequency_timer -= 1;
+        }
+        self.core.frequency_timer -= ticks;
     }
 }
 
 impl Sample for Channel4 {
     fn sample(&self) -> u8 {
-        (!self.lfsr & 0x01) as u8
+        (!self.core.wave.lfsr & 0x01) as u8
     }
 }
 
@@ -164,36 +214,14 @@ impl EnvelopeModulation for Channel4 {
     fn envelope_register(&self) -> u8 {
         self.reg_nr42
     }
-
-    fn envelope_timer(&mut self) -> &mut u8 {
-        &mut self.envelope_timer
-    }
-
-    fn envelope_volume(&self) -> u8 {
-        self.current_volume
-    }
-
-    fn set_envelope_volume(&mut self, value: u8) {
-        self.current_volume = value;
-    }
 }
 
 impl LengthModulation for Channel4 {
+    const MAX_LENGTH: u16 = 64;
+
     fn is_length_enabled(&self) -> bool {
         is_set!(self.reg_nr44, 0b0100_0000)
     }
-
-    fn length_counter(&self) -> u16 {
-        self.length_counter as u16
-    }
-
-    fn set_length_counter(&mut self, value: u16) {
-        self.length_counter = value as u8;
-    }
-
-    fn set_half_length_period(&mut self, half: bool) {
-        self.length_half_period = half;
-    }
 }
 
 impl MemoryRegion for Channel4 {
@@ -210,29 +238,21 @@ impl MemoryRegion for Channel4 {
     fn write(&mut self, address: u16, value: u8) {
         match address {
             REG_NR41_ADDR => {
-                self.length_counter = 64 - (value & 0b0011_1111);
+                self.write_length(value);
                 self.reg_nr41 = value
             },
             REG_NR42_ADDR => {
                 self.reg_nr42 = value;
                 if !self.is_dac_enabled() {
-                    self.enabled = false;
+                    self.core.enabled = false;
                 }
             },
             REG_NR43_ADDR => self.reg_nr43 = value,
             REG_NR44_ADDR => {
-                let trigger = is_set!(value, 0b1000_0000);
-                let length_enabled = is_set!(value, 0b0100_0000);
-
-                if self.length_half_period && !self.is_length_enabled() && length_enabled && self.length_counter > 0 {
-                    self.length_counter -= 1;
-                    if self.length_counter == 0 {
-                        self.enabled = false;
-                    }
-                }
+                self.enable_length(is_set!(value, 0b0100_0000));
                 self.reg_nr44 = value;
                 // trigger a channel restart
-                if trigger {
+                if is_set!(value, 0b1000_0000) {
                     self.trigger();
                 }
             },
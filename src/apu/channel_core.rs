@@ -0,0 +1,75 @@
+/// Volume envelope of the pulse and noise channels
+#[derive(Default)]
+pub struct Envelope {
+    /// Volume between 0x0 and 0xF
+    pub volume: u8,
+    /// Envelope Period timer
+    pub timer: u8,
+}
+
+impl Envelope {
+    /// Envelope as set by a trigger with this NRx2 value
+    pub fn new(register: u8) -> Self {
+        Self {
+            volume: register >> 4,
+            timer: register & 0b111,
+        }
+    }
+}
+
+/// Frequency sweep of channel 1
+#[derive(Default)]
+pub struct Sweep {
+    /// Sweep timer
+    pub timer: u8,
+    /// Sweep shadow frequency
+    pub shadow_frequency: u16,
+    /// Whether sweep is enabled
+    pub enabled: bool,
+    /// Fix issues with neg -> pos sweep
+    pub was_decreasing: bool,
+}
+
+/// Position in the duty pattern of a pulse channel
+#[derive(Default)]
+pub struct Duty {
+    /// Wave cursor position
+    pub cursor: u8,
+}
+
+/// State shared by the four channels
+///
+/// Env, Sweep and Wave hold the state of the units that differ between channels,
+/// () is used when a channel does not have one.
+pub struct ChannelCore<Env, Sweep, Wave> {
+    /// Whether this channel is enabled or not
+    pub enabled: bool,
+    /// Length counter between 0 and the maximum length of the channel
+    pub length_counter: u16,
+    /// Length period is half
+    pub length_half_period: bool,
+    /// Ticks before the next step of the waveform
+    pub frequency_timer: u32,
+    pub envelope: Env,
+    pub sweep: Sweep,
+    pub wave: Wave,
+}
+
+impl<Env: Default, Sweep: Default, Wave: Default> ChannelCore<Env, Sweep, Wave> {
+    pub fn new(length_counter: u16) -> Self {
+        Self {
+            enabled: false,
+            length_counter,
+            length_half_period: false,
+            frequency_timer: 4,
+            envelope: Env::default(),
+            sweep: Sweep::default(),
+            wave: Wave::default(),
+        }
+    }
+
+    /// Clear everything but the length counter when the APU is powered off
+    pub fn power_off(&mut self) {
+        *self = Self::new(self.length_counter);
+    }
+}
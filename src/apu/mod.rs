@@ -3,11 +3,14 @@ mod channel1;
 mod channel2;
 mod channel3;
 mod channel4;
+mod channel_core;
 mod modulation;
+mod state;
 
 use channel1::Channel1;
 use channel2::Channel2;
 use channel3::Channel3;
 use channel4::Channel4;
 
-pub use apu::{AUDIO_SAMPLE_RATE, Apu, AudioSpeaker};
+pub use apu::{AUDIO_SAMPLE_RATE, Apu, AudioMode, AudioSpeaker};
+pub use state::{ApuState, ChannelState};
@@ -1,3 +1,5 @@
+use super::channel_core::{ChannelCore, Envelope, Sweep};
+
 //
 // All wave duty patterns
 //
@@ -20,12 +22,27 @@ pub trait DigitalAmplitude {
 //     fn step(&mut self);
 // }
 
-pub trait Clock {
+/// Access to the state shared by all the channels
+pub trait HasCore {
+    type Envelope;
+    type Sweep;
+    type Wave;
+
+    fn core(&self) -> &ChannelCore<Self::Envelope, Self::Sweep, Self::Wave>;
+
+    fn core_mut(&mut self) -> &mut ChannelCore<Self::Envelope, Self::Sweep, Self::Wave>;
+}
+
+pub trait Clock: HasCore {
     fn frequency(&self) -> u32;
 
-    fn frequency_timer(&self) -> u32;
+    fn frequency_timer(&self) -> u32 {
+        self.core().frequency_timer
+    }
 
-    fn set_frequency_timer(&mut self, value: u32);
+    fn set_frequency_timer(&mut self, value: u32) {
+        self.core_mut().frequency_timer = value;
+    }
 
     fn set_frequency(&mut self, _value: u32) {
     }
@@ -42,12 +59,23 @@ pub trait Sample {
 
 pub trait Step {
     fn step(&mut self);
+
+    /// Same as calling step several times
+    fn step_by(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
 }
 
 pub trait Channel: DigitalAmplitude + Clock + Sample + Step {
-    fn is_enabled(&self) -> bool;
+    fn is_enabled(&self) -> bool {
+        self.core().enabled
+    }
 
-    fn set_enabled(&mut self, enabled: bool);
+    fn set_enabled(&mut self, enabled: bool) {
+        self.core_mut().enabled = enabled;
+    }
 
     fn is_dac_enabled(&self) -> bool;
 
@@ -63,14 +91,20 @@ pub trait Channel: DigitalAmplitude + Clock + Sample + Step {
     }
 }
 
-pub trait EnvelopeModulation {
+pub trait EnvelopeModulation: HasCore<Envelope = Envelope> {
     fn envelope_register(&self) -> u8;
 
-    fn envelope_timer(&mut self) -> &mut u8;
+    fn envelope_timer(&mut self) -> &mut u8 {
+        &mut self.core_mut().envelope.timer
+    }
 
-    fn envelope_volume(&self) -> u8;
+    fn envelope_volume(&self) -> u8 {
+        self.core().envelope.volume
+    }
 
-    fn set_envelope_volume(&mut self, value: u8);
+    fn set_envelope_volume(&mut self, value: u8) {
+        self.core_mut().envelope.volume = value;
+    }
 
     fn envelope_init_volume(&self) -> u8 {
         self.envelope_register() >> 4
@@ -116,13 +150,33 @@ pub trait EnvelopeModulation {
 }
 
 pub trait LengthModulation: Channel {
+    /// Length loaded on trigger when the counter is empty
+    const MAX_LENGTH: u16;
+
     fn is_length_enabled(&self) -> bool;
 
-    fn length_counter(&self) -> u16;
+    fn length_counter(&self) -> u16 {
+        self.core().length_counter
+    }
 
-    fn set_length_counter(&mut self, value: u16);
+    fn set_length_counter(&mut self, value: u16) {
+        self.core_mut().length_counter = value;
+    }
 
-    fn set_half_length_period(&mut self, half: bool);
+    fn set_half_length_period(&mut self, half: bool) {
+        self.core_mut().length_half_period = half;
+    }
+
+    /// The next frame sequencer step does not clock the length
+    fn is_half_length_period(&self) -> bool {
+        self.core().length_half_period
+    }
+
+    /// Load the length from a NRx1 write
+    fn write_length(&mut self, value: u8) {
+        let length = value as u16 & (Self::MAX_LENGTH - 1);
+        self.set_length_counter(Self::MAX_LENGTH - length);
+    }
 
     fn length_step(&mut self) {
         let mut counter = self.length_counter();
@@ -135,20 +189,57 @@ pub trait LengthModulation: Channel {
             }
         }
     }
+
+    /// Enabling the length through NRx4 while the next frame sequencer step does not clock it
+    /// clocks it once more, which disables the channel if it reaches 0 without a trigger
+    fn enable_length(&mut self, enabled: bool) {
+        let counter = self.length_counter();
+
+        if enabled && !self.is_length_enabled() && self.is_half_length_period() && counter > 0 {
+            self.set_length_counter(counter - 1);
+            if counter == 1 {
+                self.set_enabled(false);
+            }
+        }
+    }
+
+    /// On trigger, an empty length counter is reloaded with its maximum
+    /// and gets the extra clock if the length is enabled in the first half of a period
+    /// This happens whether the DAC is on or off
+    fn reload_length(&mut self) {
+        if self.length_counter() == 0 {
+            let extra = self.is_length_enabled() && self.is_half_length_period();
+            self.set_length_counter(Self::MAX_LENGTH - extra as u16);
+        }
+    }
 }
 
-pub trait SweepModulation: Channel + WaveModulation {
+pub trait SweepModulation: Channel + WaveModulation + HasCore<Sweep = Sweep> {
     fn sweep_register(&self) -> u8;
 
-    fn sweep_timer(&mut self) -> &mut u8;
+    fn sweep_timer(&mut self) -> &mut u8 {
+        &mut self.core_mut().sweep.timer
+    }
+
+    fn shadow_frequency(&mut self) -> &mut u16 {
+        &mut self.core_mut().sweep.shadow_frequency
+    }
 
-    fn shadow_frequency(&mut self) -> &mut u16;
+    fn is_sweep_enabled(&self) -> bool {
+        self.core().sweep.enabled
+    }
 
-    fn is_sweep_enabled(&self) -> bool;
+    fn set_sweep_enabled(&mut self, enabled: bool) {
+        self.core_mut().sweep.enabled = enabled;
+    }
 
-    fn set_sweep_enabled(&mut self, enabled: bool);
+    fn sweep_was_decreasing(&self) -> bool {
+        self.core().sweep.was_decreasing
+    }
 
-    fn set_sweep_was_decreasing(&mut self, decreasing: bool);
+    fn set_sweep_was_decreasing(&mut self, decreasing: bool) {
+        self.core_mut().sweep.was_decreasing = decreasing;
+    }
 
     #[inline]
     fn sweep_period(&self) -> u8 {
@@ -279,4 +370,16 @@ impl<T: WaveModulation + Channel> Step for T {
             self.inc_wave_cursor();
         }
     }
+
+    fn step_by(&mut self, ticks: u32) {
+        let mut ticks = ticks;
+
+        // Only stop at each wave cursor move instead of each tick
+        while self.frequency_timer() > 0 && ticks >= self.frequency_timer() {
+            ticks -= self.frequency_timer();
+            self.reset_frequency_timer();
+            self.inc_wave_cursor();
+        }
+        self.set_frequency_timer(self.frequency_timer() - ticks);
+    }
 }
@@ -0,0 +1,56 @@
+use core::time::Duration;
+
+/// Time between two length clocks, on every other step of the frame sequencer (256 Hz)
+const LENGTH_CLOCK_NANOS: u64   = 1_000_000_000 / 256;
+
+/// Snapshot of a sound channel, see System::apu_state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelState {
+    /// The channel is playing (NR52 bits 0-3)
+    pub enabled: bool,
+    /// The DAC is powered, a disabled DAC also disables the channel
+    pub dac_enabled: bool,
+    /// Tone frequency in Hz, for the noise channel this is the rate the LFSR is clocked at
+    pub frequency: u32,
+    /// Current volume (0 - 15), after the envelope
+    /// The wave channel only has 4 levels: 0 (mute), 15 (100%), 7 (50%) and 3 (25%)
+    pub volume: u8,
+    /// Remaining length before the channel is disabled (when length_enabled is set)
+    pub length_counter: u16,
+    /// The length counter is decremented
+    pub length_enabled: bool,
+    /// Time before the length counter disables the channel, None if it is not counting down
+    pub length_remaining: Option<Duration>,
+    /// Registers NRx0 to NRx4 as written, including the write only bits
+    /// Registers that don't exist (NR20 & NR40) read 0xFF
+    pub registers: [u8; 5],
+}
+
+/// Snapshot of the APU registers and internal counters
+/// Unlike register reads, no bit is masked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ApuState {
+    /// All sound on/off (NR52 bit 7)
+    pub enabled: bool,
+    /// Master volume & VIN panning
+    pub nr50: u8,
+    /// Sound panning
+    pub nr51: u8,
+    /// Step of the frame sequencer (0 - 7)
+    pub frame_sequencer_step: u8,
+    /// Pulse with sweep, pulse, wave and noise channels
+    pub channels: [ChannelState; 4],
+    /// Wave pattern ram, 32 samples of 4 bits
+    pub wave_ram: [u8; 16],
+    /// Linear feedback shift register of the noise channel (15 bits)
+    pub noise_lfsr: u16,
+}
+
+/// Time left for a channel's length counter, see ChannelState::length_remaining
+pub(super) fn length_remaining(enabled: bool, length_enabled: bool, counter: u16) -> Option<Duration> {
+    if enabled && length_enabled {
+        Some(Duration::from_nanos(counter as u64 * LENGTH_CLOCK_NANOS))
+    } else {
+        None
+    }
+}
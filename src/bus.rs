@@ -1,17 +1,30 @@
-use core::ops::Deref;
-
 use crate::apu::Apu;
+use crate::default::NoExpansion;
 use crate::error::{io_error_read, io_error_write};
-use crate::interrupt::InterruptHandler;
+#[cfg(feature = "hooks")]
+use crate::hooks::Hooks;
+use crate::interrupt::{InterruptFlag, InterruptHandler};
 use crate::joypad::Joypad;
-use crate::ppu::Ppu;
+use crate::ppu::{Ppu, PpuMode};
 use crate::ram::Ram;
 use crate::region::*;
-use crate::rom::Rom;
+use crate::rom::{Rom, RomStorage};
 use crate::serial::Serial;
+use crate::stats::{AccessCounters, DiagnosticCounters};
 use crate::timer::Timer;
 
-pub struct Bus<T: Deref<Target=[u8]>> {
+/// What reads of the unusable region (0xFEA0 - 0xFEFF) return, this depends on the hardware revision
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnusableRegion {
+    /// Always 0xFF, reads are reported as unmapped
+    Open,
+    /// DMG: 0x00, or 0xFF while the PPU uses OAM (modes 2 & 3)
+    Dmg,
+    /// CGB revision E: the upper nibble of the address low byte, repeated (0xFEB4 reads 0xBB)
+    Cgb,
+}
+
+pub struct Bus<T: RomStorage, E: MemoryRegion = NoExpansion> {
     /// Access to io APU ports
     pub apu: Apu,
     /// Access to io joypad ports
@@ -30,9 +43,22 @@ pub struct Bus<T: Deref<Target=[u8]>> {
     wram: Ram<WRAM_REGION_SIZE>,
     /// High ram
     hram: Ram<HRAM_REGION_SIZE>,
+    /// 0xE000 - 0xFDFF mirrors the working ram
+    pub echo_ram: bool,
+    /// Behavior of 0xFEA0 - 0xFEFF
+    pub unusable_region: UnusableRegion,
+    /// User device answering the I/O addresses (0xFF00 - 0xFF7F) no other device uses
+    pub expansion: Option<E>,
+    /// Reads and writes per memory area
+    pub access: AccessCounters,
+    /// Unmapped accesses, illegal op codes, ...
+    pub diagnostics: DiagnosticCounters,
+    /// User callbacks
+    #[cfg(feature = "hooks")]
+    pub hooks: Hooks,
 }
 
-impl<T: Deref<Target=[u8]>> Bus<T> {
+impl<T: RomStorage, E: MemoryRegion> Bus<T, E> {
     pub fn new(rom: Rom<T>) -> Self {
         Self {
             apu: Apu::new(),
@@ -44,23 +70,77 @@ impl<T: Deref<Target=[u8]>> Bus<T> {
             hram: Ram::new(),
             wram: Ram::new(),
             it: InterruptHandler::new(),
+            echo_ram: true,
+            unusable_region: UnusableRegion::Open,
+            expansion: None,
+            access: AccessCounters::new(),
+            diagnostics: DiagnosticCounters::new(),
+            #[cfg(feature = "hooks")]
+            hooks: Hooks::default(),
         }
     }
 
-    pub fn set_rom(&mut self, rom: Rom<T>) {
-        self.rom = rom;
+    /// Reset every component and clear the internal ram
+    /// APU pending ticks must be flushed in fast mode
+    pub fn reset(&mut self, clear_eram: bool) {
+        self.apu.reset();
+        self.joypad.reset();
+        self.ppu.reset();
+        self.serial.reset();
+        self.timer.reset();
+        self.rom.reset(clear_eram);
+        self.it.reset();
+        self.wram.clear();
+        self.hram.clear();
+    }
+
+    pub fn set_rom(&mut self, rom: Rom<T>) -> Rom<T> {
+        core::mem::replace(&mut self.rom, rom)
     }
 
     pub fn read(&self, address: u16) -> u8 {
-        match address {
+        self.access.count_read(address);
+        let value = self.read_mapped(address).unwrap_or_else(|| {
+            self.diagnostics.unmapped_read();
+            io_error_read(address);
+            0xFF
+        });
+        #[cfg(feature = "hooks")]
+        self.hooks.on_read(address, value);
+        value
+    }
+
+    /// Read a byte without reporting unmapped addresses
+    /// This is meant for debugging purposes (memory dumps, etc)
+    pub fn peek(&self, address: u16) -> u8 {
+        self.read_mapped(address).unwrap_or(0xFF)
+    }
+
+    /// Retrieve the byte mapped at address, if any
+    fn read_mapped(&self, address: u16) -> Option<u8> {
+        let byte = match address {
             ROM_REGION_START..=ROM_REGION_END => self.rom.read(address),
             VRAM_REGION_START..=VRAM_REGION_END => self.ppu.read(address),
+            // Nothing answers on cartridges without ram
+            ERAM_REGION_START..=ERAM_REGION_END if self.rom.ram().is_empty() => return None,
             ERAM_REGION_START..=ERAM_REGION_END => self.rom.read(address),
             WRAM_REGION_START..=WRAM_REGION_END => self.wram.read(address - WRAM_REGION_START),
-            ECHORAM_REGION_START..=ECHORAM_REGION_END => {
+            ECHORAM_REGION_START..=ECHORAM_REGION_END if self.echo_ram => {
                 self.wram.read(address - ECHORAM_REGION_START)
             },
+            OAM_REGION_START..=OAM_REGION_END if self.ppu.is_oam_locked() => 0xFF,
             OAM_REGION_START..=OAM_REGION_END => self.ppu.read(address),
+            UNUSABLE_REGION_START..=UNUSABLE_REGION_END => match self.unusable_region {
+                UnusableRegion::Open => return None,
+                UnusableRegion::Dmg => match self.ppu.mode() {
+                    PpuMode::OamScan | PpuMode::Transfer => 0xFF,
+                    _ => 0x00,
+                },
+                UnusableRegion::Cgb => {
+                    let nibble = (address as u8) & 0xF0;
+                    nibble | (nibble >> 4)
+                },
+            },
             // I/O Registers
             IO_JOYPAD_REGION => self.joypad.read(address),
             IO_SERIAL_REGION_START..=IO_SERIAL_REGION_END => self.serial.read(address),
@@ -69,27 +149,46 @@ impl<T: Deref<Target=[u8]>> Bus<T> {
             IO_PPU_REGION_START..=IO_PPU_REGION_END => self.ppu.read(address),
             HRAM_REGION_START..=HRAM_REGION_END => self.hram.read(address - HRAM_REGION_START),
             REG_IF_ADDR | REG_IE_ADDR => self.it.read(address),
-            _ => {
-                io_error_read(address);
-                0xFF
+            _ => match (address, self.expansion.as_ref()) {
+                (IO_REGION_START..=IO_REGION_END, Some(expansion)) => expansion.read(address),
+                _ => return None,
             },
-        }
+        };
+        Some(byte)
     }
 
     pub fn write(&mut self, address: u16, value: u8) {
+        self.access.count_write(address);
+        #[cfg(feature = "hooks")]
+        self.hooks.on_write(address, value);
+        #[cfg(feature = "hooks")]
+        let video_byte = self.video_byte(address);
+
         match address {
             ROM_REGION_START..=ROM_REGION_END => self.rom.write(address, value),
             VRAM_REGION_START..=VRAM_REGION_END => self.ppu.write(address, value),
+            ERAM_REGION_START..=ERAM_REGION_END if self.rom.ram().is_empty() => {
+                self.diagnostics.unmapped_write();
+                io_error_write(address);
+            },
             ERAM_REGION_START..=ERAM_REGION_END => self.rom.write(address, value),
             WRAM_REGION_START..=WRAM_REGION_END => {
                 self.wram.write(address - WRAM_REGION_START, value)
             },
-            ECHORAM_REGION_START..=ECHORAM_REGION_END => {
+            ECHORAM_REGION_START..=ECHORAM_REGION_END if self.echo_ram => {
                 self.wram.write(address - ECHORAM_REGION_START, value)
             },
+            OAM_REGION_START..=OAM_REGION_END if self.ppu.is_oam_locked() => (),
             OAM_REGION_START..=OAM_REGION_END => self.ppu.write(address, value),
+            // Writes are ignored by all revisions
+            UNUSABLE_REGION_START..=UNUSABLE_REGION_END if self.unusable_region != UnusableRegion::Open => (),
             // I/O Registers
-            IO_JOYPAD_REGION => self.joypad.write(address, value),
+            IO_JOYPAD_REGION => {
+                self.joypad.write(address, value);
+                if self.joypad.take_interrupt() {
+                    self.it.request(InterruptFlag::Joypad);
+                }
+            },
             IO_SERIAL_REGION_START..=IO_SERIAL_REGION_END => self.serial.write(address, value),
             IO_TIMER_REGION_START..=IO_TIMER_REGION_END => self.timer.write(address, value),
             IO_SOUND_REGION_START..=IO_SOUND_REGION_END => self.apu.write(address, value),
@@ -98,16 +197,61 @@ impl<T: Deref<Target=[u8]>> Bus<T> {
                 self.hram.write(address - HRAM_REGION_START, value)
             },
             REG_IF_ADDR | REG_IE_ADDR => self.it.write(address, value),
-            _ => io_error_write(address),
+            _ => match (address, self.expansion.as_mut()) {
+                (IO_REGION_START..=IO_REGION_END, Some(expansion)) => expansion.write(address, value),
+                _ => {
+                    self.diagnostics.unmapped_write();
+                    io_error_write(address);
+                },
+            },
+        }
+
+        #[cfg(feature = "hooks")]
+        if let Some(old) = video_byte {
+            self.notify_video(address, old);
         }
     }
 
+    /// Retrieve the current byte if address contains video data and a video hook is set
+    #[cfg(feature = "hooks")]
+    fn video_byte(&self, address: u16) -> Option<u8> {
+        self.hooks.video?;
+        match address {
+            VRAM_REGION_START..=VRAM_REGION_END
+                | OAM_REGION_START..=OAM_REGION_END
+                | REG_BGP_ADDR..=REG_OBP1_ADDR => Some(self.ppu.read(address)),
+            _ => None,
+        }
+    }
+
+    /// Call the video hook if the byte at address is different from old
+    #[cfg(feature = "hooks")]
+    fn notify_video(&self, address: u16, old: u8) {
+        let value = self.ppu.read(address);
+        if value != old {
+            self.hooks.on_video(address, value);
+        }
+    }
+
+    /// Copy one byte to OAM, a transfer takes 160 M-cycles
     pub fn dma_tick(&mut self) {
-        if !self.ppu.is_dma_active() {
+        if !self.ppu.is_dma_active() || self.ppu.dma_wait() {
             return;
         }
-        // The bus can read addresses from 0x0000 to 0xDF9F
-        let byte = self.read(self.ppu.dma_source());
+        // Sources from 0xE000 read the working ram, like the echo ram
+        let source = match self.ppu.dma_source() {
+            address @ ECHORAM_REGION_START..=0xFFFF => address - (ECHORAM_REGION_START - WRAM_REGION_START),
+            address => address,
+        };
+        let byte = self.read(source);
+        #[cfg(feature = "hooks")]
+        let address = OAM_REGION_START + (self.ppu.dma_source() & 0xFF);
+        #[cfg(feature = "hooks")]
+        let video_byte = self.video_byte(address);
         self.ppu.dma_write(byte);
+        #[cfg(feature = "hooks")]
+        if let Some(old) = video_byte {
+            self.notify_video(address, old);
+        }
     }
 }
@@ -0,0 +1,78 @@
+use crate::region::VRAM_REGION_START;
+
+/// Maximum number of addresses frozen at the same time
+pub const MAX_FREEZES: usize            = 16;
+
+/// When frozen addresses are written again
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreezeMode {
+    /// After each instruction, the game never sees another value
+    Instruction,
+    /// Once per frame, like GameShark codes
+    Frame,
+}
+
+/// Value kept at an address, e.g. a number of lives
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Freeze {
+    pub address: u16,
+    pub value: u8,
+}
+
+/// Fixed set of frozen addresses
+pub struct Freezes {
+    list: [Option<Freeze>; MAX_FREEZES],
+    pub mode: FreezeMode,
+}
+
+impl Freezes {
+    pub fn new() -> Self {
+        Self {
+            list: [None; MAX_FREEZES],
+            mode: FreezeMode::Frame,
+        }
+    }
+
+    /// Returns false if the address cannot be frozen (rom area or no room left)
+    /// The value is replaced if the address is already frozen
+    pub fn add(&mut self, address: u16, value: u8) -> bool {
+        // Writes in the rom area would be taken as MBC commands
+        if address < VRAM_REGION_START {
+            return false;
+        }
+        if let Some(freeze) = self.list.iter_mut().flatten().find(|freeze| freeze.address == address) {
+            freeze.value = value;
+            return true;
+        }
+        match self.list.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(Freeze { address, value });
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns false if the address was not frozen
+    pub fn remove(&mut self, address: u16) -> bool {
+        match self.list.iter_mut().find(|slot| slot.is_some_and(|freeze| freeze.address == address)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list = [None; MAX_FREEZES];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.iter().all(|slot| slot.is_none())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Freeze> {
+        self.list.iter().flatten()
+    }
+}
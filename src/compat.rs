@@ -0,0 +1,169 @@
+//! Score roms to build compatibility lists
+//!
+//! A rom runs without display, sound or link partner for a number of frames,
+//! the frames are hashed to tell whether the game drew something and settled on a still picture,
+//! which is usually a title screen waiting for a button press.
+//! ```no_run
+//! use std::fs;
+//!
+//! use padme_core::compat::{scan, ScanConfig};
+//!
+//! for entry in fs::read_dir("roms").unwrap() {
+//!     let path = entry.unwrap().path();
+//!     match scan(fs::read(&path).unwrap(), ScanConfig::default()) {
+//!         Ok(report) => println!("{}: {:?}", path.display(), report),
+//!         Err(err) => println!("{}: {}", path.display(), err),
+//!     }
+//! }
+//! ```
+use crate::default::{NoSerial, NoSpeaker};
+use crate::{CLOCK_SPEED, Diagnostics, Error, FRAME_WIDTH, FrameInfo, Pixel, RenderMode, Rom, RomStorage, Screen, System};
+
+/// FNV-1a parameters
+const FNV_OFFSET: u64                   = 0xCBF29CE484222325;
+const FNV_PRIME: u64                    = 0x100000001B3;
+
+/// Settings of a scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanConfig {
+    /// Frames to run at most
+    pub frames: u32,
+    /// Identical frames in a row for the picture to be considered still
+    pub stable_frames: u32,
+}
+
+impl Default for ScanConfig {
+    /// 30 seconds, a picture is still after 2 seconds
+    fn default() -> Self {
+        Self {
+            frames: 30 * 60,
+            stable_frames: 2 * 60,
+        }
+    }
+}
+
+/// Outcome of a scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanReport {
+    /// The game displayed a frame with more than one color
+    pub booted: bool,
+    /// Frame from which the picture stayed still for ScanConfig::stable_frames frames
+    /// This is a heuristic: a logo shown long enough also counts
+    pub title_screen: Option<u32>,
+    /// The CPU locked up on an illegal op code
+    pub locked: bool,
+    /// Frames executed before the scan stopped
+    pub frames: u32,
+    /// Unmapped accesses, illegal op codes and serial interrupts
+    pub diagnostics: Diagnostics,
+}
+
+/// Screen hashing the frames instead of displaying them
+struct HashScreen {
+    /// Hash of the frame in progress
+    hash: u64,
+    /// The frame in progress only has one color so far
+    uniform: bool,
+    first_pixel: u32,
+    /// Hash of the last frame with more than one color
+    last_hash: Option<u64>,
+    /// Identical frames in a row
+    stable: u32,
+    /// Frame the identical frames started at
+    stable_since: u32,
+    frames: u32,
+    booted: bool,
+}
+
+impl HashScreen {
+    fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET,
+            uniform: true,
+            first_pixel: 0,
+            last_hash: None,
+            stable: 0,
+            stable_since: 0,
+            frames: 0,
+            booted: false,
+        }
+    }
+
+    /// Hash a pixel of the frame in progress
+    fn add(&mut self, x: u8, y: u8, word: u32) {
+        if x == 0 && y == 0 {
+            self.first_pixel = word;
+        }
+        self.uniform &= word == self.first_pixel;
+        for byte in word.to_ne_bytes() {
+            self.hash = (self.hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl Screen for HashScreen {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.add(x, y, px.packed());
+    }
+
+    fn set_row(&mut self, y: u8, row: &[u32; FRAME_WIDTH]) {
+        for (x, word) in row.iter().enumerate() {
+            self.add(x as u8, y, *word);
+        }
+    }
+
+    fn update(&mut self) {
+    }
+
+    fn update_with(&mut self, info: &FrameInfo) {
+        let hash = if info.lcd_enabled && !self.uniform { Some(self.hash) } else { None };
+
+        self.booted |= hash.is_some();
+        if hash.is_some() && hash == self.last_hash {
+            self.stable += 1;
+        } else {
+            self.stable = 0;
+            self.stable_since = self.frames;
+        }
+        self.last_hash = hash;
+        self.frames += 1;
+        self.hash = FNV_OFFSET;
+        self.uniform = true;
+    }
+}
+
+/// Run a rom until its picture stays still, the CPU locks up or the frames of the config are executed
+/// Returns an error if the rom cannot be loaded, e.g. for unsupported cartridges
+/// ```
+/// use padme_core::compat::{scan, ScanConfig};
+///
+/// let report = scan(vec![0u8; 32 * 1024], ScanConfig { frames: 60, stable_frames: 30 }).unwrap();
+/// // Nothing is drawn
+/// assert!(!report.booted);
+/// assert_eq!(report.title_screen, None);
+/// ```
+pub fn scan<T: RomStorage>(storage: T, config: ScanConfig) -> Result<ScanReport, Error> {
+    let rom = Rom::load(storage)?;
+    let mut emu = System::new(rom, HashScreen::new(), NoSerial, NoSpeaker);
+    emu.set_render_mode(RenderMode::Fast);
+
+    // A frame is far shorter than a second, even with the LCD off
+    let max_cycles = config.frames as u64 * CLOCK_SPEED as u64;
+    let stable_frames = config.stable_frames.max(1);
+    emu.run_with_watchdog(max_cycles, |emu| {
+        let screen = emu.screen();
+        let done = screen.frames >= config.frames || screen.stable >= stable_frames;
+        done || emu.is_locked()
+    });
+
+    let locked = emu.is_locked();
+    let diagnostics = emu.diagnostics();
+    let screen = emu.screen();
+    Ok(ScanReport {
+        booted: screen.booted,
+        title_screen: if screen.stable >= stable_frames { Some(screen.stable_since) } else { None },
+        locked,
+        frames: screen.frames,
+        diagnostics,
+    })
+}
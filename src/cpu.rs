@@ -1,12 +1,15 @@
-use core::ops::Deref;
+use core::fmt;
 
 use log::error;
 #[cfg(debug_assertions)]
 use log::trace;
 
 use crate::bus::Bus;
+#[cfg(debug_assertions)]
+use crate::disasm::Instruction;
 use crate::interrupt::InterruptFlag;
 use crate::region::*;
+use crate::rom::RomStorage;
 
 pub const CLOCK_SPEED: u32              = 4194304;
 
@@ -16,6 +19,9 @@ const IR_LCDC_STATUS_ADDR: u16          = 0x0048;
 const IR_TIMER_OVERFLOW_ADDR: u16       = 0x0050;
 const IR_SERIAL_TRANSFER_ADDR: u16      = 0x0058;
 const IR_JOYPAD_PRESS_ADDR: u16         = 0x0060;
+const IR_CANCELLED_ADDR: u16            = 0x0000;
+/// 2 wait states, 2 cycles to push PC and 1 to jump
+const IR_DISPATCH_TICKS: u8             = 20;
 
 // Flags for register F
 const FLAG_ZERO: u8                     = 0x80;
@@ -33,6 +39,34 @@ const DEFAULT_REG_E: u8                 = 0xD8;
 const DEFAULT_REG_H: u8                 = 0x01;
 const DEFAULT_REG_L: u8                 = 0x4D;
 
+/// Hardware revision, which decides the registers the boot rom leaves behind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// Early Game Boy boot rom
+    Dmg0,
+    /// Game Boy
+    Dmg,
+    /// Game Boy Pocket
+    Mgb,
+}
+
+impl Model {
+    /// Registers A, F, B, C, D, E, H, L after the boot rom
+    fn registers(self) -> [u8; 8] {
+        match self {
+            Model::Dmg0 => [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+            Model::Dmg => [
+                DEFAULT_REG_A, DEFAULT_REG_F, DEFAULT_REG_B, DEFAULT_REG_C,
+                DEFAULT_REG_D, DEFAULT_REG_E, DEFAULT_REG_H, DEFAULT_REG_L,
+            ],
+            Model::Mgb => [
+                0xFF, DEFAULT_REG_F, DEFAULT_REG_B, DEFAULT_REG_C,
+                DEFAULT_REG_D, DEFAULT_REG_E, DEFAULT_REG_H, DEFAULT_REG_L,
+            ],
+        }
+    }
+}
+
 const DEFAULT_SP: u16                   = 0xFFFE;
 const DEFAULT_PC: u16                   = 0x0100;
 
@@ -50,6 +84,52 @@ macro_rules! fmt_registers {
     }
 }
 
+/// Snapshot of the CPU registers and state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    /// Master interrupt enable
+    pub ime: bool,
+    pub halted: bool,
+    pub stopped: bool,
+}
+
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # let mut bin = [0u8; 32 * 1024];
+/// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+/// let emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// assert_eq!(format!("{}", emu.cpu_state()),
+///            "PC: 0x0100 | SP: 0xFFFE | AF: 0x01B0 (Z, -, H, C) | BC: 0x0013 | DE: 0x00D8 | HL: 0x014D");
+/// ```
+impl fmt::Display for CpuState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let af = u16::from_be_bytes([self.a, self.f]);
+        let bc = u16::from_be_bytes([self.b, self.c]);
+        let de = u16::from_be_bytes([self.d, self.e]);
+        let hl = u16::from_be_bytes([self.h, self.l]);
+
+        f.write_fmt(fmt_registers!(self.pc, self.sp, af, bc, de, hl))?;
+        if self.halted {
+            write!(f, " | HALT")?;
+        }
+        if self.stopped {
+            write!(f, " | STOP")?;
+        }
+        Ok(())
+    }
+}
+
 pub struct Cpu {
     // Registers
     a: u8,
@@ -66,9 +146,13 @@ pub struct Cpu {
     halted: bool,
     // CPU stopped until button is pressed
     stopped: bool,
+    // CPU locked up after an illegal op code, only a reset gets it out
+    locked: bool,
     // Master Interrupt Enable
     master_ie: bool,
     enabling_ie: bool,
+    // Interrupt serviced by the last step
+    dispatched: Option<InterruptFlag>,
 }
 
 impl Cpu {
@@ -86,11 +170,42 @@ impl Cpu {
             pc: DEFAULT_PC,
             halted: false,
             stopped: false,
+            locked: false,
             master_ie: true,
             enabling_ie: false,
+            dispatched: None,
         }
     }
 
+    /// Retrieve a copy of all registers
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            f: self.f,
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+            sp: self.sp,
+            pc: self.pc,
+            ime: self.master_ie,
+            halted: self.halted,
+            stopped: self.stopped,
+        }
+    }
+
+    /// Checks whether the CPU locked up on an illegal op code
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Stop executing anything until the next reset, like the boot rom does on an invalid header
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
     fn af(&self) -> u16 {
         make_u16!(self.a, self.f)
     }
@@ -147,21 +262,21 @@ impl Cpu {
     }
 
     /// Retrieve next byte
-    fn fetch<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>) -> u8 {
+    fn fetch<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>) -> u8 {
         let byte = bus.read(self.pc);
         self.pc = self.pc.wrapping_add(1);
         byte
     }
 
     /// Retrieve next 2 bytes as a u16
-    fn fetch16<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>) -> u16 {
+    fn fetch16<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>) -> u16 {
         let l = self.fetch(bus);
         let h = self.fetch(bus);
         make_u16!(h, l)
     }
 
     /// Put SP + n into HL
-    fn ld_hl_spn<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>) {
+    fn ld_hl_spn<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>) {
         let n = self.fetch(bus);
         let res = (self.sp as i32).wrapping_add((n as i8) as i32) as u16;
 
@@ -173,7 +288,7 @@ impl Cpu {
     }
 
     /// PUSH element on top of the stack
-    fn push<T: Deref<Target=[u8]>>(&mut self, bus: &mut Bus<T>, value: u16) {
+    fn push<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>, value: u16) {
         self.sp = self.sp.wrapping_sub(1);
         bus.write(self.sp, (value >> 8) as u8);
         self.sp = self.sp.wrapping_sub(1);
@@ -181,7 +296,7 @@ impl Cpu {
     }
 
     /// POP top element of the stack
-    fn pop<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>) -> u16 {
+    fn pop<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>) -> u16 {
         let l = bus.read(self.sp);
         self.sp = self.sp.wrapping_add(1);
         let h = bus.read(self.sp);
@@ -298,7 +413,7 @@ impl Cpu {
 
     /// Swap upper & lower nibbles of value
     fn swap(&mut self, value: u8) -> u8 {
-        let r = (value << 4) | (value >> 4);
+        let r = value.rotate_left(4);
         self.set_flag(FLAG_ZERO, r == 0);
         self.set_flag(FLAG_SUBSTRACT, false);
         self.set_flag(FLAG_CARRY, false);
@@ -432,13 +547,13 @@ impl Cpu {
     }
 
     /// Save PC and jump to address
-    fn call<T: Deref<Target=[u8]>>(&mut self, bus: &mut Bus<T>, address: u16) {
+    fn call<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>, address: u16) {
         self.push(bus, self.pc);
         self.pc = address;
     }
 
     /// Save PC and jump to address if condition is true
-    fn call_if<T: Deref<Target=[u8]>>(&mut self, bus: &mut Bus<T>, nn: u16, condition: bool) -> u8 {
+    fn call_if<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>, nn: u16, condition: bool) -> u8 {
         if condition {
             self.call(bus, nn);
             24
@@ -448,7 +563,7 @@ impl Cpu {
     }
 
     /// Return if condition is true
-    fn ret_if<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>, condition: bool) -> u8 {
+    fn ret_if<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>, condition: bool) -> u8 {
         if condition {
             self.pc = self.pop(bus);
             20
@@ -458,546 +573,22 @@ impl Cpu {
     }
 
     #[cfg(debug_assertions)]
-    fn dump_instruction<T: Deref<Target=[u8]>>(&mut self, bus: &Bus<T>, op: u8) {
-        macro_rules! trace_instruction {
-            ($($arg:tt)*) => {
-                trace!("{} | {}", fmt_registers!(self.pc.wrapping_sub(1), self.sp, self.af(),
-                                                 self.bc(), self.de(), self.hl()),
-                           format_args!($($arg)*))
-            };
-        }
-
-        macro_rules! rel_address {
-            ($n: expr) => {
-                ((self.pc as i32 + 1) + (($n as i8) as i32)) as u16
-            }
-        }
-
-        let next = bus.read(self.pc);
-        let next16 = {
-            let l = bus.read(self.pc);
-            let h = bus.read(self.pc.wrapping_add(1));
-            make_u16!(h, l)
-        };
-
-        match op {
-            0x00 => { trace_instruction!("NOP") },
-            0x27 => { trace_instruction!("DAA") },
-            0x2F => { trace_instruction!("CPL") },
-            0x37 => { trace_instruction!("SCF") },
-            0x3F => { trace_instruction!("CCF") },
-            0x76 => { trace_instruction!("HALT") },
-            0x10 => { trace_instruction!("STOP") },
-            0x01 => { trace_instruction!("LD BC, ${:04X}", next16) },
-            0x11 => { trace_instruction!("LD DE, ${:04X}", next16) },
-            0x21 => { trace_instruction!("LD HL, ${:04X}", next16) },
-            0x31 => { trace_instruction!("LD SP, ${:04X}", next16) },
-            0x06 => { trace_instruction!("LD B, ${:02X}", next) },
-            0x0E => { trace_instruction!("LD C, ${:02X}", next) },
-            0x16 => { trace_instruction!("LD D, ${:02X}", next) },
-            0x1E => { trace_instruction!("LD E, ${:02X}", next) },
-            0x26 => { trace_instruction!("LD H, ${:02X}", next) },
-            0x2E => { trace_instruction!("LD L, ${:02X}", next) },
-            0x3E => { trace_instruction!("LD A, ${:02X}", next) },
-            0x40 => { trace_instruction!("LD B, B") },
-            0x41 => { trace_instruction!("LD B, C") },
-            0x42 => { trace_instruction!("LD B, D") },
-            0x43 => { trace_instruction!("LD B, E") },
-            0x44 => { trace_instruction!("LD B, H") },
-            0x45 => { trace_instruction!("LD B, L") },
-            0x46 => { trace_instruction!("LD B, (HL)") },
-            0x47 => { trace_instruction!("LD B, A") },
-            0x48 => { trace_instruction!("LD C, B") },
-            0x49 => { trace_instruction!("LD C, C") },
-            0x4A => { trace_instruction!("LD C, D") },
-            0x4B => { trace_instruction!("LD C, E") },
-            0x4C => { trace_instruction!("LD C, H") },
-            0x4D => { trace_instruction!("LD C, L") },
-            0x4E => { trace_instruction!("LD C, (HL)") },
-            0x4F => { trace_instruction!("LD C, A") },
-            0x50 => { trace_instruction!("LD D, B") },
-            0x51 => { trace_instruction!("LD D, C") },
-            0x52 => { trace_instruction!("LD D, D") },
-            0x53 => { trace_instruction!("LD D, E") },
-            0x54 => { trace_instruction!("LD D, H") },
-            0x55 => { trace_instruction!("LD D, L") },
-            0x56 => { trace_instruction!("LD D, (HL)") },
-            0x57 => { trace_instruction!("LD D, A") },
-            0x58 => { trace_instruction!("LD E, B") },
-            0x59 => { trace_instruction!("LD E, C") },
-            0x5A => { trace_instruction!("LD E, D") },
-            0x5B => { trace_instruction!("LD E, E") },
-            0x5C => { trace_instruction!("LD E, H") },
-            0x5D => { trace_instruction!("LD E, L") },
-            0x5E => { trace_instruction!("LD E, (HL)") },
-            0x5F => { trace_instruction!("LD E, A") },
-            0x60 => { trace_instruction!("LD H, B") },
-            0x61 => { trace_instruction!("LD H, C") },
-            0x62 => { trace_instruction!("LD H, D") },
-            0x63 => { trace_instruction!("LD H, E") },
-            0x64 => { trace_instruction!("LD H, H") },
-            0x65 => { trace_instruction!("LD H, L") },
-            0x66 => { trace_instruction!("LD B, (HL)") },
-            0x67 => { trace_instruction!("LD H, A") },
-            0x68 => { trace_instruction!("LD L, B") },
-            0x69 => { trace_instruction!("LD L, C") },
-            0x6A => { trace_instruction!("LD L, D") },
-            0x6B => { trace_instruction!("LD L, E") },
-            0x6C => { trace_instruction!("LD L, H") },
-            0x6D => { trace_instruction!("LD L, L") },
-            0x6E => { trace_instruction!("LD L, (HL)") },
-            0x6F => { trace_instruction!("LD L, A") },
-            0x78 => { trace_instruction!("LD A, B") },
-            0x79 => { trace_instruction!("LD A, C") },
-            0x7A => { trace_instruction!("LD A, D") },
-            0x7B => { trace_instruction!("LD A, E") },
-            0x7C => { trace_instruction!("LD A, H") },
-            0x7D => { trace_instruction!("LD A, L") },
-            0x7E => { trace_instruction!("LD A, (HL)") },
-            0x7F => { trace_instruction!("LD A, A") },
-            0x2A => { trace_instruction!("LD A, (HL+)") },
-            0x3A => { trace_instruction!("LD A, (HL-)") },
-            0x0A => { trace_instruction!("LD A, (BC)") },
-            0x1A => { trace_instruction!("LD A, (DE)") },
-            0xFA => { trace_instruction!("LD A, (${:04X})", next16) },
-            0xEA => { trace_instruction!("LD (${:04X}), A", next16) },
-            0x36 => { trace_instruction!("LD (HL), ${:02X}", next) },
-            0x70 => { trace_instruction!("LD (HL), B") },
-            0x71 => { trace_instruction!("LD (HL), C") },
-            0x72 => { trace_instruction!("LD (HL), D") },
-            0x73 => { trace_instruction!("LD (HL), E") },
-            0x74 => { trace_instruction!("LD (HL), H") },
-            0x75 => { trace_instruction!("LD (HL), L") },
-            0x77 => { trace_instruction!("LD (HL), A") },
-            0x02 => { trace_instruction!("LD (BC), A") },
-            0x12 => { trace_instruction!("LD (DE), A") },
-            0x22 => { trace_instruction!("LD (HL+), A") },
-            0x32 => { trace_instruction!("LD (HL-), A") },
-            0xE0 => { trace_instruction!("LD ($FF00 + ${:02X}), A", next) },
-            0xF0 => { trace_instruction!("LD A, ($FF00 + ${:02X})", next) },
-            0xE2 => { trace_instruction!("LD ($FF00 + C), A") },
-            0xF2 => { trace_instruction!("LD A, ($FF00 + C)") },
-            0xF8 => { trace_instruction!("LD HL, SP + ${:02X}", next) },
-            0x08 => { trace_instruction!("LD (${:04X}), SP", next16) },
-            0xF9 => { trace_instruction!("LD SP, HL") },
-            0xF5 => { trace_instruction!("PUSH AF") },
-            0xC5 => { trace_instruction!("PUSH BC") },
-            0xD5 => { trace_instruction!("PUSH DE") },
-            0xE5 => { trace_instruction!("PUSH HL") },
-            0xF1 => { trace_instruction!("POP AF") },
-            0xC1 => { trace_instruction!("POP BC") },
-            0xD1 => { trace_instruction!("POP DE") },
-            0xE1 => { trace_instruction!("POP HL") },
-            0xC3 => { trace_instruction!("JP ${:04X}", next16) },
-            0xC2 => { trace_instruction!("JP NZ, ${:04X}", next16) },
-            0xCA => { trace_instruction!("JP Z, ${:04X}", next16) },
-            0xD2 => { trace_instruction!("JP NC, ${:04X}", next16) },
-            0xDA => { trace_instruction!("JP C, ${:04X}", next16) },
-            0xE9 => { trace_instruction!("JP (HL)") },
-            0x18 => { trace_instruction!("JR ${:2X}", rel_address!(next)) },
-            0x20 => { trace_instruction!("JR NZ, ${:02X}", rel_address!(next)) },
-            0x28 => { trace_instruction!("JR Z, ${:02X}", rel_address!(next)) },
-            0x30 => { trace_instruction!("JR NC, ${:02X}", rel_address!(next)) },
-            0x38 => { trace_instruction!("JR C, ${:02X}", rel_address!(next)) },
-            0xCD => { trace_instruction!("CALL ${:04X}", next16) },
-            0xC4 => { trace_instruction!("CALL NZ, ${:04X}", next16) },
-            0xCC => { trace_instruction!("CALL Z, ${:04X}", next16) },
-            0xD4 => { trace_instruction!("CALL NC, ${:04X}", next16) },
-            0xDC => { trace_instruction!("CALL C, ${:04X}", next16) },
-            0xC7 => { trace_instruction!("RST ${:04X}", 0x00u16) },
-            0xCF => { trace_instruction!("RST ${:04X}", 0x08u16) },
-            0xD7 => { trace_instruction!("RST ${:04X}", 0x10u16) },
-            0xDF => { trace_instruction!("RST ${:04X}", 0x18u16) },
-            0xE7 => { trace_instruction!("RST ${:04X}", 0x20u16) },
-            0xEF => { trace_instruction!("RST ${:04X}", 0x28u16) },
-            0xF7 => { trace_instruction!("RST ${:04X}", 0x30u16) },
-            0xFF => { trace_instruction!("RST ${:04X}", 0x38u16) },
-            0xC9 => { trace_instruction!("RET") },
-            0xC0 => { trace_instruction!("RET NZ") },
-            0xC8 => { trace_instruction!("RET Z") },
-            0xD0 => { trace_instruction!("RET NC") },
-            0xD8 => { trace_instruction!("RET C") },
-            0xD9 => { trace_instruction!("RETI") }
-            0x87 => { trace_instruction!("ADD A, A") },
-            0x80 => { trace_instruction!("ADD A, B") },
-            0x81 => { trace_instruction!("ADD A, C") },
-            0x82 => { trace_instruction!("ADD A, D") },
-            0x83 => { trace_instruction!("ADD A, E") },
-            0x84 => { trace_instruction!("ADD A, H") },
-            0x85 => { trace_instruction!("ADD A, L") },
-            0x86 => { trace_instruction!("ADD A, (HL)") },
-            0xC6 => { trace_instruction!("ADD A, ${:02X}", next) },
-            0x8F => { trace_instruction!("ADC A, A") },
-            0x88 => { trace_instruction!("ADC A, B") },
-            0x89 => { trace_instruction!("ADC A, C") },
-            0x8A => { trace_instruction!("ADC A, D") },
-            0x8B => { trace_instruction!("ADC A, E") },
-            0x8C => { trace_instruction!("ADC A, H") },
-            0x8D => { trace_instruction!("ADC A, L") },
-            0x8E => { trace_instruction!("ADC A, (HL)") },
-            0xCE => { trace_instruction!("ADC A, ${:02X}", next) },
-            0x97 => { trace_instruction!("SUB A, A") },
-            0x90 => { trace_instruction!("SUB A, B") },
-            0x91 => { trace_instruction!("SUB A, C") },
-            0x92 => { trace_instruction!("SUB A, D") },
-            0x93 => { trace_instruction!("SUB A, E") },
-            0x94 => { trace_instruction!("SUB A, H") },
-            0x95 => { trace_instruction!("SUB A, L") },
-            0x96 => { trace_instruction!("SUB A, (HL)") },
-            0xD6 => { trace_instruction!("SUB A, ${:02X}", next) },
-            0x9F => { trace_instruction!("SBC A, A") },
-            0x98 => { trace_instruction!("SBC A, B") },
-            0x99 => { trace_instruction!("SBC A, C") },
-            0x9A => { trace_instruction!("SBC A, D") },
-            0x9B => { trace_instruction!("SBC A, E") },
-            0x9C => { trace_instruction!("SBC A, H") },
-            0x9D => { trace_instruction!("SBC A, L") },
-            0x9E => { trace_instruction!("SBC A, (HL)") },
-            0xDE => { trace_instruction!("SBC A, ${:02X}", next) },
-            0xA7 => { trace_instruction!("AND A") },
-            0xA0 => { trace_instruction!("AND B") },
-            0xA1 => { trace_instruction!("AND C") },
-            0xA2 => { trace_instruction!("AND D") },
-            0xA3 => { trace_instruction!("AND E") },
-            0xA4 => { trace_instruction!("AND H") },
-            0xA5 => { trace_instruction!("AND L") },
-            0xA6 => { trace_instruction!("AND (HL)") },
-            0xE6 => { trace_instruction!("AND ${:02X}", next) },
-            0xB7 => { trace_instruction!("OR A") },
-            0xB0 => { trace_instruction!("OR B") },
-            0xB1 => { trace_instruction!("OR C") },
-            0xB2 => { trace_instruction!("OR D") },
-            0xB3 => { trace_instruction!("OR E") },
-            0xB4 => { trace_instruction!("OR H") },
-            0xB5 => { trace_instruction!("OR L") },
-            0xB6 => { trace_instruction!("OR (HL)") },
-            0xF6 => { trace_instruction!("OR ${:02X}", next) },
-            0xAF => { trace_instruction!("XOR A") },
-            0xA8 => { trace_instruction!("XOR B") },
-            0xA9 => { trace_instruction!("XOR C") },
-            0xAA => { trace_instruction!("XOR D") },
-            0xAB => { trace_instruction!("XOR E") },
-            0xAC => { trace_instruction!("XOR H") },
-            0xAD => { trace_instruction!("XOR L") },
-            0xAE => { trace_instruction!("XOR (HL)") },
-            0xEE => { trace_instruction!("XOR ${:02X}", next) },
-            0xBF => { trace_instruction!("CP A") },
-            0xB8 => { trace_instruction!("CP B") },
-            0xB9 => { trace_instruction!("CP C") },
-            0xBA => { trace_instruction!("CP D") },
-            0xBB => { trace_instruction!("CP E") },
-            0xBC => { trace_instruction!("CP H") },
-            0xBD => { trace_instruction!("CP L") },
-            0xBE => { trace_instruction!("CP (HL)") },
-            0xFE => { trace_instruction!("CP ${:02X}", next) },
-            0x3C => { trace_instruction!("INC A") },
-            0x04 => { trace_instruction!("INC B") },
-            0x0C => { trace_instruction!("INC C") },
-            0x14 => { trace_instruction!("INC D") },
-            0x1C => { trace_instruction!("INC E") },
-            0x24 => { trace_instruction!("INC H") },
-            0x2C => { trace_instruction!("INC L") },
-            0x34 => { trace_instruction!("INC (HL)") },
-            0x3D => { trace_instruction!("DEC A") },
-            0x05 => { trace_instruction!("DEC B") },
-            0x0D => { trace_instruction!("DEC C") },
-            0x15 => { trace_instruction!("DEC D") },
-            0x1D => { trace_instruction!("DEC E") },
-            0x25 => { trace_instruction!("DEC H") },
-            0x2D => { trace_instruction!("DEC L") },
-            0x35 => { trace_instruction!("DEC (HL)") },
-            0x09 => { trace_instruction!("ADD HL, BC") },
-            0x19 => { trace_instruction!("ADD HL, DE") },
-            0x29 => { trace_instruction!("ADD HL, HL") },
-            0x39 => { trace_instruction!("ADD HL, SP") },
-            0xE8 => { trace_instruction!("ADD SP, ${:02X}", next as i8) },
-            0x03 => { trace_instruction!("INC BC") },
-            0x13 => { trace_instruction!("INC DE") },
-            0x23 => { trace_instruction!("INC HL") },
-            0x33 => { trace_instruction!("INC SP") },
-            0x0B => { trace_instruction!("DEC BC") },
-            0x1B => { trace_instruction!("DEC DE") },
-            0x2B => { trace_instruction!("DEC HL") },
-            0x3B => { trace_instruction!("DEC SP") },
-            0xF3 => { trace_instruction!("DI") },
-            0xFB => { trace_instruction!("EI") },
-            0x07 => { trace_instruction!("RLCA") },
-            0x17 => { trace_instruction!("RLA") },
-            0x0F => { trace_instruction!("RRCA") },
-            0x1F => { trace_instruction!("RRA") },
-            0xCB => {
-                let op2 = next;
+    fn dump_instruction<T: RomStorage, E: MemoryRegion>(&mut self, bus: &Bus<T, E>, op: u8) {
+        let address = self.pc.wrapping_sub(1);
+        let ins = Instruction::new(address, [op, bus.peek(self.pc), bus.peek(self.pc.wrapping_add(1))]);
 
-                match op2 {
-                    0x37 => { trace_instruction!("SWAP A") },
-                    0x30 => { trace_instruction!("SWAP B") },
-                    0x31 => { trace_instruction!("SWAP C") },
-                    0x32 => { trace_instruction!("SWAP D") },
-                    0x33 => { trace_instruction!("SWAP E") },
-                    0x34 => { trace_instruction!("SWAP H") },
-                    0x35 => { trace_instruction!("SWAP L") },
-                    0x36 => { trace_instruction!("SWAP (HL)") },
-                    0x07 => { trace_instruction!("RLC A") },
-                    0x00 => { trace_instruction!("RLC B") },
-                    0x01 => { trace_instruction!("RLC C") },
-                    0x02 => { trace_instruction!("RLC D") },
-                    0x03 => { trace_instruction!("RLC E") },
-                    0x04 => { trace_instruction!("RLC H") },
-                    0x05 => { trace_instruction!("RLC L") },
-                    0x06 => { trace_instruction!("RLC (HL)") },
-                    0x17 => { trace_instruction!("RL A") },
-                    0x10 => { trace_instruction!("RL B") },
-                    0x11 => { trace_instruction!("RL C") },
-                    0x12 => { trace_instruction!("RL D") },
-                    0x13 => { trace_instruction!("RL E") },
-                    0x14 => { trace_instruction!("RL H") },
-                    0x15 => { trace_instruction!("RL L") },
-                    0x16 => { trace_instruction!("RL (HL)") },
-                    0x0F => { trace_instruction!("RRC A") },
-                    0x08 => { trace_instruction!("RRC B") },
-                    0x09 => { trace_instruction!("RRC C") },
-                    0x0A => { trace_instruction!("RRC D") },
-                    0x0B => { trace_instruction!("RRC E") },
-                    0x0C => { trace_instruction!("RRC H") },
-                    0x0D => { trace_instruction!("RRC L") },
-                    0x0E => { trace_instruction!("RRC (HL)") },
-                    0x1F => { trace_instruction!("RR A") },
-                    0x18 => { trace_instruction!("RR B") },
-                    0x19 => { trace_instruction!("RR C") },
-                    0x1A => { trace_instruction!("RR D") },
-                    0x1B => { trace_instruction!("RR E") },
-                    0x1C => { trace_instruction!("RR H") },
-                    0x1D => { trace_instruction!("RR L") },
-                    0x1E => { trace_instruction!("RR (HL)") },
-                    0x27 => { trace_instruction!("SLA A") },
-                    0x20 => { trace_instruction!("SLA B") },
-                    0x21 => { trace_instruction!("SLA C") },
-                    0x22 => { trace_instruction!("SLA D") },
-                    0x23 => { trace_instruction!("SLA E") },
-                    0x24 => { trace_instruction!("SLA H") },
-                    0x25 => { trace_instruction!("SLA L") },
-                    0x26 => { trace_instruction!("SLA (HL)") },
-                    0x2F => { trace_instruction!("SRA A") },
-                    0x28 => { trace_instruction!("SRA B") },
-                    0x29 => { trace_instruction!("SRA C") },
-                    0x2A => { trace_instruction!("SRA D") },
-                    0x2B => { trace_instruction!("SRA E") },
-                    0x2C => { trace_instruction!("SRA H") },
-                    0x2D => { trace_instruction!("SRA L") },
-                    0x2E => { trace_instruction!("SRA (HL)") },
-                    0x3F => { trace_instruction!("SRL A") },
-                    0x38 => { trace_instruction!("SRL B") },
-                    0x39 => { trace_instruction!("SRL C") },
-                    0x3A => { trace_instruction!("SRL D") },
-                    0x3B => { trace_instruction!("SRL E") },
-                    0x3C => { trace_instruction!("SRL H") },
-                    0x3D => { trace_instruction!("SRL L") },
-                    0x3E => { trace_instruction!("SRL (HL)") },
-                    0x47 => { trace_instruction!("BIT 0, A") },
-                    0x40 => { trace_instruction!("BIT 0, B") },
-                    0x41 => { trace_instruction!("BIT 0, C") },
-                    0x42 => { trace_instruction!("BIT 0, D") },
-                    0x43 => { trace_instruction!("BIT 0, E") },
-                    0x44 => { trace_instruction!("BIT 0, H") },
-                    0x45 => { trace_instruction!("BIT 0, L") },
-                    0x46 => { trace_instruction!("BIT 0, (HL)") },
-                    0x4F => { trace_instruction!("BIT 1, A") },
-                    0x48 => { trace_instruction!("BIT 1, B") },
-                    0x49 => { trace_instruction!("BIT 1, C") },
-                    0x4A => { trace_instruction!("BIT 1, D") },
-                    0x4B => { trace_instruction!("BIT 1, E") },
-                    0x4C => { trace_instruction!("BIT 1, H") },
-                    0x4D => { trace_instruction!("BIT 1, L") },
-                    0x4E => { trace_instruction!("BIT 1, (HL)") },
-                    0x57 => { trace_instruction!("BIT 2, A") },
-                    0x50 => { trace_instruction!("BIT 2, B") },
-                    0x51 => { trace_instruction!("BIT 2, C") },
-                    0x52 => { trace_instruction!("BIT 2, D") },
-                    0x53 => { trace_instruction!("BIT 2, E") },
-                    0x54 => { trace_instruction!("BIT 2, H") },
-                    0x55 => { trace_instruction!("BIT 2, L") },
-                    0x56 => { trace_instruction!("BIT 2, (HL)") },
-                    0x5F => { trace_instruction!("BIT 3, A") },
-                    0x58 => { trace_instruction!("BIT 3, B") },
-                    0x59 => { trace_instruction!("BIT 3, C") },
-                    0x5A => { trace_instruction!("BIT 3, D") },
-                    0x5B => { trace_instruction!("BIT 3, E") },
-                    0x5C => { trace_instruction!("BIT 3, H") },
-                    0x5D => { trace_instruction!("BIT 3, L") },
-                    0x5E => { trace_instruction!("BIT 3, (HL)") },
-                    0x67 => { trace_instruction!("BIT 4, A") },
-                    0x60 => { trace_instruction!("BIT 4, B") },
-                    0x61 => { trace_instruction!("BIT 4, C") },
-                    0x62 => { trace_instruction!("BIT 4, D") },
-                    0x63 => { trace_instruction!("BIT 4, E") },
-                    0x64 => { trace_instruction!("BIT 4, H") },
-                    0x65 => { trace_instruction!("BIT 4, L") },
-                    0x66 => { trace_instruction!("BIT 4, (HL)") },
-                    0x6F => { trace_instruction!("BIT 5, A") },
-                    0x68 => { trace_instruction!("BIT 5, B") },
-                    0x69 => { trace_instruction!("BIT 5, C") },
-                    0x6A => { trace_instruction!("BIT 5, D") },
-                    0x6B => { trace_instruction!("BIT 5, E") },
-                    0x6C => { trace_instruction!("BIT 5, H") },
-                    0x6D => { trace_instruction!("BIT 5, L") },
-                    0x6E => { trace_instruction!("BIT 5, (HL)") },
-                    0x77 => { trace_instruction!("BIT 6, A") },
-                    0x70 => { trace_instruction!("BIT 6, B") },
-                    0x71 => { trace_instruction!("BIT 6, C") },
-                    0x72 => { trace_instruction!("BIT 6, D") },
-                    0x73 => { trace_instruction!("BIT 6, E") },
-                    0x74 => { trace_instruction!("BIT 6, H") },
-                    0x75 => { trace_instruction!("BIT 6, L") },
-                    0x76 => { trace_instruction!("BIT 6, (HL)") },
-                    0x7F => { trace_instruction!("BIT 7, A") },
-                    0x78 => { trace_instruction!("BIT 7, B") },
-                    0x79 => { trace_instruction!("BIT 7, C") },
-                    0x7A => { trace_instruction!("BIT 7, D") },
-                    0x7B => { trace_instruction!("BIT 7, E") },
-                    0x7C => { trace_instruction!("BIT 7, H") },
-                    0x7D => { trace_instruction!("BIT 7, L") },
-                    0x7E => { trace_instruction!("BIT 7, (HL)") },
-                    0x87 => { trace_instruction!("RES 0, A") },
-                    0x80 => { trace_instruction!("RES 0, B") },
-                    0x81 => { trace_instruction!("RES 0, C") },
-                    0x82 => { trace_instruction!("RES 0, D") },
-                    0x83 => { trace_instruction!("RES 0, E") },
-                    0x84 => { trace_instruction!("RES 0, H") },
-                    0x85 => { trace_instruction!("RES 0, L") },
-                    0x86 => { trace_instruction!("RES 0, (HL)") },
-                    0x8F => { trace_instruction!("RES 1, A") },
-                    0x88 => { trace_instruction!("RES 1, B") },
-                    0x89 => { trace_instruction!("RES 1, C") },
-                    0x8A => { trace_instruction!("RES 1, D") },
-                    0x8B => { trace_instruction!("RES 1, E") },
-                    0x8C => { trace_instruction!("RES 1, H") },
-                    0x8D => { trace_instruction!("RES 1, L") },
-                    0x8E => { trace_instruction!("RES 1, (HL)") },
-                    0x97 => { trace_instruction!("RES 2, A") },
-                    0x90 => { trace_instruction!("RES 2, B") },
-                    0x91 => { trace_instruction!("RES 2, C") },
-                    0x92 => { trace_instruction!("RES 2, D") },
-                    0x93 => { trace_instruction!("RES 2, E") },
-                    0x94 => { trace_instruction!("RES 2, H") },
-                    0x95 => { trace_instruction!("RES 2, L") },
-                    0x96 => { trace_instruction!("RES 2, (HL)") },
-                    0x9F => { trace_instruction!("RES 3, A") },
-                    0x98 => { trace_instruction!("RES 3, B") },
-                    0x99 => { trace_instruction!("RES 3, C") },
-                    0x9A => { trace_instruction!("RES 3, D") },
-                    0x9B => { trace_instruction!("RES 3, E") },
-                    0x9C => { trace_instruction!("RES 3, H") },
-                    0x9D => { trace_instruction!("RES 3, L") },
-                    0x9E => { trace_instruction!("RES 3, (HL)") },
-                    0xA7 => { trace_instruction!("RES 4, A") },
-                    0xA0 => { trace_instruction!("RES 4, B") },
-                    0xA1 => { trace_instruction!("RES 4, C") },
-                    0xA2 => { trace_instruction!("RES 4, D") },
-                    0xA3 => { trace_instruction!("RES 4, E") },
-                    0xA4 => { trace_instruction!("RES 4, H") },
-                    0xA5 => { trace_instruction!("RES 4, L") },
-                    0xA6 => { trace_instruction!("RES 4, (HL)") },
-                    0xAF => { trace_instruction!("RES 5, A") },
-                    0xA8 => { trace_instruction!("RES 5, B") },
-                    0xA9 => { trace_instruction!("RES 5, C") },
-                    0xAA => { trace_instruction!("RES 5, D") },
-                    0xAB => { trace_instruction!("RES 5, E") },
-                    0xAC => { trace_instruction!("RES 5, H") },
-                    0xAD => { trace_instruction!("RES 5, L") },
-                    0xAE => { trace_instruction!("RES 5, (HL)") },
-                    0xB7 => { trace_instruction!("RES 6, A") },
-                    0xB0 => { trace_instruction!("RES 6, B") },
-                    0xB1 => { trace_instruction!("RES 6, C") },
-                    0xB2 => { trace_instruction!("RES 6, D") },
-                    0xB3 => { trace_instruction!("RES 6, E") },
-                    0xB4 => { trace_instruction!("RES 6, H") },
-                    0xB5 => { trace_instruction!("RES 6, L") },
-                    0xB6 => { trace_instruction!("RES 6, (HL)") },
-                    0xBF => { trace_instruction!("RES 7, A") },
-                    0xB8 => { trace_instruction!("RES 7, B") },
-                    0xB9 => { trace_instruction!("RES 7, C") },
-                    0xBA => { trace_instruction!("RES 7, D") },
-                    0xBB => { trace_instruction!("RES 7, E") },
-                    0xBC => { trace_instruction!("RES 7, H") },
-                    0xBD => { trace_instruction!("RES 7, L") },
-                    0xBE => { trace_instruction!("RES 7, (HL)") },
-                    0xC7 => { trace_instruction!("SET 0, A") },
-                    0xC0 => { trace_instruction!("SET 0, B") },
-                    0xC1 => { trace_instruction!("SET 0, C") },
-                    0xC2 => { trace_instruction!("SET 0, D") },
-                    0xC3 => { trace_instruction!("SET 0, E") },
-                    0xC4 => { trace_instruction!("SET 0, H") },
-                    0xC5 => { trace_instruction!("SET 0, L") },
-                    0xC6 => { trace_instruction!("SET 0, (HL)") },
-                    0xCF => { trace_instruction!("SET 1, A") },
-                    0xC8 => { trace_instruction!("SET 1, B") },
-                    0xC9 => { trace_instruction!("SET 1, C") },
-                    0xCA => { trace_instruction!("SET 1, D") },
-                    0xCB => { trace_instruction!("SET 1, E") },
-                    0xCC => { trace_instruction!("SET 1, H") },
-                    0xCD => { trace_instruction!("SET 1, L") },
-                    0xCE => { trace_instruction!("SET 1, (HL)") },
-                    0xD7 => { trace_instruction!("SET 2, A") },
-                    0xD0 => { trace_instruction!("SET 2, B") },
-                    0xD1 => { trace_instruction!("SET 2, C") },
-                    0xD2 => { trace_instruction!("SET 2, D") },
-                    0xD3 => { trace_instruction!("SET 2, E") },
-                    0xD4 => { trace_instruction!("SET 2, H") },
-                    0xD5 => { trace_instruction!("SET 2, L") },
-                    0xD6 => { trace_instruction!("SET 2, (HL)") },
-                    0xDF => { trace_instruction!("SET 3, A") },
-                    0xD8 => { trace_instruction!("SET 3, B") },
-                    0xD9 => { trace_instruction!("SET 3, C") },
-                    0xDA => { trace_instruction!("SET 3, D") },
-                    0xDB => { trace_instruction!("SET 3, E") },
-                    0xDC => { trace_instruction!("SET 3, H") },
-                    0xDD => { trace_instruction!("SET 3, L") },
-                    0xDE => { trace_instruction!("SET 3, (HL)") },
-                    0xE7 => { trace_instruction!("SET 4, A") },
-                    0xE0 => { trace_instruction!("SET 4, B") },
-                    0xE1 => { trace_instruction!("SET 4, C") },
-                    0xE2 => { trace_instruction!("SET 4, D") },
-                    0xE3 => { trace_instruction!("SET 4, E") },
-                    0xE4 => { trace_instruction!("SET 4, H") },
-                    0xE5 => { trace_instruction!("SET 4, L") },
-                    0xE6 => { trace_instruction!("SET 4, (HL)") },
-                    0xEF => { trace_instruction!("SET 5, A") },
-                    0xE8 => { trace_instruction!("SET 5, B") },
-                    0xE9 => { trace_instruction!("SET 5, C") },
-                    0xEA => { trace_instruction!("SET 5, D") },
-                    0xEB => { trace_instruction!("SET 5, E") },
-                    0xEC => { trace_instruction!("SET 5, H") },
-                    0xED => { trace_instruction!("SET 5, L") },
-                    0xEE => { trace_instruction!("SET 5, (HL)") },
-                    0xF7 => { trace_instruction!("SET 6, A") },
-                    0xF0 => { trace_instruction!("SET 6, B") },
-                    0xF1 => { trace_instruction!("SET 6, C") },
-                    0xF2 => { trace_instruction!("SET 6, D") },
-                    0xF3 => { trace_instruction!("SET 6, E") },
-                    0xF4 => { trace_instruction!("SET 6, H") },
-                    0xF5 => { trace_instruction!("SET 6, L") },
-                    0xF6 => { trace_instruction!("SET 6, (HL)") },
-                    0xFF => { trace_instruction!("SET 7, A") },
-                    0xF8 => { trace_instruction!("SET 7, B") },
-                    0xF9 => { trace_instruction!("SET 7, C") },
-                    0xFA => { trace_instruction!("SET 7, D") },
-                    0xFB => { trace_instruction!("SET 7, E") },
-                    0xFC => { trace_instruction!("SET 7, H") },
-                    0xFD => { trace_instruction!("SET 7, L") },
-                    0xFE => { trace_instruction!("SET 7, (HL)") },
-                }
-            },
-            _ => { error!("Unknown op code 0x{:02X}", op) },
-        }
+        trace!("{} | {}", fmt_registers!(address, self.sp, self.af(), self.bc(), self.de(), self.hl()), ins);
     }
 
     #[cfg(not(debug_assertions))]
-    fn dump_instruction<T: Deref<Target=[u8]>>(&self, _bus: &Bus<T>, _op: u8) {
+    fn dump_instruction<T: RomStorage, E: MemoryRegion>(&self, _bus: &Bus<T, E>, _op: u8) {
     }
 
     /// Decode the provided op code and execute the instruction
-    fn decode_execute<T: Deref<Target=[u8]>>(&mut self, bus: &mut Bus<T>, op: u8) -> u8 {
+    fn decode_execute<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>, op: u8) -> u8 {
         self.dump_instruction(bus, op);
+        #[cfg(feature = "hooks")]
+        bus.hooks.on_instruction(self.pc.wrapping_sub(1), op);
 
         match op {
             // --- Misc
@@ -1327,7 +918,7 @@ impl Cpu {
                 self.set_flag(FLAG_SUBSTRACT, false);
                 self.set_flag(FLAG_CARRY, (r & 0xFF) < (self.sp & 0xFF));
                 self.set_flag(FLAG_HALF_CARRY, (r & 0xF) < (self.sp & 0xF));
-                self.sp = r as u16;
+                self.sp = r;
                 16
             },
             // INC rr
@@ -1816,80 +1407,120 @@ impl Cpu {
                     },
                 }
             }
-            // Unknown op code
+            // Unknown op code, the CPU locks up
             _ => {
                 error!("Unknown op code 0x{:02X}", op);
                 error!("{}", fmt_registers!(self.pc.wrapping_sub(1), self.sp,
                                             self.af(), self.bc(), self.de(), self.hl()));
+                self.locked = true;
+                bus.diagnostics.illegal_opcode();
+                #[cfg(feature = "hooks")]
+                bus.hooks.on_illegal(self.pc.wrapping_sub(1), op);
                 4
             }
         }
     }
 
-    /// Reset all registers & state
-    pub fn reset(&mut self) {
-        self.a = DEFAULT_REG_A;
-        self.f = DEFAULT_REG_F;
-        self.b = DEFAULT_REG_B;
-        self.c = DEFAULT_REG_C;
-        self.d = DEFAULT_REG_D;
-        self.e = DEFAULT_REG_E;
-        self.h = DEFAULT_REG_H;
-        self.l = DEFAULT_REG_L;
+    /// Reset all registers & state to the values left by the boot rom of the given model
+    pub fn reset(&mut self, model: Model) {
+        let [a, f, b, c, d, e, h, l] = model.registers();
+        self.a = a;
+        self.f = f;
+        self.b = b;
+        self.c = c;
+        self.d = d;
+        self.e = e;
+        self.h = h;
+        self.l = l;
         self.sp = DEFAULT_SP;
         self.pc = DEFAULT_PC;
         self.halted = false;
         self.stopped = false;
+        self.locked = false;
         self.master_ie = true;
         self.enabling_ie = false;
+        self.dispatched = None;
+    }
+
+    /// Retrieve the interrupt serviced by the last step, if any
+    pub fn take_dispatched(&mut self) -> Option<InterruptFlag> {
+        self.dispatched.take()
+    }
+
+    /// Jump to the vector of the highest priority pending interrupt
+    /// Returns whether an interrupt was pending
+    fn dispatch_interrupt<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>) -> bool {
+        if bus.it.pending().is_empty() {
+            return false;
+        }
+
+        self.halted = false;
+        self.master_ie = false;
+
+        // The interrupt is only picked after PC high byte is pushed,
+        // which can overwrite IE when SP is 0x0000 and cancel the dispatch
+        self.sp = self.sp.wrapping_sub(1);
+        bus.write(self.sp, (self.pc >> 8) as u8);
+        let pending = bus.it.pending();
+        self.sp = self.sp.wrapping_sub(1);
+        bus.write(self.sp, self.pc as u8);
+
+        self.pc = match pending.iter().next() {
+            Some(flag) => {
+                bus.it.clear(flag);
+                self.dispatched = Some(flag);
+                match flag {
+                    InterruptFlag::Vblank => IR_VBLANK_ADDR,
+                    InterruptFlag::Lcdc => IR_LCDC_STATUS_ADDR,
+                    InterruptFlag::TimerOverflow => IR_TIMER_OVERFLOW_ADDR,
+                    InterruptFlag::Serial => IR_SERIAL_TRANSFER_ADDR,
+                    InterruptFlag::Joypad => IR_JOYPAD_PRESS_ADDR,
+                }
+            },
+            None => IR_CANCELLED_ADDR,
+        };
+
+        true
     }
 
     /// Fetch, decode and execute next instruction
     /// Returns the number of ticks
-    pub fn step<T: Deref<Target=[u8]>>(&mut self, bus: &mut Bus<T>) -> u8 {
+    pub fn step<T: RomStorage, E: MemoryRegion>(&mut self, bus: &mut Bus<T, E>) -> u8 {
+        // Nothing is executed anymore, not even interrupts
+        if self.locked {
+            return 4;
+        }
+        if self.stopped {
+            if !bus.joypad.is_any_pressed() {
+                return 4;
+            }
+            self.stopped = false;
+        }
+
         let ticks = if !self.halted {
             // Fetch instruction
             let op = self.fetch(bus);
             // Decode & execute
             self.decode_execute(bus, op)
         } else {
-            let pending_it = bus.read(REG_IF_ADDR);
-            if pending_it != 0 {
+            // Only an enabled interrupt wakes the CPU up, whether IME is set or not
+            if !bus.it.pending().is_empty() {
                 self.halted = false;
             }
             // If CPU is halted, we assume 4 cycles and return
             4
         };
 
-        // Check for interrupts
-        if self.master_ie {
-            let int_enable = bus.read(REG_IE_ADDR);
-            let int_flag = bus.read(REG_IF_ADDR);
-
-            macro_rules! handle_interrupt {
-                ($f:expr, $addr:expr) => {
-                    if (int_enable & ($f as u8)) != 0 && (int_flag & ($f as u8)) != 0 {
-                        self.call(bus, $addr);
-                        bus.it.clear($f);
-                        self.halted = false;
-                        self.master_ie = false;
-                        true
-                    } else {
-                        false
-                    }
-                }
-            }
-
-            let _ = handle_interrupt!(InterruptFlag::Vblank, IR_VBLANK_ADDR)
-                || handle_interrupt!(InterruptFlag::Lcdc, IR_LCDC_STATUS_ADDR)
-                || handle_interrupt!(InterruptFlag::TimerOverflow, IR_TIMER_OVERFLOW_ADDR)
-                || handle_interrupt!(InterruptFlag::Serial, IR_SERIAL_TRANSFER_ADDR)
-                || handle_interrupt!(InterruptFlag::Joypad, IR_JOYPAD_PRESS_ADDR);
-
+        // Check for interrupts, at most one is serviced per instruction
+        // IF is read again here so a request written by the instruction is seen right away
+        let mut ticks = ticks;
+        if self.master_ie && self.dispatch_interrupt(bus) {
+            ticks += IR_DISPATCH_TICKS;
         }
 
-        // Enable / Disable interrupt if requested, after 1 instruction
+        // Enable interrupts if requested, after 1 instruction
         if self.enabling_ie {
+            self.enabling_ie = false;
             self.master_ie = true;
         }
 
@@ -0,0 +1,180 @@
+use crate::region::{ERAM_REGION_END, ERAM_REGION_START, ROM_REGION_END};
+
+/// Maximum number of breakpoints set at the same time
+pub const MAX_BREAKPOINTS: usize        = 16;
+/// Maximum number of watched addresses
+pub const MAX_WATCHES: usize            = 32;
+
+/// Start of the switchable rom bank
+const ROM_BANKN_START: u16              = 0x4000;
+
+/// Why the execution was stopped before the end of a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakReason {
+    /// `LD B,B` was executed at this address (software breakpoint convention)
+    Software(u16),
+    /// The CPU is about to execute the instruction at a breakpoint
+    Breakpoint(Breakpoint),
+}
+
+/// Stop the execution when the CPU reaches an address
+/// ```
+/// use padme_core::Breakpoint;
+///
+/// // Only stop in 0x4000 when the rom bank 3 is mapped
+/// let bp = Breakpoint::in_bank(0x4000, 3);
+/// assert!(bp.matches(0x4000, 3, 0));
+/// assert!(!bp.matches(0x4000, 2, 0));
+/// // Banks are ignored outside of the switchable areas
+/// assert!(Breakpoint::in_bank(0x0150, 3).matches(0x0150, 1, 0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    /// Rom bank (0x4000 - 0x7FFF) or ram bank (0xA000 - 0xBFFF) to match, any bank if None
+    pub bank: Option<u16>,
+}
+
+impl Breakpoint {
+    /// Stop at this address whatever bank is mapped
+    pub const fn new(address: u16) -> Self {
+        Self { address, bank: None }
+    }
+
+    /// Stop at this address only when the bank is mapped
+    pub const fn in_bank(address: u16, bank: u16) -> Self {
+        Self { address, bank: Some(bank) }
+    }
+
+    /// Checks whether the breakpoint is reached given the banks mapped by the MBC
+    pub fn matches(&self, address: u16, rom_bank: u16, ram_bank: u8) -> bool {
+        if address != self.address {
+            return false;
+        }
+        match (self.bank, address) {
+            (Some(bank), ROM_BANKN_START..=ROM_REGION_END) => bank == rom_bank,
+            (Some(bank), ERAM_REGION_START..=ERAM_REGION_END) => bank == ram_bank as u16,
+            _ => true,
+        }
+    }
+}
+
+/// Fixed set of breakpoints
+pub struct Breakpoints {
+    list: [Option<Breakpoint>; MAX_BREAKPOINTS],
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self {
+            list: [None; MAX_BREAKPOINTS],
+        }
+    }
+
+    /// Returns false if the breakpoint cannot be added (already set or no room left)
+    pub fn add(&mut self, bp: Breakpoint) -> bool {
+        if self.list.contains(&Some(bp)) {
+            return false;
+        }
+        match self.list.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(bp);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns false if the breakpoint was not set
+    pub fn remove(&mut self, bp: Breakpoint) -> bool {
+        match self.list.iter_mut().find(|slot| **slot == Some(bp)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list = [None; MAX_BREAKPOINTS];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.iter().all(|slot| slot.is_none())
+    }
+
+    /// Find a breakpoint reached at this address
+    pub fn find(&self, address: u16, rom_bank: u16, ram_bank: u8) -> Option<Breakpoint> {
+        self.list.iter()
+            .flatten()
+            .find(|bp| bp.matches(address, rom_bank, ram_bank))
+            .copied()
+    }
+}
+
+/// Value sampled at the end of each frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watch {
+    Byte(u16),
+    /// Little endian 16-bit value at address and address + 1
+    Word(u16),
+}
+
+impl Watch {
+    /// Read the value with a function reading a byte at an address
+    pub fn sample<F: Fn(u16) -> u8>(&self, read: F) -> u16 {
+        match *self {
+            Watch::Byte(address) => read(address) as u16,
+            Watch::Word(address) => u16::from_le_bytes([read(address), read(address.wrapping_add(1))]),
+        }
+    }
+}
+
+/// Fixed set of watches and their last sampled values
+pub struct Watches {
+    list: [Option<(Watch, u16)>; MAX_WATCHES],
+}
+
+impl Watches {
+    pub fn new() -> Self {
+        Self {
+            list: [None; MAX_WATCHES],
+        }
+    }
+
+    /// Returns the slot of the watch, None if there is no room left
+    pub fn add(&mut self, watch: Watch, value: u16) -> Option<usize> {
+        let idx = self.list.iter().position(|slot| slot.is_none())?;
+        self.list[idx] = Some((watch, value));
+        Some(idx)
+    }
+
+    /// Returns false if the slot was empty
+    pub fn remove(&mut self, idx: usize) -> bool {
+        self.list.get_mut(idx).and_then(|slot| slot.take()).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.list = [None; MAX_WATCHES];
+    }
+
+    /// Retrieve the last value sampled in a slot
+    pub fn value(&self, idx: usize) -> Option<u16> {
+        self.list.get(idx).copied().flatten().map(|(_, value)| value)
+    }
+
+    /// Iterate over the watches and their last sampled values, with their slot
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Watch, u16)> + '_ {
+        self.list.iter()
+            .enumerate()
+            .filter_map(|(idx, slot)| slot.map(|(watch, value)| (idx, watch, value)))
+    }
+
+    /// Sample all values again
+    pub fn sample<F: Fn(u16) -> u8>(&mut self, read: F) {
+        for (watch, value) in self.list.iter_mut().flatten() {
+            *value = watch.sample(&read);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::vec::Vec;
+
+use crate::{FRAME_HEIGHT, FRAME_WIDTH, Pixel, Screen};
+
+/// File format of the dumped frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary portable pixmap (P6)
+    Ppm,
+    /// Uncompressed PNG
+    #[cfg(feature = "png")]
+    Png,
+}
+
+impl ImageFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            #[cfg(feature = "png")]
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// Screen writing every nth frame to a numbered file (frame_000000.ppm, frame_000001.ppm, ...)
+/// ```no_run
+/// use padme_core::default::{FrameDumper, NoSerial, NoSpeaker};
+/// use padme_core::{Rom, System};
+///
+/// # let mut bin = [0u8; 32 * 1024];
+/// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+/// // Keep 1 frame per second
+/// let dumper = FrameDumper::new("/tmp/frames", 60);
+/// let mut emu = System::new(rom, dumper, NoSerial, NoSpeaker);
+/// for _ in 0..600 {
+///     emu.update_frame();
+/// }
+/// if let Some(err) = emu.screen().take_error() {
+///     eprintln!("could not dump frames: {}", err);
+/// }
+/// ```
+pub struct FrameDumper {
+    /// Directory where frames are written
+    dir: PathBuf,
+    format: ImageFormat,
+    /// Write 1 frame out of `every`
+    every: u32,
+    /// Number of frames displayed
+    frames: u32,
+    pixels: Vec<Pixel>,
+    /// First error that occurred while writing a frame
+    error: Option<io::Error>,
+}
+
+impl FrameDumper {
+    /// Write 1 frame out of `every` to PPM files in dir
+    pub fn new<P: AsRef<Path>>(dir: P, every: u32) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            format: ImageFormat::Ppm,
+            every: every.max(1),
+            frames: 0,
+            pixels: vec![Pixel::default(); FRAME_WIDTH * FRAME_HEIGHT],
+            error: None,
+        }
+    }
+
+    /// Select the file format (default = Ppm)
+    pub fn set_format(&mut self, format: ImageFormat) {
+        self.format = format;
+    }
+
+    /// Retrieve the number of frames displayed so far
+    pub fn frame_count(&self) -> u32 {
+        self.frames
+    }
+
+    /// Retrieve the first error that occurred while writing a frame
+    /// No frame is written until the error is retrieved
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Write the current frame to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Write the current frame using the selected format
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self.format {
+            ImageFormat::Ppm => write_ppm(writer, &self.pixels),
+            #[cfg(feature = "png")]
+            ImageFormat::Png => write_png(writer, &self.pixels),
+        }
+    }
+}
+
+/// Write a frame as a binary PPM image
+/// ```
+/// use padme_core::{FRAME_HEIGHT, FRAME_WIDTH, Pixel};
+/// use padme_core::default::write_ppm;
+///
+/// let pixels = vec![Pixel::default(); FRAME_WIDTH * FRAME_HEIGHT];
+/// let mut out = Vec::new();
+/// write_ppm(&mut out, &pixels).unwrap();
+/// assert!(out.starts_with(b"P6\n160 144\n255\n"));
+/// ```
+pub fn write_ppm<W: Write>(writer: &mut W, pixels: &[Pixel]) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", FRAME_WIDTH, FRAME_HEIGHT)?;
+    let data: Vec<u8> = pixels.iter().flat_map(|px| [px.r, px.g, px.b]).collect();
+    writer.write_all(&data)
+}
+
+/// Write a frame as a PNG image
+/// The image data is stored without compression so that no dependency is needed
+#[cfg(feature = "png")]
+pub fn write_png<W: Write>(writer: &mut W, pixels: &[Pixel]) -> io::Result<()> {
+    // Each row starts with its filter type (0 = none)
+    let mut raw = Vec::with_capacity(FRAME_HEIGHT * (FRAME_WIDTH * 3 + 1));
+    for row in pixels.chunks(FRAME_WIDTH) {
+        raw.push(0);
+        raw.extend(row.iter().flat_map(|px| [px.r, px.g, px.b]));
+    }
+
+    // Zlib stream made of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(FRAME_WIDTH as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(FRAME_HEIGHT as u32).to_be_bytes());
+    // 8-bit depth, truecolor, deflate, no filter, no interlace
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    write_png_chunk(writer, b"IHDR", &ihdr)?;
+    write_png_chunk(writer, b"IDAT", &zlib)?;
+    write_png_chunk(writer, b"IEND", &[])
+}
+
+#[cfg(feature = "png")]
+fn write_png_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+#[cfg(feature = "png")]
+fn crc32<'a, I: Iterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(feature = "png")]
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+impl Screen for FrameDumper {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.pixels[y as usize * FRAME_WIDTH + x as usize] = *px;
+    }
+
+    fn update(&mut self) {
+        let frame = self.frames;
+        self.frames += 1;
+        if self.error.is_some() || !frame.is_multiple_of(self.every) {
+            return;
+        }
+        let name = format!("frame_{:06}.{}", frame / self.every, self.format.extension());
+        if let Err(err) = self.save(self.dir.join(name)) {
+            self.error = Some(err);
+        }
+    }
+}
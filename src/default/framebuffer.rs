@@ -0,0 +1,165 @@
+use core::mem;
+
+use crate::{DirtyLines, FRAME_HEIGHT, FRAME_WIDTH, Pixel, Screen, Transform};
+
+/// Integer factor applied by FrameBuffer::set_scaled_output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scale {
+    X2 = 2,
+    X3 = 3,
+    X4 = 4,
+}
+
+
+/// Screen keeping the pixels of the last frame as packed RGBA words (see Pixel::packed)
+/// Rows are copied in bulk and the buffer can be uploaded as is to a RGBA8 texture
+/// ```
+/// use padme_core::{FRAME_HEIGHT, FRAME_WIDTH, Rom, System};
+/// use padme_core::default::{FrameBuffer, NoSerial, NoSpeaker};
+///
+/// # let mut bin = [0u8; 32 * 1024];
+/// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+/// let mut emu = System::new(rom, FrameBuffer::new(), NoSerial, NoSpeaker);
+/// emu.update_frame();
+/// let pixels: &[u32] = emu.screen().as_u32_slice();
+/// assert_eq!(pixels.len(), FRAME_WIDTH * FRAME_HEIGHT);
+/// ```
+pub struct FrameBuffer {
+    pixels: [u32; FRAME_WIDTH * FRAME_HEIGHT],
+    /// Pixels per line, FRAME_HEIGHT when frames are rotated
+    width: usize,
+    /// Caller buffer receiving the frame scaled up
+    scaled: Option<&'static mut [u32]>,
+    scale: Scale,
+    /// Every line must be scaled on the next update
+    scale_all: bool,
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self {
+            pixels: [0u32; FRAME_WIDTH * FRAME_HEIGHT],
+            width: FRAME_WIDTH,
+            scaled: None,
+            scale: Scale::X2,
+            scale_all: false,
+        }
+    }
+
+    /// Lay out the pixels like the frames sent with this transform, see System::set_transform
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.width = transform.width();
+        self.scale_all = true;
+    }
+
+    /// Retrieve the number of pixels per line
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Retrieve all pixels, line by line
+    pub fn as_u32_slice(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Retrieve all pixels, line by line
+    pub fn as_mut_u32_slice(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// Retrieve the pixels of a line
+    pub fn row(&self, y: u8) -> &[u32] {
+        let start = y as usize * self.width;
+        &self.pixels[start..start + self.width]
+    }
+
+    /// Copy every frame scaled up with nearest neighbor into buffer, None to stop
+    /// The lines that changed are scaled when the frame ends, buffer is width * scale pixels wide
+    /// and must hold width * height * scale * scale pixels, extra lines are not written
+    /// Returns the previous buffer
+    /// ```
+    /// use padme_core::{FRAME_HEIGHT, FRAME_WIDTH, Rom, System};
+    /// use padme_core::default::{FrameBuffer, NoSerial, NoSpeaker, Scale};
+    ///
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut screen = FrameBuffer::new();
+    /// let scaled = Box::leak(vec![0u32; FRAME_WIDTH * FRAME_HEIGHT * 9].into_boxed_slice());
+    /// screen.set_scaled_output(Scale::X3, Some(scaled));
+    ///
+    /// let mut emu = System::new(rom, screen, NoSerial, NoSpeaker);
+    /// emu.update_frame();
+    /// let pixel = emu.screen().row(0)[0];
+    /// let scaled = emu.screen().set_scaled_output(Scale::X3, None).unwrap();
+    /// assert_eq!(scaled[FRAME_WIDTH * 3 * 2 + 2], pixel);
+    /// ```
+    pub fn set_scaled_output(&mut self, scale: Scale, buffer: Option<&'static mut [u32]>) -> Option<&'static mut [u32]> {
+        self.scale = scale;
+        self.scale_all = true;
+        mem::replace(&mut self.scaled, buffer)
+    }
+
+    /// Scale a line into the output buffer, the first scaled line is copied to the next ones
+    fn scale_line(&mut self, y: usize) {
+        let scaled = match self.scaled.as_mut() {
+            Some(scaled) => scaled,
+            None => return,
+        };
+        let factor = self.scale as usize;
+        let scaled_width = self.width * factor;
+        let start = y * factor * scaled_width;
+        if start + factor * scaled_width > scaled.len() {
+            return;
+        }
+
+        let row = &self.pixels[(y * self.width)..((y + 1) * self.width)];
+        let line = &mut scaled[start..(start + scaled_width)];
+        for (pixels, word) in line.chunks_exact_mut(factor).zip(row.iter()) {
+            pixels.fill(*word);
+        }
+        for i in 1..factor {
+            scaled.copy_within(start..(start + scaled_width), start + i * scaled_width);
+        }
+    }
+
+    /// Retrieve a single pixel
+    pub fn pixel(&self, x: u8, y: u8) -> Pixel {
+        Pixel::from_packed(self.pixels[y as usize * self.width + x as usize])
+    }
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen for FrameBuffer {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.pixels[y as usize * self.width + x as usize] = px.packed();
+    }
+
+    fn set_row(&mut self, y: u8, row: &[u32; FRAME_WIDTH]) {
+        let start = y as usize * FRAME_WIDTH;
+        self.pixels[start..start + FRAME_WIDTH].copy_from_slice(row);
+    }
+
+    fn update(&mut self) {
+    }
+
+    fn update_lines(&mut self, lines: &DirtyLines) {
+        if self.scaled.is_none() {
+            return;
+        }
+        // Rotated frames do not follow the lines of the Gameboy screen
+        if mem::take(&mut self.scale_all) || self.width != FRAME_WIDTH {
+            for y in 0..(self.pixels.len() / self.width) {
+                self.scale_line(y);
+            }
+        } else {
+            for y in lines.iter() {
+                self.scale_line(y as usize);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use core::cell::Cell;
+
+use crate::SerialOutput;
+
+/// Serial device sending back every byte it receives
+///
+/// ```
+/// use padme_core::SerialOutput;
+/// use padme_core::default::Loopback;
+///
+/// let mut link = Loopback;
+/// assert_eq!(link.transfer(0x42), 0x42);
+/// ```
+pub struct Loopback;
+
+impl SerialOutput for Loopback {
+    fn putchar(&mut self, _ch: u8) {
+    }
+
+    fn transfer(&mut self, byte: u8) -> u8 {
+        byte
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+/// In-memory cable shared by the two ends of a NullModem
+#[derive(Default)]
+pub struct NullModemChannel {
+    /// Byte each side waits to send to the other one's clock
+    ready: [Cell<Option<u8>>; 2],
+    /// Byte each side received from the other one's clock
+    inbox: [Cell<Option<u8>>; 2],
+}
+
+impl NullModemChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// One end of a cable linking two emulators in the same process
+///
+/// The side clocking a transfer receives the byte the other side is waiting to send,
+/// or 0xFF when the other side is not waiting for a transfer yet.
+///
+/// ```
+/// use padme_core::SerialOutput;
+/// use padme_core::default::{NullModem, NullModemChannel};
+///
+/// let channel = NullModemChannel::new();
+/// let (mut master, mut slave) = NullModem::pair(&channel);
+///
+/// // The slave waits with its byte, the master clocks the transfer
+/// assert_eq!(slave.poll_transfer(0x11), None);
+/// assert_eq!(master.transfer(0x22), 0x11);
+/// assert_eq!(slave.poll_transfer(0x11), Some(0x22));
+/// ```
+pub struct NullModem<'a> {
+    channel: &'a NullModemChannel,
+    side: usize,
+}
+
+impl<'a> NullModem<'a> {
+    /// Create both ends of the cable
+    pub fn pair(channel: &'a NullModemChannel) -> (Self, Self) {
+        (Self { channel, side: 0 }, Self { channel, side: 1 })
+    }
+}
+
+impl SerialOutput for NullModem<'_> {
+    fn putchar(&mut self, _ch: u8) {
+    }
+
+    fn transfer(&mut self, byte: u8) -> u8 {
+        let other = 1 - self.side;
+        match self.channel.ready[other].take() {
+            Some(received) => {
+                self.channel.inbox[other].set(Some(byte));
+                received
+            },
+            None => 0xFF,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn poll_transfer(&mut self, byte: u8) -> Option<u8> {
+        let received = self.channel.inbox[self.side].take();
+        // Keep the byte to send up to date until the other side clocks a transfer
+        self.channel.ready[self.side].set(if received.is_some() { None } else { Some(byte) });
+        received
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{AudioSpeaker, MemoryRegion, Pixel, Screen, SerialOutput};
+
+#[cfg(feature = "std")]
+mod dumper;
+mod framebuffer;
+mod link;
+#[cfg(feature = "resampler")]
+mod resampler;
+#[cfg(feature = "std")]
+mod terminal;
+#[cfg(feature = "std")]
+mod wav;
+mod writer;
+
+#[cfg(feature = "std")]
+pub use dumper::{FrameDumper, ImageFormat, write_ppm};
+#[cfg(feature = "png")]
+pub use dumper::write_png;
+pub use framebuffer::{FrameBuffer, Scale};
+pub use link::{Loopback, NullModem, NullModemChannel};
+#[cfg(feature = "resampler")]
+pub use resampler::{Interpolation, Resampler};
+#[cfg(feature = "std")]
+pub use terminal::{TerminalColors, TerminalScreen};
+#[cfg(feature = "std")]
+pub use wav::WavWriter;
+pub use writer::{PixelFormat, PixelWriter};
+
+pub struct NoScreen;
+
+impl Screen for NoScreen {
+    const HEADLESS: bool = true;
+
+    fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+    }
+
+    fn update(&mut self) {
+    }
+}
+
+pub struct NoSpeaker;
+
+impl AudioSpeaker for NoSpeaker {
+    fn set_samples(&mut self, _left: f32, _right: f32) {
+    }
+}
+
+pub struct NoSerial;
+
+impl SerialOutput for NoSerial {
+    fn putchar(&mut self, _ch: u8) {
+    }
+}
+
+/// Nothing plugged on the unused I/O ports, they stay unmapped
+pub struct NoExpansion;
+
+impl MemoryRegion for NoExpansion {
+    fn read(&self, _address: u16) -> u8 {
+        0xFF
+    }
+
+    fn write(&mut self, _address: u16, _value: u8) {
+    }
+}
@@ -0,0 +1,113 @@
+use crate::AudioSpeaker;
+
+/// Largest correction of the output rate applied by Resampler::correct_drift
+const MAX_DRIFT: f64                    = 0.005;
+
+/// How output samples are computed from the input samples around them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Closest input sample
+    Nearest,
+    /// Weighted average of the 2 input samples around the output sample
+    Linear,
+}
+
+/// Speaker converting the samples of the APU to another rate before sending them to output
+/// ```
+/// use padme_core::{AUDIO_SAMPLE_RATE, AudioSpeaker};
+/// use padme_core::default::Resampler;
+///
+/// struct Count(u32);
+///
+/// impl AudioSpeaker for Count {
+///     fn set_samples(&mut self, _left: f32, _right: f32) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let mut resampler = Resampler::new(AUDIO_SAMPLE_RATE as f64, 44100, Count(0));
+/// for _ in 0..AUDIO_SAMPLE_RATE {
+///     resampler.set_samples(0.0, 0.0);
+/// }
+/// assert!(resampler.output().0.abs_diff(44100) <= 1);
+/// ```
+pub struct Resampler<AS: AudioSpeaker> {
+    output: AS,
+    interpolation: Interpolation,
+    /// Input samples per output sample
+    step: f64,
+    /// Factor applied to step to follow the clock of the audio device
+    correction: f64,
+    /// Position of the next output sample after the previous input sample, in input samples
+    position: f64,
+    /// Last input samples received
+    previous: (f32, f32),
+}
+
+impl<AS: AudioSpeaker> Resampler<AS> {
+    /// Convert input_rate samples per second (see System::audio_sample_rate) to output_rate
+    pub fn new(input_rate: f64, output_rate: u32, output: AS) -> Self {
+        let mut resampler = Self {
+            output,
+            interpolation: Interpolation::Linear,
+            step: 1.0,
+            correction: 1.0,
+            position: 0.0,
+            previous: (0.0, 0.0),
+        };
+        resampler.set_rates(input_rate, output_rate);
+        resampler
+    }
+
+    /// Change the rates, e.g. after the emulation speed changed
+    pub fn set_rates(&mut self, input_rate: f64, output_rate: u32) {
+        if input_rate > 0.0 && output_rate > 0 {
+            self.step = input_rate / output_rate as f64;
+        }
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Compensate the drift between the emulation and the audio device clocks
+    /// with the fill level of the device buffer: 0.0 when empty, 1.0 when full
+    /// Less samples are produced when the buffer is more than half full, up to 0.5% less
+    pub fn correct_drift(&mut self, fill: f32) {
+        let fill = (fill as f64).clamp(0.0, 1.0);
+        self.correction = 1.0 + (fill - 0.5) * 2.0 * MAX_DRIFT;
+    }
+
+    pub fn output(&self) -> &AS {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut AS {
+        &mut self.output
+    }
+
+    pub fn into_inner(self) -> AS {
+        self.output
+    }
+}
+
+impl<AS: AudioSpeaker> AudioSpeaker for Resampler<AS> {
+    fn set_samples(&mut self, left: f32, right: f32) {
+        let (prev_left, prev_right) = self.previous;
+        // Output samples between the previous and this input sample
+        while self.position < 1.0 {
+            let t = self.position as f32;
+            match self.interpolation {
+                Interpolation::Nearest if t < 0.5 => self.output.set_samples(prev_left, prev_right),
+                Interpolation::Nearest => self.output.set_samples(left, right),
+                Interpolation::Linear => self.output.set_samples(
+                    prev_left + (left - prev_left) * t,
+                    prev_right + (right - prev_right) * t,
+                ),
+            }
+            self.position += self.step * self.correction;
+        }
+        self.position -= 1.0;
+        self.previous = (left, right);
+    }
+}
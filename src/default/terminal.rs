@@ -0,0 +1,117 @@
+use std::io::{self, Stdout, Write};
+use std::string::String;
+use std::vec::Vec;
+
+use crate::{FRAME_HEIGHT, FRAME_WIDTH, Pixel, Screen};
+
+/// Characters used from the lightest to the darkest shade in ascii mode
+const ASCII_SHADES: [char; 4]           = [' ', '.', '+', '#'];
+/// Upper half block, the foreground is the top pixel and the background the bottom one
+const HALF_BLOCK: char                  = '\u{2580}';
+
+/// How pixels are turned into characters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerminalColors {
+    /// 24-bit ANSI colors, 2 pixels per character using half blocks
+    TrueColor,
+    /// Plain characters based on the brightness of 2 pixels, for terminals without colors
+    Ascii,
+}
+
+/// Screen drawing each frame in a terminal
+/// Each character covers 2 lines, so a frame uses 160 columns and 72 rows
+/// ```
+/// use padme_core::Screen;
+/// use padme_core::default::TerminalScreen;
+///
+/// let mut screen = TerminalScreen::with_writer(Vec::new());
+/// screen.update();
+/// let output = String::from_utf8(screen.into_inner()).unwrap();
+/// assert_eq!(output.lines().count(), 72);
+/// ```
+pub struct TerminalScreen<W: Write = Stdout> {
+    writer: W,
+    colors: TerminalColors,
+    pixels: Vec<Pixel>,
+}
+
+impl TerminalScreen<Stdout> {
+    /// Draw frames to stdout
+    pub fn new() -> Self {
+        Self::with_writer(io::stdout())
+    }
+}
+
+impl Default for TerminalScreen<Stdout> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> TerminalScreen<W> {
+    /// Draw frames to any writer, e.g. a file or a socket
+    pub fn with_writer(writer: W) -> Self {
+        Self {
+            writer,
+            colors: TerminalColors::TrueColor,
+            pixels: vec![Pixel::default(); FRAME_WIDTH * FRAME_HEIGHT],
+        }
+    }
+
+    /// Select how pixels are turned into characters (default = TrueColor)
+    pub fn set_colors(&mut self, colors: TerminalColors) {
+        self.colors = colors;
+    }
+
+    /// Retrieve the writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Build the whole frame, starting from the top left corner of the terminal
+    fn draw(&self) -> String {
+        let mut out = String::with_capacity(FRAME_WIDTH * FRAME_HEIGHT * 20);
+        out.push_str("\x1b[H");
+
+        for y in (0..FRAME_HEIGHT).step_by(2) {
+            for x in 0..FRAME_WIDTH {
+                let top = &self.pixels[y * FRAME_WIDTH + x];
+                let bottom = &self.pixels[(y + 1) * FRAME_WIDTH + x];
+                match self.colors {
+                    TerminalColors::TrueColor => {
+                        out.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{}",
+                                              top.r, top.g, top.b,
+                                              bottom.r, bottom.g, bottom.b,
+                                              HALF_BLOCK));
+                    },
+                    TerminalColors::Ascii => {
+                        let luma = (brightness(top) + brightness(bottom)) / 2;
+                        out.push(ASCII_SHADES[3 - (luma as usize * ASCII_SHADES.len() / 256)]);
+                    },
+                }
+            }
+            if self.colors == TerminalColors::TrueColor {
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Perceived brightness (0 - 255)
+fn brightness(px: &Pixel) -> u32 {
+    (px.r as u32 * 299 + px.g as u32 * 587 + px.b as u32 * 114) / 1000
+}
+
+impl<W: Write> Screen for TerminalScreen<W> {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.pixels[y as usize * FRAME_WIDTH + x as usize] = *px;
+    }
+
+    fn update(&mut self) {
+        let frame = self.draw();
+        // Nothing to do if the terminal is gone
+        let _ = self.writer.write_all(frame.as_bytes()).and_then(|_| self.writer.flush());
+    }
+}
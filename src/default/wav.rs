@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{AUDIO_SAMPLE_RATE, AudioSpeaker};
+
+const CHANNELS: u16                     = 2;
+const BITS_PER_SAMPLE: u16              = 16;
+const BLOCK_ALIGN: u16                  = CHANNELS * BITS_PER_SAMPLE / 8;
+const HEADER_SIZE: u32                  = 44;
+/// Offset of the RIFF chunk size
+const RIFF_SIZE_OFFSET: u64             = 4;
+/// Offset of the data chunk size
+const DATA_SIZE_OFFSET: u64             = 40;
+/// WAVE_FORMAT_PCM
+const FORMAT_PCM: u16                   = 1;
+
+/// Speaker writing samples to a 16-bit stereo PCM .wav file
+/// The header sizes are written when the writer is finalized or dropped
+/// ```
+/// use std::io::Cursor;
+/// use padme_core::AudioSpeaker;
+/// use padme_core::default::WavWriter;
+///
+/// let mut wav = WavWriter::new(Cursor::new(Vec::new())).unwrap();
+/// wav.set_samples(0.5, -0.5);
+/// wav.finalize().unwrap();
+/// let bytes = wav.get_ref().get_ref();
+/// assert_eq!(&bytes[..4], b"RIFF");
+/// assert_eq!(bytes.len(), 44 + 4);
+/// ```
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    /// Number of stereo samples written
+    samples: u32,
+    /// First error that occurred while writing samples
+    error: Option<io::Error>,
+}
+
+impl WavWriter<BufWriter<File>> {
+    /// Create or truncate a .wav file
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Write the header, the sizes are only known once finalized
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let byte_rate = AUDIO_SAMPLE_RATE * BLOCK_ALIGN as u32;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16u32.to_le_bytes())?;
+        writer.write_all(&FORMAT_PCM.to_le_bytes())?;
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&AUDIO_SAMPLE_RATE.to_le_bytes())?;
+        writer.write_all(&byte_rate.to_le_bytes())?;
+        writer.write_all(&BLOCK_ALIGN.to_le_bytes())?;
+        writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        writer.write_all(b"data")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self { writer, samples: 0, error: None })
+    }
+
+    /// Retrieve the number of stereo samples written
+    pub fn sample_count(&self) -> u32 {
+        self.samples
+    }
+
+    /// Retrieve the writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Write the chunk sizes in the header
+    /// Returns the first error that occurred while writing samples, if any
+    pub fn finalize(&mut self) -> io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        let data_size = self.samples * BLOCK_ALIGN as u32;
+
+        self.writer.seek(SeekFrom::Start(RIFF_SIZE_OFFSET))?;
+        self.writer.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(DATA_SIZE_OFFSET))?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()
+    }
+
+    fn write_sample(&mut self, left: f32, right: f32) -> io::Result<()> {
+        self.writer.write_all(&to_pcm(left).to_le_bytes())?;
+        self.writer.write_all(&to_pcm(right).to_le_bytes())
+    }
+}
+
+/// Convert a sample from [-1.0, 1.0] to a signed 16-bit value
+fn to_pcm(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+impl<W: Write + Seek> AudioSpeaker for WavWriter<W> {
+    fn set_samples(&mut self, left: f32, right: f32) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.write_sample(left, right) {
+            Ok(()) => self.samples += 1,
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
+
+impl<W: Write + Seek> Drop for WavWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.finalize();
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{FRAME_WIDTH, Pixel, Screen};
+
+/// Layout of a pixel in a byte buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 4 bytes: red, green, blue, alpha
+    Rgba8888,
+    /// 4 bytes: blue, green, red, alpha
+    Bgra8888,
+    /// 3 bytes: red, green, blue
+    Rgb888,
+    /// 2 bytes, little endian Pixel::to_rgb565
+    Rgb565,
+}
+
+impl PixelFormat {
+    /// Number of bytes used by a pixel
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8888 | PixelFormat::Bgra8888 => 4,
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgb565 => 2,
+        }
+    }
+}
+
+/// Screen writing pixels into a byte buffer, e.g. a locked texture or a display framebuffer
+/// Lines start every `stride` bytes, which can be larger than the frame width for padded buffers
+/// Pixels that do not fit in the buffer are dropped
+/// ```
+/// use padme_core::{Pixel, Screen};
+/// use padme_core::default::{PixelFormat, PixelWriter};
+///
+/// let mut buffer = [0u8; 16 * 2];
+/// let mut writer = PixelWriter::new(&mut buffer, 16, PixelFormat::Bgra8888);
+/// writer.set_pixel(&Pixel::from_rgb(0x123456), 1, 1);
+/// assert_eq!(buffer[20..24], [0x56, 0x34, 0x12, 0xFF]);
+/// ```
+/// With Rgba8888 and no padding, rows are copied in bulk straight into the buffer borrowed by the system,
+/// e.g. the memory of an ImageData in a web page
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// #
+/// # let bin = [0u8; 0x150];
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut buffer = [0u8; FRAME_WIDTH * FRAME_HEIGHT * 4];
+/// let screen = PixelWriter::packed(&mut buffer, PixelFormat::Rgba8888);
+/// let mut emu = System::new(rom, screen, NoSerial, NoSpeaker);
+/// emu.update_frame();
+/// drop(emu);
+///
+/// // The screen is blank: lightest shade of the palette
+/// assert_eq!(buffer[0..4], palette::GRAYSCALE[0].packed().to_ne_bytes());
+/// ```
+pub struct PixelWriter<'a> {
+    buffer: &'a mut [u8],
+    stride: usize,
+    format: PixelFormat,
+}
+
+impl<'a> PixelWriter<'a> {
+    /// Write pixels in buffer, stride is the number of bytes per line
+    pub fn new(buffer: &'a mut [u8], stride: usize, format: PixelFormat) -> Self {
+        Self { buffer, stride, format }
+    }
+
+    /// Write pixels in a buffer without padding between lines
+    pub fn packed(buffer: &'a mut [u8], format: PixelFormat) -> Self {
+        Self::new(buffer, FRAME_WIDTH * format.bytes_per_pixel(), format)
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Retrieve the buffer
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.buffer
+    }
+
+    /// Write a pixel at the given coordinates
+    pub fn write(&mut self, px: &Pixel, x: usize, y: usize) {
+        let bpp = self.format.bytes_per_pixel();
+        let offset = y * self.stride + x * bpp;
+        if let Some(dest) = self.buffer.get_mut(offset..offset + bpp) {
+            match self.format {
+                PixelFormat::Rgba8888 => dest.copy_from_slice(&[px.r, px.g, px.b, px.a]),
+                PixelFormat::Bgra8888 => dest.copy_from_slice(&[px.b, px.g, px.r, px.a]),
+                PixelFormat::Rgb888 => dest.copy_from_slice(&[px.r, px.g, px.b]),
+                PixelFormat::Rgb565 => dest.copy_from_slice(&px.to_rgb565().to_le_bytes()),
+            }
+        }
+    }
+}
+
+impl Screen for PixelWriter<'_> {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.write(px, x as usize, y as usize);
+    }
+
+    fn set_row(&mut self, y: u8, row: &[u32; FRAME_WIDTH]) {
+        let start = y as usize * self.stride;
+        let len = FRAME_WIDTH * PixelFormat::Rgba8888.bytes_per_pixel();
+        // Packed pixels already have the memory layout of Rgba8888
+        if self.format == PixelFormat::Rgba8888 {
+            if let Some(dest) = self.buffer.get_mut(start..start + len) {
+                for (bytes, word) in dest.chunks_exact_mut(4).zip(row.iter()) {
+                    bytes.copy_from_slice(&word.to_ne_bytes());
+                }
+                return;
+            }
+        }
+        for (x, word) in row.iter().enumerate() {
+            self.write(&Pixel::from_packed(*word), x, y as usize);
+        }
+    }
+
+    fn update(&mut self) {
+    }
+}
@@ -0,0 +1,627 @@
+use core::fmt;
+
+use crate::SymbolTable;
+
+/// A single decoded instruction
+/// It only keeps the op code and its operands,
+/// so it can be displayed using the usual assembly notation
+/// ```
+/// use padme_core::Instruction;
+///
+/// let ins = Instruction::new(0x0100, [0xC3, 0x50, 0x01]);
+/// assert_eq!(ins.len(), 3);
+/// assert_eq!(format!("{}", ins), "JP $0150");
+/// ```
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    /// Address of the op code
+    address: u16,
+    /// Op code followed by the next 2 bytes
+    bytes: [u8; 3],
+}
+
+impl Instruction {
+    pub fn new(address: u16, bytes: [u8; 3]) -> Self {
+        Self { address, bytes }
+    }
+
+    /// Retrieve the address of the instruction
+    pub fn address(&self) -> u16 {
+        self.address
+    }
+
+    /// Retrieve the op code
+    pub fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    /// Retrieve the number of bytes used by the instruction (op code included)
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u8 {
+        match self.bytes[0] {
+            0x01 | 0x11 | 0x21 | 0x31 |
+            0x08 | 0xEA | 0xFA |
+            0xC2 | 0xC3 | 0xCA | 0xD2 | 0xDA |
+            0xC4 | 0xCC | 0xCD | 0xD4 | 0xDC => 3,
+            0x06 | 0x0E | 0x16 | 0x1E | 0x26 | 0x2E | 0x36 | 0x3E |
+            0x18 | 0x20 | 0x28 | 0x30 | 0x38 |
+            0xC6 | 0xCE | 0xD6 | 0xDE | 0xE6 | 0xEE | 0xF6 | 0xFE |
+            0xE0 | 0xF0 | 0xE8 | 0xF8 |
+            0x10 | 0xCB => 2,
+            _ => 1,
+        }
+    }
+
+    /// Retrieve the address used by a jump, a call or a direct memory access
+    /// ```
+    /// use padme_core::Instruction;
+    ///
+    /// assert_eq!(Instruction::new(0x0150, [0x18, 0xFE, 0x00]).target(), Some(0x0150));
+    /// assert_eq!(Instruction::new(0x0150, [0x00, 0x00, 0x00]).target(), None);
+    /// ```
+    pub fn target(&self) -> Option<u16> {
+        let op = self.bytes[0];
+        let next = self.bytes[1];
+
+        match op {
+            0xC3 | 0xC2 | 0xCA | 0xD2 | 0xDA |
+            0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC |
+            0xFA | 0xEA | 0x08 => Some(make_u16!(self.bytes[2], next)),
+            0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
+                Some(((self.address as i32 + 2) + ((next as i8) as i32)) as u16)
+            },
+            0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => Some((op & 0x38) as u16),
+            0xE0 | 0xF0 => Some(0xFF00 | next as u16),
+            _ => None,
+        }
+    }
+
+    /// Display the instruction followed by the label of its target
+    /// bank is the rom bank mapped at 0x4000-0x7FFF
+    /// ```
+    /// use padme_core::{Instruction, SymbolTable};
+    ///
+    /// let symbols = SymbolTable::parse("00:0150 Main\n00:0200 WaitVBlank").unwrap();
+    /// let ins = Instruction::new(0x0150, [0xCD, 0x03, 0x02]);
+    /// assert_eq!(format!("{}", ins.with_symbols(&symbols, 1)), "CALL $0203 ; 00:WaitVBlank+$3");
+    /// ```
+    pub fn with_symbols<'a>(&self, symbols: &'a SymbolTable<'a>, bank: u16) -> SymbolicInstruction<'a> {
+        SymbolicInstruction { ins: *self, symbols, bank }
+    }
+}
+
+/// An instruction displayed with symbols, see [`Instruction::with_symbols`]
+pub struct SymbolicInstruction<'a> {
+    ins: Instruction,
+    symbols: &'a SymbolTable<'a>,
+    bank: u16,
+}
+
+impl<'a> fmt::Display for SymbolicInstruction<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.ins)?;
+        if let Some(location) = self.ins.target().and_then(|target| self.symbols.resolve(self.bank, target)) {
+            write!(f, " ; {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = self.bytes[0];
+        let next = self.bytes[1];
+        let next16 = make_u16!(self.bytes[2], self.bytes[1]);
+        let rel_address = ((self.address as i32 + 2) + ((next as i8) as i32)) as u16;
+
+        match op {
+            0x00 => write!(f, "NOP"),
+            0x27 => write!(f, "DAA"),
+            0x2F => write!(f, "CPL"),
+            0x37 => write!(f, "SCF"),
+            0x3F => write!(f, "CCF"),
+            0x76 => write!(f, "HALT"),
+            0x10 => write!(f, "STOP"),
+            0x01 => write!(f, "LD BC, ${:04X}", next16),
+            0x11 => write!(f, "LD DE, ${:04X}", next16),
+            0x21 => write!(f, "LD HL, ${:04X}", next16),
+            0x31 => write!(f, "LD SP, ${:04X}", next16),
+            0x06 => write!(f, "LD B, ${:02X}", next),
+            0x0E => write!(f, "LD C, ${:02X}", next),
+            0x16 => write!(f, "LD D, ${:02X}", next),
+            0x1E => write!(f, "LD E, ${:02X}", next),
+            0x26 => write!(f, "LD H, ${:02X}", next),
+            0x2E => write!(f, "LD L, ${:02X}", next),
+            0x3E => write!(f, "LD A, ${:02X}", next),
+            0x40 => write!(f, "LD B, B"),
+            0x41 => write!(f, "LD B, C"),
+            0x42 => write!(f, "LD B, D"),
+            0x43 => write!(f, "LD B, E"),
+            0x44 => write!(f, "LD B, H"),
+            0x45 => write!(f, "LD B, L"),
+            0x46 => write!(f, "LD B, (HL)"),
+            0x47 => write!(f, "LD B, A"),
+            0x48 => write!(f, "LD C, B"),
+            0x49 => write!(f, "LD C, C"),
+            0x4A => write!(f, "LD C, D"),
+            0x4B => write!(f, "LD C, E"),
+            0x4C => write!(f, "LD C, H"),
+            0x4D => write!(f, "LD C, L"),
+            0x4E => write!(f, "LD C, (HL)"),
+            0x4F => write!(f, "LD C, A"),
+            0x50 => write!(f, "LD D, B"),
+            0x51 => write!(f, "LD D, C"),
+            0x52 => write!(f, "LD D, D"),
+            0x53 => write!(f, "LD D, E"),
+            0x54 => write!(f, "LD D, H"),
+            0x55 => write!(f, "LD D, L"),
+            0x56 => write!(f, "LD D, (HL)"),
+            0x57 => write!(f, "LD D, A"),
+            0x58 => write!(f, "LD E, B"),
+            0x59 => write!(f, "LD E, C"),
+            0x5A => write!(f, "LD E, D"),
+            0x5B => write!(f, "LD E, E"),
+            0x5C => write!(f, "LD E, H"),
+            0x5D => write!(f, "LD E, L"),
+            0x5E => write!(f, "LD E, (HL)"),
+            0x5F => write!(f, "LD E, A"),
+            0x60 => write!(f, "LD H, B"),
+            0x61 => write!(f, "LD H, C"),
+            0x62 => write!(f, "LD H, D"),
+            0x63 => write!(f, "LD H, E"),
+            0x64 => write!(f, "LD H, H"),
+            0x65 => write!(f, "LD H, L"),
+            0x66 => write!(f, "LD H, (HL)"),
+            0x67 => write!(f, "LD H, A"),
+            0x68 => write!(f, "LD L, B"),
+            0x69 => write!(f, "LD L, C"),
+            0x6A => write!(f, "LD L, D"),
+            0x6B => write!(f, "LD L, E"),
+            0x6C => write!(f, "LD L, H"),
+            0x6D => write!(f, "LD L, L"),
+            0x6E => write!(f, "LD L, (HL)"),
+            0x6F => write!(f, "LD L, A"),
+            0x78 => write!(f, "LD A, B"),
+            0x79 => write!(f, "LD A, C"),
+            0x7A => write!(f, "LD A, D"),
+            0x7B => write!(f, "LD A, E"),
+            0x7C => write!(f, "LD A, H"),
+            0x7D => write!(f, "LD A, L"),
+            0x7E => write!(f, "LD A, (HL)"),
+            0x7F => write!(f, "LD A, A"),
+            0x2A => write!(f, "LD A, (HL+)"),
+            0x3A => write!(f, "LD A, (HL-)"),
+            0x0A => write!(f, "LD A, (BC)"),
+            0x1A => write!(f, "LD A, (DE)"),
+            0xFA => write!(f, "LD A, (${:04X})", next16),
+            0xEA => write!(f, "LD (${:04X}), A", next16),
+            0x36 => write!(f, "LD (HL), ${:02X}", next),
+            0x70 => write!(f, "LD (HL), B"),
+            0x71 => write!(f, "LD (HL), C"),
+            0x72 => write!(f, "LD (HL), D"),
+            0x73 => write!(f, "LD (HL), E"),
+            0x74 => write!(f, "LD (HL), H"),
+            0x75 => write!(f, "LD (HL), L"),
+            0x77 => write!(f, "LD (HL), A"),
+            0x02 => write!(f, "LD (BC), A"),
+            0x12 => write!(f, "LD (DE), A"),
+            0x22 => write!(f, "LD (HL+), A"),
+            0x32 => write!(f, "LD (HL-), A"),
+            0xE0 => write!(f, "LD ($FF00 + ${:02X}), A", next),
+            0xF0 => write!(f, "LD A, ($FF00 + ${:02X})", next),
+            0xE2 => write!(f, "LD ($FF00 + C), A"),
+            0xF2 => write!(f, "LD A, ($FF00 + C)"),
+            0xF8 => write!(f, "LD HL, SP + ${:02X}", next),
+            0x08 => write!(f, "LD (${:04X}), SP", next16),
+            0xF9 => write!(f, "LD SP, HL"),
+            0xF5 => write!(f, "PUSH AF"),
+            0xC5 => write!(f, "PUSH BC"),
+            0xD5 => write!(f, "PUSH DE"),
+            0xE5 => write!(f, "PUSH HL"),
+            0xF1 => write!(f, "POP AF"),
+            0xC1 => write!(f, "POP BC"),
+            0xD1 => write!(f, "POP DE"),
+            0xE1 => write!(f, "POP HL"),
+            0xC3 => write!(f, "JP ${:04X}", next16),
+            0xC2 => write!(f, "JP NZ, ${:04X}", next16),
+            0xCA => write!(f, "JP Z, ${:04X}", next16),
+            0xD2 => write!(f, "JP NC, ${:04X}", next16),
+            0xDA => write!(f, "JP C, ${:04X}", next16),
+            0xE9 => write!(f, "JP (HL)"),
+            0x18 => write!(f, "JR ${:04X}", rel_address),
+            0x20 => write!(f, "JR NZ, ${:04X}", rel_address),
+            0x28 => write!(f, "JR Z, ${:04X}", rel_address),
+            0x30 => write!(f, "JR NC, ${:04X}", rel_address),
+            0x38 => write!(f, "JR C, ${:04X}", rel_address),
+            0xCD => write!(f, "CALL ${:04X}", next16),
+            0xC4 => write!(f, "CALL NZ, ${:04X}", next16),
+            0xCC => write!(f, "CALL Z, ${:04X}", next16),
+            0xD4 => write!(f, "CALL NC, ${:04X}", next16),
+            0xDC => write!(f, "CALL C, ${:04X}", next16),
+            0xC7 => write!(f, "RST ${:04X}", 0x00u16),
+            0xCF => write!(f, "RST ${:04X}", 0x08u16),
+            0xD7 => write!(f, "RST ${:04X}", 0x10u16),
+            0xDF => write!(f, "RST ${:04X}", 0x18u16),
+            0xE7 => write!(f, "RST ${:04X}", 0x20u16),
+            0xEF => write!(f, "RST ${:04X}", 0x28u16),
+            0xF7 => write!(f, "RST ${:04X}", 0x30u16),
+            0xFF => write!(f, "RST ${:04X}", 0x38u16),
+            0xC9 => write!(f, "RET"),
+            0xC0 => write!(f, "RET NZ"),
+            0xC8 => write!(f, "RET Z"),
+            0xD0 => write!(f, "RET NC"),
+            0xD8 => write!(f, "RET C"),
+            0xD9 => write!(f, "RETI"),
+            0x87 => write!(f, "ADD A, A"),
+            0x80 => write!(f, "ADD A, B"),
+            0x81 => write!(f, "ADD A, C"),
+            0x82 => write!(f, "ADD A, D"),
+            0x83 => write!(f, "ADD A, E"),
+            0x84 => write!(f, "ADD A, H"),
+            0x85 => write!(f, "ADD A, L"),
+            0x86 => write!(f, "ADD A, (HL)"),
+            0xC6 => write!(f, "ADD A, ${:02X}", next),
+            0x8F => write!(f, "ADC A, A"),
+            0x88 => write!(f, "ADC A, B"),
+            0x89 => write!(f, "ADC A, C"),
+            0x8A => write!(f, "ADC A, D"),
+            0x8B => write!(f, "ADC A, E"),
+            0x8C => write!(f, "ADC A, H"),
+            0x8D => write!(f, "ADC A, L"),
+            0x8E => write!(f, "ADC A, (HL)"),
+            0xCE => write!(f, "ADC A, ${:02X}", next),
+            0x97 => write!(f, "SUB A, A"),
+            0x90 => write!(f, "SUB A, B"),
+            0x91 => write!(f, "SUB A, C"),
+            0x92 => write!(f, "SUB A, D"),
+            0x93 => write!(f, "SUB A, E"),
+            0x94 => write!(f, "SUB A, H"),
+            0x95 => write!(f, "SUB A, L"),
+            0x96 => write!(f, "SUB A, (HL)"),
+            0xD6 => write!(f, "SUB A, ${:02X}", next),
+            0x9F => write!(f, "SBC A, A"),
+            0x98 => write!(f, "SBC A, B"),
+            0x99 => write!(f, "SBC A, C"),
+            0x9A => write!(f, "SBC A, D"),
+            0x9B => write!(f, "SBC A, E"),
+            0x9C => write!(f, "SBC A, H"),
+            0x9D => write!(f, "SBC A, L"),
+            0x9E => write!(f, "SBC A, (HL)"),
+            0xDE => write!(f, "SBC A, ${:02X}", next),
+            0xA7 => write!(f, "AND A"),
+            0xA0 => write!(f, "AND B"),
+            0xA1 => write!(f, "AND C"),
+            0xA2 => write!(f, "AND D"),
+            0xA3 => write!(f, "AND E"),
+            0xA4 => write!(f, "AND H"),
+            0xA5 => write!(f, "AND L"),
+            0xA6 => write!(f, "AND (HL)"),
+            0xE6 => write!(f, "AND ${:02X}", next),
+            0xB7 => write!(f, "OR A"),
+            0xB0 => write!(f, "OR B"),
+            0xB1 => write!(f, "OR C"),
+            0xB2 => write!(f, "OR D"),
+            0xB3 => write!(f, "OR E"),
+            0xB4 => write!(f, "OR H"),
+            0xB5 => write!(f, "OR L"),
+            0xB6 => write!(f, "OR (HL)"),
+            0xF6 => write!(f, "OR ${:02X}", next),
+            0xAF => write!(f, "XOR A"),
+            0xA8 => write!(f, "XOR B"),
+            0xA9 => write!(f, "XOR C"),
+            0xAA => write!(f, "XOR D"),
+            0xAB => write!(f, "XOR E"),
+            0xAC => write!(f, "XOR H"),
+            0xAD => write!(f, "XOR L"),
+            0xAE => write!(f, "XOR (HL)"),
+            0xEE => write!(f, "XOR ${:02X}", next),
+            0xBF => write!(f, "CP A"),
+            0xB8 => write!(f, "CP B"),
+            0xB9 => write!(f, "CP C"),
+            0xBA => write!(f, "CP D"),
+            0xBB => write!(f, "CP E"),
+            0xBC => write!(f, "CP H"),
+            0xBD => write!(f, "CP L"),
+            0xBE => write!(f, "CP (HL)"),
+            0xFE => write!(f, "CP ${:02X}", next),
+            0x3C => write!(f, "INC A"),
+            0x04 => write!(f, "INC B"),
+            0x0C => write!(f, "INC C"),
+            0x14 => write!(f, "INC D"),
+            0x1C => write!(f, "INC E"),
+            0x24 => write!(f, "INC H"),
+            0x2C => write!(f, "INC L"),
+            0x34 => write!(f, "INC (HL)"),
+            0x3D => write!(f, "DEC A"),
+            0x05 => write!(f, "DEC B"),
+            0x0D => write!(f, "DEC C"),
+            0x15 => write!(f, "DEC D"),
+            0x1D => write!(f, "DEC E"),
+            0x25 => write!(f, "DEC H"),
+            0x2D => write!(f, "DEC L"),
+            0x35 => write!(f, "DEC (HL)"),
+            0x09 => write!(f, "ADD HL, BC"),
+            0x19 => write!(f, "ADD HL, DE"),
+            0x29 => write!(f, "ADD HL, HL"),
+            0x39 => write!(f, "ADD HL, SP"),
+            0xE8 => write!(f, "ADD SP, ${:02X}", next as i8),
+            0x03 => write!(f, "INC BC"),
+            0x13 => write!(f, "INC DE"),
+            0x23 => write!(f, "INC HL"),
+            0x33 => write!(f, "INC SP"),
+            0x0B => write!(f, "DEC BC"),
+            0x1B => write!(f, "DEC DE"),
+            0x2B => write!(f, "DEC HL"),
+            0x3B => write!(f, "DEC SP"),
+            0xF3 => write!(f, "DI"),
+            0xFB => write!(f, "EI"),
+            0x07 => write!(f, "RLCA"),
+            0x17 => write!(f, "RLA"),
+            0x0F => write!(f, "RRCA"),
+            0x1F => write!(f, "RRA"),
+            0xCB => {
+                let op2 = next;
+
+                match op2 {
+                    0x37 => write!(f, "SWAP A"),
+                    0x30 => write!(f, "SWAP B"),
+                    0x31 => write!(f, "SWAP C"),
+                    0x32 => write!(f, "SWAP D"),
+                    0x33 => write!(f, "SWAP E"),
+                    0x34 => write!(f, "SWAP H"),
+                    0x35 => write!(f, "SWAP L"),
+                    0x36 => write!(f, "SWAP (HL)"),
+                    0x07 => write!(f, "RLC A"),
+                    0x00 => write!(f, "RLC B"),
+                    0x01 => write!(f, "RLC C"),
+                    0x02 => write!(f, "RLC D"),
+                    0x03 => write!(f, "RLC E"),
+                    0x04 => write!(f, "RLC H"),
+                    0x05 => write!(f, "RLC L"),
+                    0x06 => write!(f, "RLC (HL)"),
+                    0x17 => write!(f, "RL A"),
+                    0x10 => write!(f, "RL B"),
+                    0x11 => write!(f, "RL C"),
+                    0x12 => write!(f, "RL D"),
+                    0x13 => write!(f, "RL E"),
+                    0x14 => write!(f, "RL H"),
+                    0x15 => write!(f, "RL L"),
+                    0x16 => write!(f, "RL (HL)"),
+                    0x0F => write!(f, "RRC A"),
+                    0x08 => write!(f, "RRC B"),
+                    0x09 => write!(f, "RRC C"),
+                    0x0A => write!(f, "RRC D"),
+                    0x0B => write!(f, "RRC E"),
+                    0x0C => write!(f, "RRC H"),
+                    0x0D => write!(f, "RRC L"),
+                    0x0E => write!(f, "RRC (HL)"),
+                    0x1F => write!(f, "RR A"),
+                    0x18 => write!(f, "RR B"),
+                    0x19 => write!(f, "RR C"),
+                    0x1A => write!(f, "RR D"),
+                    0x1B => write!(f, "RR E"),
+                    0x1C => write!(f, "RR H"),
+                    0x1D => write!(f, "RR L"),
+                    0x1E => write!(f, "RR (HL)"),
+                    0x27 => write!(f, "SLA A"),
+                    0x20 => write!(f, "SLA B"),
+                    0x21 => write!(f, "SLA C"),
+                    0x22 => write!(f, "SLA D"),
+                    0x23 => write!(f, "SLA E"),
+                    0x24 => write!(f, "SLA H"),
+                    0x25 => write!(f, "SLA L"),
+                    0x26 => write!(f, "SLA (HL)"),
+                    0x2F => write!(f, "SRA A"),
+                    0x28 => write!(f, "SRA B"),
+                    0x29 => write!(f, "SRA C"),
+                    0x2A => write!(f, "SRA D"),
+                    0x2B => write!(f, "SRA E"),
+                    0x2C => write!(f, "SRA H"),
+                    0x2D => write!(f, "SRA L"),
+                    0x2E => write!(f, "SRA (HL)"),
+                    0x3F => write!(f, "SRL A"),
+                    0x38 => write!(f, "SRL B"),
+                    0x39 => write!(f, "SRL C"),
+                    0x3A => write!(f, "SRL D"),
+                    0x3B => write!(f, "SRL E"),
+                    0x3C => write!(f, "SRL H"),
+                    0x3D => write!(f, "SRL L"),
+                    0x3E => write!(f, "SRL (HL)"),
+                    0x47 => write!(f, "BIT 0, A"),
+                    0x40 => write!(f, "BIT 0, B"),
+                    0x41 => write!(f, "BIT 0, C"),
+                    0x42 => write!(f, "BIT 0, D"),
+                    0x43 => write!(f, "BIT 0, E"),
+                    0x44 => write!(f, "BIT 0, H"),
+                    0x45 => write!(f, "BIT 0, L"),
+                    0x46 => write!(f, "BIT 0, (HL)"),
+                    0x4F => write!(f, "BIT 1, A"),
+                    0x48 => write!(f, "BIT 1, B"),
+                    0x49 => write!(f, "BIT 1, C"),
+                    0x4A => write!(f, "BIT 1, D"),
+                    0x4B => write!(f, "BIT 1, E"),
+                    0x4C => write!(f, "BIT 1, H"),
+                    0x4D => write!(f, "BIT 1, L"),
+                    0x4E => write!(f, "BIT 1, (HL)"),
+                    0x57 => write!(f, "BIT 2, A"),
+                    0x50 => write!(f, "BIT 2, B"),
+                    0x51 => write!(f, "BIT 2, C"),
+                    0x52 => write!(f, "BIT 2, D"),
+                    0x53 => write!(f, "BIT 2, E"),
+                    0x54 => write!(f, "BIT 2, H"),
+                    0x55 => write!(f, "BIT 2, L"),
+                    0x56 => write!(f, "BIT 2, (HL)"),
+                    0x5F => write!(f, "BIT 3, A"),
+                    0x58 => write!(f, "BIT 3, B"),
+                    0x59 => write!(f, "BIT 3, C"),
+                    0x5A => write!(f, "BIT 3, D"),
+                    0x5B => write!(f, "BIT 3, E"),
+                    0x5C => write!(f, "BIT 3, H"),
+                    0x5D => write!(f, "BIT 3, L"),
+                    0x5E => write!(f, "BIT 3, (HL)"),
+                    0x67 => write!(f, "BIT 4, A"),
+                    0x60 => write!(f, "BIT 4, B"),
+                    0x61 => write!(f, "BIT 4, C"),
+                    0x62 => write!(f, "BIT 4, D"),
+                    0x63 => write!(f, "BIT 4, E"),
+                    0x64 => write!(f, "BIT 4, H"),
+                    0x65 => write!(f, "BIT 4, L"),
+                    0x66 => write!(f, "BIT 4, (HL)"),
+                    0x6F => write!(f, "BIT 5, A"),
+                    0x68 => write!(f, "BIT 5, B"),
+                    0x69 => write!(f, "BIT 5, C"),
+                    0x6A => write!(f, "BIT 5, D"),
+                    0x6B => write!(f, "BIT 5, E"),
+                    0x6C => write!(f, "BIT 5, H"),
+                    0x6D => write!(f, "BIT 5, L"),
+                    0x6E => write!(f, "BIT 5, (HL)"),
+                    0x77 => write!(f, "BIT 6, A"),
+                    0x70 => write!(f, "BIT 6, B"),
+                    0x71 => write!(f, "BIT 6, C"),
+                    0x72 => write!(f, "BIT 6, D"),
+                    0x73 => write!(f, "BIT 6, E"),
+                    0x74 => write!(f, "BIT 6, H"),
+                    0x75 => write!(f, "BIT 6, L"),
+                    0x76 => write!(f, "BIT 6, (HL)"),
+                    0x7F => write!(f, "BIT 7, A"),
+                    0x78 => write!(f, "BIT 7, B"),
+                    0x79 => write!(f, "BIT 7, C"),
+                    0x7A => write!(f, "BIT 7, D"),
+                    0x7B => write!(f, "BIT 7, E"),
+                    0x7C => write!(f, "BIT 7, H"),
+                    0x7D => write!(f, "BIT 7, L"),
+                    0x7E => write!(f, "BIT 7, (HL)"),
+                    0x87 => write!(f, "RES 0, A"),
+                    0x80 => write!(f, "RES 0, B"),
+                    0x81 => write!(f, "RES 0, C"),
+                    0x82 => write!(f, "RES 0, D"),
+                    0x83 => write!(f, "RES 0, E"),
+                    0x84 => write!(f, "RES 0, H"),
+                    0x85 => write!(f, "RES 0, L"),
+                    0x86 => write!(f, "RES 0, (HL)"),
+                    0x8F => write!(f, "RES 1, A"),
+                    0x88 => write!(f, "RES 1, B"),
+                    0x89 => write!(f, "RES 1, C"),
+                    0x8A => write!(f, "RES 1, D"),
+                    0x8B => write!(f, "RES 1, E"),
+                    0x8C => write!(f, "RES 1, H"),
+                    0x8D => write!(f, "RES 1, L"),
+                    0x8E => write!(f, "RES 1, (HL)"),
+                    0x97 => write!(f, "RES 2, A"),
+                    0x90 => write!(f, "RES 2, B"),
+                    0x91 => write!(f, "RES 2, C"),
+                    0x92 => write!(f, "RES 2, D"),
+                    0x93 => write!(f, "RES 2, E"),
+                    0x94 => write!(f, "RES 2, H"),
+                    0x95 => write!(f, "RES 2, L"),
+                    0x96 => write!(f, "RES 2, (HL)"),
+                    0x9F => write!(f, "RES 3, A"),
+                    0x98 => write!(f, "RES 3, B"),
+                    0x99 => write!(f, "RES 3, C"),
+                    0x9A => write!(f, "RES 3, D"),
+                    0x9B => write!(f, "RES 3, E"),
+                    0x9C => write!(f, "RES 3, H"),
+                    0x9D => write!(f, "RES 3, L"),
+                    0x9E => write!(f, "RES 3, (HL)"),
+                    0xA7 => write!(f, "RES 4, A"),
+                    0xA0 => write!(f, "RES 4, B"),
+                    0xA1 => write!(f, "RES 4, C"),
+                    0xA2 => write!(f, "RES 4, D"),
+                    0xA3 => write!(f, "RES 4, E"),
+                    0xA4 => write!(f, "RES 4, H"),
+                    0xA5 => write!(f, "RES 4, L"),
+                    0xA6 => write!(f, "RES 4, (HL)"),
+                    0xAF => write!(f, "RES 5, A"),
+                    0xA8 => write!(f, "RES 5, B"),
+                    0xA9 => write!(f, "RES 5, C"),
+                    0xAA => write!(f, "RES 5, D"),
+                    0xAB => write!(f, "RES 5, E"),
+                    0xAC => write!(f, "RES 5, H"),
+                    0xAD => write!(f, "RES 5, L"),
+                    0xAE => write!(f, "RES 5, (HL)"),
+                    0xB7 => write!(f, "RES 6, A"),
+                    0xB0 => write!(f, "RES 6, B"),
+                    0xB1 => write!(f, "RES 6, C"),
+                    0xB2 => write!(f, "RES 6, D"),
+                    0xB3 => write!(f, "RES 6, E"),
+                    0xB4 => write!(f, "RES 6, H"),
+                    0xB5 => write!(f, "RES 6, L"),
+                    0xB6 => write!(f, "RES 6, (HL)"),
+                    0xBF => write!(f, "RES 7, A"),
+                    0xB8 => write!(f, "RES 7, B"),
+                    0xB9 => write!(f, "RES 7, C"),
+                    0xBA => write!(f, "RES 7, D"),
+                    0xBB => write!(f, "RES 7, E"),
+                    0xBC => write!(f, "RES 7, H"),
+                    0xBD => write!(f, "RES 7, L"),
+                    0xBE => write!(f, "RES 7, (HL)"),
+                    0xC7 => write!(f, "SET 0, A"),
+                    0xC0 => write!(f, "SET 0, B"),
+                    0xC1 => write!(f, "SET 0, C"),
+                    0xC2 => write!(f, "SET 0, D"),
+                    0xC3 => write!(f, "SET 0, E"),
+                    0xC4 => write!(f, "SET 0, H"),
+                    0xC5 => write!(f, "SET 0, L"),
+                    0xC6 => write!(f, "SET 0, (HL)"),
+                    0xCF => write!(f, "SET 1, A"),
+                    0xC8 => write!(f, "SET 1, B"),
+                    0xC9 => write!(f, "SET 1, C"),
+                    0xCA => write!(f, "SET 1, D"),
+                    0xCB => write!(f, "SET 1, E"),
+                    0xCC => write!(f, "SET 1, H"),
+                    0xCD => write!(f, "SET 1, L"),
+                    0xCE => write!(f, "SET 1, (HL)"),
+                    0xD7 => write!(f, "SET 2, A"),
+                    0xD0 => write!(f, "SET 2, B"),
+                    0xD1 => write!(f, "SET 2, C"),
+                    0xD2 => write!(f, "SET 2, D"),
+                    0xD3 => write!(f, "SET 2, E"),
+                    0xD4 => write!(f, "SET 2, H"),
+                    0xD5 => write!(f, "SET 2, L"),
+                    0xD6 => write!(f, "SET 2, (HL)"),
+                    0xDF => write!(f, "SET 3, A"),
+                    0xD8 => write!(f, "SET 3, B"),
+                    0xD9 => write!(f, "SET 3, C"),
+                    0xDA => write!(f, "SET 3, D"),
+                    0xDB => write!(f, "SET 3, E"),
+                    0xDC => write!(f, "SET 3, H"),
+                    0xDD => write!(f, "SET 3, L"),
+                    0xDE => write!(f, "SET 3, (HL)"),
+                    0xE7 => write!(f, "SET 4, A"),
+                    0xE0 => write!(f, "SET 4, B"),
+                    0xE1 => write!(f, "SET 4, C"),
+                    0xE2 => write!(f, "SET 4, D"),
+                    0xE3 => write!(f, "SET 4, E"),
+                    0xE4 => write!(f, "SET 4, H"),
+                    0xE5 => write!(f, "SET 4, L"),
+                    0xE6 => write!(f, "SET 4, (HL)"),
+                    0xEF => write!(f, "SET 5, A"),
+                    0xE8 => write!(f, "SET 5, B"),
+                    0xE9 => write!(f, "SET 5, C"),
+                    0xEA => write!(f, "SET 5, D"),
+                    0xEB => write!(f, "SET 5, E"),
+                    0xEC => write!(f, "SET 5, H"),
+                    0xED => write!(f, "SET 5, L"),
+                    0xEE => write!(f, "SET 5, (HL)"),
+                    0xF7 => write!(f, "SET 6, A"),
+                    0xF0 => write!(f, "SET 6, B"),
+                    0xF1 => write!(f, "SET 6, C"),
+                    0xF2 => write!(f, "SET 6, D"),
+                    0xF3 => write!(f, "SET 6, E"),
+                    0xF4 => write!(f, "SET 6, H"),
+                    0xF5 => write!(f, "SET 6, L"),
+                    0xF6 => write!(f, "SET 6, (HL)"),
+                    0xFF => write!(f, "SET 7, A"),
+                    0xF8 => write!(f, "SET 7, B"),
+                    0xF9 => write!(f, "SET 7, C"),
+                    0xFA => write!(f, "SET 7, D"),
+                    0xFB => write!(f, "SET 7, E"),
+                    0xFC => write!(f, "SET 7, H"),
+                    0xFD => write!(f, "SET 7, L"),
+                    0xFE => write!(f, "SET 7, (HL)"),
+                }
+            },
+            _ => write!(f, "??? ${:02X}", op),
+        }
+    }
+}
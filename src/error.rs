@@ -1,10 +1,54 @@
+use core::fmt;
+
 use log::warn;
 
-#[cfg_attr(debug_assertions, derive(Debug))]
+use crate::CartridgeType;
+
+#[derive(Debug)]
 pub enum Error {
     InvalidRomSize(usize),
+    /// Header checksum stored in the rom and the one computed from the header
+    InvalidHeaderChecksum(u8, u8),
+    /// Global checksum stored in the rom and the one computed from the whole rom
+    InvalidGlobalChecksum(u16, u16),
+    /// Offset of the first logo byte that does not match
+    InvalidLogo(usize),
+    /// Line number of a malformed symbol
+    InvalidSymbol(usize),
+    /// The name does not match any button
+    InvalidButton,
+    /// The rom uses a memory bank controller that is not supported yet
+    UnsupportedCartridge(CartridgeType),
+}
+
+/// ```
+/// use padme_core::{CartridgeType, Error};
+///
+/// assert_eq!(format!("{}", Error::UnsupportedCartridge(CartridgeType::Mbc5RamBattery)),
+///            "unsupported cartridge: MBC5+RAM+BATTERY");
+/// ```
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidRomSize(size) => write!(f, "invalid rom size: {} bytes", size),
+            Error::InvalidHeaderChecksum(stored, computed) => {
+                write!(f, "invalid header checksum: 0x{:02X}, expected 0x{:02X}", stored, computed)
+            },
+            Error::InvalidGlobalChecksum(stored, computed) => {
+                write!(f, "invalid global checksum: 0x{:04X}, expected 0x{:04X}", stored, computed)
+            },
+            Error::InvalidLogo(offset) => write!(f, "invalid logo byte at 0x{:04X}", offset),
+            Error::InvalidSymbol(line) => write!(f, "invalid symbol at line {}", line),
+            Error::InvalidButton => write!(f, "invalid button name"),
+            Error::UnsupportedCartridge(cartridge_type) => {
+                write!(f, "unsupported cartridge: {}", cartridge_type)
+            },
+        }
+    }
 }
 
+impl core::error::Error for Error {}
+
 macro_rules! io_error {
     ($addr: expr, $read: expr) => {
         warn!("Cannot {} @ 0x{:04X}", if $read { "read" } else { "write" }, $addr)
@@ -0,0 +1,136 @@
+use core::slice;
+
+use crate::interrupt::InterruptFlag;
+use crate::ppu::PpuMode;
+
+/// Maximum number of events kept per frame, the next ones are only counted
+pub const MAX_FRAME_EVENTS: usize       = 1024;
+
+/// Something that happened during a frame, see System::set_event_log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// The CPU jumped to the handler of this interrupt
+    Interrupt(InterruptFlag),
+    /// A serial transfer completed
+    Serial { sent: u8, received: u8 },
+    /// An OAM DMA transfer was started from this page (source / 0x100)
+    DmaStart(u8),
+    /// The PPU entered a mode on line ly
+    ModeChange { mode: PpuMode, ly: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameEvent {
+    /// Cycles since the start of the frame, at the end of the instruction for CPU events
+    pub cycle: u32,
+    pub kind: EventKind,
+}
+
+/// Events of the last complete frame, in order
+/// ```
+/// use padme_core::*;
+/// use padme_core::default::*;
+///
+/// let mut bin = [0u8; 0x150];
+/// // RETI
+/// bin[0x40] = 0xD9;
+/// // LD A,$01 ; LDH (IE),A ; LD A,$C0 ; LDH (DMA),A ; EI ; JR -2
+/// bin[0x100..0x10B].copy_from_slice(&[0x3E, 0x01, 0xE0, 0xFF, 0x3E, 0xC0, 0xE0, 0x46, 0xFB, 0x18, 0xFE]);
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// emu.set_event_log(true);
+/// emu.update_frame();
+///
+/// let events = emu.frame_events().unwrap();
+/// assert!(events.iter().any(|event| event.kind == EventKind::DmaStart(0xC0)));
+/// let vblank = events.iter().position(|event| event.kind == EventKind::ModeChange { mode: PpuMode::VBlank, ly: 144 });
+/// let handler = events.iter().rposition(|event| event.kind == EventKind::Interrupt(InterruptFlag::Vblank));
+/// assert!(vblank.unwrap() < handler.unwrap());
+/// assert_eq!(events.dropped(), 0);
+/// ```
+pub struct FrameEvents {
+    events: [FrameEvent; MAX_FRAME_EVENTS],
+    len: usize,
+    /// Events that did not fit
+    dropped: u32,
+    /// Cycles executed in the frame being recorded
+    cycle: u32,
+    /// The frame is complete, the events are cleared when the next one starts
+    complete: bool,
+}
+
+impl FrameEvents {
+    pub fn new() -> Self {
+        Self {
+            events: [FrameEvent { cycle: 0, kind: EventKind::DmaStart(0) }; MAX_FRAME_EVENTS],
+            len: 0,
+            dropped: 0,
+            cycle: 0,
+            complete: false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+        self.dropped = 0;
+        self.cycle = 0;
+        self.complete = false;
+    }
+
+    /// Record an event that happened after ticks cycles of the current instruction
+    pub(crate) fn push(&mut self, ticks: u32, kind: EventKind) {
+        if self.complete {
+            self.clear();
+        }
+        match self.events.get_mut(self.len) {
+            Some(event) => {
+                *event = FrameEvent { cycle: self.cycle + ticks, kind };
+                self.len += 1;
+            },
+            None => self.dropped += 1,
+        }
+    }
+
+    /// Move to the next instruction
+    pub(crate) fn advance(&mut self, ticks: u32) {
+        if self.complete {
+            self.clear();
+        }
+        self.cycle += ticks;
+    }
+
+    /// The current frame is complete and can be retrieved
+    pub(crate) fn end_frame(&mut self) {
+        if self.complete {
+            self.clear();
+        }
+        self.complete = true;
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, FrameEvent> {
+        self.as_slice().iter()
+    }
+
+    pub fn as_slice(&self) -> &[FrameEvent] {
+        &self.events[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of events that happened after MAX_FRAME_EVENTS were recorded
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+}
+
+impl Default for FrameEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
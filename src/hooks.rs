@@ -0,0 +1,81 @@
+//! Optional callbacks on instructions and memory accesses
+//!
+//! This is only available with the `hooks` feature, so that the default build doesn't pay
+//! for the extra checks. Hooks are plain functions, any state (counters, traces, coverage maps)
+//! has to be kept by the caller, e.g. in a static.
+//! ```
+//! use core::sync::atomic::{AtomicUsize, Ordering};
+//! use padme_core::*;
+//! use padme_core::default::*;
+//!
+//! static INSTRUCTIONS: AtomicUsize = AtomicUsize::new(0);
+//!
+//! fn count_instruction(_pc: u16, _opcode: u8) {
+//!     INSTRUCTIONS.fetch_add(1, Ordering::Relaxed);
+//! }
+//!
+//! # let mut bin = [0u8; 32 * 1024];
+//! # let mut rom = Rom::load(&mut bin[..]).unwrap();
+//! let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+//! emu.set_hooks(Hooks { instruction: Some(count_instruction), ..Hooks::default() });
+//! emu.step();
+//! assert_eq!(INSTRUCTIONS.load(Ordering::Relaxed), 1);
+//! ```
+
+/// Called before an instruction is executed with its address and op code
+pub type InstructionHook = fn(pc: u16, opcode: u8);
+
+/// Called on a memory access with the address and the value read or written
+pub type MemoryHook = fn(address: u16, value: u8);
+
+#[derive(Clone, Copy, Default)]
+pub struct Hooks {
+    /// Every executed instruction
+    pub instruction: Option<InstructionHook>,
+    /// Every read on the bus (CPU and OAM DMA)
+    pub read: Option<MemoryHook>,
+    /// Every write on the bus
+    pub write: Option<MemoryHook>,
+    /// The CPU locked up on an illegal op code
+    pub illegal: Option<InstructionHook>,
+    /// Every change of VRAM, OAM (including DMA transfers) or palette registers,
+    /// only called when the new value is different from the previous one
+    pub video: Option<MemoryHook>,
+}
+
+impl Hooks {
+    #[inline]
+    pub(crate) fn on_instruction(&self, pc: u16, opcode: u8) {
+        if let Some(hook) = self.instruction {
+            hook(pc, opcode);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_illegal(&self, pc: u16, opcode: u8) {
+        if let Some(hook) = self.illegal {
+            hook(pc, opcode);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_read(&self, address: u16, value: u8) {
+        if let Some(hook) = self.read {
+            hook(address, value);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_write(&self, address: u16, value: u8) {
+        if let Some(hook) = self.write {
+            hook(address, value);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn on_video(&self, address: u16, value: u8) {
+        if let Some(hook) = self.video {
+            hook(address, value);
+        }
+    }
+}
@@ -6,8 +6,11 @@ use crate::region::*;
 const DEFAULT_REG_DMG_IF: u8    = 0xE1;
 const DEFAULT_REG_DMG_IE: u8    = 0x00;
 
+/// Only the 5 lower bits of IF / IE are used
+const INTERRUPT_MASK: u8        = 0x1F;
+
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum InterruptFlag {
     Vblank        = 0b00000001,
     Lcdc          = 0b00000010,
@@ -16,6 +19,54 @@ pub enum InterruptFlag {
     Joypad        = 0b00010000,
 }
 
+impl InterruptFlag {
+    /// All interrupts, from the highest priority to the lowest
+    pub const ALL: [InterruptFlag; 5] = [
+        InterruptFlag::Vblank,
+        InterruptFlag::Lcdc,
+        InterruptFlag::TimerOverflow,
+        InterruptFlag::Serial,
+        InterruptFlag::Joypad,
+    ];
+}
+
+/// Set of interrupts, as found in IF or IE
+/// ```
+/// use padme_core::{InterruptFlag, Interrupts};
+///
+/// let its = Interrupts::from_bits(0xE5);
+/// assert!(its.contains(InterruptFlag::Vblank));
+/// assert!(!its.contains(InterruptFlag::Lcdc));
+/// assert_eq!(its.bits(), 0x05);
+/// assert_eq!(its.iter().collect::<Vec<_>>(), vec![InterruptFlag::Vblank, InterruptFlag::TimerOverflow]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interrupts(u8);
+
+impl Interrupts {
+    /// Build a set from a register value, unused bits are ignored
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & INTERRUPT_MASK)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, flag: InterruptFlag) -> bool {
+        is_set!(self.0, flag as u8)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the interrupts in the set, from the highest priority to the lowest
+    pub fn iter(&self) -> impl Iterator<Item = InterruptFlag> + '_ {
+        InterruptFlag::ALL.into_iter().filter(move |flag| self.contains(*flag))
+    }
+}
+
 pub struct InterruptHandler {
     /// Interrupt flag
     reg_if: u8,
@@ -44,6 +95,21 @@ impl InterruptHandler {
     pub fn clear(&mut self, flag: InterruptFlag) {
         self.reg_if &= !(flag as u8);
     }
+
+    /// Interrupts requested (IF)
+    pub fn requested(&self) -> Interrupts {
+        Interrupts::from_bits(self.reg_if)
+    }
+
+    /// Interrupts enabled (IE)
+    pub fn enabled(&self) -> Interrupts {
+        Interrupts::from_bits(self.reg_ie)
+    }
+
+    /// Interrupts both requested and enabled (IF & IE)
+    pub fn pending(&self) -> Interrupts {
+        Interrupts::from_bits(self.reg_if & self.reg_ie)
+    }
 }
 
 impl MemoryRegion for InterruptHandler {
@@ -1,3 +1,8 @@
+use core::fmt;
+use core::mem;
+use core::str::FromStr;
+
+use crate::Error;
 use crate::region::*;
 use crate::interrupt::{InterruptFlag, InterruptHandler};
 
@@ -8,7 +13,7 @@ const FLAG_ACTION_BUTTON: u8    = 0x20;
 const FLAG_DIR_BUTTON: u8       = 0x10;
 
 #[repr(u8)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Button {
     Start       = 0b00101000,
     Select      = 0b00100100,
@@ -20,6 +25,148 @@ pub enum Button {
     Right       = 0b00010001,
 }
 
+impl Button {
+    /// All buttons, in the order of their Buttons bit
+    pub const ALL: [Button; 8] = [
+        Button::Right,
+        Button::Left,
+        Button::Up,
+        Button::Down,
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start,
+    ];
+
+    /// Name of the button, as accepted by from_str
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Button::Start => "Start",
+            Button::Select => "Select",
+            Button::B => "B",
+            Button::A => "A",
+            Button::Down => "Down",
+            Button::Up => "Up",
+            Button::Left => "Left",
+            Button::Right => "Right",
+        }
+    }
+}
+
+impl fmt::Display for Button {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse a button name, ignoring case
+/// ```
+/// use padme_core::Button;
+///
+/// assert_eq!("select".parse::<Button>().ok(), Some(Button::Select));
+/// assert!("turbo".parse::<Button>().is_err());
+/// for button in Button::ALL {
+///     assert_eq!(button.as_str().parse::<Button>().ok(), Some(button));
+/// }
+/// ```
+impl FromStr for Button {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Button::ALL
+            .into_iter()
+            .find(|button| button.as_str().eq_ignore_ascii_case(name))
+            .ok_or(Error::InvalidButton)
+    }
+}
+
+/// Bit of the button in Buttons
+impl From<Button> for u8 {
+    fn from(button: Button) -> u8 {
+        match button {
+            Button::Right => 0x01,
+            Button::Left => 0x02,
+            Button::Up => 0x04,
+            Button::Down => 0x08,
+            Button::A => 0x10,
+            Button::B => 0x20,
+            Button::Select => 0x40,
+            Button::Start => 0x80,
+        }
+    }
+}
+
+/// Set of buttons, 1 bit per button
+/// ```
+/// use padme_core::{Button, Buttons};
+///
+/// let mut buttons = Buttons::from(Button::A);
+/// buttons.insert(Button::Start);
+/// assert_eq!(buttons.bits(), 0x90);
+/// assert!(buttons.contains(Button::Start));
+/// assert_eq!(buttons.iter().collect::<Vec<_>>(), vec![Button::A, Button::Start]);
+/// buttons.remove(Button::A);
+/// assert_eq!(Buttons::from_bits(0x80), buttons);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Buttons(u8);
+
+impl Buttons {
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, button: Button) -> bool {
+        is_set!(self.0, u8::from(button))
+    }
+
+    pub fn insert(&mut self, button: Button) {
+        self.0 |= u8::from(button);
+    }
+
+    pub fn remove(&mut self, button: Button) {
+        self.0 &= !u8::from(button);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the buttons in the set, in the order of Button::ALL
+    pub fn iter(&self) -> impl Iterator<Item = Button> + '_ {
+        Button::ALL.into_iter().filter(move |button| self.contains(*button))
+    }
+}
+
+impl From<Button> for Buttons {
+    fn from(button: Button) -> Self {
+        Self(u8::from(button))
+    }
+}
+
+impl FromIterator<Button> for Buttons {
+    fn from_iter<I: IntoIterator<Item = Button>>(iter: I) -> Self {
+        let mut buttons = Buttons::default();
+        for button in iter {
+            buttons.insert(button);
+        }
+        buttons
+    }
+}
+
+/// When button changes reach the joypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputMode {
+    /// Right away, even in the middle of a frame
+    Immediate,
+    /// At the end of the current frame, so input is deterministic relative to frames
+    Frame,
+}
+
 pub struct Joypad {
     /// Joypad register @ 0xFF00, only for bit 4 and 5
     reg_p1: u8,
@@ -27,59 +174,92 @@ pub struct Joypad {
     button_state: u8,
     /// Keep register state in direction mode
     dir_state: u8,
+    /// A selection change pulled an input line low, see take_interrupt
+    interrupt: bool,
 }
 
 impl Joypad {
     pub fn new() -> Self {
         Self {
-            reg_p1: DEFAULT_REG_DMG_P1,
+            reg_p1: !DEFAULT_REG_DMG_P1,
             button_state: 0,
             dir_state: 0,
+            interrupt: false,
         }
     }
 
     /// Reset all registers and state
     pub fn reset(&mut self) {
-        self.reg_p1 = DEFAULT_REG_DMG_P1;
+        self.reg_p1 = !DEFAULT_REG_DMG_P1;
         self.button_state = 0;
         self.dir_state = 0;
+        self.interrupt = false;
+    }
+
+    /// Input lines P10 - P13 pulled low by the selected groups, 1 bit per line
+    fn lines(&self) -> u8 {
+        let mut lines = 0;
+        if is_set!(self.reg_p1, FLAG_DIR_BUTTON) {
+            lines |= self.dir_state;
+        }
+        if is_set!(self.reg_p1, FLAG_ACTION_BUTTON) {
+            lines |= self.button_state;
+        }
+        lines & 0x0F
     }
 
     pub fn set_button(&mut self, button: Button, is_pressed: bool, it: &mut InterruptHandler) {
+        let lines = self.lines();
         let button = button as u8;
-        if is_set!(button, FLAG_ACTION_BUTTON) {
-            if is_pressed {
-                self.button_state |= button;
-                it.request(InterruptFlag::Joypad);
-            } else {
-                self.button_state &= !button;
-            }
-        } else if is_set!(button, FLAG_DIR_BUTTON) {
-            if is_pressed {
-                self.dir_state |= button;
-                it.request(InterruptFlag::Joypad);
-            } else {
-                self.dir_state &= !button;
-            }
+        let state = if is_set!(button, FLAG_ACTION_BUTTON) {
+            &mut self.button_state
+        } else {
+            &mut self.dir_state
+        };
+        if is_pressed {
+            *state |= button;
+        } else {
+            *state &= !button;
+        }
+        // The interrupt is only raised when a selected line goes from high to low
+        if self.lines() & !lines != 0 {
+            it.request(InterruptFlag::Joypad);
         }
-        // Not clear what to do if both are enabled or disabled so do nothing
+    }
+
+    /// Whether the last P1 write raised the joypad interrupt
+    pub fn take_interrupt(&mut self) -> bool {
+        mem::take(&mut self.interrupt)
+    }
+
+    /// Buttons currently pressed
+    pub fn pressed(&self) -> Buttons {
+        Button::ALL
+            .into_iter()
+            .filter(|&button| {
+                let state = if is_set!(button as u8, FLAG_ACTION_BUTTON) { self.button_state } else { self.dir_state };
+                state & (button as u8) & 0x0F != 0
+            })
+            .collect()
+    }
+
+    /// Checks whether at least 1 button is pressed
+    pub fn is_any_pressed(&self) -> bool {
+        (self.button_state | self.dir_state) & 0x0F != 0
     }
 }
 
 impl MemoryRegion for Joypad {
     fn read(&self, _address: u16) -> u8 {
-        // retrieve state depending on the current mode
-        let select = self.reg_p1 & 0x30;
-        match select {
-            0x10 => select | !self.dir_state,
-            0x20 => select | !self.button_state,
-            _ => self.reg_p1,
-        }
+        // reg_p1 keeps the selection inverted and lines are 1 when pressed
+        0xC0 | (!self.reg_p1 & 0x30) | (!self.lines() & 0x0F)
     }
 
     fn write(&mut self, _address: u16, value: u8) {
         // 0 means enabled, so we only care about storing ~bit4 and ~bit5
-        // so during read, we can just apply a mask to bit4 and bit5
+        let lines = self.lines();
         self.reg_p1 = !value;
+        // Selecting a group with a pressed button also pulls a line low
+        self.interrupt |= self.lines() & !lines != 0;
     }
 }
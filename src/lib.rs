@@ -85,33 +85,78 @@
 //! }
 //! ```
 
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate std;
+
 // Private mods
 #[macro_use]
 mod bitops;
 
 mod apu;
 mod bus;
+mod cheat;
 mod collections;
 mod cpu;
+mod debug;
+mod disasm;
 mod error;
+mod events;
+#[cfg(feature = "hooks")]
+mod hooks;
 mod interrupt;
 mod joypad;
+mod link;
 mod ppu;
 mod ram;
 mod region;
 mod rom;
+mod script;
 mod serial;
+mod stats;
+mod symbols;
 mod system;
 mod timer;
 
 // Public exports
-pub use apu::{AUDIO_SAMPLE_RATE, AudioSpeaker};
-pub use cpu::CLOCK_SPEED;
+pub use apu::{AUDIO_SAMPLE_RATE, ApuState, AudioMode, AudioSpeaker, ChannelState};
+pub use bus::UnusableRegion;
+pub use cheat::{Freeze, FreezeMode, MAX_FREEZES};
+pub use cpu::{CLOCK_SPEED, CpuState, Model};
+pub use debug::{BreakReason, Breakpoint, MAX_BREAKPOINTS, MAX_WATCHES, Watch};
+pub use disasm::{Instruction, SymbolicInstruction};
 pub use error::Error;
-pub use joypad::Button;
-pub use ppu::{FRAME_HEIGHT, FRAME_WIDTH, Pixel, Screen};
-pub use rom::{CartridgeType, CgbMode, Licensee, Rom};
-pub use serial::SerialOutput;
-pub use system::System;
+pub use events::{EventKind, FrameEvent, FrameEvents, MAX_FRAME_EVENTS};
+pub use interrupt::{InterruptFlag, Interrupts};
+#[cfg(feature = "hooks")]
+pub use hooks::{Hooks, InstructionHook, MemoryHook};
+pub use joypad::{Button, Buttons, InputMode};
+pub use link::{LINK_PROTOCOL_VERSION, LinkTransport, NetworkLink};
+pub use ppu::{DebugOverlay, DirtyLines, FRAME_HEIGHT, FRAME_WIDTH, FrameInfo, Palette, PaletteRegister, Pixel, PpuMode, RenderMode, Screen, Transform, palette};
+pub use region::MemoryRegion;
+pub use rom::{BankedStorage, CartridgeType, CgbMode, Licensee, MbcState, ROM_BANK_SIZE, Rom, RomStorage, StreamedRom, StrictChecks, TimeSource};
+pub use script::{FrameScript, Script, ScriptCtx};
+pub use serial::{SerialOutput, SerialState};
+pub use stats::{AccessStats, AvStats, CycleStats, Diagnostics, MemoryArea};
+pub use symbols::{Location, Symbol, SymbolTable};
+pub use system::{FrameProgress, FrameSamples, NextFrame, System, Watchdog};
+pub use timer::TimerState;
 
+#[cfg(feature = "std")]
+pub mod compat;
 pub mod default;
+/// Memory map of the hardware: region boundaries, register addresses
+/// and the trait implemented by memory mapped devices
+///
+/// ```
+/// use padme_core::hw::{MemoryRegion, REG_LCDC_ADDR, VRAM_REGION_START};
+///
+/// assert_eq!(REG_LCDC_ADDR, 0xFF40);
+/// assert_eq!(VRAM_REGION_START, 0x8000);
+/// ```
+pub mod hw {
+    pub use crate::region::*;
+}
+#[cfg(feature = "std")]
+pub mod runner;
+pub mod testing;
@@ -0,0 +1,158 @@
+use log::{debug, warn};
+
+use crate::SerialOutput;
+
+/// Version sent in the hello message, both sides must use the same
+pub const LINK_PROTOCOL_VERSION: u8     = 1;
+
+// Messages are a tag byte followed by a data byte
+const TAG_HELLO: u8                     = 0x01;
+const TAG_READY: u8                     = 0x02;
+const TAG_CLOCK: u8                     = 0x03;
+
+/// Byte stream to the remote emulator (TCP, WebRTC data channel, ...)
+///
+/// Both methods must not block: bytes are sent in order and read when they arrived
+pub trait LinkTransport {
+    /// Queue a byte for the remote side
+    fn send(&mut self, byte: u8);
+
+    /// Next byte received from the remote side, None if nothing arrived yet
+    fn recv(&mut self) -> Option<u8>;
+}
+
+/// Link cable to a remote emulator over any transport
+///
+/// The side waiting for a transfer announces the byte it will send,
+/// so the side clocking a transfer never waits for a round trip:
+/// it receives the last byte announced by the remote side, or 0xFF if the remote side
+/// was not waiting for a transfer yet, like a game would with a real cable.
+///
+/// ```
+/// use padme_core::{LinkTransport, NetworkLink, SerialOutput};
+///
+/// /// A transport sending bytes to itself
+/// struct Echo(Vec<u8>);
+///
+/// impl LinkTransport for Echo {
+///     fn send(&mut self, byte: u8) {
+///         self.0.push(byte);
+///     }
+///
+///     fn recv(&mut self) -> Option<u8> {
+///         if self.0.is_empty() { None } else { Some(self.0.remove(0)) }
+///     }
+/// }
+///
+/// let mut link = NetworkLink::new(Echo(Vec::new()));
+/// link.poll();
+/// assert!(link.is_connected());
+///
+/// // Nobody announced a byte yet
+/// assert_eq!(link.transfer(0x42), 0xFF);
+/// ```
+pub struct NetworkLink<T: LinkTransport> {
+    transport: T,
+    /// Tag of the message being received
+    tag: Option<u8>,
+    /// The remote side sent a hello with the same protocol version
+    connected: bool,
+    /// Byte the remote side waits to send when this side clocks a transfer
+    remote_ready: Option<u8>,
+    /// Byte received from a transfer clocked by the remote side
+    received: Option<u8>,
+    /// Byte announced to the remote side while waiting for a transfer
+    announced: Option<u8>,
+}
+
+impl<T: LinkTransport> NetworkLink<T> {
+    /// Wrap a transport and send the hello message
+    pub fn new(mut transport: T) -> Self {
+        transport.send(TAG_HELLO);
+        transport.send(LINK_PROTOCOL_VERSION);
+
+        Self {
+            transport,
+            tag: None,
+            connected: false,
+            remote_ready: None,
+            received: None,
+            announced: None,
+        }
+    }
+
+    /// The remote side answered with a compatible hello
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Get the transport back
+    pub fn into_inner(self) -> T {
+        self.transport
+    }
+
+    /// Read all the messages received so far
+    /// This is done on each transfer but can be called every frame to notice the remote side earlier
+    pub fn poll(&mut self) {
+        while let Some(byte) = self.transport.recv() {
+            match self.tag.take() {
+                None => self.tag = Some(byte),
+                Some(tag) => self.handle(tag, byte),
+            }
+        }
+    }
+
+    fn handle(&mut self, tag: u8, data: u8) {
+        match tag {
+            TAG_HELLO => {
+                self.connected = data == LINK_PROTOCOL_VERSION;
+                if !self.connected {
+                    warn!("link protocol version mismatch: {} != {}", data, LINK_PROTOCOL_VERSION);
+                }
+            },
+            TAG_READY => self.remote_ready = Some(data),
+            TAG_CLOCK => self.received = Some(data),
+            _ => debug!("unknown link message 0x{:02X}", tag),
+        }
+    }
+}
+
+impl<T: LinkTransport> SerialOutput for NetworkLink<T> {
+    fn putchar(&mut self, _ch: u8) {
+    }
+
+    fn transfer(&mut self, byte: u8) -> u8 {
+        self.poll();
+        match self.remote_ready.take() {
+            Some(received) => {
+                self.transport.send(TAG_CLOCK);
+                self.transport.send(byte);
+                received
+            },
+            None => 0xFF,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Keep up with the remote side when no transfer happens, e.g. to see the hello message
+    fn end_of_frame(&mut self) {
+        self.poll();
+    }
+
+    fn poll_transfer(&mut self, byte: u8) -> Option<u8> {
+        self.poll();
+        if let Some(received) = self.received.take() {
+            self.announced = None;
+            return Some(received);
+        }
+        if self.announced != Some(byte) {
+            self.transport.send(TAG_READY);
+            self.transport.send(byte);
+            self.announced = Some(byte);
+        }
+        None
+    }
+}
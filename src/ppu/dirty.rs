@@ -0,0 +1,108 @@
+use super::FRAME_HEIGHT;
+
+const FNV_OFFSET_BASIS: u32 = 0x811C9DC5;
+const FNV_PRIME: u32 = 0x01000193;
+
+/// Set of scanlines that changed since the last screen update
+/// ```
+/// use padme_core::DirtyLines;
+///
+/// let mut lines = DirtyLines::new();
+/// lines.set(3);
+/// lines.set(42);
+/// assert!(lines.is_dirty(42));
+/// assert_eq!(lines.iter().collect::<Vec<u8>>(), vec![3, 42]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyLines {
+    bits: [u8; FRAME_HEIGHT / 8],
+}
+
+impl DirtyLines {
+    pub const fn new() -> Self {
+        Self { bits: [0u8; FRAME_HEIGHT / 8] }
+    }
+
+    /// Mark a line as changed
+    #[inline]
+    pub fn set(&mut self, line: u8) {
+        self.bits[line as usize / 8] |= 1 << (line % 8);
+    }
+
+    /// Mark all lines as changed
+    pub fn set_all(&mut self) {
+        self.bits.iter_mut().for_each(|byte| *byte = 0xFF);
+    }
+
+    /// Mark all lines as unchanged
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    /// Checks whether a line changed
+    #[inline]
+    pub fn is_dirty(&self, line: u8) -> bool {
+        (line as usize) < FRAME_HEIGHT && is_set!(self.bits[line as usize / 8], 1 << (line % 8))
+    }
+
+    /// Checks whether no line changed
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|byte| *byte == 0)
+    }
+
+    /// Number of lines that changed
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    /// Iterate over the changed lines, from top to bottom
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..FRAME_HEIGHT as u8).filter(move |line| self.is_dirty(*line))
+    }
+}
+
+impl Default for DirtyLines {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keep a hash of each line to find out which ones changed between frames
+pub struct LineTracker {
+    /// Hash of each line of the last frame
+    hashes: [u32; FRAME_HEIGHT],
+    /// Lines that changed since the last update
+    pub dirty: DirtyLines,
+}
+
+impl LineTracker {
+    pub fn new() -> Self {
+        let mut dirty = DirtyLines::new();
+        // Nothing has been displayed yet
+        dirty.set_all();
+
+        Self {
+            hashes: [0u32; FRAME_HEIGHT],
+            dirty,
+        }
+    }
+
+    /// Compare a rendered line with the previous frame
+    pub fn end_line(&mut self, line: u8, row: &[u32]) {
+        let current = row.iter()
+            .flat_map(|word| word.to_le_bytes())
+            .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ byte as u32).wrapping_mul(FNV_PRIME));
+        if let Some(hash) = self.hashes.get_mut(line as usize) {
+            if *hash != current {
+                *hash = current;
+                self.dirty.set(line);
+            }
+        }
+    }
+
+    /// The whole screen changed without going through the pipeline
+    pub fn invalidate(&mut self) {
+        self.hashes.iter_mut().for_each(|hash| *hash = 0);
+        self.dirty.set_all();
+    }
+}
@@ -1,10 +1,17 @@
+mod dirty;
+pub mod palette;
 mod pipeline;
 mod pixel;
 mod ppu;
 mod sprite;
+mod tile_cache;
 
-use pipeline::{FetchState, Pipeline};
+use dirty::LineTracker;
+use pipeline::{FetchState, FifoPixel, Pipeline};
 use sprite::Sprite;
+use tile_cache::TileCache;
 
+pub use dirty::DirtyLines;
+pub use palette::{Palette, PaletteRegister};
 pub use ppu::*;
 pub use pixel::Pixel;
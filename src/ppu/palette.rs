@@ -0,0 +1,131 @@
+//! Color palettes used to turn the 4 DMG shades into pixels
+//!
+//! Each preset lists the colors from the lightest (color 0) to the darkest (color 3).
+//! ```
+//! use padme_core::{palette, Palette};
+//!
+//! let green = Palette::from(palette::DMG_GREEN);
+//! let sgb = Palette::with_objects(palette::SGB_1A, palette::SGB_1A, palette::SGB_1B);
+//! assert!(green.bg == green.obj1);
+//! assert!(sgb.bg != sgb.obj1);
+//! ```
+use super::Pixel;
+
+/// Build an opaque pixel from a 0xRRGGBB value
+const fn rgb(value: u32) -> Pixel {
+    Pixel {
+        r: (value >> 16) as u8,
+        g: (value >> 8) as u8,
+        b: value as u8,
+        a: 0xFF,
+    }
+}
+
+/// Default shades of gray
+pub const GRAYSCALE: [Pixel; 4]         = [
+    Pixel { r: 0xFE, g: 0xFE, b: 0xFE, a: 0xFE },
+    rgb(0xC0C0C0), rgb(0x606060), rgb(0x000000),
+];
+
+/// Original DMG green LCD
+pub const DMG_GREEN: [Pixel; 4]         = [rgb(0x9BBC0F), rgb(0x8BAC0F), rgb(0x306230), rgb(0x0F380F)];
+/// Game Boy Pocket gray LCD
+pub const POCKET_GRAY: [Pixel; 4]       = [rgb(0xC4CFA1), rgb(0x8B956D), rgb(0x4D533C), rgb(0x1F1F1F)];
+/// Game Boy Light backlit LCD
+pub const LIGHT: [Pixel; 4]             = [rgb(0x00B581), rgb(0x009A71), rgb(0x00694A), rgb(0x004F3B)];
+
+//
+// Super Game Boy built-in palettes (1-A to 1-H)
+//
+pub const SGB_1A: [Pixel; 4]            = [rgb(0xF8E8C8), rgb(0xD89048), rgb(0xA82820), rgb(0x301850)];
+pub const SGB_1B: [Pixel; 4]            = [rgb(0xD8D8C0), rgb(0xC8B070), rgb(0xB05010), rgb(0x000000)];
+pub const SGB_1C: [Pixel; 4]            = [rgb(0xF8C0F8), rgb(0xE89850), rgb(0x983860), rgb(0x383898)];
+pub const SGB_1D: [Pixel; 4]            = [rgb(0xF8F8A8), rgb(0xC08048), rgb(0xF80000), rgb(0x501800)];
+pub const SGB_1E: [Pixel; 4]            = [rgb(0xF8D8B0), rgb(0x78C078), rgb(0x688840), rgb(0x583820)];
+pub const SGB_1F: [Pixel; 4]            = [rgb(0xD8E8F8), rgb(0xE08850), rgb(0xA80000), rgb(0x004010)];
+pub const SGB_1G: [Pixel; 4]            = [rgb(0x000050), rgb(0x00A0E8), rgb(0x787800), rgb(0xF8F858)];
+pub const SGB_1H: [Pixel; 4]            = [rgb(0xF8E8E0), rgb(0xF8B888), rgb(0x804000), rgb(0x301800)];
+
+/// Shade (0 - 3) a palette register gives to a color id
+#[inline]
+pub const fn shade(register: u8, color_id: u8) -> u8 {
+    (register >> (color_id * 2)) & 0x3
+}
+
+/// Shade of each color id in a palette register
+/// ```
+/// use padme_core::palette;
+///
+/// assert_eq!(palette::decode(0xE4), [0, 1, 2, 3]);
+/// assert_eq!(palette::decode(0x1B), [3, 2, 1, 0]);
+/// ```
+pub const fn decode(register: u8) -> [u8; 4] {
+    [shade(register, 0), shade(register, 1), shade(register, 2), shade(register, 3)]
+}
+
+/// Palette registers of the PPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteRegister {
+    /// Background & window palette
+    Bgp,
+    /// Sprite palette 0
+    Obp0,
+    /// Sprite palette 1
+    Obp1,
+}
+
+/// Colors used by the PPU for each layer
+#[derive(Clone, Copy, PartialEq)]
+pub struct Palette {
+    /// Background & window colors (BGP)
+    pub bg: [Pixel; 4],
+    /// Sprite colors when OBP0 is selected
+    pub obj0: [Pixel; 4],
+    /// Sprite colors when OBP1 is selected
+    pub obj1: [Pixel; 4],
+}
+
+impl Palette {
+    /// Use the same colors for all layers
+    pub const fn new(colors: [Pixel; 4]) -> Self {
+        Self { bg: colors, obj0: colors, obj1: colors }
+    }
+
+    /// Use different colors for the background and each sprite palette
+    pub const fn with_objects(bg: [Pixel; 4], obj0: [Pixel; 4], obj1: [Pixel; 4]) -> Self {
+        Self { bg, obj0, obj1 }
+    }
+
+    /// Colors of the layer a palette register applies to
+    pub fn colors(&self, register: PaletteRegister) -> &[Pixel; 4] {
+        match register {
+            PaletteRegister::Bgp => &self.bg,
+            PaletteRegister::Obp0 => &self.obj0,
+            PaletteRegister::Obp1 => &self.obj1,
+        }
+    }
+
+    /// Color of each color id once a palette register value is applied
+    /// ```
+    /// use padme_core::palette::{self, GRAYSCALE, PaletteRegister};
+    ///
+    /// let colors = palette::Palette::default().apply(PaletteRegister::Bgp, 0x1B);
+    /// assert!(colors[0] == GRAYSCALE[3] && colors[3] == GRAYSCALE[0]);
+    /// ```
+    pub fn apply(&self, register: PaletteRegister, value: u8) -> [Pixel; 4] {
+        let colors = self.colors(register);
+        decode(value).map(|shade| colors[shade as usize])
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(GRAYSCALE)
+    }
+}
+
+impl From<[Pixel; 4]> for Palette {
+    fn from(colors: [Pixel; 4]) -> Self {
+        Self::new(colors)
+    }
+}
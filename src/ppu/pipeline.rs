@@ -1,5 +1,22 @@
 use crate::collections::Queue;
-use super::{Pixel, Sprite};
+use super::Sprite;
+
+/// Pixel waiting in the fifo
+/// Colors are resolved when the pixel is shifted out,
+/// so palette and LCDC changes during mode 3 take effect on the next dot
+#[derive(Clone, Copy, Default)]
+pub struct FifoPixel {
+    /// Background / window color id
+    pub bg_color_id: u8,
+    /// The background pixel comes from the window
+    pub window: bool,
+    /// Color id of the first non transparent sprite (0 if none)
+    pub obj_color_id: u8,
+    /// Sprite palette number (OBP0 / OBP1)
+    pub obj_palette: u8,
+    /// Whether the background/window has priority over the sprite
+    pub obj_bgwin_prio: bool,
+}
 
 /// 5 steps of the fetching
 pub enum FetchState {
@@ -16,11 +33,12 @@ pub struct Pipeline {
     /// To process 1 / 2 times
     pub ticks: u8,
     /// BG/Win Pixel fifo
-    pub bgw_fifo: Queue<Pixel, 16>,
+    pub bgw_fifo: Queue<FifoPixel, 16>,
     /// Objects list
     pub obj_list: [Sprite; 10],
     pub obj_count: u8,
-    pub obj_fetched_idx: [u8; 3],
+    /// Sprites overlapping the fetched tile, all 10 can share the same X
+    pub obj_fetched_idx: [u8; 10],
     pub obj_fetched_count: u8,
     /// Tile map y offset
     pub addr_y_offset: u16,
@@ -32,16 +50,26 @@ pub struct Pipeline {
     pub render_x: u8,
     /// Current X to render within scx
     pub lx: u8,
+    /// SCX lower 3 bits latched at the beginning of the line
+    pub scx_fine: u8,
     /// Fetch data (tile index, tile data low, tile data high)
     pub bgw_data: [u8; 3],
+    /// Decoded tile row when the tile cache is used
+    pub bgw_row: [u8; 8],
     /// Sprite data (tile data low, tile data high)
-    pub obj_data: [u8; 6],
+    pub obj_data: [u8; 20],
     /// State of the processing
     pub state: FetchState,
     /// At some point in this frame the value of WY was equal to LY
     pub win_y_triggered: bool,
-    /// Save the window line y coords
+    /// Window line counter, only incremented on lines where the window is drawn
     pub win_ly: u8,
+    /// The fetcher switched to the window on this line
+    pub win_active: bool,
+    /// Next window tile column to fetch
+    pub win_tile_x: u8,
+    /// Pixels of the next fetched tile that are not pushed (window with WX < 7)
+    pub skip: u8,
 }
 
 impl Pipeline {
@@ -49,33 +77,42 @@ impl Pipeline {
         Self {
             disabled: false,
             ticks: 0,
-            bgw_fifo: Queue::new([Pixel::default(); 16]),
+            bgw_fifo: Queue::new([FifoPixel::default(); 16]),
             obj_list: [Sprite::default(); 10],
             obj_count: 0,
-            obj_fetched_idx: [0u8; 3],
+            obj_fetched_idx: [0u8; 10],
             obj_fetched_count: 0,
             addr_y_offset: 0,
             fetch_x: 0,
             tile_y: 0,
             bgw_data: [0u8; 3],
-            obj_data: [0u8; 6],
+            bgw_row: [0u8; 8],
+            obj_data: [0u8; 20],
             state: FetchState::Tile,
             render_x: 0,
             lx: 0,
+            scx_fine: 0,
             win_y_triggered: false,
             win_ly: 0,
+            win_active: false,
+            win_tile_x: 0,
+            skip: 0,
         }
     }
 
     /// Init the pipeline fetcher to handle the pipeline during mode 3 (transfer)
-    pub fn init_fetcher(&mut self, addr_y_offset: u16, tile_y: u8) {
+    pub fn init_fetcher(&mut self, addr_y_offset: u16, tile_y: u8, scx_fine: u8) {
         self.addr_y_offset = addr_y_offset;
         self.tile_y = tile_y;
+        self.scx_fine = scx_fine;
         self.state = FetchState::Tile;
         self.bgw_fifo.clear();
         self.render_x = 0;
         self.fetch_x = 0;
         self.lx = 0;
+        self.win_active = false;
+        self.win_tile_x = 0;
+        self.skip = 0;
     }
 
     /// Init sprites storage
@@ -90,7 +127,7 @@ impl Pipeline {
         self.obj_count += 1;
     }
 
-    /// Sort sprites by X
+    /// Sort sprites by X, then by OAM index
     pub fn sort_sprites(&mut self) {
         self.obj_list[..self.obj_count as usize].sort_unstable();
     }
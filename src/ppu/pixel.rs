@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub struct Pixel {
     pub r: u8,
     pub g: u8,
@@ -7,10 +7,6 @@ pub struct Pixel {
 }
 
 impl Pixel {
-    pub fn default() -> Self {
-        Self { r: 0, g: 0, b: 0, a: 0 }
-    }
-
     pub fn rgb(&self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
@@ -22,4 +18,59 @@ impl Pixel {
     pub fn rgba(&self) -> u32 {
         ((self.r as u32) << 24) | ((self.g as u32) << 16) | ((self.b as u32) << 8) | (self.a as u32)
     }
+
+    /// Build an opaque pixel from a 0xRRGGBB value
+    /// ```
+    /// use padme_core::Pixel;
+    ///
+    /// let px = Pixel::from_rgb(0x123456);
+    /// assert!(px == Pixel { r: 0x12, g: 0x34, b: 0x56, a: 0xFF });
+    /// assert_eq!(px.rgb(), 0x123456);
+    /// ```
+    pub fn from_rgb(rgb: u32) -> Self {
+        Self {
+            r: (rgb >> 16) as u8,
+            g: (rgb >> 8) as u8,
+            b: rgb as u8,
+            a: 0xFF,
+        }
+    }
+
+    /// Same as rgba with the red and blue components swapped
+    pub fn to_bgra(&self) -> u32 {
+        ((self.b as u32) << 24) | ((self.g as u32) << 16) | ((self.r as u32) << 8) | (self.a as u32)
+    }
+
+    /// Convert to 16-bit colors: 5 bits of red, 6 bits of green, 5 bits of blue
+    /// ```
+    /// use padme_core::Pixel;
+    ///
+    /// assert_eq!(Pixel::from_rgb(0xFFFFFF).to_rgb565(), 0xFFFF);
+    /// assert_eq!(Pixel::from_rgb(0xFF0000).to_rgb565(), 0xF800);
+    /// assert_eq!(Pixel::from_rgb(0x00FF00).to_rgb565(), 0x07E0);
+    /// ```
+    pub fn to_rgb565(&self) -> u16 {
+        ((self.r as u16 >> 3) << 11) | ((self.g as u16 >> 2) << 5) | (self.b as u16 >> 3)
+    }
+
+    /// Pack the pixel into a word holding the bytes r, g, b, a in memory order,
+    /// i.e. the layout of a RGBA8 texture whatever the endianness
+    /// ```
+    /// use padme_core::Pixel;
+    ///
+    /// let px = Pixel { r: 1, g: 2, b: 3, a: 4 };
+    /// assert_eq!(px.packed().to_ne_bytes(), [1, 2, 3, 4]);
+    /// assert!(Pixel::from_packed(px.packed()) == px);
+    /// ```
+    #[inline]
+    pub fn packed(&self) -> u32 {
+        u32::from_ne_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// Retrieve a pixel packed with Pixel::packed
+    #[inline]
+    pub fn from_packed(word: u32) -> Self {
+        let [r, g, b, a] = word.to_ne_bytes();
+        Self { r, g, b, a }
+    }
 }
@@ -1,9 +1,12 @@
+use core::mem;
+
 use log::trace;
 
 use crate::interrupt::{InterruptHandler, InterruptFlag};
 use crate::region::*;
 
-use super::{FetchState, Pipeline, Pixel, Sprite};
+use super::{DirtyLines, FetchState, FifoPixel, LineTracker, Palette, Pipeline, Pixel, Sprite, TileCache};
+use super::palette::{self, PaletteRegister};
 
 //
 // Frame configuration
@@ -27,6 +30,12 @@ const DEFAULT_REG_DMG_WX: u8            = 0x00;
 const DEFAULT_REG_DMG_OBP0: u8          = 0xFF;
 const DEFAULT_REG_DMG_OBP1: u8          = 0xFF;
 
+/// Index of the dots with the LCD off in the mode counters
+const LCD_OFF_DOTS: usize               = 4;
+
+/// M-cycles between a write to the DMA register and the first byte copied
+const DMA_START_DELAY: u8               = 1;
+
 //
 // Tile regions
 //
@@ -74,15 +83,28 @@ const XFER_LIMIT_PERIOD: u32            = OAM_LIMIT_PERIOD + 172;
 const HBLANK_LIMIT_PERIOD: u32          = 456;
 const FRAME_LIMIT_PERIOD: u32           = HBLANK_LIMIT_PERIOD * (FRAME_HEIGHT as u32);
 const VBLANK_LIMIT_PERIOD: u32          = FRAME_LIMIT_PERIOD + HBLANK_LIMIT_PERIOD * 10;
+const LAST_LINE: u8                     = (VBLANK_LIMIT_PERIOD / HBLANK_LIMIT_PERIOD - 1) as u8;
+
+//
+// LY=LYC comparison timings (in dots from the start of the line)
+//
+// The comparison only happens 1 M-cycle after LY changed, the flag is cleared in between
+const LYC_COMPARE_DELAY: u32            = 4;
+// On the last line, LY reads 153 only for 1 M-cycle before switching to 0
+const LAST_LINE_LY_RESET: u32           = 4;
+// The comparator still sees 153 for 1 M-cycle after LY switched to 0
+const LAST_LINE_LYC_END: u32            = LAST_LINE_LY_RESET + 4;
+const LAST_LINE_LYC_RESET: u32          = LAST_LINE_LYC_END + 4;
 
 //
-// Default pixels
+// Debug overlay tints, blended with the pixel colors
 //
-// This white is slightly less white than pixel used during disabled screen
-const PIXEL_COLOR_WHITE: Pixel          = Pixel { r: 0xFE, g: 0xFE, b: 0xFE, a: 0xFE };
-const PIXEL_COLOR_LIGHTGRAY: Pixel      = Pixel { r: 0xC0, g: 0xC0, b: 0xC0, a: 0xFF };
-const PIXEL_COLOR_DARKGRAY: Pixel       = Pixel { r: 0x60, g: 0x60, b: 0x60, a: 0xFF };
-const PIXEL_COLOR_BLACK: Pixel          = Pixel { r: 0x00, g: 0x00, b: 0x00, a: 0xFF };
+const TINT_BACKGROUND: Pixel            = Pixel { r: 0x00, g: 0x00, b: 0xFF, a: 0xFF };
+const TINT_WINDOW: Pixel                = Pixel { r: 0x00, g: 0xFF, b: 0x00, a: 0xFF };
+const TINT_OBJ0: Pixel                  = Pixel { r: 0xFF, g: 0x00, b: 0x00, a: 0xFF };
+const TINT_OBJ1: Pixel                  = Pixel { r: 0xFF, g: 0x00, b: 0xFF, a: 0xFF };
+const TINT_OBJ_DRAWN: Pixel             = TINT_WINDOW;
+const TINT_OBJ_HIDDEN: Pixel            = TINT_OBJ0;
 
 // Debug functions
 macro_rules! trace_mode {
@@ -91,6 +113,132 @@ macro_rules! trace_mode {
     }
 }
 
+/// Current mode of the PPU, as reported in the STAT register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PpuMode {
+    /// Mode 0: waiting for the end of the line
+    HBlank = 0,
+    /// Mode 1: waiting for the next frame
+    VBlank = 1,
+    /// Mode 2: searching sprites for the current line
+    OamScan = 2,
+    /// Mode 3: drawing pixels
+    Transfer = 3,
+}
+
+/// How pixels are produced during mode 3
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The pixel fifo runs on every dot, mid-line register changes are visible
+    Accurate,
+    /// The whole line is rendered at once when entering HBlank, using the
+    /// registers at that time, which is a lot faster but less accurate
+    Fast,
+}
+
+/// Debug view tinting the pixels sent to the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugOverlay {
+    None,
+    /// Tint by the layer a pixel comes from:
+    /// blue for the background, green for the window, red for OBP0 sprites and magenta for OBP1 sprites
+    Layers,
+    /// Tint where a sprite pixel is not transparent:
+    /// green when it is drawn, red when the background/window has priority over it
+    Priority,
+}
+
+/// Orientation of the frame sent to the screen, e.g. for LCDs mounted rotated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    /// Rotate clockwise, the frame is FRAME_HEIGHT pixels wide
+    Rotate90,
+    Rotate180,
+    /// Rotate counterclockwise, the frame is FRAME_HEIGHT pixels wide
+    Rotate270,
+    /// Mirror left and right
+    FlipHorizontal,
+    /// Mirror top and bottom
+    FlipVertical,
+}
+
+impl Transform {
+    /// Width of the transformed frame
+    pub fn width(&self) -> usize {
+        match self {
+            Transform::Rotate90 | Transform::Rotate270 => FRAME_HEIGHT,
+            _ => FRAME_WIDTH,
+        }
+    }
+
+    /// Height of the transformed frame
+    pub fn height(&self) -> usize {
+        match self {
+            Transform::Rotate90 | Transform::Rotate270 => FRAME_WIDTH,
+            _ => FRAME_HEIGHT,
+        }
+    }
+
+    /// Position of the pixel x, y in the transformed frame
+    pub fn apply(&self, x: u8, y: u8) -> (u8, u8) {
+        let (last_x, last_y) = ((FRAME_WIDTH - 1) as u8, (FRAME_HEIGHT - 1) as u8);
+        match self {
+            Transform::Identity => (x, y),
+            Transform::Rotate90 => (last_y - y, x),
+            Transform::Rotate180 => (last_x - x, last_y - y),
+            Transform::Rotate270 => (y, last_x - x),
+            Transform::FlipHorizontal => (last_x - x, y),
+            Transform::FlipVertical => (x, last_y - y),
+        }
+    }
+}
+
+/// Metadata of a frame sent to the screen, see Screen::update_with
+///
+/// ```
+/// use padme_core::*;
+/// use padme_core::default::*;
+///
+/// #[derive(Default)]
+/// struct Recorder(Vec<FrameInfo>);
+///
+/// impl Screen for Recorder {
+///     fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+///     }
+///
+///     fn update(&mut self) {
+///     }
+///
+///     fn update_with(&mut self, info: &FrameInfo) {
+///         self.0.push(*info);
+///     }
+/// }
+///
+/// # let bin = [0u8; 0x150];
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, Recorder::default(), NoSerial, NoSpeaker);
+/// emu.update_frame();
+/// emu.update_frame();
+///
+/// let cycles = emu.cycle_count();
+/// let frames = &emu.screen().0;
+/// assert_eq!(frames[1].number, 1);
+/// assert_eq!(frames[1].cycles, cycles);
+/// assert!(frames[1].lcd_enabled);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Number of frames sent to the screen before this one
+    pub number: u64,
+    /// Cycle the frame ended at, see System::cycle_count
+    pub cycles: u64,
+    /// Whether the LCD was on, the frame is blank otherwise
+    pub lcd_enabled: bool,
+    /// Lines that changed since the previous frame
+    pub dirty_lines: DirtyLines,
+}
+
 /// This represents a Screen surface
 /// # Example
 ///
@@ -111,6 +259,9 @@ macro_rules! trace_mode {
 /// }
 /// ```
 pub trait Screen {
+    /// Pixels are never displayed, e.g. for headless test runs
+    /// The PPU then skips the pixel pipeline, the modes and interrupts follow the timing of RenderMode::Fast
+    const HEADLESS: bool = false;
     /// Set a single pixel on a screen
     /// This could be used to either store the pixel in a buffer
     /// or draw directly (in this case, the draw method can be empty)
@@ -118,6 +269,28 @@ pub trait Screen {
     /// Notify the screen of a new frame
     /// This is dependent on the FPS
     fn update(&mut self);
+    /// Same as update, with the lines that changed since the previous update
+    /// so that slow displays can only refresh these rows
+    fn update_lines(&mut self, _lines: &DirtyLines) {
+        self.update();
+    }
+    /// Same as update_lines, with the frame number, the cycle it ended at
+    /// and whether the LCD was on, e.g. to timestamp captures or not present blank frames
+    fn update_with(&mut self, info: &FrameInfo) {
+        self.update_lines(&info.dirty_lines);
+    }
+    /// Set a whole line at once, pixels are packed with Pixel::packed
+    /// This is called once the line is rendered and can be implemented to copy
+    /// the row in bulk, otherwise each pixel goes through set_pixel
+    fn set_row(&mut self, y: u8, row: &[u32; FRAME_WIDTH]) {
+        for (x, word) in row.iter().enumerate() {
+            self.set_pixel(&Pixel::from_packed(*word), x as u8, y);
+        }
+    }
+    /// Called on every PPU mode transition with the current line and the
+    /// number of dots elapsed in that line, e.g. to schedule work in the middle of a frame
+    fn on_mode_change(&mut self, _mode: PpuMode, _ly: u8, _dots: u32) {
+    }
 }
 
 pub struct Ppu {
@@ -150,11 +323,46 @@ pub struct Ppu {
     reg_obp1: u8,
     /// Keep tracks of horizontal dots (max = 456)
     hdots: u32,
+    /// Dot where mode 3 ends, the fine scroll of SCX delays it
+    xfer_end: u32,
+    /// Line 153 is being processed (LY reads 0 for most of it)
+    last_line: bool,
     /// Pixel pipeline
     pipeline: Pipeline,
+    /// Packed pixels of the line being rendered
+    row: [u32; FRAME_WIDTH],
+    /// Colors used to render each shade
+    palette: Palette,
+    /// Track lines that changed between frames
+    lines: LineTracker,
+    /// Decoded background / window tiles
+    tile_cache: TileCache,
+    tile_cache_enabled: bool,
+    /// Renderer used during mode 3
+    render_mode: RenderMode,
+    /// Number of frames skipped between 2 frames sent to the screen
+    frame_skip: u8,
+    /// Frames skipped since the last frame sent to the screen
+    skipped_frames: u8,
+    /// Pixels of the current frame are sent to the screen
+    output: bool,
+    /// A frame sent to the screen has been completed
+    frame_ready: bool,
     /// Dma
     dma_active: bool,
     dma_idx: u8,
+    /// M-cycles before the first byte of the transfer is copied
+    dma_delay: u8,
+    /// The CPU cannot access OAM, it stays locked when a transfer is restarted
+    dma_locked: bool,
+    /// A transfer was started since the last call to take_dma_started
+    dma_started: bool,
+    /// Orientation of the frame sent to the screen
+    transform: Transform,
+    /// Debug tint applied to the pixels
+    overlay: DebugOverlay,
+    /// Dots spent in each mode, then with the LCD off, when counted
+    mode_dots: Option<[u32; 5]>,
 }
 
 impl Ppu {
@@ -175,9 +383,27 @@ impl Ppu {
             reg_obp0: DEFAULT_REG_DMG_OBP0,
             reg_obp1: DEFAULT_REG_DMG_OBP1,
             hdots: 0,
+            xfer_end: XFER_LIMIT_PERIOD,
+            last_line: false,
             pipeline: Pipeline::new(),
+            row: [0u32; FRAME_WIDTH],
+            palette: Palette::default(),
+            lines: LineTracker::new(),
+            tile_cache: TileCache::new(),
+            tile_cache_enabled: false,
+            render_mode: RenderMode::Accurate,
+            frame_skip: 0,
+            skipped_frames: 0,
+            output: true,
+            frame_ready: false,
             dma_active: false,
             dma_idx: 0,
+            dma_delay: 0,
+            dma_locked: false,
+            dma_started: false,
+            transform: Transform::Identity,
+            overlay: DebugOverlay::None,
+            mode_dots: None,
         }
     }
 
@@ -196,9 +422,19 @@ impl Ppu {
         self.reg_obp0 = DEFAULT_REG_DMG_OBP0;
         self.reg_obp1 = DEFAULT_REG_DMG_OBP1;
         self.hdots = 0;
+        self.xfer_end = XFER_LIMIT_PERIOD;
+        self.last_line = false;
         self.pipeline = Pipeline::new();
+        self.lines.invalidate();
+        self.tile_cache.clear();
+        self.skipped_frames = 0;
+        self.output = true;
+        self.frame_ready = false;
         self.dma_active = false;
         self.dma_idx = 0;
+        self.dma_delay = 0;
+        self.dma_locked = false;
+        self.dma_started = false;
         self.vram.iter_mut().for_each(| byte | *byte = 0);
         self.oam.iter_mut().for_each(| byte | *byte = 0);
     }
@@ -208,6 +444,8 @@ impl Ppu {
         self.reg_dma = source;
         self.dma_active = true;
         self.dma_idx = 0;
+        self.dma_delay = DMA_START_DELAY;
+        self.dma_started = true;
         trace!("dma start with source = 0x{:04X}, destination = 0x{:04X}",
                self.dma_source(), OAM_REGION_START);
     }
@@ -218,6 +456,27 @@ impl Ppu {
         self.dma_active
     }
 
+    /// Wait for the start of the transfer, returns true while no byte can be copied
+    #[inline]
+    pub fn dma_wait(&mut self) -> bool {
+        if self.dma_delay > 0 {
+            self.dma_delay -= 1;
+            return true;
+        }
+        false
+    }
+
+    /// Checks whether a DMA transfer was started since the last call
+    pub fn take_dma_started(&mut self) -> bool {
+        mem::take(&mut self.dma_started)
+    }
+
+    /// Checks whether OAM is used by a DMA transfer and cannot be accessed by the CPU
+    #[inline]
+    pub fn is_oam_locked(&self) -> bool {
+        self.dma_locked
+    }
+
     /// Retrieve current DMA source address
     #[inline]
     pub fn dma_source(&self) -> u16 {
@@ -228,41 +487,171 @@ impl Ppu {
     #[inline]
     pub fn dma_write(&mut self, byte: u8) {
         self.oam[self.dma_idx as usize] = byte;
+        self.dma_locked = true;
         self.dma_idx += 1;
         if self.dma_idx as usize >= OAM_REGION_SIZE {
             self.dma_active = false;
+            self.dma_locked = false;
         }
     }
 
-    /// Sets the new line y coordinate which eventually triggers some exceptions
-    fn set_ly(&mut self, value: u8, it: &mut InterruptHandler) {
-        self.reg_ly = value;
-        if self.reg_ly == self.reg_lyc {
-            self.reg_stat |= FLAG_STAT_LYC;
-            if is_set!(self.reg_stat, FLAG_STAT_IT_LYC) {
-                it.request(InterruptFlag::Lcdc);
+    #[inline]
+    fn inc_ly(&mut self) {
+        self.reg_ly += 1;
+    }
+
+    /// Retrieve the line seen by the LY=LYC comparator at the current dot, if any
+    fn ly_for_compare(&self) -> Option<u8> {
+        if self.last_line {
+            match self.hdots {
+                0..LAST_LINE_LY_RESET => None,
+                LAST_LINE_LY_RESET..LAST_LINE_LYC_END => Some(LAST_LINE),
+                LAST_LINE_LYC_END..LAST_LINE_LYC_RESET => None,
+                _ => Some(0),
+            }
+        } else if self.reg_ly == 0 || self.hdots >= LYC_COMPARE_DELAY {
+            // LY does not change between line 153 and line 0
+            Some(self.reg_ly)
+        } else {
+            None
+        }
+    }
+
+    /// Update the LY=LYC flag and request an interrupt when it gets set
+    fn update_lyc(&mut self, it: &mut InterruptHandler) {
+        if self.ly_for_compare() == Some(self.reg_lyc) {
+            if is_not_set!(self.reg_stat, FLAG_STAT_LYC) {
+                self.reg_stat |= FLAG_STAT_LYC;
+                if is_set!(self.reg_stat, FLAG_STAT_IT_LYC) {
+                    it.request(InterruptFlag::Lcdc);
+                }
             }
         } else {
             self.reg_stat &= !FLAG_STAT_LYC;
         }
     }
 
-    #[inline]
-    fn inc_ly(&mut self, it: &mut InterruptHandler) {
-        self.set_ly(self.reg_ly + 1, it);
+    /// Sets the colors used to render the next pixels
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
     }
 
-    /// Retrieve pixel color from color id
-    fn pixel_from_id(pal: u8, color_id: u8) -> Pixel {
-        match (pal >> (color_id * 2)) & 0x3 {
-            0 => PIXEL_COLOR_WHITE,
-            1 => PIXEL_COLOR_LIGHTGRAY,
-            2 => PIXEL_COLOR_DARKGRAY,
-            3 => PIXEL_COLOR_BLACK,
-            _ => unreachable!(),
+    /// Retrieve the colors currently used
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Value of a palette register
+    pub fn palette_register(&self, register: PaletteRegister) -> u8 {
+        match register {
+            PaletteRegister::Bgp => self.reg_bgp,
+            PaletteRegister::Obp0 => self.reg_obp0,
+            PaletteRegister::Obp1 => self.reg_obp1,
         }
     }
 
+    /// Retrieve the lines that changed since the last call to clear_dirty_lines
+    pub fn dirty_lines(&self) -> &DirtyLines {
+        &self.lines.dirty
+    }
+
+    /// Mark all lines as unchanged, usually after the screen has been updated
+    pub fn clear_dirty_lines(&mut self) {
+        self.lines.dirty.clear();
+    }
+
+    /// Use decoded tiles to render the background & window instead of decoding
+    /// the tile data for every pixel
+    pub fn set_tile_cache(&mut self, enabled: bool) {
+        self.tile_cache_enabled = enabled;
+        self.tile_cache.clear();
+    }
+
+    /// Select how lines are rendered (default = Accurate)
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
+    }
+
+    /// Retrieve how lines are rendered
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Rotate or mirror the frame sent to the screen (default = Identity)
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.lines.invalidate();
+    }
+
+    /// Retrieve the orientation of the frame sent to the screen
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Tint the pixels to show where they come from (default = None)
+    pub fn set_overlay(&mut self, overlay: DebugOverlay) {
+        self.overlay = overlay;
+        self.lines.invalidate();
+    }
+
+    /// Retrieve the debug tint applied to the pixels
+    pub fn overlay(&self) -> DebugOverlay {
+        self.overlay
+    }
+
+    /// Send a finished row to the screen
+    fn output_row<S: Screen>(screen: &mut S, transform: Transform, y: u8, row: &[u32; FRAME_WIDTH]) {
+        match transform {
+            Transform::Identity => screen.set_row(y, row),
+            // Mirrored rows can still be sent at once
+            Transform::FlipVertical => screen.set_row((FRAME_HEIGHT - 1) as u8 - y, row),
+            Transform::FlipHorizontal => {
+                let mut mirrored = *row;
+                mirrored.reverse();
+                screen.set_row(y, &mirrored);
+            },
+            _ => {
+                for (x, word) in row.iter().enumerate() {
+                    let (tx, ty) = transform.apply(x as u8, y);
+                    screen.set_pixel(&Pixel::from_packed(*word), tx, ty);
+                }
+            },
+        }
+    }
+
+    /// Only send 1 frame out of n + 1 to the screen (default = 0)
+    pub fn set_frame_skip(&mut self, n: u8) {
+        self.frame_skip = n;
+        self.skipped_frames = 0;
+    }
+
+    /// Retrieve the number of frames skipped between 2 frames sent to the screen
+    pub fn frame_skip(&self) -> u8 {
+        self.frame_skip
+    }
+
+    /// Checks whether a frame sent to the screen has been completed since the last call
+    pub fn take_frame_ready(&mut self) -> bool {
+        core::mem::take(&mut self.frame_ready)
+    }
+
+    /// Decide whether the frame starting is sent to the screen
+    fn start_frame(&mut self) {
+        if self.skipped_frames >= self.frame_skip {
+            self.skipped_frames = 0;
+            self.output = true;
+        } else {
+            self.skipped_frames += 1;
+            self.output = false;
+        }
+    }
+
+    /// Retrieve pixel color from color id
+    #[inline]
+    fn pixel_from_id(colors: &[Pixel; 4], pal: u8, color_id: u8) -> Pixel {
+        colors[palette::shade(pal, color_id) as usize]
+    }
+
     /// Sets pixel mode
     #[inline]
     fn set_mode(&mut self, mode: u8) {
@@ -329,8 +718,74 @@ impl Ppu {
         is_set!(self.reg_lcdc, FLAG_LCDC_LCD_ENABLE)
     }
 
+    /// Retrieve the current mode
+    pub fn mode(&self) -> PpuMode {
+        match self.reg_stat & FLAG_STAT_MODE {
+            LCD_STATUS_MODE_HBLANK => PpuMode::HBlank,
+            LCD_STATUS_MODE_VBLANK => PpuMode::VBlank,
+            LCD_STATUS_MODE_OAM => PpuMode::OamScan,
+            LCD_STATUS_MODE_XFER => PpuMode::Transfer,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Retrieve the line being processed (0 - 153)
+    /// Unlike LY, this is still 153 at the end of the last line
+    pub fn current_line(&self) -> u8 {
+        if self.last_line { LAST_LINE } else { self.reg_ly }
+    }
+
+    /// Retrieve the number of dots elapsed in the current line (0 - 455)
+    pub fn line_dots(&self) -> u32 {
+        self.hdots
+    }
+
+    /// Checks whether the LCD is on
+    pub fn is_lcd_on(&self) -> bool {
+        !self.pipeline.disabled
+    }
+
+    /// Checks whether the visible lines of a frame are being drawn
+    pub fn is_frame_in_progress(&self) -> bool {
+        self.is_lcd_on() && (self.current_line() as usize) < FRAME_HEIGHT
+    }
+
     /// Used to advance the PPU mode after some CPU cycles
     pub fn step<S: Screen>(&mut self, screen: &mut S, it: &mut InterruptHandler) {
+        let mode = self.reg_stat & FLAG_STAT_MODE;
+
+        if self.is_lcd_enabled() {
+            if self.pipeline.disabled {
+                self.enable(it);
+            }
+            self.tick(screen, it);
+        } else if !self.pipeline.disabled {
+            // LCDC bit 7 can be toggled at any time, even outside vblank
+            self.disable(screen);
+        }
+
+        if self.reg_stat & FLAG_STAT_MODE != mode {
+            screen.on_mode_change(self.mode(), self.reg_ly, self.hdots);
+        }
+
+        if let Some(dots) = self.mode_dots.as_mut() {
+            let index = if self.pipeline.disabled { LCD_OFF_DOTS } else { (self.reg_stat & FLAG_STAT_MODE) as usize };
+            dots[index] += 1;
+        }
+    }
+
+    /// Count the dots spent in each mode (default = false)
+    pub fn set_mode_counters(&mut self, enabled: bool) {
+        self.mode_dots = if enabled { Some([0; 5]) } else { None };
+    }
+
+    /// Retrieve the dots spent in each mode and with the LCD off, then start over
+    pub fn take_mode_dots(&mut self) -> Option<[u32; 5]> {
+        self.mode_dots.as_mut().map(mem::take)
+    }
+
+    /// Process a single dot
+    fn tick<S: Screen>(&mut self, screen: &mut S, it: &mut InterruptHandler) {
         // Dots counter is reset during hblank
         self.hdots += 1;
 
@@ -338,9 +793,11 @@ impl Ppu {
             LCD_STATUS_MODE_OAM => self.handle_mode_oam(),
             LCD_STATUS_MODE_XFER => self.handle_mode_xfer(screen, it),
             LCD_STATUS_MODE_HBLANK => self.handle_mode_hblank(it),
-            LCD_STATUS_MODE_VBLANK => self.handle_mode_vblank(screen, it),
+            LCD_STATUS_MODE_VBLANK => self.handle_mode_vblank(it),
             _ => unreachable!(),
         }
+
+        self.update_lyc(it);
     }
 
     /// Mode 2: OAM scanning
@@ -348,36 +805,45 @@ impl Ppu {
         trace_mode!("oam");
         if self.hdots == 1 {
             self.scan_sprites();
-            // check if this line is a window_y trigger
-            if self.is_win_enabled() &&
-                self.reg_wx < (FRAME_WIDTH as u8) &&
-                self.reg_wy < (FRAME_HEIGHT as u8) &&
-                self.reg_ly >= self.reg_wy &&
-                self.reg_ly < self.reg_wy.wrapping_add(FRAME_HEIGHT as u8)
-            {
-                if !self.pipeline.win_y_triggered {
-                    self.pipeline.win_y_triggered = true;
-                } else {
-                    self.pipeline.win_ly += 1;
-                }
+            // The window can be drawn from the line where LY = WY until the end of the frame
+            if self.is_win_enabled() && self.reg_ly == self.reg_wy {
+                self.pipeline.win_y_triggered = true;
             }
         } else if self.hdots >= OAM_LIMIT_PERIOD {
             self.set_mode(LCD_STATUS_MODE_XFER);
+            // The pixels discarded for SCX % 8 take one dot each
+            self.xfer_end = XFER_LIMIT_PERIOD + (self.reg_scx % 8) as u32;
 
             let y = self.reg_ly.wrapping_add(self.reg_scy);
             let addr_y_offset = (y / 8) as u16 * 32;
             let tile_y = y % 8;
 
-            self.pipeline.init_fetcher(addr_y_offset, tile_y);
+            self.pipeline.init_fetcher(addr_y_offset, tile_y, self.reg_scx % 8);
         }
     }
 
     /// Mode 3: Drawing pixels
     fn handle_mode_xfer<S: Screen>(&mut self, screen: &mut S, it: &mut InterruptHandler) {
         trace!("xfer");
-        if self.pipeline.render_x < FRAME_WIDTH as u8 {
-            self.render(screen);
-        } else if self.hdots >= XFER_LIMIT_PERIOD {
+        // Nothing to draw for a headless screen
+        let draw = !S::HEADLESS;
+        if draw && self.render_mode == RenderMode::Accurate && self.pipeline.render_x < FRAME_WIDTH as u8 {
+            self.render();
+        } else if self.hdots >= self.xfer_end {
+            if self.output && draw {
+                if self.render_mode == RenderMode::Fast {
+                    self.render_line();
+                }
+                Ppu::output_row(screen, self.transform, self.reg_ly, &self.row);
+                self.lines.end_line(self.reg_ly, &self.row);
+            }
+            if self.render_mode == RenderMode::Fast || !draw {
+                self.pipeline.win_active = self.is_win_visible();
+            }
+            // Lines where the window is hidden do not move the window line
+            if self.pipeline.win_active {
+                self.pipeline.win_ly = self.pipeline.win_ly.wrapping_add(1);
+            }
             self.pipeline.bgw_fifo.clear();
             self.set_mode(LCD_STATUS_MODE_HBLANK);
             if is_set!(self.reg_stat, FLAG_STAT_IT_HBLANK) {
@@ -390,9 +856,10 @@ impl Ppu {
     fn handle_mode_hblank(&mut self, it: &mut InterruptHandler) {
         trace!("hblank");
         if self.hdots >= HBLANK_LIMIT_PERIOD {
-            self.inc_ly(it);
+            self.inc_ly();
             // When the frame height is reached, switch to vblank mode
             if self.reg_ly >= FRAME_HEIGHT as u8 {
+                self.frame_ready |= self.output;
                 self.set_mode(LCD_STATUS_MODE_VBLANK);
                 it.request(InterruptFlag::Vblank);
                 if is_set!(self.reg_stat, FLAG_STAT_IT_VBLANK) {
@@ -410,22 +877,16 @@ impl Ppu {
     }
 
     /// Mode 1: Handle VBlank
-    fn handle_mode_vblank<S: Screen>(&mut self, screen: &mut S, it: &mut InterruptHandler) {
+    fn handle_mode_vblank(&mut self, it: &mut InterruptHandler) {
         trace!("vblank");
-        if !self.pipeline.disabled && !self.is_lcd_enabled() {
-            // disable ppu + next frame is white
-            self.disable(screen);
-        } else if self.pipeline.disabled && self.is_lcd_enabled() {
-            // if ppu is enabled, the drawing starts immediately but the frame remains white
-            // instead we can just not re-enable the pipeline in the VBlank mode
-            self.pipeline.disabled = false;
-        }
         if self.hdots >= HBLANK_LIMIT_PERIOD {
             // End of line is reached
-            self.inc_ly(it);
-            if (self.reg_ly as u32 * HBLANK_LIMIT_PERIOD) >= VBLANK_LIMIT_PERIOD {
-                // reset ly
-                self.set_ly(0, it);
+            if !self.last_line {
+                self.inc_ly();
+            } else {
+                // ly was already reset during the last line
+                self.last_line = false;
+                self.start_frame();
                 // reset window conditions
                 self.pipeline.win_ly = 0;
                 self.pipeline.win_y_triggered = false;
@@ -435,17 +896,39 @@ impl Ppu {
                 }
             }
             self.hdots = 0;
+        } else if self.reg_ly == LAST_LINE && self.hdots == LAST_LINE_LY_RESET {
+            self.reg_ly = 0;
+            self.last_line = true;
         }
     }
 
-    /// Disable PPU & sets default LCD screen color
+    /// Disable PPU & fills the LCD screen with the lightest background color
+    /// LY and the line dots are reset, the LCD stays in mode 0 until it is enabled again
     fn disable<S: Screen>(&mut self, screen: &mut S) {
         self.pipeline.disabled = true;
-        let px = Pixel { r: 0xFF, g: 0xFF, b: 0xFF, a: 0xFF };
+        self.reg_ly = 0;
+        self.hdots = 0;
+        self.last_line = false;
+        self.set_mode(LCD_STATUS_MODE_HBLANK);
+        self.reg_stat &= !FLAG_STAT_LYC;
+        self.lines.invalidate();
+        // The blank screen is always sent, nothing else is until the LCD is enabled again
+        self.frame_ready = true;
+        let row = [self.palette.bg[0].packed(); FRAME_WIDTH];
         for y in 0..FRAME_HEIGHT {
-            for x in 0..FRAME_WIDTH {
-                screen.set_pixel(&px, x as u8, y as u8);
-            }
+            Ppu::output_row(screen, self.transform, y as u8, &row);
+        }
+    }
+
+    /// Restart the PPU from the beginning of a frame
+    fn enable(&mut self, it: &mut InterruptHandler) {
+        self.pipeline.disabled = false;
+        self.start_frame();
+        self.pipeline.win_ly = 0;
+        self.pipeline.win_y_triggered = false;
+        self.set_mode(LCD_STATUS_MODE_OAM);
+        if is_set!(self.reg_stat, FLAG_STAT_IT_OAM) {
+            it.request(InterruptFlag::Lcdc);
         }
     }
 
@@ -461,22 +944,45 @@ impl Ppu {
         self.pipeline.bgw_data[0] = tile_index.wrapping_add(offset);
     }
 
-    /// Retrieve window tile index for the current X
+    /// Retrieve the next window tile index
     fn select_win_tiles(&mut self) {
-        if self.reg_wx < (FRAME_WIDTH as u8 + 7)
-            && self.reg_wy < (FRAME_HEIGHT as u8)
+        let tile_y = self.pipeline.win_ly as u16 / 8;
+        let addr = self.pipeline.win_tile_x as u16 + tile_y * 32;
+        let tile_index = self.read(self.win_map_area() + addr);
+        let offset = if is_not_set!(self.reg_lcdc, FLAG_LCDC_BGWIN_TDATA_AREA) {
+            128u8
+        } else {
+            0u8
+        };
+        self.pipeline.bgw_data[0] = tile_index.wrapping_add(offset);
+        self.pipeline.win_tile_x = (self.pipeline.win_tile_x + 1) % 32;
+    }
+
+    /// Whether the window covers part of the current line
+    ///
+    /// The window is drawn from the first line where LY = WY while it is enabled, until the end of the frame.
+    /// It starts when the next pixel to render reaches WX - 7, after the SCX % 8 background pixels are discarded,
+    /// so SCX never moves it.
+    /// Its tile row comes from win_ly and not from LY - WY: win_ly only moves at the end of a line where the window
+    /// was drawn, so hiding it for a few lines (WX >= 167 or LCDC bit 5 cleared) resumes it on its next line.
+    fn is_win_visible(&self) -> bool {
+        self.is_win_enabled()
             && self.pipeline.win_y_triggered
-            && (self.pipeline.fetch_x + 7) >= self.reg_wx {
-                let tile_y = self.pipeline.win_ly as u16 / 8;
-                let addr = (self.pipeline.fetch_x as u16 + 7 - self.reg_wx as u16) / 8 + tile_y * 32;
-                let tile_index = self.read(self.win_map_area() + addr);
-                let offset = if is_not_set!(self.reg_lcdc, FLAG_LCDC_BGWIN_TDATA_AREA) {
-                    128u8
-                } else {
-                    0u8
-                };
-                self.pipeline.bgw_data[0] = tile_index.wrapping_add(offset);
-            }
+            && self.reg_wx < (FRAME_WIDTH as u8 + 7)
+    }
+
+    /// Restart the fetcher on the first window tile when the next pixel to render reaches WX - 7
+    /// The background pixels already in the fifo are dropped
+    fn start_window(&mut self) {
+        self.pipeline.win_active = true;
+        self.pipeline.bgw_fifo.clear();
+        // Keep the fifo positions in sync with the screen for sprites
+        self.pipeline.fetch_x = self.pipeline.lx;
+        self.pipeline.tile_y = self.pipeline.win_ly % 8;
+        self.pipeline.win_tile_x = 0;
+        // With WX < 7 the first window pixels are off screen
+        self.pipeline.skip = 7u8.saturating_sub(self.reg_wx);
+        self.pipeline.state = FetchState::Tile;
     }
 
     /// Retrieve the current background/window tile data
@@ -486,6 +992,13 @@ impl Ppu {
         self.pipeline.bgw_data[1 + offset as usize] = self.read(addr);
     }
 
+    /// Retrieve the current background/window tile row from the tile cache
+    fn load_bgwin_row(&mut self) {
+        let tile = (self.bgwin_data_area() - TILE_DATA_0_START_ADDR) as usize / 16
+            + self.pipeline.bgw_data[0] as usize;
+        self.pipeline.bgw_row = *self.tile_cache.row(&self.vram, tile, self.pipeline.tile_y);
+    }
+
     /// Scan for max 10 sprites in the current scan line
     fn scan_sprites(&mut self) {
         let rel_y = self.reg_ly + 16;
@@ -500,20 +1013,20 @@ impl Ppu {
             let tile_index = self.oam[i + 2];
             let attrs = self.oam[i + 3];
 
-            if rel_y >= y && rel_y < y + obj_size {
-                self.pipeline.push_sprite(Sprite::new(x, y, tile_index, attrs));
+            if rel_y >= y && rel_y - y < obj_size {
+                self.pipeline.push_sprite(Sprite::new(x, y, tile_index, attrs, (i / 4) as u8));
                 if self.pipeline.obj_count >= 10 {
                     break;
                 }
             }
         }
-        // Sort sprites by their X coord
+        // Sort sprites by drawing priority
         self.pipeline.sort_sprites();
     }
 
     /// Retrieve sprite tile index(es) for the current X
     fn select_sprites(&mut self) {
-        let offset = (self.reg_scx % 8) as i16;
+        let offset = self.pipeline.scx_fine as i16;
         self.pipeline.obj_fetched_count = 0;
 
         for i in 0..(self.pipeline.obj_count as usize) {
@@ -526,11 +1039,6 @@ impl Ppu {
                 || (rel_x1 >= self.pipeline.fetch_x as i16 && rel_x1 < fetch_x1) {
                     self.pipeline.obj_fetched_idx[self.pipeline.obj_fetched_count as usize] = i as u8;
                     self.pipeline.obj_fetched_count += 1;
-                    // There cannot be more than 3 sprites to appear within 8 pixels
-                    // left + middle + right
-                    if self.pipeline.obj_fetched_count >= 3 {
-                        break;
-                    }
                 }
         }
     }
@@ -541,10 +1049,12 @@ impl Ppu {
 
         for i in 0..(self.pipeline.obj_fetched_count as usize) {
             let obj = &self.pipeline.obj_list[self.pipeline.obj_fetched_idx[i] as usize];
+            // The object size may have changed since the OAM scan, only keep the row bits
+            let row = (self.reg_ly + 16).wrapping_sub(obj.y) & (obj_size - 1);
             let tile_y = if obj.is_y_flipped() {
-                ((obj_size * 2) - 2) - ((self.reg_ly + 16) - obj.y) * 2
+                (obj_size - 1 - row) * 2
             } else {
-                ((self.reg_ly + 16) - obj.y) * 2
+                row * 2
             } as u16;
             let tile_index = if obj_size == 16 {
                 obj.tile_index & 0xFE
@@ -557,6 +1067,7 @@ impl Ppu {
     }
 
     /// Push pixel in the bgw_fifo
+    /// Only color ids are pushed, the final color is resolved in render
     fn push_pixels(&mut self) {
         // get color id from low and high bytes at the bit position
         macro_rules! color_id {
@@ -569,68 +1080,163 @@ impl Ppu {
         let bg_high = self.pipeline.bgw_data[2];
 
         for i in (0..=7u8).rev() {
-            let mut bg_color_id = 0;
-
-            // Retrieve bg color id if enabled
-            if self.is_bgwin_enabled() {
-                bg_color_id = color_id!(bg_low, bg_high, i);
+            if self.pipeline.skip > 0 {
+                self.pipeline.skip -= 1;
+                continue;
             }
+            let bg_color_id = if self.tile_cache_enabled {
+                self.pipeline.bgw_row[7 - i as usize]
+            } else {
+                color_id!(bg_low, bg_high, i)
+            };
+            let mut fifo_px = FifoPixel {
+                bg_color_id,
+                window: self.pipeline.win_active,
+                ..FifoPixel::default()
+            };
 
-            let mut pixel = Ppu::pixel_from_id(self.reg_bgp, bg_color_id);
-
-            // Check sprites if enabled
-            if self.is_obj_enabled() {
-                for j in 0..(self.pipeline.obj_fetched_count as usize) {
-                    let obj = self.pipeline.obj_list[self.pipeline.obj_fetched_idx[j] as usize];
-                    let rel_x = (obj.x as i16).wrapping_sub(8).wrapping_add((self.reg_scx % 8) as i16);
-
-                    // Too far
-                    if rel_x.wrapping_add(8) < self.pipeline.fetch_x as i16 {
-                        continue;
-                    }
-                    let offset = self.pipeline.fetch_x as i16 - rel_x as i16;
-                    if !(0..=7).contains(&offset) {
-                        continue;
-                    }
-                    let bit = if obj.is_x_flipped() { offset } else { 7 - offset };
-                    let obj_low = self.pipeline.obj_data[j * 2];
-                    let obj_high = self.pipeline.obj_data[j * 2 + 1];
-                    let obj_color_id = color_id!(obj_low, obj_high, bit);
+            // Keep the first non transparent sprite pixel
+            for j in 0..(self.pipeline.obj_fetched_count as usize) {
+                let obj = self.pipeline.obj_list[self.pipeline.obj_fetched_idx[j] as usize];
+                let rel_x = (obj.x as i16).wrapping_sub(8).wrapping_add(self.pipeline.scx_fine as i16);
 
-                    if obj_color_id == 0 {
-                        continue;
-                    }
-                    if !obj.is_bgwin_prio() || bg_color_id == 0 {
-                        let pal = if obj.palette_number() == 0 { self.reg_obp0 } else { self.reg_obp1 };
-                        pixel = Ppu::pixel_from_id(pal, obj_color_id);
-                        break;
-                    }
+                // Too far
+                if rel_x.wrapping_add(8) < self.pipeline.fetch_x as i16 {
+                    continue;
+                }
+                let offset = self.pipeline.fetch_x as i16 - rel_x;
+                if !(0..=7).contains(&offset) {
+                    continue;
+                }
+                let bit = if obj.is_x_flipped() { offset } else { 7 - offset };
+                let obj_low = self.pipeline.obj_data[j * 2];
+                let obj_high = self.pipeline.obj_data[j * 2 + 1];
+                let obj_color_id = color_id!(obj_low, obj_high, bit);
+
+                if obj_color_id != 0 {
+                    fifo_px.obj_color_id = obj_color_id;
+                    fifo_px.obj_palette = obj.palette_number();
+                    fifo_px.obj_bgwin_prio = obj.is_bgwin_prio();
+                    break;
                 }
             }
-            self.pipeline.bgw_fifo.push(pixel);
+            self.pipeline.bgw_fifo.push(fifo_px);
             self.pipeline.fetch_x += 1;
         }
+    }
 
+    /// Resolve the color of a pixel coming out of the fifo
+    /// This uses the current palettes and LCDC flags
+    fn mix_pixel(&self, fifo_px: &FifoPixel) -> Pixel {
+        let bg_color_id = if self.is_bgwin_enabled() { fifo_px.bg_color_id } else { 0 };
+        let obj_visible = self.is_obj_enabled() && fifo_px.obj_color_id != 0;
+        let obj_drawn = obj_visible && (!fifo_px.obj_bgwin_prio || bg_color_id == 0);
+
+        let px = if obj_drawn {
+            if fifo_px.obj_palette == 0 {
+                Ppu::pixel_from_id(&self.palette.obj0, self.reg_obp0, fifo_px.obj_color_id)
+            } else {
+                Ppu::pixel_from_id(&self.palette.obj1, self.reg_obp1, fifo_px.obj_color_id)
+            }
+        } else {
+            Ppu::pixel_from_id(&self.palette.bg, self.reg_bgp, bg_color_id)
+        };
+
+        let tint = match self.overlay {
+            DebugOverlay::None => return px,
+            DebugOverlay::Layers if obj_drawn && fifo_px.obj_palette == 0 => TINT_OBJ0,
+            DebugOverlay::Layers if obj_drawn => TINT_OBJ1,
+            DebugOverlay::Layers if fifo_px.window => TINT_WINDOW,
+            DebugOverlay::Layers => TINT_BACKGROUND,
+            DebugOverlay::Priority if obj_drawn => TINT_OBJ_DRAWN,
+            DebugOverlay::Priority if obj_visible => TINT_OBJ_HIDDEN,
+            DebugOverlay::Priority => return px,
+        };
+        Pixel {
+            r: ((px.r as u16 + tint.r as u16) / 2) as u8,
+            g: ((px.g as u16 + tint.g as u16) / 2) as u8,
+            b: ((px.b as u16 + tint.b as u16) / 2) as u8,
+            a: px.a,
+        }
     }
 
     /// Handle pixel row and display pixels if any
-    fn render<S: Screen>(&mut self, screen: &mut S) {
-        if !self.pipeline.disabled {
-            self.fetch_pixel_row();
-
-            if self.pipeline.bgw_fifo.size() > 0 {
-                let px = self.pipeline.bgw_fifo.pop();
-                if self.pipeline.lx >= (self.reg_scx % 8) {
-                    screen.set_pixel(&px, self.pipeline.render_x, self.reg_ly);
-                    self.pipeline.render_x += 1;
+    fn render(&mut self) {
+        // The SCX fine pixels are discarded before the window can start
+        if !self.pipeline.win_active
+            && self.pipeline.lx >= self.pipeline.scx_fine
+            && self.pipeline.render_x + 7 >= self.reg_wx
+            && self.is_win_visible() {
+                self.start_window();
+            }
+        self.fetch_pixel_row();
+
+        if self.pipeline.bgw_fifo.size() > 0 {
+            let fifo_px = self.pipeline.bgw_fifo.pop();
+            if self.pipeline.lx >= self.pipeline.scx_fine {
+                if self.output {
+                    self.row[self.pipeline.render_x as usize] = self.mix_pixel(&fifo_px).packed();
                 }
-                self.pipeline.lx += 1;
+                self.pipeline.render_x += 1;
             }
-        } else {
-            self.pipeline.render_x += 1;
+            self.pipeline.lx += 1;
         }
     }
 
+    /// Render the whole current line at once
+    /// Sprites are the ones found during the OAM scan
+    fn render_line(&mut self) {
+        let bg_y = self.reg_ly.wrapping_add(self.reg_scy);
+        let data_area_tile = (self.bgwin_data_area() - TILE_DATA_0_START_ADDR) as usize / 16;
+        let data_offset = if is_not_set!(self.reg_lcdc, FLAG_LCDC_BGWIN_TDATA_AREA) { 128u8 } else { 0u8 };
+        let win_visible = self.is_win_visible();
+        let obj_size = self.obj_size();
+
+        for x in 0..FRAME_WIDTH as u8 {
+            let mut fifo_px = FifoPixel::default();
+
+            if self.is_bgwin_enabled() {
+                // Select either the window or the background coordinates
+                fifo_px.window = win_visible && x + 7 >= self.reg_wx;
+                let (map_area, map_x, map_y) = if fifo_px.window {
+                    (self.win_map_area(), x + 7 - self.reg_wx, self.pipeline.win_ly)
+                } else {
+                    (self.bg_map_area(), x.wrapping_add(self.reg_scx), bg_y)
+                };
+                let map_addr = map_area + (map_y / 8) as u16 * 32 + (map_x / 8) as u16;
+                let tile = data_area_tile + self.read(map_addr).wrapping_add(data_offset) as usize;
+                fifo_px.bg_color_id = self.tile_cache.row(&self.vram, tile, map_y % 8)[(map_x % 8) as usize];
+            }
+
+            // Keep the first non transparent sprite pixel
+            for i in 0..(self.pipeline.obj_count as usize) {
+                let obj = self.pipeline.obj_list[i];
+                let offset = (x + 8).wrapping_sub(obj.x);
+                if offset >= 8 {
+                    continue;
+                }
+                let mut row = (self.reg_ly + 16).wrapping_sub(obj.y) & (obj_size - 1);
+                if obj.is_y_flipped() {
+                    row = obj_size - 1 - row;
+                }
+                let tile_index = if obj_size == 16 { obj.tile_index & 0xFE } else { obj.tile_index };
+                let tile = tile_index as usize + (row / 8) as usize;
+                let col = if obj.is_x_flipped() { 7 - offset } else { offset };
+                let obj_color_id = self.tile_cache.row(&self.vram, tile, row % 8)[col as usize];
+
+                if obj_color_id != 0 {
+                    fifo_px.obj_color_id = obj_color_id;
+                    fifo_px.obj_palette = obj.palette_number();
+                    fifo_px.obj_bgwin_prio = obj.is_bgwin_prio();
+                    break;
+                }
+            }
+
+            self.row[x as usize] = self.mix_pixel(&fifo_px).packed();
+        }
+        self.pipeline.render_x = FRAME_WIDTH as u8;
+    }
+
     /// Manage the pixel fetcher state machine
     fn fetch_pixel_row(&mut self) {
         self.pipeline.ticks += 1;
@@ -646,24 +1252,29 @@ impl Ppu {
             FetchState::Tile => {
                 // Retrieve tile index
                 if self.is_bgwin_enabled() {
-                    self.select_bg_tiles();
-
-                    if self.is_win_enabled() {
+                    if self.pipeline.win_active {
                         self.select_win_tiles();
+                    } else {
+                        self.select_bg_tiles();
                     }
                 }
-                if self.is_obj_enabled() {
-                    self.select_sprites();
-                }
+                // Sprites are always fetched, OBJ enable is checked when mixing pixels
+                self.select_sprites();
                 self.pipeline.state = FetchState::TileDataLow;
             },
             FetchState::TileDataLow => {
-                self.load_bgwin_data(0);
+                if self.tile_cache_enabled {
+                    self.load_bgwin_row();
+                } else {
+                    self.load_bgwin_data(0);
+                }
                 self.load_sprite_data(0);
                 self.pipeline.state = FetchState::TileDataHigh;
             },
             FetchState::TileDataHigh => {
-                self.load_bgwin_data(1);
+                if !self.tile_cache_enabled {
+                    self.load_bgwin_data(1);
+                }
                 self.load_sprite_data(1);
                 self.pipeline.state = FetchState::Sleep;
             },
@@ -708,7 +1319,8 @@ impl MemoryRegion for Ppu {
     fn write(&mut self, address: u16, value: u8) {
         match address {
             VRAM_REGION_START..=VRAM_REGION_END => {
-                self.vram[(address - VRAM_REGION_START) as usize] = value
+                self.vram[(address - VRAM_REGION_START) as usize] = value;
+                self.tile_cache.invalidate(address);
             },
             OAM_REGION_START..=OAM_REGION_END => {
                 self.oam[(address - OAM_REGION_START) as usize] = value;
@@ -718,6 +1330,8 @@ impl MemoryRegion for Ppu {
             REG_STAT_ADDR => self.reg_stat = (value & 0xF8) | (self.reg_stat & 0x07),
             REG_SCY_ADDR => self.reg_scy = value,
             REG_SCX_ADDR => self.reg_scx = value,
+            // LY is readonly
+            REG_LY_ADDR => (),
             REG_LYC_ADDR => self.reg_lyc = value,
             REG_WY_ADDR => self.reg_wy = value,
             REG_WX_ADDR => self.reg_wx = value,
@@ -729,3 +1343,598 @@ impl MemoryRegion for Ppu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::palette::{DMG_GREEN, GRAYSCALE};
+
+    const PIXEL_COLOR_WHITE: Pixel = GRAYSCALE[0];
+    const PIXEL_COLOR_BLACK: Pixel = GRAYSCALE[3];
+
+    struct TestScreen {
+        pixels: [[Pixel; FRAME_WIDTH]; FRAME_HEIGHT],
+    }
+
+    impl Screen for TestScreen {
+        fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+            self.pixels[y as usize][x as usize] = *px;
+        }
+
+        fn update(&mut self) {
+        }
+    }
+
+    fn new_screen() -> TestScreen {
+        TestScreen { pixels: [[Pixel::default(); FRAME_WIDTH]; FRAME_HEIGHT] }
+    }
+
+    /// Create a ppu where the whole background is filled with color id 3
+    fn new_ppu() -> Ppu {
+        let mut ppu = Ppu::new();
+        for address in TILE_DATA_0_START_ADDR..(TILE_DATA_0_START_ADDR + 16) {
+            ppu.write(address, 0xFF);
+        }
+        ppu.write(REG_BGP_ADDR, 0xE4);
+        ppu
+    }
+
+    /// Step until the ppu reaches the provided line & dot
+    fn run_until<S: Screen>(ppu: &mut Ppu, screen: &mut S, it: &mut InterruptHandler, ly: u8, hdots: u32) {
+        while ppu.reg_ly != ly || ppu.hdots != hdots {
+            ppu.step(screen, it);
+        }
+    }
+
+    #[test]
+    fn it_applies_bgp_writes_during_mode_3() {
+        let mut ppu = new_ppu();
+        let mut screen = new_screen();
+        let mut it = InterruptHandler::new();
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, 0, OAM_LIMIT_PERIOD + 80);
+        assert_eq!(ppu.reg_stat & FLAG_STAT_MODE, LCD_STATUS_MODE_XFER);
+        let split_x = ppu.pipeline.render_x as usize;
+        assert!(split_x > 0 && split_x < FRAME_WIDTH);
+
+        ppu.write(REG_BGP_ADDR, 0x00);
+        run_until(&mut ppu, &mut screen, &mut it, 1, 0);
+
+        let line = &screen.pixels[0];
+        assert!(line[..split_x].iter().all(|px| *px == PIXEL_COLOR_BLACK));
+        assert!(line[split_x..].iter().all(|px| *px == PIXEL_COLOR_WHITE));
+    }
+
+    #[test]
+    fn it_sets_the_lyc_flag_one_m_cycle_after_ly_changes() {
+        let mut ppu = new_ppu();
+        let mut screen = new_screen();
+        let mut it = InterruptHandler::new();
+
+        ppu.write(REG_LYC_ADDR, 10);
+        ppu.write(REG_STAT_ADDR, FLAG_STAT_IT_LYC);
+        run_until(&mut ppu, &mut screen, &mut it, 10, LYC_COMPARE_DELAY - 1);
+        it.clear(InterruptFlag::Lcdc);
+        assert!(is_not_set!(ppu.reg_stat, FLAG_STAT_LYC));
+
+        ppu.step(&mut screen, &mut it);
+        assert!(is_set!(ppu.reg_stat, FLAG_STAT_LYC));
+        assert!(is_set!(it.read(REG_IF_ADDR), InterruptFlag::Lcdc as u8));
+
+        // The flag is cleared as soon as the next line starts
+        run_until(&mut ppu, &mut screen, &mut it, 11, 0);
+        assert!(is_not_set!(ppu.reg_stat, FLAG_STAT_LYC));
+    }
+
+    #[test]
+    fn it_handles_lyc_on_the_last_line() {
+        let mut ppu = new_ppu();
+        let mut screen = new_screen();
+        let mut it = InterruptHandler::new();
+
+        ppu.write(REG_LYC_ADDR, LAST_LINE);
+        run_until(&mut ppu, &mut screen, &mut it, LAST_LINE, LAST_LINE_LY_RESET - 1);
+        assert!(is_not_set!(ppu.reg_stat, FLAG_STAT_LYC));
+
+        // LY now reads 0 but the comparator still sees 153
+        ppu.step(&mut screen, &mut it);
+        assert_eq!(ppu.read(REG_LY_ADDR), 0);
+        assert!(is_set!(ppu.reg_stat, FLAG_STAT_LYC));
+
+        ppu.write(REG_LYC_ADDR, 0);
+        ppu.step(&mut screen, &mut it);
+        assert!(is_not_set!(ppu.reg_stat, FLAG_STAT_LYC));
+
+        // LYC=0 matches before the end of the last line and stays set on line 0
+        while ppu.hdots < LAST_LINE_LYC_RESET {
+            ppu.step(&mut screen, &mut it);
+        }
+        assert!(ppu.last_line);
+        assert!(is_set!(ppu.reg_stat, FLAG_STAT_LYC));
+        run_until(&mut ppu, &mut screen, &mut it, 0, 1);
+        assert!(!ppu.last_line);
+        assert!(is_set!(ppu.reg_stat, FLAG_STAT_LYC));
+    }
+
+    #[test]
+    fn it_disables_the_lcd_outside_vblank() {
+        let mut ppu = new_ppu();
+        let mut screen = new_screen();
+        let mut it = InterruptHandler::new();
+
+        ppu.set_palette(Palette::new(DMG_GREEN));
+        run_until(&mut ppu, &mut screen, &mut it, 40, OAM_LIMIT_PERIOD + 100);
+        ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC & !FLAG_LCDC_LCD_ENABLE);
+        ppu.step(&mut screen, &mut it);
+
+        assert_eq!(ppu.read(REG_LY_ADDR), 0);
+        assert_eq!(ppu.reg_stat & FLAG_STAT_MODE, LCD_STATUS_MODE_HBLANK);
+        // The blank screen uses the lightest color of the background palette
+        assert!(screen.pixels.iter().flatten().all(|px| *px == DMG_GREEN[0]));
+
+        // Nothing happens while the lcd is off
+        for _ in 0..HBLANK_LIMIT_PERIOD * 2 {
+            ppu.step(&mut screen, &mut it);
+        }
+        assert_eq!(ppu.read(REG_LY_ADDR), 0);
+        assert_eq!(ppu.hdots, 0);
+
+        // The next frame starts from the first line
+        ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC);
+        ppu.step(&mut screen, &mut it);
+        assert_eq!(ppu.reg_stat & FLAG_STAT_MODE, LCD_STATUS_MODE_OAM);
+        assert_eq!(ppu.hdots, 1);
+        run_until(&mut ppu, &mut screen, &mut it, 1, 0);
+        assert!(screen.pixels[0].iter().all(|px| *px == DMG_GREEN[3]));
+    }
+
+    struct CountScreen {
+        pixels: usize,
+    }
+
+    impl Screen for CountScreen {
+        fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+            self.pixels += 1;
+        }
+
+        fn update(&mut self) {
+        }
+    }
+
+    #[test]
+    fn it_skips_frames() {
+        let mut ppu = new_ppu();
+        let mut screen = CountScreen { pixels: 0 };
+        let mut it = InterruptHandler::new();
+        ppu.set_frame_skip(1);
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        ppu.take_frame_ready();
+        let mut frames = [0usize; 4];
+        for pixels in frames.iter_mut() {
+            screen.pixels = 0;
+            run_until(&mut ppu, &mut screen, &mut it, FRAME_HEIGHT as u8, 0);
+            run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+            *pixels = screen.pixels;
+            assert_eq!(ppu.take_frame_ready(), *pixels > 0);
+        }
+
+        assert_eq!(frames[0] + frames[1], FRAME_WIDTH * FRAME_HEIGHT);
+        assert_eq!(frames[..2], frames[2..]);
+    }
+
+    struct ModeScreen {
+        changes: [(PpuMode, u8, u32); 8],
+        count: usize,
+    }
+
+    impl Screen for ModeScreen {
+        fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+        }
+
+        fn update(&mut self) {
+        }
+
+        fn on_mode_change(&mut self, mode: PpuMode, ly: u8, dots: u32) {
+            if self.count < self.changes.len() {
+                self.changes[self.count] = (mode, ly, dots);
+                self.count += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn it_notifies_mode_changes() {
+        let mut ppu = new_ppu();
+        let mut screen = ModeScreen { changes: [(PpuMode::HBlank, 0, 0); 8], count: 0 };
+        let mut it = InterruptHandler::new();
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        screen.count = 0;
+        run_until(&mut ppu, &mut screen, &mut it, 1, 1);
+
+        assert_eq!(screen.count, 3);
+        assert_eq!(screen.changes[0], (PpuMode::Transfer, 0, OAM_LIMIT_PERIOD));
+        assert_eq!(screen.changes[1].0, PpuMode::HBlank);
+        assert!(screen.changes[1].2 >= XFER_LIMIT_PERIOD);
+        assert_eq!(screen.changes[2], (PpuMode::OamScan, 1, 0));
+        assert_eq!(ppu.mode(), PpuMode::OamScan);
+    }
+
+    #[test]
+    fn it_tracks_lines_that_changed() {
+        let mut ppu = new_ppu();
+        let mut screen = new_screen();
+        let mut it = InterruptHandler::new();
+
+        // The first frame is fully dirty
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, 0, 1);
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        assert_eq!(ppu.dirty_lines().count(), FRAME_HEIGHT);
+
+        ppu.clear_dirty_lines();
+        run_until(&mut ppu, &mut screen, &mut it, 0, 1);
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        assert!(ppu.dirty_lines().is_empty());
+
+        // Change the first row of tile 0 only, it appears on every 8th line
+        ppu.write(TILE_DATA_0_START_ADDR, 0x00);
+        ppu.write(TILE_DATA_0_START_ADDR + 1, 0x00);
+        run_until(&mut ppu, &mut screen, &mut it, 0, 1);
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+
+        assert_eq!(ppu.dirty_lines().count(), FRAME_HEIGHT / 8);
+        assert!(ppu.dirty_lines().iter().all(|line| line % 8 == 0));
+    }
+
+    #[test]
+    fn it_renders_the_same_frame_with_the_tile_cache() {
+        let mut screen = new_screen();
+        let mut cached_screen = new_screen();
+        let mut it = InterruptHandler::new();
+        let mut ppu = new_ppu();
+        let mut cached_ppu = new_ppu();
+        cached_ppu.set_tile_cache(true);
+
+        for ppu in [&mut ppu, &mut cached_ppu] {
+            // A few different tiles in the first map row
+            for address in (TILE_DATA_0_START_ADDR + 16)..(TILE_DATA_0_START_ADDR + 64) {
+                ppu.write(address, address as u8);
+            }
+            for x in 0..4 {
+                ppu.write(TILE_MAP_0_START_ADDR + x, x as u8);
+            }
+            ppu.write(REG_SCX_ADDR, 3);
+        }
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, 8, 0);
+        run_until(&mut cached_ppu, &mut cached_screen, &mut it, 0, 0);
+        run_until(&mut cached_ppu, &mut cached_screen, &mut it, 8, 0);
+        // Tile data changes are picked up by the cache
+        cached_ppu.write(TILE_DATA_0_START_ADDR + 16, 0xAA);
+        ppu.write(TILE_DATA_0_START_ADDR + 16, 0xAA);
+        run_until(&mut ppu, &mut screen, &mut it, 16, 0);
+        run_until(&mut cached_ppu, &mut cached_screen, &mut it, 16, 0);
+
+        assert!(screen.pixels[..16] == cached_screen.pixels[..16]);
+    }
+
+    #[test]
+    fn it_renders_the_same_frame_with_the_fast_renderer() {
+        let mut screen = new_screen();
+        let mut fast_screen = new_screen();
+        let mut it = InterruptHandler::new();
+        let mut ppu = new_ppu();
+        let mut fast_ppu = new_ppu();
+        fast_ppu.set_render_mode(RenderMode::Fast);
+
+        for ppu in [&mut ppu, &mut fast_ppu] {
+            for address in (TILE_DATA_0_START_ADDR + 16)..(TILE_DATA_0_START_ADDR + 64) {
+                ppu.write(address, address as u8);
+            }
+            for x in 0..4 {
+                ppu.write(TILE_MAP_0_START_ADDR + x, x as u8);
+            }
+            ppu.write(REG_SCX_ADDR, 3);
+            ppu.write(REG_SCY_ADDR, 2);
+            ppu.write(REG_OBP0_ADDR, 0x1B);
+            ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC | FLAG_LCDC_OBJ_ENABLE);
+            // 2 sprites using tile 2, the second one is flipped
+            for (i, byte) in [20, 12, 2, 0, 22, 30, 2, 0x60].iter().enumerate() {
+                ppu.write(OAM_REGION_START + i as u16, *byte);
+            }
+        }
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, 24, 0);
+        run_until(&mut fast_ppu, &mut fast_screen, &mut it, 0, 0);
+        run_until(&mut fast_ppu, &mut fast_screen, &mut it, 24, 0);
+
+        assert!(screen.pixels[..24] == fast_screen.pixels[..24]);
+    }
+
+    #[test]
+    fn it_starts_the_window_at_wx_on_scrolled_lines() {
+        for mode in [RenderMode::Accurate, RenderMode::Fast] {
+            let mut screen = new_screen();
+            let mut it = InterruptHandler::new();
+            let mut ppu = new_ppu();
+            ppu.set_render_mode(mode);
+
+            // White window (tile 1) over the black background
+            for address in TILE_MAP_1_START_ADDR..(TILE_MAP_1_START_ADDR + 32) {
+                ppu.write(address, 1);
+            }
+            ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC | FLAG_LCDC_WIN_ENABLE | FLAG_LCDC_WIN_TMAP_AREA);
+            ppu.write(REG_SCX_ADDR, 3);
+            ppu.write(REG_WY_ADDR, 0);
+            ppu.write(REG_WX_ADDR, 7 + 16);
+
+            run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+            run_until(&mut ppu, &mut screen, &mut it, 1, 0);
+
+            let line = &screen.pixels[0];
+            assert!(line[..16].iter().all(|px| *px == PIXEL_COLOR_BLACK));
+            assert!(line[16..].iter().all(|px| *px == PIXEL_COLOR_WHITE));
+        }
+    }
+
+    #[test]
+    fn it_only_counts_window_lines_where_the_window_is_drawn() {
+        for mode in [RenderMode::Accurate, RenderMode::Fast] {
+            let mut screen = new_screen();
+            let mut it = InterruptHandler::new();
+            let mut ppu = new_ppu();
+            ppu.set_render_mode(mode);
+
+            // Window rows: white (tile 1) then light gray (tile 2)
+            for address in (TILE_DATA_0_START_ADDR + 32)..(TILE_DATA_0_START_ADDR + 48) {
+                ppu.write(address, if address % 2 == 0 { 0xFF } else { 0x00 });
+            }
+            for x in 0..32 {
+                ppu.write(TILE_MAP_1_START_ADDR + x, 1);
+                ppu.write(TILE_MAP_1_START_ADDR + 32 + x, 2);
+            }
+            ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC | FLAG_LCDC_WIN_ENABLE | FLAG_LCDC_WIN_TMAP_AREA);
+            ppu.write(REG_WY_ADDR, 0);
+            ppu.write(REG_WX_ADDR, 7);
+
+            run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+            run_until(&mut ppu, &mut screen, &mut it, 4, 0);
+            // Hide the window for 8 lines
+            ppu.write(REG_WX_ADDR, 200);
+            run_until(&mut ppu, &mut screen, &mut it, 12, 0);
+            ppu.write(REG_WX_ADDR, 7);
+            run_until(&mut ppu, &mut screen, &mut it, 20, 0);
+
+            assert!(screen.pixels[..4].iter().flatten().all(|px| *px == PIXEL_COLOR_WHITE));
+            assert!(screen.pixels[4..12].iter().flatten().all(|px| *px == PIXEL_COLOR_BLACK));
+            assert!(screen.pixels[12..16].iter().flatten().all(|px| *px == PIXEL_COLOR_WHITE));
+            assert!(screen.pixels[16..20].iter().flatten().all(|px| *px == GRAYSCALE[1]));
+        }
+    }
+
+    #[test]
+    fn it_draws_sprites_by_x_then_oam_index() {
+        for mode in [RenderMode::Accurate, RenderMode::Fast] {
+            let mut screen = new_screen();
+            let mut it = InterruptHandler::new();
+            let mut ppu = new_ppu();
+            ppu.set_render_mode(mode);
+
+            // Background off, black (OBP0) and light gray (OBP1) sprites using tile 0
+            ppu.write(REG_LCDC_ADDR, FLAG_LCDC_LCD_ENABLE | FLAG_LCDC_OBJ_ENABLE);
+            ppu.write(REG_OBP0_ADDR, 0xE4);
+            ppu.write(REG_OBP1_ADDR, 0x54);
+            let sprites = [
+                // 3 transparent sprites (tile 3) in front
+                [16, 28, 3, 0x00], [16, 28, 3, 0x00], [16, 28, 3, 0x00],
+                // Same X: the first one in OAM wins
+                [16, 28, 0, 0x10], [16, 28, 0, 0x00],
+                // Overlapping the previous ones with a bigger X
+                [16, 32, 0, 0x00],
+            ];
+            for (i, byte) in sprites.iter().flatten().enumerate() {
+                ppu.write(OAM_REGION_START + i as u16, *byte);
+            }
+
+            run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+            run_until(&mut ppu, &mut screen, &mut it, 1, 0);
+
+            let line = &screen.pixels[0];
+            assert!(line[..20].iter().all(|px| *px == PIXEL_COLOR_WHITE));
+            assert!(line[20..28].iter().all(|px| *px == GRAYSCALE[1]));
+            assert!(line[28..32].iter().all(|px| *px == PIXEL_COLOR_BLACK));
+            assert!(line[32..].iter().all(|px| *px == PIXEL_COLOR_WHITE));
+        }
+    }
+
+    #[test]
+    fn it_tints_pixels_with_the_debug_overlay() {
+        let blend = |px: Pixel, tint: Pixel| Pixel {
+            r: ((px.r as u16 + tint.r as u16) / 2) as u8,
+            g: ((px.g as u16 + tint.g as u16) / 2) as u8,
+            b: ((px.b as u16 + tint.b as u16) / 2) as u8,
+            a: px.a,
+        };
+
+        for mode in [RenderMode::Accurate, RenderMode::Fast] {
+            let mut ppu = new_ppu();
+            ppu.set_render_mode(mode);
+
+            // White window (tile 1) from x = 16 over the black background
+            for address in TILE_MAP_1_START_ADDR..(TILE_MAP_1_START_ADDR + 32) {
+                ppu.write(address, 1);
+            }
+            ppu.write(REG_LCDC_ADDR, DEFAULT_REG_DMG_LCDC | FLAG_LCDC_WIN_ENABLE
+                      | FLAG_LCDC_WIN_TMAP_AREA | FLAG_LCDC_OBJ_ENABLE);
+            ppu.write(REG_WY_ADDR, 0);
+            ppu.write(REG_WX_ADDR, 7 + 16);
+            ppu.write(REG_OBP0_ADDR, 0xE4);
+            // A sprite behind the background at x = 4, another one over the window at x = 20
+            for (i, byte) in [16, 12, 0, 0x80, 16, 28, 0, 0x00].iter().enumerate() {
+                ppu.write(OAM_REGION_START + i as u16, *byte);
+            }
+
+            for overlay in [DebugOverlay::Layers, DebugOverlay::Priority] {
+                let mut screen = new_screen();
+                let mut it = InterruptHandler::new();
+                ppu.set_overlay(overlay);
+                run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+                run_until(&mut ppu, &mut screen, &mut it, 1, 0);
+
+                let line = &screen.pixels[0];
+                let expected = match overlay {
+                    DebugOverlay::Layers => [
+                        (0..16, blend(PIXEL_COLOR_BLACK, TINT_BACKGROUND)),
+                        (16..20, blend(PIXEL_COLOR_WHITE, TINT_WINDOW)),
+                        (20..28, blend(PIXEL_COLOR_BLACK, TINT_OBJ0)),
+                        (28..FRAME_WIDTH, blend(PIXEL_COLOR_WHITE, TINT_WINDOW)),
+                    ],
+                    _ => [
+                        (0..4, PIXEL_COLOR_BLACK),
+                        (4..12, blend(PIXEL_COLOR_BLACK, TINT_OBJ_HIDDEN)),
+                        (16..20, PIXEL_COLOR_WHITE),
+                        (20..28, blend(PIXEL_COLOR_BLACK, TINT_OBJ_DRAWN)),
+                    ],
+                };
+                for (range, px) in expected {
+                    assert!(line[range].iter().all(|p| *p == px), "{:?} in {:?}", overlay, mode);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_emits_packed_rows() {
+        struct RowScreen {
+            rows: [[u32; FRAME_WIDTH]; FRAME_HEIGHT],
+        }
+
+        impl Screen for RowScreen {
+            fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+                unreachable!();
+            }
+
+            fn set_row(&mut self, y: u8, row: &[u32; FRAME_WIDTH]) {
+                self.rows[y as usize] = *row;
+            }
+
+            fn update(&mut self) {
+            }
+        }
+
+        let mut screen = new_screen();
+        let mut row_screen = RowScreen { rows: [[0u32; FRAME_WIDTH]; FRAME_HEIGHT] };
+        let mut it = InterruptHandler::new();
+        let mut ppu = new_ppu();
+        let mut row_ppu = new_ppu();
+
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, FRAME_HEIGHT as u8, 0);
+        run_until(&mut row_ppu, &mut row_screen, &mut it, 0, 0);
+        run_until(&mut row_ppu, &mut row_screen, &mut it, FRAME_HEIGHT as u8, 0);
+
+        for (pixels, row) in screen.pixels.iter().zip(row_screen.rows.iter()) {
+            assert!(pixels.iter().map(Pixel::packed).eq(row.iter().copied()));
+        }
+    }
+
+    #[test]
+    fn it_transforms_rows_sent_to_the_screen() {
+        /// Large enough for both orientations
+        struct SquareScreen {
+            pixels: [[Pixel; FRAME_WIDTH]; FRAME_WIDTH],
+        }
+
+        impl Screen for SquareScreen {
+            fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+                self.pixels[y as usize][x as usize] = *px;
+            }
+
+            fn update(&mut self) {
+            }
+        }
+
+        let mut row = [PIXEL_COLOR_WHITE.packed(); FRAME_WIDTH];
+        row[0] = PIXEL_COLOR_BLACK.packed();
+        let last_x = FRAME_WIDTH - 1;
+        let last_y = FRAME_HEIGHT - 1;
+        let cases = [
+            (Transform::Identity, 0, 0),
+            (Transform::Rotate90, last_y, 0),
+            (Transform::Rotate180, last_x, last_y),
+            (Transform::Rotate270, 0, last_x),
+            (Transform::FlipHorizontal, last_x, 0),
+            (Transform::FlipVertical, 0, last_y),
+        ];
+
+        for (transform, x, y) in cases {
+            let mut screen = SquareScreen { pixels: [[Pixel::default(); FRAME_WIDTH]; FRAME_WIDTH] };
+            Ppu::output_row(&mut screen, transform, 0, &row);
+            assert!(screen.pixels[y][x] == PIXEL_COLOR_BLACK, "{:?}", transform);
+            let white = screen.pixels.iter().flatten().filter(|px| **px == PIXEL_COLOR_WHITE).count();
+            assert_eq!(white, FRAME_WIDTH - 1, "{:?}", transform);
+        }
+    }
+
+    #[test]
+    fn it_keeps_the_fast_timing_without_drawing_for_headless_screens() {
+        /// Sum of the mode changes, weighted by their position
+        struct TraceScreen {
+            pixels: usize,
+            trace: u64,
+        }
+
+        impl Screen for TraceScreen {
+            fn set_pixel(&mut self, _px: &Pixel, _x: u8, _y: u8) {
+                self.pixels += 1;
+            }
+
+            fn update(&mut self) {
+            }
+
+            fn on_mode_change(&mut self, mode: PpuMode, ly: u8, dots: u32) {
+                self.trace = self.trace.wrapping_mul(31).wrapping_add((mode as u64) << 24 | (ly as u64) << 16 | dots as u64);
+            }
+        }
+
+        struct HeadlessScreen(TraceScreen);
+
+        impl Screen for HeadlessScreen {
+            const HEADLESS: bool = true;
+
+            fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+                self.0.set_pixel(px, x, y);
+            }
+
+            fn update(&mut self) {
+            }
+
+            fn on_mode_change(&mut self, mode: PpuMode, ly: u8, dots: u32) {
+                self.0.on_mode_change(mode, ly, dots);
+            }
+        }
+
+        let mut ppu = new_ppu();
+        ppu.set_render_mode(RenderMode::Fast);
+        let mut screen = TraceScreen { pixels: 0, trace: 0 };
+        let mut it = InterruptHandler::new();
+        run_until(&mut ppu, &mut screen, &mut it, 0, 0);
+        run_until(&mut ppu, &mut screen, &mut it, LAST_LINE, 0);
+
+        let mut headless_ppu = new_ppu();
+        let mut headless = HeadlessScreen(TraceScreen { pixels: 0, trace: 0 });
+        let mut headless_it = InterruptHandler::new();
+        run_until(&mut headless_ppu, &mut headless, &mut headless_it, 0, 0);
+        run_until(&mut headless_ppu, &mut headless, &mut headless_it, LAST_LINE, 0);
+
+        assert_eq!(screen.pixels, FRAME_WIDTH * FRAME_HEIGHT);
+        assert_eq!(headless.0.pixels, 0);
+        assert_eq!(headless.0.trace, screen.trace);
+        assert_eq!(headless_it.requested(), it.requested());
+    }
+}
@@ -15,15 +15,17 @@ pub struct Sprite {
     pub tile_index: u8,
     /// Tile attributes
     attrs: u8,
+    /// Position in OAM, breaks ties between sprites with the same X
+    index: u8,
 }
 
 impl Sprite {
-    pub fn new(x: u8, y: u8, tile_index: u8, attrs: u8) -> Self {
-        Self { x, y, tile_index, attrs }
+    pub fn new(x: u8, y: u8, tile_index: u8, attrs: u8, index: u8) -> Self {
+        Self { x, y, tile_index, attrs, index }
     }
 
     pub fn default() -> Self {
-        Self { x: 0, y: 0, tile_index: 0, attrs: 0 }
+        Self { x: 0, y: 0, tile_index: 0, attrs: 0, index: 0 }
     }
 
     #[inline]
@@ -47,9 +49,10 @@ impl Sprite {
     }
 }
 
+/// Drawing priority: the smallest X first, then the first one in OAM
 impl Ord for Sprite {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.x.cmp(&other.x)
+        (self.x, self.index).cmp(&(other.x, other.index))
     }
 }
 
@@ -61,6 +64,6 @@ impl PartialOrd for Sprite {
 
 impl PartialEq for Sprite {
     fn eq(&self, other: &Self) -> bool {
-        self.x == other.x
+        self.cmp(other) == Ordering::Equal
     }
 }
@@ -0,0 +1,65 @@
+use crate::region::VRAM_REGION_START;
+
+/// Number of tiles in the tile data area (0x8000 - 0x97FF)
+pub const TILE_COUNT: usize = 384;
+/// Size of a tile in bytes (8 rows of 2 bytes)
+const TILE_SIZE: usize = 16;
+
+/// Decoded tile rows
+/// Each row is kept as 8 color ids so that bitplanes are decoded once
+/// and then only when the tile data changes in vram
+pub struct TileCache {
+    /// Color ids for each row of each tile, from left to right
+    rows: [[u8; 8]; TILE_COUNT * 8],
+    /// One bit per tile, set when the decoded rows are up to date
+    valid: [u8; TILE_COUNT / 8],
+}
+
+impl TileCache {
+    pub fn new() -> Self {
+        Self {
+            rows: [[0u8; 8]; TILE_COUNT * 8],
+            valid: [0u8; TILE_COUNT / 8],
+        }
+    }
+
+    /// Mark every tile as outdated
+    pub fn clear(&mut self) {
+        self.valid.iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    /// Called on vram writes to mark the related tile as outdated
+    #[inline]
+    pub fn invalidate(&mut self, address: u16) {
+        let tile = (address - VRAM_REGION_START) as usize / TILE_SIZE;
+        if tile < TILE_COUNT {
+            self.valid[tile / 8] &= !(1 << (tile % 8));
+        }
+    }
+
+    /// Retrieve the color ids of a tile row, decoding the tile if needed
+    /// tile is the index in the whole tile data area (0 - 383)
+    #[inline]
+    pub fn row(&mut self, vram: &[u8], tile: usize, y: u8) -> &[u8; 8] {
+        if is_not_set!(self.valid[tile / 8], 1 << (tile % 8)) {
+            self.decode(vram, tile);
+        }
+        &self.rows[tile * 8 + y as usize]
+    }
+
+    /// Decode the 8 rows of a tile
+    fn decode(&mut self, vram: &[u8], tile: usize) {
+        let data = &vram[tile * TILE_SIZE..(tile + 1) * TILE_SIZE];
+
+        for y in 0..8 {
+            let low = data[y * 2];
+            let high = data[y * 2 + 1];
+            let row = &mut self.rows[tile * 8 + y];
+            for (x, color_id) in row.iter_mut().enumerate() {
+                let bit = 7 - x;
+                *color_id = ((low >> bit) & 0x01) | (((high >> bit) & 0x01) << 1);
+            }
+        }
+        self.valid[tile / 8] |= 1 << (tile % 8);
+    }
+}
@@ -8,6 +8,10 @@ impl<const N: usize> Ram<N> {
     pub fn new() -> Self {
         Self { bytes: [0u8; N] }
     }
+
+    pub fn clear(&mut self) {
+        self.bytes.fill(0);
+    }
 }
 
 
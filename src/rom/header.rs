@@ -1,3 +1,5 @@
+use core::fmt;
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CgbMode {
@@ -40,6 +42,43 @@ pub enum CartridgeType {
     Unknown,
 }
 
+/// Name of the cartridge type, as written in the pandocs
+impl fmt::Display for CartridgeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CartridgeType::RomOnly => "ROM ONLY",
+            CartridgeType::Mbc1 => "MBC1",
+            CartridgeType::Mbc1Ram => "MBC1+RAM",
+            CartridgeType::Mbc1RamBattery => "MBC1+RAM+BATTERY",
+            CartridgeType::Mbc2 => "MBC2",
+            CartridgeType::Mbc2Battery => "MBC2+BATTERY",
+            CartridgeType::RomRam => "ROM+RAM",
+            CartridgeType::RomRamBattery => "ROM+RAM+BATTERY",
+            CartridgeType::Mmm01 => "MMM01",
+            CartridgeType::Mmm01Ram => "MMM01+RAM",
+            CartridgeType::Mmm01RamBattery => "MMM01+RAM+BATTERY",
+            CartridgeType::Mbc3TimerBattery => "MBC3+TIMER+BATTERY",
+            CartridgeType::Mbc3TimerRamBattery => "MBC3+TIMER+RAM+BATTERY",
+            CartridgeType::Mbc3 => "MBC3",
+            CartridgeType::Mbc3Ram => "MBC3+RAM",
+            CartridgeType::Mbc3RamBattery => "MBC3+RAM+BATTERY",
+            CartridgeType::Mbc5 => "MBC5",
+            CartridgeType::Mbc5Ram => "MBC5+RAM",
+            CartridgeType::Mbc5RamBattery => "MBC5+RAM+BATTERY",
+            CartridgeType::Mbc5Rumble => "MBC5+RUMBLE",
+            CartridgeType::Mbc5RumbleRam => "MBC5+RUMBLE+RAM",
+            CartridgeType::Mbc5RumbleRamBattery => "MBC5+RUMBLE+RAM+BATTERY",
+            CartridgeType::Mbc6 => "MBC6",
+            CartridgeType::Mbc7SensorRumbleRamBattery => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            CartridgeType::PocketCamera => "POCKET CAMERA",
+            CartridgeType::BandaiTama5 => "BANDAI TAMA5",
+            CartridgeType::HuC3 => "HuC3",
+            CartridgeType::HuC1RamBattery => "HuC1+RAM+BATTERY",
+            CartridgeType::Unknown => "UNKNOWN",
+        })
+    }
+}
+
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Licensee {
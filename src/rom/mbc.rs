@@ -1,8 +1,12 @@
+use core::mem;
+
 use enum_dispatch::enum_dispatch;
 
 use crate::error::{io_error_read, io_error_write};
 use crate::region::*;
 
+use super::rtc::{RTC_REG_DAY_HIGH, RTC_REG_SECONDS, Rtc};
+
 const DEFAULT_RAM_BANK: u8              = 0x00;
 const DEFAULT_ROM_BANK: u8              = 0x01;
 
@@ -21,13 +25,90 @@ const ROM_REGION_BANK0_END: u16         = 0x3FFF;
 const ROM_REGION_BANKN_START: u16       = 0x4000;
 const ROM_REGION_BANKN_END: u16         = ROM_REGION_END;
 
-const ROM_BANK_SIZE: usize              = (ROM_REGION_BANKN_END - ROM_REGION_BANKN_START + 1) as usize;
-const RAM_BANK_SIZE: usize              = ERAM_REGION_SIZE;
+pub const ROM_BANK_SIZE: usize          = (ROM_REGION_BANKN_END - ROM_REGION_BANKN_START + 1) as usize;
+pub(crate) const RAM_BANK_SIZE: usize   = ERAM_REGION_SIZE;
+
+/// Index in the rom of an address in the switchable bank area
+/// Banks that are not part of the rom wrap around when read from the storage,
+/// the same way unused bank bits are ignored by the hardware
+#[inline]
+fn banked_rom_index(bank: u8, address: u16) -> usize {
+    let offset = (address - ROM_REGION_BANKN_START) as usize;
+    offset + ROM_BANK_SIZE * bank as usize
+}
+
+/// Registers of the memory bank controller, as seen by the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MbcState {
+    /// No controller, the rom is mapped as is
+    None,
+    Mbc1 {
+        rom_bank: u8,
+        ram_bank: u8,
+        ram_enabled: bool,
+        /// The 2-bit register selects the ram bank instead of the upper rom bank bits
+        ram_banking_mode: bool,
+    },
+    Mbc3 {
+        rom_bank: u8,
+        ram_bank: u8,
+        ram_timer_enabled: bool,
+        /// Clock register mapped at 0xA000 - 0xBFFF instead of the ram
+        rtc_register: Option<u8>,
+    },
+}
 
 #[enum_dispatch]
 pub trait MbcController {
-    fn read(&self, storage: &[u8], address: u16) -> u8;
+    /// Index in the rom of an address between 0x0000 - 0x7FFF
+    fn rom_index(&self, address: u16) -> usize;
+    fn read(&self, address: u16) -> u8;
     fn write(&mut self, address: u16, value: u8);
+
+    /// Rom bank mapped at 0x4000 - 0x7FFF
+    fn rom_bank(&self) -> u16 {
+        DEFAULT_ROM_BANK as u16
+    }
+
+    /// Ram bank mapped at 0xA000 - 0xBFFF
+    fn ram_bank(&self) -> u8 {
+        DEFAULT_RAM_BANK
+    }
+
+    /// Current value of the bank registers
+    fn state(&self) -> MbcState {
+        MbcState::None
+    }
+
+    /// External ram, empty when the controller has none
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+
+    /// Bit n is set when ram bank n changed since the last clear_dirty
+    fn dirty_banks(&self) -> u8 {
+        0
+    }
+
+    fn clear_dirty(&mut self) {
+    }
+
+    /// Whether the game disabled the ram since the last call
+    fn take_flush(&mut self) -> bool {
+        false
+    }
+
+    /// Restore the bank registers and disable the ram, the ram content is kept
+    fn reset(&mut self) {
+    }
+
+    /// Advance the real time clock, if the cartridge has one
+    fn advance_clock(&mut self, _seconds: u64) {
+    }
 }
 
 #[enum_dispatch(MbcController)]
@@ -40,17 +121,14 @@ pub enum Mbc {
 pub struct Mbc0;
 
 impl MbcController for Mbc0 {
-    fn read(&self, storage: &[u8], address: u16) -> u8 {
-        match address {
-            ROM_REGION_START..=ROM_REGION_END => {
-                // We know storage.len() >= ROM_REGION_END (32K)
-                storage[(address - ROM_REGION_START) as usize]
-            },
-            _ => {
-                io_error_read(address);
-                0xFF
-            }
-        }
+    fn rom_index(&self, address: u16) -> usize {
+        // We know the storage size >= ROM_REGION_END (32K)
+        (address - ROM_REGION_START) as usize
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        io_error_read(address);
+        0xFF
     }
 
     fn write(&mut self, address: u16, _value: u8) {
@@ -69,6 +147,10 @@ pub struct Mbc1 {
     ram_bank: u8,
     /// Whether bank mode is rom or ram
     ram_bank_mode: bool,
+    /// Ram banks written since the last save
+    dirty: u8,
+    /// Ram was disabled, which games do once done saving
+    flush: bool,
 }
 
 impl Mbc1 {
@@ -79,6 +161,8 @@ impl Mbc1 {
             ram_bank: DEFAULT_RAM_BANK,
             rom_bank: DEFAULT_ROM_BANK,
             ram_bank_mode: false,
+            dirty: 0,
+            flush: false,
         }
     }
 
@@ -91,14 +175,15 @@ impl Mbc1 {
 }
 
 impl MbcController for Mbc1 {
-    fn read(&self, storage: &[u8], address: u16) -> u8 {
+    fn rom_index(&self, address: u16) -> usize {
+        match address {
+            ROM_REGION_BANK0_START..=ROM_REGION_BANK0_END => address as usize,
+            _ => banked_rom_index(self.rom_bank, address),
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
         match address {
-            ROM_REGION_BANK0_START..=ROM_REGION_BANK0_END => storage[address as usize],
-            ROM_REGION_BANKN_START..=ROM_REGION_BANKN_END => {
-                let offset = address - ROM_REGION_BANKN_START;
-                let idx = offset as usize + (ROM_BANK_SIZE * self.rom_bank as usize);
-                storage[idx]
-            },
             ERAM_REGION_START..=ERAM_REGION_END => {
                 if self.ram_enabled {
                     let offset = address - ERAM_REGION_START;
@@ -114,7 +199,11 @@ impl MbcController for Mbc1 {
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
-            RAM_ENABLE_START..=RAM_ENABLE_END => self.ram_enabled = (value & 0xA) == 0xA,
+            RAM_ENABLE_START..=RAM_ENABLE_END => {
+                let enabled = (value & 0xA) == 0xA;
+                self.flush |= self.ram_enabled && !enabled;
+                self.ram_enabled = enabled;
+            },
             ROM_BANK_SEL_START..=ROM_BANK_SEL_END => {
                 let bank = value & 0x1F;
                 self.set_rom_bank((self.rom_bank & 0xE0) | bank);
@@ -132,21 +221,73 @@ impl MbcController for Mbc1 {
                 if self.ram_enabled {
                     let offset = address - ERAM_REGION_START;
                     let idx = offset as usize + (RAM_BANK_SIZE * self.ram_bank as usize);
-                    self.eram[idx] = value;
+                    if self.eram[idx] != value {
+                        self.eram[idx] = value;
+                        self.dirty |= 1 << self.ram_bank;
+                    }
                 }
             },
             _ => io_error_write(address),
         }
     }
+
+    fn rom_bank(&self) -> u16 {
+        self.rom_bank as u16
+    }
+
+    fn ram_bank(&self) -> u8 {
+        self.ram_bank
+    }
+
+    fn state(&self) -> MbcState {
+        MbcState::Mbc1 {
+            rom_bank: self.rom_bank,
+            ram_bank: self.ram_bank,
+            ram_enabled: self.ram_enabled,
+            ram_banking_mode: self.ram_bank_mode,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.eram
+    }
+
+    fn dirty_banks(&self) -> u8 {
+        self.dirty
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty = 0;
+    }
+
+    fn take_flush(&mut self) -> bool {
+        mem::take(&mut self.flush)
+    }
+
+    fn reset(&mut self) {
+        self.ram_enabled = false;
+        self.rom_bank = DEFAULT_ROM_BANK;
+        self.ram_bank = DEFAULT_RAM_BANK;
+        self.ram_bank_mode = false;
+    }
 }
 
 pub struct Mbc3 {
     ram_timer_enabled: bool,
     rom_bank: u8,
     ram_bank: u8,
-    reg_rtc: u8,
-    rtc_mode: bool,
+    /// Clock register mapped at 0xA000 - 0xBFFF instead of the ram
+    rtc_register: Option<u8>,
+    rtc: Rtc,
     eram: [u8; ERAM_SIZE],
+    /// Ram banks written since the last save
+    dirty: u8,
+    /// Ram was disabled, which games do once done saving
+    flush: bool,
 }
 
 impl Mbc3 {
@@ -155,30 +296,34 @@ impl Mbc3 {
             ram_timer_enabled: false,
             rom_bank: DEFAULT_ROM_BANK,
             ram_bank: DEFAULT_RAM_BANK,
-            reg_rtc: 0,
-            rtc_mode: false,
+            rtc_register: None,
+            rtc: Rtc::new(),
             eram: [0u8; ERAM_SIZE],
+            dirty: 0,
+            flush: false,
         }
     }
 }
 
 impl MbcController for Mbc3 {
-    fn read(&self, storage: &[u8], address: u16) -> u8 {
+    fn rom_index(&self, address: u16) -> usize {
+        match address {
+            ROM_REGION_BANK0_START..=ROM_REGION_BANK0_END => address as usize,
+            _ => banked_rom_index(self.rom_bank, address),
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
         match address {
-            ROM_REGION_BANK0_START..=ROM_REGION_BANK0_END => storage[address as usize],
-            ROM_REGION_BANKN_START..=ROM_REGION_BANKN_END => {
-                let offset = address - ROM_REGION_BANKN_START;
-                let idx = offset as usize + (ROM_BANK_SIZE * self.rom_bank as usize);
-                storage[idx]
-            },
             ERAM_REGION_START..=ERAM_REGION_END => {
                 if self.ram_timer_enabled {
-                    if self.rtc_mode {
-                        self.reg_rtc
-                    } else {
-                        let offset = address - ERAM_REGION_START;
-                        let idx = offset as usize + (RAM_BANK_SIZE * self.ram_bank as usize);
-                        self.eram[idx]
+                    match self.rtc_register {
+                        Some(register) => self.rtc.read(register),
+                        None => {
+                            let offset = address - ERAM_REGION_START;
+                            let idx = offset as usize + (RAM_BANK_SIZE * self.ram_bank as usize);
+                            self.eram[idx]
+                        },
                     }
                 } else {
                     0xFF
@@ -190,29 +335,87 @@ impl MbcController for Mbc3 {
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
-            RAM_ENABLE_START..=RAM_ENABLE_END => self.ram_timer_enabled = (value & 0xA) == 0xA,
+            RAM_ENABLE_START..=RAM_ENABLE_END => {
+                let enabled = (value & 0xA) == 0xA;
+                self.flush |= self.ram_timer_enabled && !enabled;
+                self.ram_timer_enabled = enabled;
+            },
             ROM_BANK_SEL_START..=ROM_BANK_SEL_END => self.rom_bank = value,
             RAM_BANK_SEL_START..=RAM_BANK_SEL_END => {
                 if value <= 0x03 {
                     // Ram selection
-                    self.rtc_mode = false;
+                    self.rtc_register = None;
                     self.ram_bank = value;
-                } else if (0x08..=0x0C).contains(&value) {
-                    self.rtc_mode = true;
+                } else if (RTC_REG_SECONDS..=RTC_REG_DAY_HIGH).contains(&value) {
+                    self.rtc_register = Some(value);
                 }
             },
+            BANK_MODE_START..=BANK_MODE_END => self.rtc.latch(value),
             ERAM_REGION_START..=ERAM_REGION_END => {
                 if self.ram_timer_enabled {
-                    if self.rtc_mode {
-                        self.reg_rtc = value;
-                    } else {
-                        let offset = address - ERAM_REGION_START;
-                        let idx = offset as usize + (RAM_BANK_SIZE * self.ram_bank as usize);
-                        self.eram[idx] = value;
+                    match self.rtc_register {
+                        Some(register) => self.rtc.write(register, value),
+                        None => {
+                            let offset = address - ERAM_REGION_START;
+                            let idx = offset as usize + (RAM_BANK_SIZE * self.ram_bank as usize);
+                            if self.eram[idx] != value {
+                                self.eram[idx] = value;
+                                self.dirty |= 1 << self.ram_bank;
+                            }
+                        },
                     }
                 }
             },
             _ => io_error_write(address),
         }
     }
+
+    fn rom_bank(&self) -> u16 {
+        self.rom_bank as u16
+    }
+
+    fn ram_bank(&self) -> u8 {
+        self.ram_bank
+    }
+
+    fn state(&self) -> MbcState {
+        MbcState::Mbc3 {
+            rom_bank: self.rom_bank,
+            ram_bank: self.ram_bank,
+            ram_timer_enabled: self.ram_timer_enabled,
+            rtc_register: self.rtc_register,
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.eram
+    }
+
+    fn dirty_banks(&self) -> u8 {
+        self.dirty
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty = 0;
+    }
+
+    fn take_flush(&mut self) -> bool {
+        mem::take(&mut self.flush)
+    }
+
+    fn reset(&mut self) {
+        // The clock keeps running on its own battery
+        self.ram_timer_enabled = false;
+        self.rom_bank = DEFAULT_ROM_BANK;
+        self.ram_bank = DEFAULT_RAM_BANK;
+        self.rtc_register = None;
+    }
+
+    fn advance_clock(&mut self, seconds: u64) {
+        self.rtc.advance(seconds);
+    }
 }
@@ -1,6 +1,11 @@
 mod rom;
 mod header;
 mod mbc;
+mod rtc;
+mod storage;
 
 pub use header::{CgbMode, CartridgeType, Licensee};
+pub use mbc::{MbcState, ROM_BANK_SIZE};
 pub use rom::*;
+pub use rtc::TimeSource;
+pub use storage::{BankedStorage, RomStorage, StreamedRom};
@@ -1,13 +1,13 @@
-#[cfg(debug_assertions)]
 use core::fmt;
-use core::ops::Deref;
 use core::str;
 
 use crate::region::*;
 use crate::Error;
 use super::{CgbMode, CartridgeType, Licensee};
 use super::mbc::*;
+use super::storage::RomStorage;
 
+const HEADER_LOGO_START: usize          = 0x0104;
 const HEADER_TITLE_START: usize         = 0x0134;
 const HEADER_TITLE_END: usize           = 0x0143;
 const HEADER_CGB_FLAG: usize            = 0x0143;
@@ -20,21 +20,81 @@ const HEADER_DESTINATION_CODE: usize    = 0x014A;
 const HEADER_OLD_LICENSEE_CODE: usize   = 0x014B;
 const HEADER_VERSION: usize             = 0x014C;
 const HEADER_HEADER_CHECKSUM: usize     = 0x014D;
+const HEADER_GLOBAL_CHECKSUM: usize     = 0x014E;
+const HEADER_END: usize                 = 0x014F;
 
-pub struct Rom<T: Deref<Target=[u8]>> {
+/// Logo every licensed cartridge displays at boot, the boot rom refuses to start without it
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83,
+    0x00, 0x0C, 0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E,
+    0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63,
+    0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Optional checks done by Rom::load_strict on top of the header checksum
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StrictChecks {
+    /// Verify the checksum of the whole rom, which real hardware ignores
+    pub global_checksum: bool,
+    /// Verify the logo bytes, which the boot rom checks
+    pub logo: bool,
+}
+
+fn header_checksum<T: RomStorage>(storage: &T) -> u8 {
+    storage.bank0()[HEADER_TITLE_START..HEADER_HEADER_CHECKSUM]
+        .iter()
+        .fold(0u8, |x, &byte| x.wrapping_sub(byte).wrapping_sub(1))
+}
+
+fn global_checksum<T: RomStorage>(storage: &T) -> u16 {
+    (0..storage.size())
+        .filter(|&i| i != HEADER_GLOBAL_CHECKSUM && i != HEADER_GLOBAL_CHECKSUM + 1)
+        .fold(0u16, |x, i| x.wrapping_add(storage.read(i) as u16))
+}
+
+fn stored_global_checksum<T: RomStorage>(storage: &T) -> u16 {
+    let bank0 = storage.bank0();
+    u16::from_be_bytes([bank0[HEADER_GLOBAL_CHECKSUM], bank0[HEADER_GLOBAL_CHECKSUM + 1]])
+}
+
+fn mbc_controller(cartridge_type: CartridgeType) -> Result<Mbc, Error> {
+    match cartridge_type {
+        CartridgeType::RomOnly => Ok(Mbc::from(Mbc0)),
+        CartridgeType::Mbc1 |
+        CartridgeType::Mbc1Ram |
+        CartridgeType::Mbc1RamBattery => Ok(Mbc::from(Mbc1::new())),
+        CartridgeType::Mbc3 |
+        CartridgeType::Mbc3Ram |
+        CartridgeType::Mbc3RamBattery |
+        CartridgeType::Mbc3TimerBattery |
+        CartridgeType::Mbc3TimerRamBattery => Ok(Mbc::from(Mbc3::new())),
+        cartridge_type => Err(Error::UnsupportedCartridge(cartridge_type)),
+    }
+}
+
+fn logo_mismatch<T: RomStorage>(storage: &T) -> Option<usize> {
+    storage.bank0()[HEADER_LOGO_START..HEADER_TITLE_START]
+        .iter()
+        .zip(NINTENDO_LOGO.iter())
+        .position(|(a, b)| a != b)
+        .map(|i| HEADER_LOGO_START + i)
+}
+
+pub struct Rom<T: RomStorage> {
     /// Cartridge data, this is provided by the user depending on their platform
     /// This can be a Vec<u8>, a static array,
     /// Or generally any kind of structure that can be dereferenced to a u8
+    /// Roms too big for memory can be streamed with a StreamedRom
     storage: T,
     /// Support for Mbc0, Mbc1, etc
     mbc_ctrl: Mbc,
 }
 
-impl<T: Deref<Target=[u8]>> Rom<T> {
+impl<T: RomStorage> Rom<T> {
     /// Build a rom from a sequence of storage
     pub fn load(storage: T) -> Result<Self, Error> {
-        if storage.len() < ROM_REGION_SIZE {
-            Err(Error::InvalidRomSize(storage.len()))
+        if storage.size() < ROM_REGION_SIZE {
+            Err(Error::InvalidRomSize(storage.size()))
         } else {
             let mut rom = Self {
                 storage,
@@ -42,37 +102,244 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
             };
             // MBC can be a dynamically dispatched on the stack
             // which is awesome in a no_std / no alloc environment
-            // This still can be improved by extracting the Rom header
-            // which would allow setting the mbc controller before creating the rom instance
-            rom.mbc_ctrl = match rom.cartridge_type() {
-                CartridgeType::RomOnly => Mbc::from(Mbc0),
-                CartridgeType::Mbc1 |
-                CartridgeType::Mbc1Ram |
-                CartridgeType::Mbc1RamBattery => Mbc::from(Mbc1::new()),
-                CartridgeType::Mbc3 |
-                CartridgeType::Mbc3Ram |
-                CartridgeType::Mbc3RamBattery |
-                CartridgeType::Mbc3TimerBattery |
-                CartridgeType::Mbc3TimerRamBattery => Mbc::from(Mbc3::new()),
-                _ => unimplemented!(),
-            };
+            rom.mbc_ctrl = mbc_controller(rom.cartridge_type())?;
 
             Ok(rom)
         }
     }
 
+    /// Build a rom from a sequence of storage without looking at its header
+    ///
+    /// The given cartridge type selects the memory bank controller, so test programs
+    /// and generated code only need to be at least as long as a header (0x150 bytes).
+    /// Header shortcuts still read the raw bytes where the header would be.
+    ///
+    /// ```
+    /// use padme_core::{CartridgeType, Rom, System};
+    /// use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+    ///
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,$42 then JR -2
+    /// bin[0x100..0x104].copy_from_slice(&[0x3E, 0x42, 0x18, 0xFE]);
+    ///
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1).unwrap();
+    /// assert_eq!(rom.rom_bank(), 1);
+    ///
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.step();
+    /// assert_eq!(emu.cpu_state().a, 0x42);
+    /// ```
+    pub fn load_raw(storage: T, cartridge_type: CartridgeType) -> Result<Self, Error> {
+        if storage.size() <= HEADER_END {
+            return Err(Error::InvalidRomSize(storage.size()));
+        }
+
+        Ok(Self {
+            mbc_ctrl: mbc_controller(cartridge_type)?,
+            storage,
+        })
+    }
+
+    /// Build a rom from a sequence of storage, refusing corrupted dumps
+    ///
+    /// The header checksum is always verified, the global checksum and the logo only
+    /// when asked to. Use load for homebrew roms that do not bother with a valid header.
+    ///
+    /// ```
+    /// use padme_core::{Error, Rom, StrictChecks};
+    ///
+    /// let mut bin = [0u8; 32 * 1024];
+    /// assert!(Rom::load(&bin[..]).is_ok());
+    /// assert!(matches!(Rom::load_strict(&bin[..], StrictChecks::default()),
+    ///                  Err(Error::InvalidHeaderChecksum(0x00, 0xE7))));
+    ///
+    /// bin[0x014D] = 0xE7;
+    /// assert!(Rom::load_strict(&bin[..], StrictChecks::default()).is_ok());
+    /// let checks = StrictChecks { logo: true, ..StrictChecks::default() };
+    /// assert!(matches!(Rom::load_strict(&bin[..], checks), Err(Error::InvalidLogo(0x0104))));
+    /// ```
+    pub fn load_strict(storage: T, checks: StrictChecks) -> Result<Self, Error> {
+        if storage.size() < ROM_REGION_SIZE {
+            return Err(Error::InvalidRomSize(storage.size()));
+        }
+
+        let stored = storage.bank0()[HEADER_HEADER_CHECKSUM];
+        let computed = header_checksum(&storage);
+        if stored != computed {
+            return Err(Error::InvalidHeaderChecksum(stored, computed));
+        }
+
+        if checks.logo {
+            if let Some(offset) = logo_mismatch(&storage) {
+                return Err(Error::InvalidLogo(offset));
+            }
+        }
+
+        if checks.global_checksum {
+            let stored = stored_global_checksum(&storage);
+            let computed = global_checksum(&storage);
+            if stored != computed {
+                return Err(Error::InvalidGlobalChecksum(stored, computed));
+            }
+        }
+
+        Self::load(storage)
+    }
+
+    /// Retrieve the rom bank currently mapped at 0x4000 - 0x7FFF
+    pub fn rom_bank(&self) -> u16 {
+        self.mbc_ctrl.rom_bank()
+    }
+
+    /// Retrieve the ram bank currently mapped at 0xA000 - 0xBFFF
+    pub fn ram_bank(&self) -> u8 {
+        self.mbc_ctrl.ram_bank()
+    }
+
+    /// Retrieve the bank registers of the memory bank controller, for debuggers
+    ///
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,$0A ; LD ($0000),A ; LD A,$05 ; LD ($2000),A ; JR -2
+    /// bin[0x100..0x10C].copy_from_slice(&[0x3E, 0x0A, 0xEA, 0x00, 0x00,
+    ///                                     0x3E, 0x05, 0xEA, 0x00, 0x20, 0x18, 0xFE]);
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.update_frame();
+    ///
+    /// assert_eq!(emu.rom().mbc_state(), MbcState::Mbc1 {
+    ///     rom_bank: 5,
+    ///     ram_bank: 0,
+    ///     ram_enabled: true,
+    ///     ram_banking_mode: false,
+    /// });
+    /// ```
+    pub fn mbc_state(&self) -> MbcState {
+        self.mbc_ctrl.state()
+    }
+
+    /// External ram, to export battery saves
+    /// This is empty for cartridges without a memory bank controller
+    pub fn ram(&self) -> &[u8] {
+        self.mbc_ctrl.ram()
+    }
+
+    /// External ram, to import battery saves
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        self.mbc_ctrl.ram_mut()
+    }
+
+    /// External ram split in 8K banks, as many as the header declares
+    ///
+    /// ```
+    /// # use padme_core::*;
+    /// let mut bin = [0u8; 0x150];
+    /// bin[0x149] = 0x03;
+    /// let mut rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// rom.ram_mut()[0x2000] = 0x42;
+    ///
+    /// assert_eq!(rom.ram_banks().count(), 4);
+    /// let (bank, data) = rom.ram_banks().nth(1).unwrap();
+    /// assert_eq!(bank, 1);
+    /// assert_eq!(data.len(), 0x2000);
+    /// assert_eq!(data[0], 0x42);
+    /// ```
+    pub fn ram_banks(&self) -> impl Iterator<Item = (u8, &[u8])> + '_ {
+        let ram = self.ram();
+        let size = (self.ram_size() as usize * 1024).min(ram.len());
+        ram[..size]
+            .chunks(RAM_BANK_SIZE)
+            .enumerate()
+            .map(|(bank, data)| (bank as u8, data))
+    }
+
+    /// Whether the external ram changed since the last clear_dirty
+    ///
+    /// ```
+    /// # use padme_core::{CartridgeType, Rom, System};
+    /// # use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,$0A ; LD ($0000),A ; LD ($A000),A ; JR -2
+    /// bin[0x100..0x10A].copy_from_slice(&[0x3E, 0x0A, 0xEA, 0x00, 0x00, 0xEA, 0x00, 0xA0, 0x18, 0xFE]);
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// assert!(!emu.rom().ram_dirty());
+    ///
+    /// emu.update_frame();
+    /// assert!(emu.rom().ram_dirty());
+    /// assert_eq!(emu.rom().dirty_ram_banks(), 0b0001);
+    /// assert_eq!(emu.rom().ram()[0], 0x0A);
+    ///
+    /// emu.rom_mut().clear_dirty();
+    /// emu.update_frame();
+    /// assert!(!emu.rom().ram_dirty());
+    /// ```
+    pub fn ram_dirty(&self) -> bool {
+        self.mbc_ctrl.dirty_banks() != 0
+    }
+
+    /// Bit n is set when ram bank n changed since the last clear_dirty
+    pub fn dirty_ram_banks(&self) -> u8 {
+        self.mbc_ctrl.dirty_banks()
+    }
+
+    /// Mark the external ram as saved
+    pub fn clear_dirty(&mut self) {
+        self.mbc_ctrl.clear_dirty()
+    }
+
+    /// Whether the game disabled the external ram since the last call
+    ///
+    /// Games usually disable the ram once done saving,
+    /// which makes it a good time to persist battery saves.
+    ///
+    /// ```
+    /// # use padme_core::{CartridgeType, Rom, System};
+    /// # use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,$0A ; LD ($0000),A ; LD ($A000),A ; XOR A ; LD ($0000),A ; JR -2
+    /// bin[0x100..0x10E].copy_from_slice(&[0x3E, 0x0A, 0xEA, 0x00, 0x00, 0xEA, 0x00, 0xA0,
+    ///                                     0xAF, 0xEA, 0x00, 0x00, 0x18, 0xFE]);
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    ///
+    /// emu.update_frame();
+    /// assert!(emu.rom_mut().take_flush());
+    /// assert!(!emu.rom_mut().take_flush());
+    /// ```
+    pub fn take_flush(&mut self) -> bool {
+        self.mbc_ctrl.take_flush()
+    }
+
+    /// Restore the bank registers as they are at power on
+    /// The external ram is kept unless clear_ram is set, the dirty banks are left as is
+    pub fn reset(&mut self, clear_ram: bool) {
+        self.mbc_ctrl.reset();
+        if clear_ram {
+            self.mbc_ctrl.ram_mut().fill(0);
+        }
+    }
+
+    /// Advance the cartridge clock, e.g. by the time elapsed since the battery save was written
+    /// This does nothing for cartridges without a clock
+    pub fn advance_clock(&mut self, seconds: u64) {
+        self.mbc_ctrl.advance_clock(seconds);
+    }
+
     /// Shortcut to retrieve header part
     pub fn header(&self) -> &[u8] {
-        &self.storage[HEADER_TITLE_START..HEADER_HEADER_CHECKSUM]
+        &self.storage.bank0()[HEADER_TITLE_START..HEADER_HEADER_CHECKSUM]
     }
 
     /// Shortcut to retrieve the location of the title
     pub fn title(&self) -> Result<&str, str::Utf8Error> {
-        let title_part = &self.storage[HEADER_TITLE_START..=HEADER_TITLE_END];
+        let title_part = &self.storage.bank0()[HEADER_TITLE_START..=HEADER_TITLE_END];
         for (i, &byte) in title_part.iter().enumerate() {
             if byte == 0x00 {
                 return str::from_utf8(
-                    &self.storage[HEADER_TITLE_START..(HEADER_TITLE_START + i)]
+                    &self.storage.bank0()[HEADER_TITLE_START..(HEADER_TITLE_START + i)]
                 );
             }
         }
@@ -82,7 +349,7 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
 
     /// Shortcut to retrieve the cgb mode from the header
     pub fn cgb_mode(&self) -> CgbMode {
-        let cgb_flag = self.storage[HEADER_CGB_FLAG];
+        let cgb_flag = self.storage.bank0()[HEADER_CGB_FLAG];
 
         match cgb_flag {
             0xC0 => CgbMode::Cgb,
@@ -93,7 +360,7 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
 
     /// Shortcut to retrieve the rom size from the header
     pub fn size(&self) -> u16 {
-        let n = self.storage[HEADER_ROM_SIZE];
+        let n = self.storage.bank0()[HEADER_ROM_SIZE];
 
         match n {
             0x00..=0x08 => (32 << n) as u16,
@@ -103,7 +370,7 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
 
     /// Shortcut to retrieve the ram size from the header
     pub fn ram_size(&self) -> u16 {
-        match self.storage[HEADER_RAM_SIZE] {
+        match self.storage.bank0()[HEADER_RAM_SIZE] {
             0x00 => 0u16,
             0x02 => 8u16,
             0x03 => 32u16,
@@ -115,12 +382,12 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
 
     /// Shortcut to retrieve if the rom supports sgb from the header
     pub fn is_sgb(&self) -> bool {
-        self.storage[HEADER_SGB_FLAG] == 0x03
+        self.storage.bank0()[HEADER_SGB_FLAG] == 0x03
     }
 
     /// Shortcut to retrieve the cartridge type from the header
     pub fn cartridge_type(&self) -> CartridgeType {
-        match self.storage[HEADER_CARTRIDGE_TYPE] {
+        match self.storage.bank0()[HEADER_CARTRIDGE_TYPE] {
             0x00 => CartridgeType::RomOnly,
             0x01 => CartridgeType::Mbc1,
             0x02 => CartridgeType::Mbc1Ram,
@@ -155,28 +422,38 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
 
     /// Shortcut to retrieve if the cartridge is japanese from the header
     pub fn is_jp(&self) -> bool {
-        self.storage[HEADER_DESTINATION_CODE] == 0x00
+        self.storage.bank0()[HEADER_DESTINATION_CODE] == 0x00
     }
 
     /// Shortcut to retrieve the version from the header
     pub fn version(&self) -> u8 {
-        self.storage[HEADER_VERSION]
+        self.storage.bank0()[HEADER_VERSION]
     }
 
     /// Verify the checksum from the header
     pub fn verify_header_checksum(&self) -> bool {
-        let mut x = 0u8;
+        header_checksum(&self.storage) == self.storage.bank0()[HEADER_HEADER_CHECKSUM]
+    }
 
-        for &byte in self.header().iter() {
-            x = x.wrapping_sub(byte).wrapping_sub(1);
-        }
+    /// Verify the checksum of the whole rom
+    pub fn verify_global_checksum(&self) -> bool {
+        global_checksum(&self.storage) == stored_global_checksum(&self.storage)
+    }
 
-        x == self.storage[HEADER_HEADER_CHECKSUM]
+    /// Verify the logo bytes from the header, a dump failing this check is likely corrupted
+    /// ```
+    /// # use padme_core::*;
+    /// let mut bin = [0u8; 0x150];
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// assert!(!rom.verify_logo());
+    /// ```
+    pub fn verify_logo(&self) -> bool {
+        logo_mismatch(&self.storage).is_none()
     }
 
     /// Shortcut to retrieve the licensee from the header
     pub fn licensee(&self) -> Licensee {
-        let old_licensee_code = self.storage[HEADER_OLD_LICENSEE_CODE];
+        let old_licensee_code = self.storage.bank0()[HEADER_OLD_LICENSEE_CODE];
 
         match old_licensee_code {
             0x00 => Licensee::None,
@@ -323,8 +600,8 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
             0xFF => Licensee::Ljn,
             0x33 => {
                 let new_licensee_code = make_u16!(
-                    self.storage[HEADER_NEW_LICENSEE_CODE],
-                    self.storage[HEADER_NEW_LICENSEE_CODE + 1]
+                    self.storage.bank0()[HEADER_NEW_LICENSEE_CODE],
+                    self.storage.bank0()[HEADER_NEW_LICENSEE_CODE + 1]
                 );
                 match new_licensee_code {
                     0x3030 => Licensee::None,
@@ -396,9 +673,14 @@ impl<T: Deref<Target=[u8]>> Rom<T> {
     }
 }
 
-impl<T: Deref<Target=[u8]>> MemoryRegion for Rom<T> {
+impl<T: RomStorage> MemoryRegion for Rom<T> {
     fn read(&self, address: u16) -> u8 {
-        self.mbc_ctrl.read(&self.storage, address)
+        match address {
+            ROM_REGION_START..=ROM_REGION_END => {
+                self.storage.read(self.mbc_ctrl.rom_index(address))
+            },
+            _ => self.mbc_ctrl.read(address),
+        }
     }
 
     fn write(&mut self, address: u16, value: u8) {
@@ -406,8 +688,13 @@ impl<T: Deref<Target=[u8]>> MemoryRegion for Rom<T> {
     }
 }
 
-#[cfg(debug_assertions)]
-impl<T: Deref<Target=[u8]>> fmt::Debug for Rom<T> {
+impl<T: RomStorage> fmt::Debug for Rom<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<T: RomStorage> fmt::Display for Rom<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "ROM \n\
                    ---\n\
@@ -0,0 +1,108 @@
+// Registers selected with 0x08 - 0x0C written to 0x4000 - 0x5FFF
+pub const RTC_REG_SECONDS: u8           = 0x08;
+pub const RTC_REG_DAY_HIGH: u8          = 0x0C;
+
+const FLAG_DH_DAY_HIGH: u8              = 0x01;
+const FLAG_DH_HALT: u8                  = 0x40;
+const FLAG_DH_CARRY: u8                 = 0x80;
+
+const SECONDS_PER_DAY: u64              = 24 * 60 * 60;
+/// Days counted before the carry is set
+const DAYS: u64                         = 512;
+
+/// Where the cartridge clock gets its time from
+#[derive(Clone, Copy, Debug)]
+pub enum TimeSource {
+    /// The clock follows the host time, given in seconds by the function
+    /// (e.g. since the unix epoch), whatever the emulation speed is
+    Wall(fn() -> u64),
+    /// The clock advances with the emulated cycles, fast-forward speeds up the in-game time
+    /// and replays see the same time
+    Emulated,
+}
+
+/// Real time clock of MBC3 cartridges
+pub struct Rtc {
+    /// Seconds since day 0, 00:00:00
+    time: u64,
+    /// The clock is stopped
+    halted: bool,
+    /// The day counter overflowed
+    carry: bool,
+    /// Registers copied by the last latch
+    latched: [u8; 5],
+    /// 0x00 was written to the latch register, writing 0x01 now latches the time
+    latch_armed: bool,
+}
+
+impl Rtc {
+    pub fn new() -> Self {
+        Self {
+            time: 0,
+            halted: false,
+            carry: false,
+            latched: [0u8; 5],
+            latch_armed: false,
+        }
+    }
+
+    /// Live value of the registers
+    fn registers(&self) -> [u8; 5] {
+        let days = self.time / SECONDS_PER_DAY;
+        let mut dh = ((days >> 8) as u8) & FLAG_DH_DAY_HIGH;
+        if self.halted {
+            dh |= FLAG_DH_HALT;
+        }
+        if self.carry {
+            dh |= FLAG_DH_CARRY;
+        }
+        [
+            (self.time % 60) as u8,
+            ((self.time / 60) % 60) as u8,
+            ((self.time / 3600) % 24) as u8,
+            days as u8,
+            dh,
+        ]
+    }
+
+    /// Write to 0x6000 - 0x7FFF
+    pub fn latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers();
+        }
+        self.latch_armed = value == 0x00;
+    }
+
+    /// Read a latched register
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[(register - RTC_REG_SECONDS) as usize]
+    }
+
+    /// Set a live register, the latched copy is updated too
+    pub fn write(&mut self, register: u8, value: u8) {
+        let mut regs = self.registers();
+        let index = (register - RTC_REG_SECONDS) as usize;
+        regs[index] = value;
+        self.latched[index] = value;
+
+        let days = regs[3] as u64 | (((regs[4] & FLAG_DH_DAY_HIGH) as u64) << 8);
+        self.time = days * SECONDS_PER_DAY
+            + (regs[2] % 24) as u64 * 3600
+            + (regs[1] % 60) as u64 * 60
+            + (regs[0] % 60) as u64;
+        self.halted = is_set!(regs[4], FLAG_DH_HALT);
+        self.carry = is_set!(regs[4], FLAG_DH_CARRY);
+    }
+
+    /// Advance the clock unless it is halted
+    pub fn advance(&mut self, seconds: u64) {
+        if self.halted {
+            return;
+        }
+        self.time += seconds;
+        if self.time >= DAYS * SECONDS_PER_DAY {
+            self.time %= DAYS * SECONDS_PER_DAY;
+            self.carry = true;
+        }
+    }
+}
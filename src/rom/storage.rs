@@ -0,0 +1,166 @@
+use core::cell::{Cell, RefCell};
+use core::ops::Deref;
+
+use super::mbc::ROM_BANK_SIZE;
+
+const HEADER_ROM_SIZE: usize            = 0x0148;
+
+/// Where the cartridge data is read from
+///
+/// This is implemented for anything that can be dereferenced to a slice of bytes
+/// (a Vec<u8>, a static array, ...) and by [`StreamedRom`] for roms that do not fit in memory
+pub trait RomStorage {
+    /// Size of the rom in bytes
+    fn size(&self) -> usize;
+
+    /// Read a byte from the rom, indexes past the end wrap around
+    fn read(&self, index: usize) -> u8;
+
+    /// First bank of the rom, which holds the header and is always mapped at 0x0000 - 0x3FFF
+    fn bank0(&self) -> &[u8];
+}
+
+impl<T: Deref<Target=[u8]>> RomStorage for T {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn read(&self, index: usize) -> u8 {
+        self[index % self.len()]
+    }
+
+    fn bank0(&self) -> &[u8] {
+        self
+    }
+}
+
+/// A rom that can be read one bank at a time
+///
+/// Implement this to stream a cartridge from an SD card, a flash chip, etc.
+pub trait BankedStorage {
+    /// Fill buf with the 16 KiB bank number bank
+    fn read_bank(&mut self, bank: usize, buf: &mut [u8; ROM_BANK_SIZE]);
+}
+
+/// Rom storage keeping only the first bank and N switchable banks in memory
+///
+/// Other banks are read from the backend when they get accessed, replacing the bank
+/// that was loaded the longest ago.
+/// This keeps memory usage at (N + 1) * 16 KiB whatever the size of the rom is.
+///
+/// ```
+/// use padme_core::{BankedStorage, CartridgeType, ROM_BANK_SIZE, Rom, StreamedRom};
+///
+/// struct Flash;
+///
+/// impl BankedStorage for Flash {
+///     fn read_bank(&mut self, bank: usize, buf: &mut [u8; ROM_BANK_SIZE]) {
+///         buf.fill(bank as u8);
+///         if bank == 0 {
+///             // 1 MiB MBC1 cartridge
+///             buf[0x0147] = 0x01;
+///             buf[0x0148] = 0x05;
+///         }
+///     }
+/// }
+///
+/// let rom = Rom::load(StreamedRom::<_, 2>::new(Flash)).unwrap();
+/// assert_eq!(rom.cartridge_type(), CartridgeType::Mbc1);
+/// assert_eq!(rom.size(), 1024);
+/// ```
+pub struct StreamedRom<B: BankedStorage, const N: usize> {
+    /// Always resident bank 0
+    bank0: [u8; ROM_BANK_SIZE],
+    /// Number of banks in the rom according to its header
+    banks: usize,
+    /// Switchable banks currently in memory
+    cache: RefCell<BankCache<B, N>>,
+    /// Bank number loaded in each slot
+    tags: [Cell<Option<usize>>; N],
+    /// Slot of the last accessed bank
+    last: Cell<usize>,
+}
+
+struct BankCache<B, const N: usize> {
+    backend: B,
+    slots: [[u8; ROM_BANK_SIZE]; N],
+    /// Next slot to replace
+    next: usize,
+}
+
+impl<B: BankedStorage, const N: usize> StreamedRom<B, N> {
+    /// Read the first bank from the backend and create an empty cache
+    pub fn new(mut backend: B) -> Self {
+        assert!(N > 0, "at least one switchable bank must be cached");
+
+        let mut bank0 = [0xFFu8; ROM_BANK_SIZE];
+        backend.read_bank(0, &mut bank0);
+
+        let banks = match bank0[HEADER_ROM_SIZE] {
+            n @ 0x00..=0x08 => 2 << n,
+            _ => 2,
+        };
+
+        Self {
+            bank0,
+            banks,
+            cache: RefCell::new(BankCache {
+                backend,
+                slots: [[0xFFu8; ROM_BANK_SIZE]; N],
+                next: 0,
+            }),
+            tags: core::array::from_fn(|_| Cell::new(None)),
+            last: Cell::new(0),
+        }
+    }
+
+    /// Give the backend back
+    pub fn into_inner(self) -> B {
+        self.cache.into_inner().backend
+    }
+
+    fn slot(&self, bank: usize) -> usize {
+        let last = self.last.get();
+        if self.tags[last].get() == Some(bank) {
+            return last;
+        }
+
+        let slot = match self.tags.iter().position(|tag| tag.get() == Some(bank)) {
+            Some(slot) => slot,
+            None => {
+                let mut cache = self.cache.borrow_mut();
+                let cache = &mut *cache;
+                let slot = cache.next;
+                cache.backend.read_bank(bank, &mut cache.slots[slot]);
+                cache.next = (slot + 1) % N;
+                self.tags[slot].set(Some(bank));
+                slot
+            }
+        };
+        self.last.set(slot);
+        slot
+    }
+}
+
+impl<B: BankedStorage, const N: usize> RomStorage for StreamedRom<B, N> {
+    fn size(&self) -> usize {
+        self.banks * ROM_BANK_SIZE
+    }
+
+    fn read(&self, index: usize) -> u8 {
+        let bank = (index / ROM_BANK_SIZE) % self.banks;
+        let offset = index % ROM_BANK_SIZE;
+
+        if bank == 0 {
+            self.bank0[offset]
+        } else {
+            let slot = self.slot(bank);
+            self.cache.borrow().slots[slot][offset]
+        }
+    }
+
+    fn bank0(&self) -> &[u8] {
+        &self.bank0
+    }
+}
@@ -0,0 +1,244 @@
+//! Run a system on its own thread
+//!
+//! The runner owns the system and talks with the frontend through channels:
+//! commands go in, frames, audio samples and serial bytes come out.
+//! ```
+//! use padme_core::{FRAME_HEIGHT, FRAME_WIDTH};
+//! use padme_core::runner::{Command, Event, SystemRunner};
+//!
+//! let runner = SystemRunner::spawn();
+//! // Run as fast as possible instead of 60 frames per second
+//! runner.send(Command::SetPacing(false));
+//! runner.send(Command::LoadRom(vec![0u8; 32 * 1024]));
+//! loop {
+//!     match runner.recv() {
+//!         Some(Event::Frame(pixels)) => {
+//!             assert_eq!(pixels.len(), FRAME_WIDTH * FRAME_HEIGHT);
+//!             break;
+//!         },
+//!         Some(_) => continue,
+//!         None => panic!("the runner stopped"),
+//!     }
+//! }
+//! ```
+use std::sync::mpsc::{self, Receiver, Sender, TryIter};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use std::vec::Vec;
+
+use crate::{AudioSpeaker, Button, Error, FRAME_HEIGHT, FRAME_WIDTH, Pixel, Rom, Screen, SerialOutput, System};
+
+/// Requests sent to the runner thread
+pub enum Command {
+    /// Load a rom and restart the system, an Error event is sent if the rom is invalid
+    LoadRom(Vec<u8>),
+    /// Press or release a button
+    Button(Button, bool),
+    /// Stop or resume the emulation
+    Pause(bool),
+    /// Restart the current rom
+    Reset,
+    /// Wait between frames to keep the frame rate (default = true)
+    SetPacing(bool),
+    /// Stop the thread
+    Stop,
+}
+
+/// Output of the runner thread
+pub enum Event {
+    /// A complete frame, line by line
+    Frame(Vec<Pixel>),
+    /// Samples produced during the last frame (left, right)
+    Audio(Vec<(f32, f32)>),
+    /// Bytes sent on the serial port during the last frame
+    Serial(Vec<u8>),
+    /// A rom could not be loaded
+    Error(Error),
+}
+
+/// Screen keeping the last frame until it is sent
+struct FrameBuffer {
+    pixels: Vec<Pixel>,
+    ready: bool,
+}
+
+impl Screen for FrameBuffer {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        self.pixels[y as usize * FRAME_WIDTH + x as usize] = *px;
+    }
+
+    fn update(&mut self) {
+        self.ready = true;
+    }
+}
+
+struct SampleBuffer(Vec<(f32, f32)>);
+
+impl AudioSpeaker for SampleBuffer {
+    fn set_samples(&mut self, left: f32, right: f32) {
+        self.0.push((left, right));
+    }
+}
+
+struct SerialBuffer(Vec<u8>);
+
+impl SerialOutput for SerialBuffer {
+    fn putchar(&mut self, ch: u8) {
+        self.0.push(ch);
+    }
+}
+
+type RunnerSystem = System<Vec<u8>, FrameBuffer, SerialBuffer, SampleBuffer>;
+
+/// Handle on the thread running the system
+/// The thread stops when the runner is dropped
+pub struct SystemRunner {
+    commands: Sender<Command>,
+    events: Receiver<Event>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SystemRunner {
+    /// Start the thread, it waits for a rom to be loaded
+    pub fn spawn() -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (event_tx, events) = mpsc::channel();
+        let thread = thread::spawn(move || run(command_rx, event_tx));
+
+        Self {
+            commands,
+            events,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns false if the thread has stopped
+    pub fn send(&self, command: Command) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    /// Shortcut to press or release a button
+    pub fn set_button(&self, button: Button, is_pressed: bool) -> bool {
+        self.send(Command::Button(button, is_pressed))
+    }
+
+    /// Wait for the next event, returns None if the thread has stopped
+    pub fn recv(&self) -> Option<Event> {
+        self.events.recv().ok()
+    }
+
+    /// Retrieve the next event if any
+    pub fn try_recv(&self) -> Option<Event> {
+        self.events.try_recv().ok()
+    }
+
+    /// Iterate over the pending events without waiting
+    pub fn events(&self) -> TryIter<'_, Event> {
+        self.events.try_iter()
+    }
+}
+
+impl Drop for SystemRunner {
+    fn drop(&mut self) {
+        let _ = self.commands.send(Command::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Apply a command, returns false when the thread must stop
+fn handle_command(command: Command, system: &mut Option<RunnerSystem>, state: &mut RunnerState,
+                  events: &Sender<Event>) -> bool {
+    match command {
+        Command::LoadRom(bin) => match Rom::load(bin) {
+            Ok(rom) => {
+                let screen = FrameBuffer {
+                    pixels: vec![Pixel::default(); FRAME_WIDTH * FRAME_HEIGHT],
+                    ready: false,
+                };
+                *system = Some(System::new(rom, screen, SerialBuffer(Vec::new()), SampleBuffer(Vec::new())));
+            },
+            Err(err) => return events.send(Event::Error(err)).is_ok(),
+        },
+        Command::Button(button, is_pressed) => {
+            if let Some(system) = system {
+                system.set_button(button, is_pressed);
+            }
+        },
+        Command::Pause(paused) => state.paused = paused,
+        Command::Reset => {
+            if let Some(system) = system {
+                system.reset();
+            }
+        },
+        Command::SetPacing(paced) => state.paced = paced,
+        Command::Stop => return false,
+    }
+    true
+}
+
+struct RunnerState {
+    paused: bool,
+    paced: bool,
+}
+
+/// Send what the last frame produced, returns false if nobody listens anymore
+fn send_output(system: &mut RunnerSystem, events: &Sender<Event>) -> bool {
+    let mut sent = true;
+
+    if system.screen().ready {
+        system.screen().ready = false;
+        sent &= events.send(Event::Frame(system.screen().pixels.clone())).is_ok();
+    }
+    if !system.speaker().0.is_empty() {
+        let samples = core::mem::take(&mut system.speaker().0);
+        sent &= events.send(Event::Audio(samples)).is_ok();
+    }
+    if !system.serial().0.is_empty() {
+        let bytes = core::mem::take(&mut system.serial().0);
+        sent &= events.send(Event::Serial(bytes)).is_ok();
+    }
+    sent
+}
+
+/// Thread main loop
+fn run(commands: Receiver<Command>, events: Sender<Event>) {
+    let mut system: Option<RunnerSystem> = None;
+    let mut state = RunnerState { paused: false, paced: true };
+
+    loop {
+        // Nothing to run, wait for a command
+        if system.is_none() || state.paused {
+            match commands.recv() {
+                Ok(command) => {
+                    if !handle_command(command, &mut system, &mut state, &events) {
+                        return;
+                    }
+                    continue;
+                },
+                Err(_) => return,
+            }
+        }
+
+        for command in commands.try_iter() {
+            if !handle_command(command, &mut system, &mut state, &events) {
+                return;
+            }
+        }
+
+        // A pause may have been requested in the meantime
+        if let Some(emu) = system.as_mut().filter(|_| !state.paused) {
+            let t0 = Instant::now();
+            emu.update_frame();
+            if !send_output(emu, &events) {
+                return;
+            }
+            let frame_time = t0.elapsed();
+            let min_frame_time = emu.min_frame_time();
+            if state.paced && frame_time < min_frame_time {
+                thread::sleep(min_frame_time - frame_time);
+            }
+        }
+    }
+}
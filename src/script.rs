@@ -0,0 +1,87 @@
+use crate::bus::Bus;
+use crate::joypad::Button;
+use crate::region::MemoryRegion;
+use crate::rom::RomStorage;
+
+/// Script attached to the system, see System::set_frame_script
+pub type Script = &'static mut (dyn FrameScript + Send);
+
+/// Code run at the start of each VBlank, e.g. a trainer or a game mod
+/// ```
+/// use padme_core::*;
+/// use padme_core::default::*;
+///
+/// /// Keep 99 lives in 0xC0A0
+/// struct Trainer;
+///
+/// impl FrameScript for Trainer {
+///     fn on_vblank(&mut self, ctx: &mut ScriptCtx<'_>) {
+///         if ctx.peek(0xC0A0) < 99 {
+///             ctx.poke(0xC0A0, 99);
+///         }
+///     }
+/// }
+///
+/// # let mut bin = [0u8; 32 * 1024];
+/// # let rom = Rom::load(&mut bin[..]).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// emu.set_frame_script(Some(Box::leak(Box::new(Trainer))));
+/// emu.update_frame();
+/// assert_eq!(emu.peek(0xC0A0), 99);
+/// ```
+pub trait FrameScript {
+    fn on_vblank(&mut self, ctx: &mut ScriptCtx<'_>);
+}
+
+/// What a script can do with the system
+pub(crate) trait ScriptTarget {
+    fn peek(&self, address: u16) -> u8;
+    fn poke(&mut self, address: u16, value: u8);
+    fn set_button(&mut self, button: Button, is_pressed: bool);
+}
+
+impl<T: RomStorage, E: MemoryRegion> ScriptTarget for Bus<T, E> {
+    fn peek(&self, address: u16) -> u8 {
+        Bus::peek(self, address)
+    }
+
+    fn poke(&mut self, address: u16, value: u8) {
+        self.write(address, value);
+    }
+
+    fn set_button(&mut self, button: Button, is_pressed: bool) {
+        self.joypad.set_button(button, is_pressed, &mut self.it);
+    }
+}
+
+/// Restricted access to the system given to FrameScript::on_vblank
+pub struct ScriptCtx<'a> {
+    target: &'a mut dyn ScriptTarget,
+    frame: u64,
+}
+
+impl<'a> ScriptCtx<'a> {
+    pub(crate) fn new(target: &'a mut dyn ScriptTarget, frame: u64) -> Self {
+        Self { target, frame }
+    }
+
+    /// Read a byte as seen by the CPU without side effects, unmapped addresses read 0xFF
+    pub fn peek(&self, address: u16) -> u8 {
+        self.target.peek(address)
+    }
+
+    /// Write a byte like the CPU would, e.g. writes to 0x0000 - 0x7FFF go to the MBC
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.target.poke(address, value);
+    }
+
+    /// Press or release a button right away, whatever the input mode is
+    pub fn set_button(&mut self, button: Button, is_pressed: bool) {
+        self.target.set_button(button, is_pressed);
+    }
+
+    /// Number of frames sent to the screen so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+}
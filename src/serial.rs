@@ -10,8 +10,88 @@ const DEFAULT_REG_SC: u8        = 0x7E;
 const FLAG_SC_TRANSFER: u8      = 0x80;
 const FLAG_SC_INT_CLOCK: u8     = 0x01;
 
+/// Number of bits shifted out by a transfer
+const TRANSFER_BITS: u8         = 8;
+/// Cycles to shift a bit with the internal clock (8192 Hz)
+const CYCLES_PER_BIT: u16       = 512;
+/// Byte shifted in when nothing drives the line: it is pulled high
+const DEFAULT_DISCONNECTED_BYTE: u8 = 0xFF;
+
+/// The device plugged in the link port
+///
+/// Only putchar is needed to print what a rom sends,
+/// transfer and poll_transfer can be implemented to exchange bytes with another device
 pub trait SerialOutput {
     fn putchar(&mut self, c: u8);
+
+    /// Exchange a byte in a transfer clocked by this side and return the byte received
+    /// By default the byte is given to putchar and 0xFF is received
+    fn transfer(&mut self, byte: u8) -> u8 {
+        self.putchar(byte);
+        0xFF
+    }
+
+    /// Whether a peer is plugged in the link port (default = false)
+    /// While it is not, transfers clocked by this side receive the disconnected byte,
+    /// see System::set_disconnected_byte, whatever transfer returns
+    fn is_connected(&self) -> bool {
+        false
+    }
+
+    /// Called while this side waits for the other one to clock a transfer, with the byte to send
+    /// Return the byte received once the other side clocked the transfer
+    fn poll_transfer(&mut self, _byte: u8) -> Option<u8> {
+        None
+    }
+
+    /// Called once the cycles of a frame are executed, e.g. to flush a line-buffered console
+    /// or the queued bytes of a transport
+    /// ```
+    /// use padme_core::*;
+    /// use padme_core::default::{NoScreen, NoSpeaker};
+    ///
+    /// struct Console {
+    ///     line: Vec<u8>,
+    ///     flushes: usize,
+    /// }
+    ///
+    /// impl SerialOutput for Console {
+    ///     fn putchar(&mut self, c: u8) {
+    ///         self.line.push(c);
+    ///     }
+    ///
+    ///     fn end_of_frame(&mut self) {
+    ///         self.line.clear();
+    ///         self.flushes += 1;
+    ///     }
+    /// }
+    ///
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, Console { line: Vec::new(), flushes: 0 }, NoSpeaker);
+    /// emu.update_frame();
+    /// emu.update_frame();
+    /// assert_eq!(emu.serial().flushes, 2);
+    /// ```
+    fn end_of_frame(&mut self) {
+    }
+}
+
+/// Snapshot of the serial registers and transfer progress, see System::serial_state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerialState {
+    /// Serial transfer data
+    pub sb: u8,
+    /// Serial transfer control
+    pub sc: u8,
+    /// A transfer was requested and is not complete yet
+    pub transferring: bool,
+    /// The transfer is clocked by this side
+    pub internal_clock: bool,
+    /// Bits left to shift before the transfer completes
+    pub bits_remaining: u8,
+    /// Cycles before the next bit is shifted, None unless an internal clock transfer is in progress
+    pub cycles_to_next_bit: Option<u16>,
 }
 
 pub struct Serial {
@@ -19,6 +99,12 @@ pub struct Serial {
     reg_sb: u8,
     /// Serial transfer control (R/W)
     reg_sc: u8,
+    /// Bits left to shift for the current transfer
+    bits_remaining: u8,
+    /// Cycles before the next bit is shifted
+    cycles: u16,
+    /// Byte received by transfers while no peer is connected
+    disconnected_byte: u8,
 }
 
 impl Serial {
@@ -26,26 +112,90 @@ impl Serial {
         Self {
             reg_sb: DEFAULT_REG_SB,
             reg_sc: DEFAULT_REG_SC,
+            bits_remaining: 0,
+            cycles: 0,
+            disconnected_byte: DEFAULT_DISCONNECTED_BYTE,
         }
     }
 
+    pub fn set_disconnected_byte(&mut self, byte: u8) {
+        self.disconnected_byte = byte;
+    }
+
     /// Reset all registers and states
     pub fn reset(&mut self) {
         self.reg_sb = DEFAULT_REG_SB;
         self.reg_sc = DEFAULT_REG_SC;
+        self.bits_remaining = 0;
+        self.cycles = 0;
+    }
+
+    fn is_transferring(&self) -> bool {
+        is_set!(self.reg_sc, FLAG_SC_TRANSFER)
+    }
+
+    fn is_internal_clock(&self) -> bool {
+        is_set!(self.reg_sc, FLAG_SC_INT_CLOCK)
+    }
+
+    pub fn state(&self) -> SerialState {
+        let transferring = self.is_transferring();
+        let internal_clock = self.is_internal_clock();
+
+        SerialState {
+            sb: self.reg_sb,
+            sc: self.reg_sc,
+            transferring,
+            internal_clock,
+            bits_remaining: if transferring { self.bits_remaining } else { 0 },
+            cycles_to_next_bit: if transferring && internal_clock { Some(self.cycles) } else { None },
+        }
     }
 
-    pub fn step<SO>(&mut self, out: &mut SO, it: &mut InterruptHandler)
+    /// Shift bits of an internal clock transfer for the given number of cycles
+    /// An external clock transfer stays pending until the other side clocks it
+    /// Returns whether a transfer completed and requested the serial interrupt
+    pub fn step<SO>(&mut self, ticks: u8, out: &mut SO, it: &mut InterruptHandler) -> bool
         where SO: SerialOutput
     {
-        const NEW_CHAR_FLAG: u8 = FLAG_SC_TRANSFER | FLAG_SC_INT_CLOCK;
+        if !self.is_transferring() {
+            return false;
+        }
+
+        if !self.is_internal_clock() {
+            if let Some(byte) = out.poll_transfer(self.reg_sb) {
+                trace!("read character: 0x{:02X}", byte);
+                self.reg_sb = byte;
+                self.bits_remaining = 0;
+                self.reg_sc &= !FLAG_SC_TRANSFER;
+                it.request(InterruptFlag::Serial);
+                return true;
+            }
+            return false;
+        }
 
-        if (self.reg_sc & NEW_CHAR_FLAG) == NEW_CHAR_FLAG {
-            self.reg_sc &= !FLAG_SC_TRANSFER;
-            trace!("write character: 0x{:02X} ({})", self.reg_sb, self.reg_sb as char);
-            out.putchar(self.reg_sb);
-            it.request(InterruptFlag::Serial);
+        let mut ticks = ticks as u16;
+        while ticks >= self.cycles {
+            ticks -= self.cycles;
+            self.cycles = CYCLES_PER_BIT;
+            self.bits_remaining -= 1;
+            if self.bits_remaining == 0 {
+                self.complete(out, it);
+                return true;
+            }
         }
+        self.cycles -= ticks;
+        false
+    }
+
+    fn complete<SO>(&mut self, out: &mut SO, it: &mut InterruptHandler)
+        where SO: SerialOutput
+    {
+        self.reg_sc &= !FLAG_SC_TRANSFER;
+        trace!("write character: 0x{:02X} ({})", self.reg_sb, self.reg_sb as char);
+        let received = out.transfer(self.reg_sb);
+        self.reg_sb = if out.is_connected() { received } else { self.disconnected_byte };
+        it.request(InterruptFlag::Serial);
     }
 }
 
@@ -61,7 +211,14 @@ impl MemoryRegion for Serial {
     fn write(&mut self, address: u16, value: u8) {
         match address {
             REG_SB_ADDR => self.reg_sb = value,
-            REG_SC_ADDR => self.reg_sc = value,
+            REG_SC_ADDR => {
+                self.reg_sc = value;
+                if self.is_transferring() {
+                    // Writing the control register starts a new transfer
+                    self.bits_remaining = TRANSFER_BITS;
+                    self.cycles = CYCLES_PER_BIT;
+                }
+            },
             _ => unreachable!(),
         }
     }
@@ -0,0 +1,312 @@
+use core::cell::Cell;
+
+use crate::apu::AUDIO_SAMPLE_RATE;
+use crate::cpu::CLOCK_SPEED;
+use crate::ppu::PpuMode;
+use crate::region::*;
+
+const MEMORY_AREAS: usize               = 7;
+
+/// Audio / video synchronization counters, updated at the end of each frame
+/// Frontends can use the drift to slightly adjust their resampling ratio
+/// instead of dropping or repeating samples
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # let mut bin = [0u8; 32 * 1024];
+/// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// for _ in 0..60 {
+///     emu.update_frame();
+/// }
+/// let stats = emu.av_stats();
+/// assert_eq!(stats.frames, 60);
+/// assert!(stats.frame_cycles >= CLOCK_SPEED / 60);
+/// // Samples are produced every 87 cycles, which is a bit more than 48 kHz
+/// assert!(stats.audio_drift() > 0.0 && stats.audio_drift() < 0.01);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AvStats {
+    /// Number of frames since the counters were reset
+    pub frames: u32,
+    /// Cycles executed during the last frame
+    pub frame_cycles: u32,
+    /// Samples produced during the last frame
+    pub frame_samples: u32,
+    /// Cycles executed since the counters were reset
+    pub total_cycles: u64,
+    /// Samples produced since the counters were reset
+    pub total_samples: u64,
+    /// Samples that should have been produced at AUDIO_SAMPLE_RATE for the emulated time
+    expected_samples: f64,
+    /// APU sample count at the end of the last frame
+    sample_count: u32,
+}
+
+impl AvStats {
+    pub(crate) fn new(sample_count: u32) -> Self {
+        Self {
+            frames: 0,
+            frame_cycles: 0,
+            frame_samples: 0,
+            total_cycles: 0,
+            total_samples: 0,
+            expected_samples: 0.0,
+            sample_count,
+        }
+    }
+
+    /// Account for a frame of `cycles` emulated at `speed`
+    pub(crate) fn end_frame(&mut self, cycles: u32, sample_count: u32, speed: f32) {
+        self.frames = self.frames.wrapping_add(1);
+        self.frame_cycles = cycles;
+        self.frame_samples = sample_count.wrapping_sub(self.sample_count);
+        self.sample_count = sample_count;
+        self.total_cycles += cycles as u64;
+        self.total_samples += self.frame_samples as u64;
+        // At 2x, twice as many cycles are emulated in the same amount of time
+        let seconds = cycles as f64 / (CLOCK_SPEED as f64 * speed as f64);
+        self.expected_samples += seconds * AUDIO_SAMPLE_RATE as f64;
+    }
+
+    /// Average number of samples per frame
+    pub fn samples_per_frame(&self) -> f32 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.total_samples as f32 / self.frames as f32
+    }
+
+    /// Relative difference between the samples produced and the nominal sample rate,
+    /// e.g. 0.01 means 1% more samples than AUDIO_SAMPLE_RATE for the emulated time
+    pub fn audio_drift(&self) -> f32 {
+        if self.expected_samples == 0.0 {
+            return 0.0;
+        }
+        (self.total_samples as f64 / self.expected_samples - 1.0) as f32
+    }
+}
+
+/// Part of the address space counted by AccessStats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryArea {
+    /// Cartridge rom, including the bank switching registers
+    Rom,
+    Vram,
+    /// Cartridge ram
+    Eram,
+    /// Working ram and its echo
+    Wram,
+    /// Object attributes and the unusable area after it
+    Oam,
+    /// I/O registers, including IE
+    Io,
+    Hram,
+}
+
+impl MemoryArea {
+    pub const ALL: [MemoryArea; MEMORY_AREAS] = [
+        MemoryArea::Rom, MemoryArea::Vram, MemoryArea::Eram, MemoryArea::Wram,
+        MemoryArea::Oam, MemoryArea::Io, MemoryArea::Hram,
+    ];
+
+    /// Area an address belongs to
+    pub fn of(address: u16) -> Self {
+        match address {
+            ROM_REGION_START..=ROM_REGION_END => MemoryArea::Rom,
+            VRAM_REGION_START..=VRAM_REGION_END => MemoryArea::Vram,
+            ERAM_REGION_START..=ERAM_REGION_END => MemoryArea::Eram,
+            WRAM_REGION_START..=ECHORAM_REGION_END => MemoryArea::Wram,
+            OAM_REGION_START..=UNUSABLE_REGION_END => MemoryArea::Oam,
+            HRAM_REGION_START..=HRAM_REGION_END => MemoryArea::Hram,
+            _ => MemoryArea::Io,
+        }
+    }
+}
+
+/// Number of reads and writes on the bus per memory area during a frame
+/// This includes the reads made by OAM DMA, not the interrupt polling or the instruction trace
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # let mut bin = [0u8; 0x150];
+/// // LD ($C000),A ; JR -5
+/// bin[0x100..0x105].copy_from_slice(&[0xEA, 0x00, 0xC0, 0x18, 0xFB]);
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// emu.set_access_stats(true);
+/// emu.update_frame();
+///
+/// let stats = emu.access_stats().unwrap();
+/// assert!(stats.writes(MemoryArea::Wram) > 0);
+/// assert_eq!(stats.writes(MemoryArea::Vram), 0);
+/// // Op codes and operands are read from the rom
+/// assert!(stats.reads(MemoryArea::Rom) > stats.writes(MemoryArea::Wram));
+/// // IE and IF are checked every instruction without a bus access
+/// assert_eq!(stats.reads(MemoryArea::Io), 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessStats {
+    reads: [u32; MEMORY_AREAS],
+    writes: [u32; MEMORY_AREAS],
+}
+
+impl AccessStats {
+    pub fn reads(&self, area: MemoryArea) -> u32 {
+        self.reads[area as usize]
+    }
+
+    pub fn writes(&self, area: MemoryArea) -> u32 {
+        self.writes[area as usize]
+    }
+}
+
+/// Access counters of the frame in progress, reads go through a shared reference
+pub struct AccessCounters {
+    pub enabled: bool,
+    reads: [Cell<u32>; MEMORY_AREAS],
+    writes: [Cell<u32>; MEMORY_AREAS],
+}
+
+impl AccessCounters {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            reads: Default::default(),
+            writes: Default::default(),
+        }
+    }
+
+    #[inline]
+    pub fn count_read(&self, address: u16) {
+        if self.enabled {
+            let counter = &self.reads[MemoryArea::of(address) as usize];
+            counter.set(counter.get().wrapping_add(1));
+        }
+    }
+
+    #[inline]
+    pub fn count_write(&self, address: u16) {
+        if self.enabled {
+            let counter = &self.writes[MemoryArea::of(address) as usize];
+            counter.set(counter.get().wrapping_add(1));
+        }
+    }
+
+    /// Retrieve the counters and start over
+    pub fn take(&self) -> AccessStats {
+        AccessStats {
+            reads: core::array::from_fn(|i| self.reads[i].take()),
+            writes: core::array::from_fn(|i| self.writes[i].take()),
+        }
+    }
+}
+
+/// Cycles spent by the components in each of their states during a frame
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # let mut bin = [0u8; 0x150];
+/// // EI ; HALT ; JR -3
+/// bin[0x100..0x104].copy_from_slice(&[0xFB, 0x76, 0x18, 0xFD]);
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// emu.set_cycle_stats(true);
+/// emu.update_frame();
+///
+/// let stats = emu.cycle_stats().unwrap();
+/// // The CPU sleeps until the next interrupt
+/// assert!(stats.cpu_halted > stats.cpu_active);
+/// assert_eq!(stats.ppu_mode(PpuMode::OamScan), 80 * 144);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CycleStats {
+    /// Cycles the CPU executed instructions or dispatched interrupts
+    pub cpu_active: u32,
+    /// Cycles the CPU was halted, stopped or locked up
+    pub cpu_halted: u32,
+    /// Cycles an OAM DMA transfer was in progress
+    pub dma: u32,
+    /// Dots the LCD was off
+    pub lcd_off: u32,
+    /// Dots spent in each PPU mode while the LCD was on
+    pub(crate) ppu_modes: [u32; 4],
+}
+
+impl CycleStats {
+    /// Dots spent in a PPU mode while the LCD was on
+    pub fn ppu_mode(&self, mode: PpuMode) -> u32 {
+        self.ppu_modes[mode as usize]
+    }
+}
+
+/// Accesses and instructions a well behaved rom does not make, e.g. to score roms in compatibility scans
+/// The counters add up until System::reset_diagnostics, resets included
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # let mut bin = [0u8; 0x150];
+/// // LD A,($A000) ; DB $D3
+/// bin[0x100..0x104].copy_from_slice(&[0xFA, 0x00, 0xA0, 0xD3]);
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// emu.update_frame();
+///
+/// let diagnostics = emu.diagnostics();
+/// assert_eq!(diagnostics.unmapped_reads, 1);
+/// assert_eq!(diagnostics.illegal_opcodes, 1);
+/// emu.reset_diagnostics();
+/// assert_eq!(emu.diagnostics(), Diagnostics::default());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// Reads of addresses nothing answers at, they return 0xFF
+    pub unmapped_reads: u32,
+    /// Writes to addresses nothing answers at, they are ignored
+    pub unmapped_writes: u32,
+    /// Unknown op codes executed, the CPU locks up on each of them
+    pub illegal_opcodes: u32,
+    /// Serial interrupts requested by completed transfers
+    pub serial_interrupts: u32,
+}
+
+/// Diagnostics counters, reads go through a shared reference
+pub struct DiagnosticCounters(Cell<Diagnostics>);
+
+impl DiagnosticCounters {
+    pub fn new() -> Self {
+        Self(Cell::new(Diagnostics::default()))
+    }
+
+    #[inline]
+    fn count(&self, counter: fn(&mut Diagnostics) -> &mut u32) {
+        let mut diagnostics = self.0.get();
+        let value = counter(&mut diagnostics);
+        *value = value.wrapping_add(1);
+        self.0.set(diagnostics);
+    }
+
+    pub fn unmapped_read(&self) {
+        self.count(|d| &mut d.unmapped_reads);
+    }
+
+    pub fn unmapped_write(&self) {
+        self.count(|d| &mut d.unmapped_writes);
+    }
+
+    pub fn illegal_opcode(&self) {
+        self.count(|d| &mut d.illegal_opcodes);
+    }
+
+    pub fn serial_interrupt(&self) {
+        self.count(|d| &mut d.serial_interrupts);
+    }
+
+    pub fn get(&self) -> Diagnostics {
+        self.0.get()
+    }
+
+    pub fn clear(&self) {
+        self.0.take();
+    }
+}
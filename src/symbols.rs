@@ -0,0 +1,153 @@
+//! Debug symbols produced by the assembler
+//!
+//! RGBDS writes one symbol per line with the `bank:address name` format,
+//! and comments start with `;`:
+//! ```
+//! use padme_core::SymbolTable;
+//!
+//! let sym = "; File generated by rgblink\n\
+//!            00:0150 Main\n\
+//!            00:0160 Main.loop\n\
+//!            02:4000 LoadTiles\n";
+//! let symbols = SymbolTable::parse(sym).unwrap();
+//! assert_eq!(symbols.len(), 3);
+//! assert_eq!(format!("{}", symbols.resolve(0, 0x0163).unwrap()), "00:Main.loop+$3");
+//! assert_eq!(format!("{}", symbols.resolve(2, 0x4000).unwrap()), "02:LoadTiles");
+//! assert!(symbols.resolve(1, 0x4000).is_none());
+//! ```
+use core::fmt;
+
+use crate::Error;
+use crate::region::*;
+
+/// Start of the switchable rom bank
+const ROM_BANKN_START: u16              = 0x4000;
+/// Start of the second half of wram (switchable on CGB)
+const WRAM_BANKN_START: u16             = 0xD000;
+
+/// A label defined at a given bank and address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub bank: u16,
+    pub address: u16,
+    pub name: &'a str,
+}
+
+impl<'a> Symbol<'a> {
+    /// Checks whether the symbol belongs to the memory area where the address is mapped
+    /// The bank is only relevant for switchable areas (rom bank N and eram)
+    fn is_visible(&self, bank: u16, address: u16) -> bool {
+        if area_start(self.address) != area_start(address) {
+            return false;
+        }
+        match address {
+            ROM_BANKN_START..=ROM_REGION_END | ERAM_REGION_START..=ERAM_REGION_END => self.bank == bank,
+            _ => true,
+        }
+    }
+}
+
+/// Start address of the memory area containing an address
+/// Symbols are not resolved across areas (a rom label does not describe a ram address)
+fn area_start(address: u16) -> u16 {
+    match address {
+        ROM_REGION_START..=0x3FFF => ROM_REGION_START,
+        ROM_BANKN_START..=ROM_REGION_END => ROM_BANKN_START,
+        VRAM_REGION_START..=VRAM_REGION_END => VRAM_REGION_START,
+        ERAM_REGION_START..=ERAM_REGION_END => ERAM_REGION_START,
+        WRAM_REGION_START..=0xCFFF => WRAM_REGION_START,
+        WRAM_BANKN_START..=WRAM_REGION_END => WRAM_BANKN_START,
+        HRAM_REGION_START..=HRAM_REGION_END => HRAM_REGION_START,
+        _ => address,
+    }
+}
+
+/// An address described by the closest label before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location<'a> {
+    pub symbol: Symbol<'a>,
+    /// Distance from the label
+    pub offset: u16,
+}
+
+impl<'a> fmt::Display for Location<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02X}:{}", self.symbol.bank, self.symbol.name)?;
+        if self.offset > 0 {
+            write!(f, "+${:X}", self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Symbols of a .sym file
+/// The file content is borrowed and scanned on each lookup, so nothing is allocated
+#[derive(Clone, Copy)]
+pub struct SymbolTable<'a> {
+    source: &'a str,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Check the whole file and keep it for lookups
+    /// Returns the (1-based) number of the first invalid line on error
+    pub fn parse(source: &'a str) -> Result<Self, Error> {
+        for (n, line) in source.lines().enumerate() {
+            if parse_line(line).is_err() {
+                return Err(Error::InvalidSymbol(n + 1));
+            }
+        }
+        Ok(Self { source })
+    }
+
+    /// Iterate over all symbols in file order
+    pub fn iter(&self) -> impl Iterator<Item = Symbol<'a>> + 'a {
+        // Lines have been checked by parse
+        self.source.lines().filter_map(|line| parse_line(line).ok().flatten())
+    }
+
+    /// Number of symbols
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Find a symbol by name
+    pub fn find(&self, name: &str) -> Option<Symbol<'a>> {
+        self.iter().find(|sym| sym.name == name)
+    }
+
+    /// Describe an address using the closest label at or before it
+    /// bank is the bank currently mapped where the address lies
+    pub fn resolve(&self, bank: u16, address: u16) -> Option<Location<'a>> {
+        self.iter()
+            .filter(|sym| sym.address <= address && sym.is_visible(bank, address))
+            .max_by_key(|sym| sym.address)
+            .map(|symbol| Location { symbol, offset: address - symbol.address })
+    }
+}
+
+/// Parse a `bank:address name` line
+/// Returns None for empty lines and comments
+fn parse_line(line: &str) -> Result<Option<Symbol<'_>>, ()> {
+    let line = match line.find(';') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }.trim();
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let (location, name) = line.split_once(char::is_whitespace).ok_or(())?;
+    let (bank, address) = location.split_once(':').ok_or(())?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(());
+    }
+
+    Ok(Some(Symbol {
+        bank: u16::from_str_radix(bank, 16).map_err(|_| ())?,
+        address: u16::from_str_radix(address, 16).map_err(|_| ())?,
+        name,
+    }))
+}
@@ -1,18 +1,47 @@
-use core::ops::Deref;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 use core::time::Duration;
 
-use crate::{Button, Error, Rom, Screen, AudioSpeaker, SerialOutput};
+use crate::{AccessStats, ApuState, AudioMode, AvStats, BreakReason, Breakpoint, Button, Buttons, CycleStats, DebugOverlay, Diagnostics, Freeze, InputMode, Model, Watch, Location, SymbolTable, CpuState, FrameInfo, SerialState, TimerState, TimeSource, Error, Instruction, Interrupts, Palette, PaletteRegister, Pixel, PpuMode, RenderMode, Rom, Transform, RomStorage, Screen, AudioSpeaker, SerialOutput, UnusableRegion, MemoryRegion};
 use crate::bus::Bus;
+use crate::cheat::{FreezeMode, Freezes};
 use crate::cpu::{Cpu, CLOCK_SPEED};
+use crate::debug::{Breakpoints, Watches};
+use crate::default::NoExpansion;
+use crate::events::{EventKind, FrameEvents};
+use crate::palette;
+use crate::region::{ERAM_REGION_END, ERAM_REGION_START, IO_SOUND_REGION_END, IO_SOUND_REGION_START};
+use crate::script::{Script, ScriptCtx};
+#[cfg(feature = "hooks")]
+use crate::Hooks;
+#[cfg(feature = "debug")]
+use crate::InterruptFlag;
 
 pub const DEFAULT_FRAME_RATE: u32 = 60;
 
-pub struct System<T: Deref<Target=[u8]>,
+/// Number of cycles per scanline, executed by each poll_frame
+const CYCLES_PER_LINE: u32 = 456;
+
+/// LD B,B op code, used as a software breakpoint by homebrew developers
+const OPCODE_LD_B_B: u8 = 0x40;
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF29CE484222325;
+const FNV_PRIME: u64 = 0x100000001B3;
+
+/// FNV-1a hash, only used to compare states
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, &byte| (h ^ byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+pub struct System<T: RomStorage,
                   S: Screen,
                   SO: SerialOutput,
-                  AS: AudioSpeaker> {
+                  AS: AudioSpeaker,
+                  E: MemoryRegion = NoExpansion> {
     /// Address bus
-    bus: Bus<T>,
+    bus: Bus<T, E>,
     /// To execute instructions
     cpu: Cpu,
     /// A screen to give to the PPU
@@ -23,14 +52,104 @@ pub struct System<T: Deref<Target=[u8]>,
     speaker: AS,
     /// Keep the number of cycles before a frame is refreshed
     cycles_per_frame: u32,
+    /// Emulation speed, 1.0 is the real hardware speed
+    speed: f32,
+    /// Cycles executed in the current frame, kept when a frame is stopped
+    frame_progress: u32,
+    /// Stop when LD B,B is executed
+    software_breakpoints: bool,
+    /// Reason of the last break, until it is retrieved
+    pending_break: Option<BreakReason>,
+    /// Stop before executing an instruction at these addresses
+    breakpoints: Breakpoints,
+    /// Address of the last breakpoint reached, so that the execution can resume from it
+    resume_pc: Option<u16>,
+    /// Values written again and again
+    freezes: Freezes,
+    /// Values sampled at the end of each frame
+    watches: Watches,
+    /// Audio / video synchronization counters
+    av_stats: AvStats,
+    /// Whether button changes are delayed to the end of the frame
+    input_mode: InputMode,
+    /// Buttons pressed at the end of the frame in InputMode::Frame
+    latched_buttons: Buttons,
+    /// Hardware model used on reset
+    model: Model,
+    /// Number of frames sent to the screen
+    frames: u64,
+    /// Memory accesses of the last frame, when counted
+    access_stats: Option<AccessStats>,
+    /// Cycles per component state of the frame in progress, when counted
+    cycle_counters: Option<CycleStats>,
+    /// Cycles per component state of the last frame
+    cycle_stats: Option<CycleStats>,
+    /// Host dependent sources are replaced with fixed values
+    deterministic: bool,
+    /// Time followed by the cartridge clock
+    time_source: TimeSource,
+    /// Last wall time read with TimeSource::Wall
+    wall_time: Option<u64>,
+    /// Cycles emulated since the cartridge clock was last updated
+    clock_cycles: u32,
+    /// Lock up on reset when the header would not pass the boot rom checks
+    boot_checks: bool,
+    /// Copy the external ram to a reloaded cartridge with the same header
+    keep_eram: bool,
+    /// Events of the frames, when recorded
+    events: Option<FrameEvents>,
+    /// Run at the start of each VBlank
+    script: Option<Script>,
 }
 
-impl<T: Deref<Target=[u8]>,
+impl<T: RomStorage,
      S: Screen,
      SO: SerialOutput,
      AS: AudioSpeaker> System<T, S, SO, AS> {
     pub fn new(rom: Rom<T>, screen: S, serial_output: SO, speaker: AS) -> Self {
-        let bus = Bus::new(rom);
+        Self::build(rom, screen, serial_output, speaker, None)
+    }
+}
+
+impl<T: RomStorage,
+     S: Screen,
+     SO: SerialOutput,
+     AS: AudioSpeaker,
+     E: MemoryRegion> System<T, S, SO, AS, E> {
+    /// Same as new with a device plugged on the I/O ports that are not used by the hardware (e.g. 0xFF7F)
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// struct Latch(u8);
+    ///
+    /// impl MemoryRegion for Latch {
+    ///     fn read(&self, _address: u16) -> u8 {
+    ///         self.0
+    ///     }
+    ///
+    ///     fn write(&mut self, _address: u16, value: u8) {
+    ///         self.0 = value.wrapping_add(1);
+    ///     }
+    /// }
+    ///
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,$41 ; LDH ($7F),A ; LDH A,($7F)
+    /// bin[0x100..0x106].copy_from_slice(&[0x3E, 0x41, 0xE0, 0x7F, 0xF0, 0x7F]);
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::with_expansion_io(rom, NoScreen, NoSerial, NoSpeaker, Latch(0));
+    /// for _ in 0..3 {
+    ///     emu.step();
+    /// }
+    /// assert_eq!(emu.cpu_state().a, 0x42);
+    /// assert_eq!(emu.expansion_io().map(|latch| latch.0), Some(0x42));
+    /// ```
+    pub fn with_expansion_io(rom: Rom<T>, screen: S, serial_output: SO, speaker: AS, device: E) -> Self {
+        Self::build(rom, screen, serial_output, speaker, Some(device))
+    }
+
+    fn build(rom: Rom<T>, screen: S, serial_output: SO, speaker: AS, expansion: Option<E>) -> Self {
+        let mut bus = Bus::new(rom);
+        bus.expansion = expansion;
         let cpu = Cpu::new();
 
         System {
@@ -40,48 +159,558 @@ impl<T: Deref<Target=[u8]>,
             serial_output,
             speaker,
             cycles_per_frame: CLOCK_SPEED / DEFAULT_FRAME_RATE,
+            speed: 1.0,
+            frame_progress: 0,
+            software_breakpoints: false,
+            pending_break: None,
+            breakpoints: Breakpoints::new(),
+            resume_pc: None,
+            freezes: Freezes::new(),
+            watches: Watches::new(),
+            av_stats: AvStats::new(0),
+            input_mode: InputMode::Immediate,
+            latched_buttons: Buttons::default(),
+            model: Model::Dmg,
+            frames: 0,
+            access_stats: None,
+            cycle_counters: None,
+            cycle_stats: None,
+            deterministic: false,
+            time_source: TimeSource::Emulated,
+            wall_time: None,
+            clock_cycles: 0,
+            boot_checks: false,
+            keep_eram: false,
+            events: None,
+            script: None,
         }
     }
 
+    /// Reset the console as the current model, the external ram is kept
     pub fn reset(&mut self) {
-        self.bus.ppu.reset();
-        self.bus.timer.reset();
-        self.bus.serial.reset();
-        self.bus.joypad.reset();
-        self.bus.it.reset();
-        self.cpu.reset();
+        self.reset_to(self.model, false);
+    }
+
+    /// Power cycle the console as another hardware model
+    ///
+    /// All the components are reset, including the APU and the cartridge bank registers,
+    /// the working ram is cleared and the external ram is cleared if clear_eram is set.
+    /// Settings (speed, breakpoints, freezes, ...) are kept.
+    ///
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let bin = [0u8; 0x150];
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// assert_eq!(emu.model(), Model::Dmg);
+    /// assert_eq!(emu.cpu_state().a, 0x01);
+    ///
+    /// emu.reset_to(Model::Mgb, false);
+    /// assert_eq!(emu.model(), Model::Mgb);
+    /// assert_eq!(emu.cpu_state().a, 0xFF);
+    /// ```
+    pub fn reset_to(&mut self, model: Model, clear_eram: bool) {
+        self.model = model;
+        self.bus.apu.flush(&mut self.speaker);
+        self.bus.reset(clear_eram);
+        self.cpu.reset(model);
+        self.frame_progress = 0;
+        self.pending_break = None;
+        self.resume_pc = None;
+        self.latched_buttons = Buttons::default();
+        self.check_boot();
+    }
+
+    /// Lock the CPU up if the boot rom would refuse to start the cartridge
+    fn check_boot(&mut self) {
+        let rom = &self.bus.rom;
+        if self.boot_checks && !(rom.verify_logo() && rom.verify_header_checksum()) {
+            self.cpu.lock();
+        }
+    }
+
+    /// Reproduce the checks of the boot rom on reset (default = false): the CPU locks up
+    /// when the logo or the header checksum of the cartridge is invalid
+    /// The check is done on the next reset or rom load
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let bin = [0u8; 0x150];
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_boot_checks(true);
+    /// emu.reset();
+    /// assert!(emu.is_locked());
+    /// ```
+    pub fn set_boot_checks(&mut self, enabled: bool) {
+        self.boot_checks = enabled;
+    }
+
+    /// Hardware model the console was last reset as (default = Model::Dmg)
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Carry the external ram over to the new cartridge on load_bin / load_rom
+    /// when both carts have the same header (default = false)
+    ///
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 0x150];
+    /// # bin[0x149] = 0x02;
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.rom_mut().ram_mut()[0] = 0x42;
+    /// emu.set_keep_eram(true);
+    ///
+    /// let same = Rom::load_raw(&bin[..], CartridgeType::Mbc1RamBattery).unwrap();
+    /// let previous = emu.load_rom(same);
+    /// assert_eq!(emu.rom().ram()[0], 0x42);
+    /// assert_eq!(previous.ram()[0], 0x42);
+    /// ```
+    pub fn set_keep_eram(&mut self, enabled: bool) {
+        self.keep_eram = enabled;
     }
 
     /// Replace cartridge with a new buffer
-    pub fn load_bin(&mut self, bytes: T) -> Result<(), Error> {
+    ///
+    /// The previous cartridge is returned so that its external ram can still be saved
+    pub fn load_bin(&mut self, bytes: T) -> Result<Rom<T>, Error> {
         let rom = Rom::load(bytes)?;
 
-        self.reset();
-        self.bus.set_rom(rom);
-        Ok(())
+        Ok(self.load_rom(rom))
     }
 
     /// Reload a new rom
-    pub fn load_rom(&mut self, rom: Rom<T>) {
-        self.bus.set_rom(rom);
+    ///
+    /// The previous cartridge is returned so that its external ram can still be saved
+    pub fn load_rom(&mut self, mut rom: Rom<T>) -> Rom<T> {
+        let previous = &self.bus.rom;
+        if self.keep_eram && rom.header() == previous.header() {
+            let src = previous.ram();
+            let dst = rom.ram_mut();
+            let len = src.len().min(dst.len());
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+        let previous = self.bus.set_rom(rom);
         self.reset();
+        previous
     }
 
     /// Single step to execute cpu, ppu, timer, serial & dma
     pub fn step(&mut self) -> u8 {
+        let cpu = self.cpu.state();
+        if !cpu.halted && !cpu.stopped && !self.cpu.is_locked() {
+            if !self.breakpoints.is_empty() && self.resume_pc != Some(cpu.pc) {
+                let rom = &self.bus.rom;
+                if let Some(bp) = self.breakpoints.find(cpu.pc, rom.rom_bank(), rom.ram_bank()) {
+                    self.pending_break = Some(BreakReason::Breakpoint(bp));
+                    self.resume_pc = Some(cpu.pc);
+                    return 0;
+                }
+            }
+            self.resume_pc = None;
+            if self.software_breakpoints && self.bus.peek(cpu.pc) == OPCODE_LD_B_B {
+                self.pending_break = Some(BreakReason::Software(cpu.pc));
+            }
+        }
+
+        // The audio ticks waiting in fast mode must be synthesized before the sound registers are accessed
+        if self.bus.apu.mode() == AudioMode::Fast && self.may_access_apu(&cpu) {
+            self.bus.apu.flush(&mut self.speaker);
+        }
+
+        let in_vblank = self.bus.ppu.mode() == PpuMode::VBlank;
         let ticks = self.cpu.step(&mut self.bus);
 
-        for _ in 0..ticks {
+        if let Some(counters) = self.cycle_counters.as_mut() {
+            if cpu.halted || cpu.stopped || self.cpu.is_locked() {
+                counters.cpu_halted += ticks as u32;
+            } else {
+                counters.cpu_active += ticks as u32;
+            }
+            if self.bus.ppu.is_dma_active() {
+                counters.dma += ticks as u32;
+            }
+        }
+
+        if self.freezes.mode == FreezeMode::Instruction && !self.freezes.is_empty() {
+            self.apply_freezes();
+        }
+
+        if self.events.is_some() {
+            self.step_components_logged(ticks);
+        } else {
+            for _ in 0..ticks {
+                self.bus.apu.step(&mut self.speaker);
+                self.bus.ppu.step(&mut self.screen, &mut self.bus.it);
+                self.bus.timer.step(&mut self.bus.it);
+            }
+
+            if self.bus.serial.step(ticks, &mut self.serial_output, &mut self.bus.it) {
+                self.bus.diagnostics.serial_interrupt();
+            }
+        }
+
+        if !in_vblank && self.bus.ppu.mode() == PpuMode::VBlank {
+            if let Some(script) = self.script.as_mut() {
+                self.bus.apu.flush(&mut self.speaker);
+                script.on_vblank(&mut ScriptCtx::new(&mut self.bus, self.frames));
+            }
+        }
+
+        self.clock_cycles += ticks as u32;
+        if self.clock_cycles >= CLOCK_SPEED {
+            self.clock_cycles -= CLOCK_SPEED;
+            self.update_clock();
+        }
+
+        for _ in 0..(ticks / 4) {
+            self.bus.dma_tick();
+        }
+
+        ticks
+    }
+
+    /// Same as the components steps in step, recording the events of the frame
+    fn step_components_logged(&mut self, ticks: u8) {
+        let events = match self.events.as_mut() {
+            Some(events) => events,
+            None => return,
+        };
+        if let Some(flag) = self.cpu.take_dispatched() {
+            events.push(ticks as u32, EventKind::Interrupt(flag));
+        }
+        if self.bus.ppu.take_dma_started() {
+            events.push(ticks as u32, EventKind::DmaStart((self.bus.ppu.dma_source() >> 8) as u8));
+        }
+
+        for tick in 0..ticks {
             self.bus.apu.step(&mut self.speaker);
+            let mode = self.bus.ppu.mode();
             self.bus.ppu.step(&mut self.screen, &mut self.bus.it);
+            if self.bus.ppu.mode() != mode {
+                let kind = EventKind::ModeChange { mode: self.bus.ppu.mode(), ly: self.bus.ppu.current_line() };
+                events.push(tick as u32 + 1, kind);
+            }
             self.bus.timer.step(&mut self.bus.it);
         }
 
-        self.bus.serial.step(&mut self.serial_output, &mut self.bus.it);
+        let sent = self.bus.serial.state().sb;
+        if self.bus.serial.step(ticks, &mut self.serial_output, &mut self.bus.it) {
+            self.bus.diagnostics.serial_interrupt();
+            events.push(ticks as u32, EventKind::Serial { sent, received: self.bus.serial.state().sb });
+        }
+        events.advance(ticks as u32);
+    }
 
-        self.bus.dma_tick();
+    /// Record the interrupts, serial transfers, DMA transfers and PPU mode changes
+    /// of each frame (disabled by default), see frame_events
+    pub fn set_event_log(&mut self, enabled: bool) {
+        self.events = if enabled { Some(FrameEvents::new()) } else { None };
+        self.cpu.take_dispatched();
+        self.bus.ppu.take_dma_started();
+    }
 
-        ticks
+    /// Retrieve the events of the last complete frame, None if they are not recorded
+    pub fn frame_events(&self) -> Option<&FrameEvents> {
+        self.events.as_ref()
+    }
+
+    /// Read a byte as seen by the CPU without triggering hooks or side effects
+    /// Unmapped addresses read 0xFF
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    /// Copy the whole address space as seen by the CPU into buffer
+    /// Banked regions (ROM, external RAM) are dumped as currently mapped
+    /// and unmapped addresses are filled with 0xFF
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let mut memory = [0u8; 0x10000];
+    /// emu.dump_memory(&mut memory);
+    /// assert_eq!(memory[0xFFFE], 0x00);
+    /// ```
+    pub fn dump_memory(&self, buffer: &mut [u8; 0x10000]) {
+        for (address, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.bus.peek(address as u16);
+        }
+    }
+
+    /// Retrieve a copy of the CPU registers
+    pub fn cpu_state(&self) -> CpuState {
+        self.cpu.state()
+    }
+
+    /// Checks whether the CPU is waiting for an interrupt (HALT)
+    pub fn is_halted(&self) -> bool {
+        self.cpu.state().halted
+    }
+
+    /// Checks whether the CPU is waiting for a button to be pressed (STOP)
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// let mut bin = [0u8; 32 * 1024];
+    /// // STOP
+    /// bin[0x100] = 0x10;
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.step();
+    /// emu.step();
+    /// assert!(emu.is_stopped());
+    /// assert_eq!(emu.cpu_state().pc, 0x102);
+    /// emu.set_button(Button::Start, true);
+    /// emu.step();
+    /// assert!(!emu.is_stopped());
+    /// ```
+    pub fn is_stopped(&self) -> bool {
+        self.cpu.state().stopped
+    }
+
+    /// Checks whether the CPU locked up on an illegal op code or a failed boot check
+    /// Nothing is executed anymore until the system is reset, other components keep running
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// let mut bin = [0u8; 32 * 1024];
+    /// // 0xD3 is not a valid op code
+    /// bin[0x100] = 0xD3;
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.step();
+    /// assert!(emu.is_locked());
+    /// emu.step();
+    /// assert_eq!(emu.cpu_state().pc, 0x101);
+    /// ```
+    pub fn is_locked(&self) -> bool {
+        self.cpu.is_locked()
+    }
+
+    /// Retrieve the interrupts requested and not serviced yet (IF)
+    pub fn pending_interrupts(&self) -> Interrupts {
+        self.bus.it.requested()
+    }
+
+    /// Retrieve the interrupts enabled (IE)
+    /// The master enable flag is part of the CPU state
+    pub fn interrupts_enabled(&self) -> Interrupts {
+        self.bus.it.enabled()
+    }
+
+    /// Request an interrupt as if the hardware raised it
+    /// This is only available with the `debug` feature
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.request_interrupt(InterruptFlag::Joypad);
+    /// assert!(emu.pending_interrupts().contains(InterruptFlag::Joypad));
+    /// ```
+    #[cfg(feature = "debug")]
+    pub fn request_interrupt(&mut self, flag: InterruptFlag) {
+        self.bus.it.request(flag);
+    }
+
+    /// Decode the instruction located at address
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let pc = emu.cpu_state().pc;
+    /// println!("{:04X}: {}", pc, emu.instruction_at(pc));
+    /// ```
+    pub fn instruction_at(&self, address: u16) -> Instruction {
+        Instruction::new(address, [
+            self.bus.peek(address),
+            self.bus.peek(address.wrapping_add(1)),
+            self.bus.peek(address.wrapping_add(2)),
+        ])
+    }
+
+    /// Compute a hash of the CPU registers and the whole address space
+    /// Two systems running the same code should always have the same hash,
+    /// which makes it easy to find where two runs diverge
+    pub fn state_hash(&self) -> u64 {
+        let cpu = self.cpu.state();
+        let mut hash = fnv1a(FNV_OFFSET_BASIS, &[
+            cpu.a, cpu.f, cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l,
+            (cpu.sp >> 8) as u8, cpu.sp as u8, (cpu.pc >> 8) as u8, cpu.pc as u8,
+            cpu.ime as u8, cpu.halted as u8, cpu.stopped as u8,
+        ]);
+
+        for address in 0..=0xFFFFu16 {
+            hash = fnv1a(hash, &[self.bus.peek(address)]);
+        }
+        hash
+    }
+
+    /// Retrieve the current PPU mode
+    /// Transitions are also reported to the screen through `Screen::on_mode_change`
+    pub fn ppu_mode(&self) -> PpuMode {
+        self.bus.ppu.mode()
+    }
+
+    /// Retrieve the line being processed by the PPU (0 - 153)
+    /// Lines 144 to 153 are the VBlank period
+    pub fn current_line(&self) -> u8 {
+        self.bus.ppu.current_line()
+    }
+
+    /// Retrieve the number of dots elapsed in the current line (0 - 455)
+    pub fn line_dots(&self) -> u32 {
+        self.bus.ppu.line_dots()
+    }
+
+    /// Checks whether the LCD is on
+    pub fn is_lcd_on(&self) -> bool {
+        self.bus.ppu.is_lcd_on()
+    }
+
+    /// Checks whether the PPU is drawing the visible lines of a frame,
+    /// i.e. the LCD is on and the VBlank period is not reached yet
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// // The system starts during VBlank
+    /// while !emu.is_frame_in_progress() {
+    ///     emu.step();
+    /// }
+    /// assert_eq!(emu.current_line(), 0);
+    /// while emu.is_frame_in_progress() {
+    ///     emu.step();
+    /// }
+    /// assert_eq!(emu.current_line(), FRAME_HEIGHT as u8);
+    /// assert_eq!(emu.ppu_mode(), PpuMode::VBlank);
+    /// ```
+    pub fn is_frame_in_progress(&self) -> bool {
+        self.bus.ppu.is_frame_in_progress()
+    }
+
+    /// Retrieve a snapshot of the APU registers and channels, e.g. for a channel viewer
+    /// In fast audio mode, channel counters are only up to date at the end of a frame
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let apu = emu.apu_state();
+    /// assert!(apu.enabled);
+    /// // NR13 reads 0xFF but the frequency is still known
+    /// assert_eq!(apu.channels[0].registers[3], 0xFF);
+    /// assert_eq!(apu.channels[0].frequency, 131072);
+    /// ```
+    pub fn apu_state(&self) -> ApuState {
+        self.bus.apu.state()
+    }
+
+    /// Retrieve a snapshot of the timer, including the internal divider hidden behind DIV
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let timer = emu.timer_state();
+    /// assert_eq!(timer.divider >> 8, 0x18);
+    /// assert_eq!(timer.cycles_to_increment, None);
+    ///
+    /// emu.step();
+    /// assert_eq!(emu.timer_state().divider, timer.divider + 4);
+    /// ```
+    pub fn timer_state(&self) -> TimerState {
+        self.bus.timer.state()
+    }
+
+    /// Byte received by the transfers clocked while no peer is connected (default = 0xFF)
+    /// Some games check it to detect a link partner
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 0x150];
+    /// // ld a, $81 ; ldh ($02), a ; jr -2
+    /// bin[0x100..0x106].copy_from_slice(&[0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE]);
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_disconnected_byte(0x00);
+    ///
+    /// emu.step();
+    /// emu.step();
+    /// while emu.serial_state().transferring {
+    ///     emu.step();
+    /// }
+    /// assert_eq!(emu.serial_state().sb, 0x00);
+    /// ```
+    pub fn set_disconnected_byte(&mut self, byte: u8) {
+        self.bus.serial.set_disconnected_byte(byte);
+    }
+
+    /// Get the serial registers and the progress of the current transfer
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 0x150];
+    /// // ld a, $81 ; ldh ($02), a ; jr -2
+    /// bin[0x100..0x106].copy_from_slice(&[0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE]);
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    ///
+    /// emu.step();
+    /// emu.step();
+    /// let serial = emu.serial_state();
+    /// assert!(serial.transferring && serial.internal_clock);
+    /// assert_eq!(serial.bits_remaining, 8);
+    ///
+    /// // A byte is shifted out in 4096 cycles
+    /// while emu.serial_state().transferring {
+    ///     emu.step();
+    /// }
+    /// assert_eq!(emu.serial_state().cycles_to_next_bit, None);
+    /// ```
+    pub fn serial_state(&self) -> SerialState {
+        self.bus.serial.state()
+    }
+
+    /// Seed the noise channel generator (15 bits), e.g. for deterministic audio tests
+    /// The hardware sets it to 0x7FFF again when the channel is triggered
+    pub fn set_noise_lfsr(&mut self, value: u16) {
+        self.bus.apu.flush(&mut self.speaker);
+        self.bus.apu.set_noise_lfsr(value);
+    }
+
+    /// Install callbacks on instructions and memory accesses
+    #[cfg(feature = "hooks")]
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        self.bus.hooks = hooks;
+    }
+
+    /// Retrieve the installed callbacks
+    #[cfg(feature = "hooks")]
+    pub fn hooks(&self) -> &Hooks {
+        &self.bus.hooks
     }
 
     /// Retrieve the rom in readonly
@@ -89,6 +718,11 @@ impl<T: Deref<Target=[u8]>,
         &self.bus.rom
     }
 
+    /// Retrieve the rom, to import or export its external ram
+    pub fn rom_mut(&mut self) -> &mut Rom<T> {
+        &mut self.bus.rom
+    }
+
     /// Retrieve the screen
     pub fn screen(&mut self) -> &mut S {
         &mut self.screen
@@ -105,6 +739,7 @@ impl<T: Deref<Target=[u8]>,
     }
 
     /// Forward a button press to the joypad controller
+    /// In InputMode::Frame, the change is applied at the end of the frame
     /// ```
     /// # use padme_core::*;
     /// # use padme_core::default::*;
@@ -114,50 +749,1019 @@ impl<T: Deref<Target=[u8]>,
     /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
     /// emu.set_button(Button::A, true);
     /// emu.set_button(Button::Up, true);
+    ///
+    /// emu.set_input_mode(InputMode::Frame);
+    /// emu.set_button(Button::A, false);
+    /// assert!(emu.buttons().contains(Button::A));
+    /// emu.update_frame();
+    /// assert!(!emu.buttons().contains(Button::A));
     /// ```
     pub fn set_button(&mut self, button: Button, is_pressed: bool) {
-        self.bus.joypad.set_button(button, is_pressed, &mut self.bus.it);
+        match self.input_mode {
+            InputMode::Immediate => self.bus.joypad.set_button(button, is_pressed, &mut self.bus.it),
+            InputMode::Frame if is_pressed => self.latched_buttons.insert(button),
+            InputMode::Frame => self.latched_buttons.remove(button),
+        }
     }
 
-    /// Sets the FPS (default = 60)
-    pub fn set_frame_rate(&mut self, fps: u32) {
-        if fps > 0 && fps < CLOCK_SPEED {
-            self.cycles_per_frame = CLOCK_SPEED / fps;
+    /// Select when button changes reach the joypad (default = Immediate)
+    /// Changes waiting for the end of the frame are applied when switching to Immediate
+    pub fn set_input_mode(&mut self, mode: InputMode) {
+        if mode == InputMode::Frame {
+            self.latched_buttons = self.buttons();
+        }
+        self.input_mode = mode;
+        if mode == InputMode::Immediate {
+            self.set_buttons(self.latched_buttons);
         }
     }
 
-    /// Execute enough steps to retrieve 1 frame
+    /// Retrieve when button changes reach the joypad
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    /// Apply the button changes made during the frame
+    fn latch_buttons(&mut self) {
+        if self.input_mode == InputMode::Frame {
+            for button in Button::ALL {
+                let is_pressed = self.latched_buttons.contains(button);
+                self.bus.joypad.set_button(button, is_pressed, &mut self.bus.it);
+            }
+        }
+    }
+
+    /// Press the given buttons and release all the others
     /// ```
     /// # use padme_core::*;
     /// # use padme_core::default::*;
-    /// # use std::time::Instant;
-    /// # use std::thread::sleep;
     /// #
     /// # let mut bin = [0u8; 32 * 1024];
     /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
     /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
-    /// // loop {
-    ///     let t0 = Instant::now();
-    ///     emu.update_frame();
-    ///     let frame_time = t0.elapsed();
-    ///     let min_frame_time = emu.min_frame_time();
-    ///     if frame_time < min_frame_time {
-    ///         sleep(min_frame_time - frame_time);
-    ///     }
-    /// // }
+    /// emu.set_button(Button::B, true);
+    /// emu.set_buttons([Button::A, Button::Up].into_iter().collect());
+    /// assert_eq!(emu.buttons().iter().collect::<Vec<_>>(), vec![Button::Up, Button::A]);
     /// ```
-    pub fn update_frame(&mut self) -> u32 {
-        let mut cycles = 0u32;
-        while cycles < self.cycles_per_frame {
-            cycles += self.step() as u32;
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        for button in Button::ALL {
+            self.set_button(button, buttons.contains(button));
         }
-        self.screen.update();
-        cycles
     }
 
-    /// Returns the minimum amount of time to wait between each frame
-    /// Mostly depend on the FPS
-    pub fn min_frame_time(&self) -> Duration {
-        Duration::from_millis(1000 / (CLOCK_SPEED / self.cycles_per_frame) as u64)
+    /// Retrieve the buttons currently pressed, as seen by the joypad
+    pub fn buttons(&self) -> Buttons {
+        self.bus.joypad.pressed()
+    }
+
+    /// Sets the colors used to render the screen
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_palette(Palette::from(palette::DMG_GREEN));
+    /// assert!(*emu.palette() == Palette::new(palette::DMG_GREEN));
+    /// ```
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.bus.ppu.set_palette(palette);
+    }
+
+    /// Retrieve the colors used to render the screen
+    pub fn palette(&self) -> &Palette {
+        self.bus.ppu.palette()
+    }
+
+    /// Shade (0 - 3) of each color id in a palette register
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// assert_eq!(emu.palette_shades(PaletteRegister::Bgp), [0, 3, 3, 3]);
+    /// ```
+    pub fn palette_shades(&self, register: PaletteRegister) -> [u8; 4] {
+        palette::decode(self.bus.ppu.palette_register(register))
+    }
+
+    /// Color of each color id in a palette register, as the PPU renders them
+    pub fn palette_colors(&self, register: PaletteRegister) -> [Pixel; 4] {
+        self.bus.ppu.palette().apply(register, self.bus.ppu.palette_register(register))
+    }
+
+    /// Keep decoded tiles around to speed up rendering (disabled by default)
+    /// This costs about 24KB of extra memory
+    pub fn set_tile_cache(&mut self, enabled: bool) {
+        self.bus.ppu.set_tile_cache(enabled);
+    }
+
+    /// Select how the PPU renders lines (default = Accurate)
+    /// The fast renderer draws a whole line at once and ignores changes made in the middle of a line
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.bus.ppu.set_render_mode(mode);
+    }
+
+    /// Retrieve how the PPU renders lines
+    pub fn render_mode(&self) -> RenderMode {
+        self.bus.ppu.render_mode()
+    }
+
+    /// Rotate or mirror the frames sent to the screen (default = Identity)
+    /// Pixels are moved as rows are produced, rotated frames are FRAME_HEIGHT pixels wide
+    /// and the dirty lines still refer to the lines of the Gameboy screen
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let bin = [0u8; 0x150];
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_transform(Transform::Rotate90);
+    /// assert_eq!(emu.transform().apply(0, 0), (FRAME_HEIGHT as u8 - 1, 0));
+    /// ```
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.bus.ppu.set_transform(transform);
+    }
+
+    /// Retrieve the orientation of the frames sent to the screen
+    pub fn transform(&self) -> Transform {
+        self.bus.ppu.transform()
+    }
+
+    /// Tint the pixels sent to the screen by layer or by sprite priority (default = None)
+    /// to track down priority and transparency issues
+    pub fn set_debug_overlay(&mut self, overlay: DebugOverlay) {
+        self.bus.ppu.set_overlay(overlay);
+    }
+
+    /// Retrieve the debug tint applied to the pixels
+    pub fn debug_overlay(&self) -> DebugOverlay {
+        self.bus.ppu.overlay()
+    }
+
+    /// Make every run of the same rom with the same inputs bit-identical (default = false),
+    /// e.g. for CI, tool-assisted runs or lockstep netplay
+    ///
+    /// The rams always start zeroed and a transfer with nothing plugged in the link port
+    /// always receives the disconnected byte, this flag forces the sources that depend on the host
+    /// (such as a cartridge clock following the wall time) to use fixed values instead
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        self.deterministic = enabled;
+    }
+
+    /// Checks whether host dependent sources are replaced with fixed values
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Select what the cartridge clock follows (default = TimeSource::Emulated)
+    /// The emulated time is always used in deterministic mode
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// fn unix_time() -> u64 {
+    ///     std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()
+    /// }
+    ///
+    /// # let bin = [0u8; 0x150];
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::Mbc3TimerBattery).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_time_source(TimeSource::Wall(unix_time));
+    /// ```
+    pub fn set_time_source(&mut self, source: TimeSource) {
+        self.time_source = source;
+        self.wall_time = match source {
+            TimeSource::Wall(now) => Some(now()),
+            TimeSource::Emulated => None,
+        };
+    }
+
+    /// Retrieve what the cartridge clock follows
+    pub fn time_source(&self) -> TimeSource {
+        self.time_source
+    }
+
+    /// Advance the cartridge clock, called once per emulated second
+    fn update_clock(&mut self) {
+        let seconds = match self.time_source {
+            TimeSource::Wall(now) if !self.deterministic => {
+                let now = now();
+                let last = self.wall_time.replace(now).unwrap_or(now);
+                now.saturating_sub(last)
+            },
+            _ => 1,
+        };
+        self.bus.rom.advance_clock(seconds);
+    }
+
+    /// Mirror the working ram at 0xE000 - 0xFDFF (default = true)
+    /// When disabled, the region is unmapped and reads 0xFF
+    pub fn set_echo_ram(&mut self, enabled: bool) {
+        self.bus.echo_ram = enabled;
+    }
+
+    /// Plug or unplug the device of the unused I/O ports, see System::with_expansion_io
+    /// Returns the device that was plugged before
+    pub fn set_expansion_io(&mut self, device: Option<E>) -> Option<E> {
+        mem::replace(&mut self.bus.expansion, device)
+    }
+
+    /// Retrieve the device plugged on the unused I/O ports
+    pub fn expansion_io(&mut self) -> Option<&mut E> {
+        self.bus.expansion.as_mut()
+    }
+
+    /// Run a script at the start of each VBlank with a restricted access to the system
+    /// Returns the script that was attached before
+    pub fn set_frame_script(&mut self, script: Option<Script>) -> Option<Script> {
+        mem::replace(&mut self.script, script)
+    }
+
+    /// Select what reads of 0xFEA0 - 0xFEFF return (default = Open)
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// let mut bin = [0u8; 0x150];
+    /// // LD A,($FEB4)
+    /// bin[0x100..0x103].copy_from_slice(&[0xFA, 0xB4, 0xFE]);
+    /// let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_unusable_region(UnusableRegion::Cgb);
+    /// emu.step();
+    /// assert_eq!(emu.cpu_state().a, 0xBB);
+    /// ```
+    pub fn set_unusable_region(&mut self, behavior: UnusableRegion) {
+        self.bus.unusable_region = behavior;
+    }
+
+    /// Only send 1 frame out of n + 1 to the screen (default = 0)
+    /// Skipped frames are fully emulated but no pixel is sent to the screen
+    /// and update_frame only updates the screen once a frame has been sent
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// // Display 20 frames per second
+    /// emu.set_frame_skip(2);
+    /// ```
+    pub fn set_frame_skip(&mut self, n: u8) {
+        self.bus.ppu.set_frame_skip(n);
+    }
+
+    /// Retrieve the number of frames skipped between 2 frames sent to the screen
+    pub fn frame_skip(&self) -> u8 {
+        self.bus.ppu.frame_skip()
+    }
+
+    /// Select how the APU produces samples (default = Accurate)
+    /// In fast mode, a frame worth of samples is synthesized at the end of update_frame,
+    /// or earlier when an instruction may access the sound registers
+    pub fn set_audio_mode(&mut self, mode: AudioMode) {
+        self.bus.apu.set_mode(mode, &mut self.speaker);
+    }
+
+    /// Retrieve how the APU produces samples
+    pub fn audio_mode(&self) -> AudioMode {
+        self.bus.apu.mode()
+    }
+
+    /// Scale the left and right samples sent to the speaker (default = 1.0, 1.0)
+    /// This is applied after the NR50 master volume, so the emulated registers are untouched
+    /// Negative or invalid values are ignored
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_audio_gain(0.5, 0.8);
+    /// emu.set_audio_gain(f32::NAN, 1.0);
+    /// assert_eq!(emu.audio_gain(), (0.5, 0.8));
+    /// ```
+    pub fn set_audio_gain(&mut self, left: f32, right: f32) {
+        let is_valid = |gain: f32| gain.is_finite() && gain >= 0.0;
+        if is_valid(left) && is_valid(right) {
+            self.bus.apu.flush(&mut self.speaker);
+            self.bus.apu.set_gain(left, right);
+        }
+    }
+
+    /// Retrieve the left and right gains
+    pub fn audio_gain(&self) -> (f32, f32) {
+        self.bus.apu.gain()
+    }
+
+    /// Stop update_frame when LD B,B is executed (disabled by default)
+    /// This is the software breakpoint convention used by most debugging emulators
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// // JP 0x0150 ; NOP ; LD B,B
+    /// # bin[0x100] = 0xC3;
+    /// # bin[0x101] = 0x50;
+    /// # bin[0x102] = 0x01;
+    /// # bin[0x151] = 0x40;
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_software_breakpoints(true);
+    /// emu.update_frame();
+    /// assert_eq!(emu.take_break(), Some(BreakReason::Software(0x151)));
+    /// assert_eq!(emu.cpu_state().pc, 0x152);
+    ///
+    /// // The next call finishes the same frame
+    /// let cycles = emu.update_frame();
+    /// assert!(cycles >= CLOCK_SPEED / 60);
+    /// assert_eq!(emu.cycle_count(), cycles as u64);
+    /// ```
+    pub fn set_software_breakpoints(&mut self, enabled: bool) {
+        self.software_breakpoints = enabled;
+    }
+
+    /// Stop before the CPU executes the instruction at a given address
+    /// When a breakpoint is reached, step does nothing and returns 0,
+    /// the next call resumes the execution from there
+    /// Returns false if the breakpoint is already set or if there are already MAX_BREAKPOINTS
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// // A rom without MBC always maps the bank 1
+    /// emu.add_breakpoint(Breakpoint::in_bank(0x4000, 2));
+    /// emu.add_breakpoint(Breakpoint::new(0x0110));
+    /// emu.update_frame();
+    /// assert_eq!(emu.take_break(), Some(BreakReason::Breakpoint(Breakpoint::new(0x0110))));
+    /// assert_eq!(emu.cpu_state().pc, 0x0110);
+    /// // Resume, 0x4000 is reached but the rom bank does not match
+    /// emu.update_frame();
+    /// assert!(emu.cpu_state().pc > 0x4000);
+    /// assert_eq!(emu.take_break(), None);
+    /// ```
+    pub fn add_breakpoint(&mut self, bp: Breakpoint) -> bool {
+        self.breakpoints.add(bp)
+    }
+
+    /// Returns false if the breakpoint was not set
+    pub fn remove_breakpoint(&mut self, bp: Breakpoint) -> bool {
+        self.breakpoints.remove(bp)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Keep writing value at address, e.g. to get infinite lives
+    /// The value is written right away and then after each instruction or frame, see set_freeze_mode
+    /// Returns false if the address is in the rom area or if too many addresses are frozen
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// assert!(emu.freeze_address(0xC000, 9));
+    /// assert!(!emu.freeze_address(0x2000, 1));
+    /// emu.update_frame();
+    /// let mut memory = [0u8; 0x10000];
+    /// emu.dump_memory(&mut memory);
+    /// assert_eq!(memory[0xC000], 9);
+    /// ```
+    pub fn freeze_address(&mut self, address: u16, value: u8) -> bool {
+        if !self.freezes.add(address, value) {
+            return false;
+        }
+        self.bus.write(address, value);
+        true
+    }
+
+    /// Stop writing a value at address
+    /// Returns false if the address was not frozen
+    pub fn unfreeze_address(&mut self, address: u16) -> bool {
+        self.freezes.remove(address)
+    }
+
+    pub fn clear_freezes(&mut self) {
+        self.freezes.clear();
+    }
+
+    /// Retrieve the frozen addresses and their values
+    pub fn freezes(&self) -> impl Iterator<Item = &Freeze> {
+        self.freezes.iter()
+    }
+
+    /// Select when frozen values are written again (default = Frame)
+    pub fn set_freeze_mode(&mut self, mode: FreezeMode) {
+        self.freezes.mode = mode;
+    }
+
+    pub fn freeze_mode(&self) -> FreezeMode {
+        self.freezes.mode
+    }
+
+    /// Sample a value at the end of each frame
+    /// The value is sampled right away, returns the slot of the watch or None if there is no room left
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let sp = emu.add_watch(Watch::Word(0xFFFE)).unwrap();
+    /// let lcdc = emu.add_watch(Watch::Byte(0xFF40)).unwrap();
+    /// emu.update_frame();
+    /// assert_eq!(emu.watch_value(lcdc), Some(0x91));
+    /// for (slot, watch, value) in emu.watches() {
+    ///     println!("{}: {:?} = {:04X}", slot, watch, value);
+    /// }
+    /// ```
+    pub fn add_watch(&mut self, watch: Watch) -> Option<usize> {
+        let value = watch.sample(|address| self.bus.peek(address));
+        self.watches.add(watch, value)
+    }
+
+    /// Returns false if there was no watch in this slot
+    pub fn remove_watch(&mut self, slot: usize) -> bool {
+        self.watches.remove(slot)
+    }
+
+    pub fn clear_watches(&mut self) {
+        self.watches.clear();
+    }
+
+    /// Retrieve the value sampled at the end of the last frame
+    pub fn watch_value(&self, slot: usize) -> Option<u16> {
+        self.watches.value(slot)
+    }
+
+    /// Iterate over the watches with their slot and the value sampled at the end of the last frame
+    pub fn watches(&self) -> impl Iterator<Item = (usize, Watch, u16)> + '_ {
+        self.watches.iter()
+    }
+
+    /// Whether the next instruction, or the interrupt dispatched before it, may access a sound register
+    /// Every address it can use is checked: HL, BC, DE, 0xFF00 + C, the stack and the operands
+    /// A false positive only synthesizes the pending audio ticks earlier
+    fn may_access_apu(&self, cpu: &CpuState) -> bool {
+        let operand = u16::from_le_bytes([self.bus.peek(cpu.pc.wrapping_add(1)), self.bus.peek(cpu.pc.wrapping_add(2))]);
+        let addresses = [
+            u16::from_be_bytes([cpu.h, cpu.l]),
+            u16::from_be_bytes([cpu.b, cpu.c]),
+            u16::from_be_bytes([cpu.d, cpu.e]),
+            u16::from_be_bytes([0xFF, cpu.c]),
+            u16::from_be_bytes([0xFF, operand as u8]),
+            operand,
+            cpu.sp.wrapping_sub(2),
+            cpu.sp.wrapping_sub(1),
+            cpu.sp,
+            cpu.sp.wrapping_add(1),
+        ];
+        addresses.iter().any(|address| (IO_SOUND_REGION_START..=IO_SOUND_REGION_END).contains(address))
+    }
+
+    /// Write all frozen values
+    fn apply_freezes(&mut self) {
+        for freeze in self.freezes.iter() {
+            self.bus.write(freeze.address, freeze.value);
+        }
+    }
+
+    /// Describe an address with a symbol, using the banks currently mapped by the MBC
+    pub fn resolve_symbol<'a>(&self, symbols: &SymbolTable<'a>, address: u16) -> Option<Location<'a>> {
+        let bank = match address {
+            ERAM_REGION_START..=ERAM_REGION_END => self.bus.rom.ram_bank() as u16,
+            _ => self.bus.rom.rom_bank(),
+        };
+        symbols.resolve(bank, address)
+    }
+
+    /// Retrieve why the execution was stopped, if it was
+    pub fn take_break(&mut self) -> Option<BreakReason> {
+        self.pending_break.take()
+    }
+
+    /// Sets the FPS (default = 60)
+    pub fn set_frame_rate(&mut self, fps: u32) {
+        if fps > 0 && fps < CLOCK_SPEED {
+            self.cycles_per_frame = CLOCK_SPEED / fps;
+        }
+    }
+
+    /// Sets the emulation speed (default = 1.0), e.g. 0.5 for slow motion or 2.0 for fast forward
+    /// Each frame runs speed times the cycles needed at the current FPS,
+    /// and the APU still produces samples at AUDIO_SAMPLE_RATE per second of real time
+    /// The minimum frame time is not affected
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let frame_time = emu.min_frame_time();
+    /// emu.set_speed(4.0);
+    /// assert!(emu.update_frame() >= 4 * CLOCK_SPEED / 60);
+    /// assert_eq!(emu.min_frame_time(), frame_time);
+    /// ```
+    pub fn set_speed(&mut self, speed: f32) {
+        if speed.is_finite() && speed > 0.0 {
+            self.speed = speed;
+            self.bus.apu.set_speed(speed);
+        }
+    }
+
+    /// Retrieve the emulation speed
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Number of cycles to execute per frame at the current speed
+    fn frame_cycles(&self) -> u32 {
+        ((self.cycles_per_frame as f32 * self.speed) as u32).max(1)
+    }
+
+    /// Execute enough steps to retrieve 1 frame
+    /// Returns the number of cycles of the frame, a frame stopped by a breakpoint continues on the next call
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// # use std::time::Instant;
+    /// # use std::thread::sleep;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// // loop {
+    ///     let t0 = Instant::now();
+    ///     emu.update_frame();
+    ///     let frame_time = t0.elapsed();
+    ///     let min_frame_time = emu.min_frame_time();
+    ///     if frame_time < min_frame_time {
+    ///         sleep(min_frame_time - frame_time);
+    ///     }
+    /// // }
+    /// ```
+    pub fn update_frame(&mut self) -> u32 {
+        let frame_cycles = self.frame_cycles();
+        while self.frame_progress < frame_cycles {
+            self.frame_progress += self.step() as u32;
+            if self.pending_break.is_some() {
+                // Leave the frame unfinished, it continues on the next call
+                return self.frame_progress;
+            }
+        }
+        let cycles = mem::take(&mut self.frame_progress);
+        self.end_frame(cycles);
+        cycles
+    }
+
+    /// Send the audio samples and the frame once all its cycles are executed
+    fn end_frame(&mut self, cycles: u32) {
+        if self.freezes.mode == FreezeMode::Frame {
+            self.apply_freezes();
+        }
+        self.latch_buttons();
+        self.watches.sample(|address| self.bus.peek(address));
+        self.sample_access_stats();
+        self.sample_cycle_stats();
+        if let Some(events) = self.events.as_mut() {
+            events.end_frame();
+        }
+        self.serial_output.end_of_frame();
+        self.bus.apu.flush(&mut self.speaker);
+        self.av_stats.end_frame(cycles, self.bus.apu.sample_count(), self.speed);
+        let frame_ready = self.bus.ppu.take_frame_ready();
+        if frame_ready || self.bus.ppu.frame_skip() == 0 {
+            self.present_frame();
+        }
+    }
+
+    /// Keep the memory accesses of the frame that just ended
+    fn sample_access_stats(&mut self) {
+        if self.bus.access.enabled {
+            self.access_stats = Some(self.bus.access.take());
+        }
+    }
+
+    /// Keep the cycles per component state of the frame that just ended
+    fn sample_cycle_stats(&mut self) {
+        if let Some(counters) = self.cycle_counters.as_mut() {
+            let mut stats = mem::take(counters);
+            if let Some(dots) = self.bus.ppu.take_mode_dots() {
+                stats.ppu_modes.copy_from_slice(&dots[..4]);
+                stats.lcd_off = dots[4];
+            }
+            self.cycle_stats = Some(stats);
+        }
+    }
+
+    /// Send the frame to the screen and start tracking the changes of the next one
+    fn present_frame(&mut self) {
+        let info = FrameInfo {
+            number: self.frames,
+            cycles: self.bus.apu.cycle_count(),
+            lcd_enabled: self.bus.ppu.is_lcd_on(),
+            dirty_lines: *self.bus.ppu.dirty_lines(),
+        };
+        self.screen.update_with(&info);
+        self.bus.ppu.clear_dirty_lines();
+        self.frames += 1;
+    }
+
+    /// Same as update_frame but only executes about one scanline per call,
+    /// so that an async executor can run other tasks in the meantime
+    /// Ready with FrameProgress::Complete once the frame is sent to the screen, otherwise the task is woken up to continue
+    /// A breakpoint makes it ready with FrameProgress::Partial and the cycles executed so far,
+    /// the frame continues on the next call
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// // JP 0x0150 ; NOP ; LD B,B
+    /// # bin[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+    /// # bin[0x151] = 0x40;
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// emu.set_software_breakpoints(true);
+    /// assert_eq!(emu.poll_frame(&mut cx), Poll::Ready(FrameProgress::Partial(20)));
+    /// assert_eq!(emu.take_break(), Some(BreakReason::Software(0x151)));
+    ///
+    /// let progress = loop {
+    ///     if let Poll::Ready(progress) = emu.poll_frame(&mut cx) {
+    ///         break progress;
+    ///     }
+    /// };
+    /// assert_eq!(progress, FrameProgress::Complete(emu.cycle_count() as u32));
+    /// ```
+    pub fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<FrameProgress> {
+        let frame_cycles = self.frame_cycles();
+        let slice_end = (self.frame_progress + CYCLES_PER_LINE).min(frame_cycles);
+
+        while self.frame_progress < slice_end {
+            self.frame_progress += self.step() as u32;
+            if self.pending_break.is_some() {
+                // Leave the frame unfinished, it continues on the next call
+                return Poll::Ready(FrameProgress::Partial(self.frame_progress));
+            }
+        }
+
+        if self.frame_progress >= frame_cycles {
+            self.end_frame(self.frame_progress);
+            Poll::Ready(FrameProgress::Complete(mem::take(&mut self.frame_progress)))
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    /// Same as update_frame but stops once max_cycles are executed or a breakpoint is hit,
+    /// the frame continues on the next call
+    /// This lets real-time frontends split a frame across several time slices
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let mut slices = 1;
+    /// let cycles = loop {
+    ///     match emu.update_frame_with_budget(10000) {
+    ///         FrameProgress::Complete(cycles) => break cycles,
+    ///         FrameProgress::Partial(_) => slices += 1,
+    ///     }
+    /// };
+    /// assert!(cycles >= CLOCK_SPEED / 60);
+    /// assert_eq!(slices, 7);
+    /// ```
+    /// A frame stopped by a breakpoint is resumed the same way
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// // JP 0x0150 ; NOP ; LD B,B
+    /// # bin[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+    /// # bin[0x151] = 0x40;
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.set_software_breakpoints(true);
+    /// assert_eq!(emu.update_frame_with_budget(u32::MAX), FrameProgress::Partial(20));
+    /// assert_eq!(emu.take_break(), Some(BreakReason::Software(0x151)));
+    ///
+    /// let progress = emu.update_frame_with_budget(u32::MAX);
+    /// assert_eq!(progress, FrameProgress::Complete(emu.cycle_count() as u32));
+    /// ```
+    pub fn update_frame_with_budget(&mut self, max_cycles: u32) -> FrameProgress {
+        let frame_cycles = self.frame_cycles();
+        let mut cycles = 0u32;
+
+        while self.frame_progress < frame_cycles {
+            if cycles >= max_cycles {
+                return FrameProgress::Partial(self.frame_progress);
+            }
+            let ticks = self.step() as u32;
+            cycles += ticks;
+            self.frame_progress += ticks;
+            if self.pending_break.is_some() {
+                // Leave the frame unfinished, it continues on the next call
+                return FrameProgress::Partial(self.frame_progress);
+            }
+        }
+
+        self.end_frame(self.frame_progress);
+        FrameProgress::Complete(mem::take(&mut self.frame_progress))
+    }
+
+    /// Future running the next frame with poll_frame
+    /// It resolves with FrameProgress::Partial when a breakpoint stops the frame
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// let mut frame = pin!(emu.next_frame());
+    /// let mut polls = 1;
+    /// let progress = loop {
+    ///     match frame.as_mut().poll(&mut cx) {
+    ///         Poll::Ready(progress) => break progress,
+    ///         Poll::Pending => polls += 1,
+    ///     }
+    /// };
+    /// assert!(matches!(progress, FrameProgress::Complete(_)));
+    /// assert_eq!(polls, 154);
+    /// ```
+    pub fn next_frame(&mut self) -> NextFrame<'_, T, S, SO, AS, E> {
+        NextFrame { system: self }
+    }
+
+    /// Execute enough steps to produce n audio samples
+    /// This is meant to be called from an audio callback: the screen is updated
+    /// whenever a frame is completed, instead of once per call
+    /// Returns the number of cycles executed
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let start = emu.sample_count();
+    /// emu.run_for_samples(1024);
+    /// assert_eq!(emu.sample_count() - start, 1024);
+    /// ```
+    /// Frames end after the same number of cycles as with update_frame, even with the LCD off
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 0x150];
+    /// // XOR A ; LDH ($40),A ; JR -2
+    /// bin[0x100..0x105].copy_from_slice(&[0xAF, 0xE0, 0x40, 0x18, 0xFE]);
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// emu.run_for_samples(1024);
+    /// emu.set_access_stats(true);
+    /// emu.run_for_samples(AUDIO_SAMPLE_RATE / 10);
+    /// assert!(emu.access_stats().is_some());
+    /// ```
+    pub fn run_for_samples(&mut self, n: u32) -> u32 {
+        let frame_cycles = self.frame_cycles();
+        let start = self.bus.apu.sample_count();
+        let mut cycles = 0u32;
+
+        while self.bus.apu.sample_count().wrapping_sub(start) < n {
+            let ticks = self.step() as u32;
+            cycles += ticks;
+            self.frame_progress += ticks;
+            if self.frame_progress >= frame_cycles {
+                let frame_cycles = mem::take(&mut self.frame_progress);
+                self.end_frame(frame_cycles);
+            }
+            if self.pending_break.is_some() {
+                break;
+            }
+        }
+        self.bus.apu.flush(&mut self.speaker);
+        cycles
+    }
+
+    /// Run until condition returns true, a breakpoint is hit or max_cycles are executed
+    /// The condition is checked after each instruction, frames are sent to the screen as usual
+    ///
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 0x150];
+    /// // LD A,$42 ; LD ($C000),A ; JR -2
+    /// bin[0x100..0x107].copy_from_slice(&[0x3E, 0x42, 0xEA, 0x00, 0xC0, 0x18, 0xFE]);
+    /// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    ///
+    /// let outcome = emu.run_with_watchdog(1000, |emu| emu.peek(0xC000) == 0x42);
+    /// assert_eq!(outcome, Watchdog::Met(24));
+    ///
+    /// let outcome = emu.run_with_watchdog(1000, |emu| emu.cpu_state().pc == 0x0200);
+    /// assert!(matches!(outcome, Watchdog::Expired(_)));
+    /// ```
+    pub fn run_with_watchdog<F>(&mut self, max_cycles: u64, mut condition: F) -> Watchdog
+        where F: FnMut(&mut Self) -> bool
+    {
+        let frame_cycles = self.frame_cycles();
+        let mut cycles = 0u64;
+
+        while cycles < max_cycles {
+            let ticks = self.step() as u32;
+            cycles += ticks as u64;
+            self.frame_progress += ticks;
+            if self.frame_progress >= frame_cycles {
+                let frame_cycles = mem::take(&mut self.frame_progress);
+                self.end_frame(frame_cycles);
+            }
+            if self.pending_break.is_some() {
+                return Watchdog::Break(cycles);
+            }
+            if condition(self) {
+                return Watchdog::Met(cycles);
+            }
+        }
+        Watchdog::Expired(cycles)
+    }
+
+    /// Count the reads and writes per memory area (default = false)
+    /// This adds a check to every memory access
+    pub fn set_access_stats(&mut self, enabled: bool) {
+        self.bus.access.enabled = enabled;
+        self.bus.access.take();
+        self.access_stats = None;
+    }
+
+    /// Count the cycles spent by the CPU, the OAM DMA and the PPU in each of their states (default = false)
+    /// e.g. to find where the time goes on slow targets
+    pub fn set_cycle_stats(&mut self, enabled: bool) {
+        self.bus.ppu.set_mode_counters(enabled);
+        self.cycle_counters = if enabled { Some(CycleStats::default()) } else { None };
+        self.cycle_stats = None;
+    }
+
+    /// Retrieve the cycles per component state of the last complete frame, None if they are not counted
+    pub fn cycle_stats(&self) -> Option<&CycleStats> {
+        self.cycle_stats.as_ref()
+    }
+
+    /// Retrieve the counters of unmapped accesses, illegal op codes and serial interrupts
+    pub fn diagnostics(&self) -> Diagnostics {
+        self.bus.diagnostics.get()
+    }
+
+    /// Start the diagnostics counters over, e.g. before running another rom
+    pub fn reset_diagnostics(&mut self) {
+        self.bus.diagnostics.clear();
+    }
+
+    /// Retrieve the memory accesses of the last complete frame, None if they are not counted
+    pub fn access_stats(&self) -> Option<&AccessStats> {
+        self.access_stats.as_ref()
+    }
+
+    /// Retrieve the audio / video synchronization counters
+    pub fn av_stats(&self) -> &AvStats {
+        &self.av_stats
+    }
+
+    /// Restart the audio / video synchronization counters, e.g. after a pause or a speed change
+    pub fn reset_av_stats(&mut self) {
+        self.av_stats = AvStats::new(self.bus.apu.sample_count());
+    }
+
+    /// Retrieve the number of cycles emulated since the system was created
+    /// This is the clock used to timestamp samples with AudioSpeaker::set_samples_at
+    pub fn cycle_count(&self) -> u64 {
+        self.bus.apu.cycle_count()
+    }
+
+    /// Retrieve the number of audio samples produced so far (wraps around)
+    pub fn sample_count(&self) -> u32 {
+        self.bus.apu.sample_count()
+    }
+
+    /// Retrieve the number of samples per second of real time actually produced by the APU
+    /// This is close to AUDIO_SAMPLE_RATE but a sample is produced every whole number of cycles
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// assert!((emu.audio_sample_rate() - AUDIO_SAMPLE_RATE as f64).abs() < 500.0);
+    /// ```
+    pub fn audio_sample_rate(&self) -> f64 {
+        CLOCK_SPEED as f64 * self.speed as f64 / self.bus.apu.sample_period() as f64
+    }
+
+    /// Retrieve the number of samples produced by a frame at the current frame rate and speed
+    /// The remainder is the fraction of a sample carried over to the next frames
+    /// ```
+    /// # use padme_core::*;
+    /// # use padme_core::default::*;
+    /// #
+    /// # let mut bin = [0u8; 32 * 1024];
+    /// # let mut rom = Rom::load(&mut bin[..]).unwrap();
+    /// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    /// let frame = emu.samples_per_frame();
+    /// let before = emu.sample_count();
+    /// emu.update_frame();
+    /// let produced = emu.sample_count() - before;
+    /// assert!(produced == frame.samples || produced == frame.samples + 1);
+    /// ```
+    pub fn samples_per_frame(&self) -> FrameSamples {
+        let cycles = self.frame_cycles();
+        let period = self.bus.apu.sample_period();
+        FrameSamples {
+            samples: cycles / period,
+            remainder: (cycles % period) as f32 / period as f32,
+        }
+    }
+
+    /// Returns the minimum amount of time to wait between each frame
+    /// Mostly depend on the FPS
+    pub fn min_frame_time(&self) -> Duration {
+        Duration::from_millis(1000 / (CLOCK_SPEED / self.cycles_per_frame) as u64)
+    }
+}
+
+/// Audio samples produced by a frame, see System::samples_per_frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameSamples {
+    /// Whole samples produced by every frame
+    pub samples: u32,
+    /// Fraction of a sample, between 0 and 1, an extra sample is produced every 1 / remainder frames
+    pub remainder: f32,
+}
+
+/// Where a frame run with System::update_frame_with_budget or System::poll_frame stands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameProgress {
+    /// The frame was sent to the screen, with its number of cycles
+    Complete(u32),
+    /// The budget was used or a breakpoint was hit, with the cycles of the frame executed so far
+    Partial(u32),
+}
+
+/// Why System::run_with_watchdog stopped, with the number of cycles executed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watchdog {
+    /// The condition was met
+    Met(u64),
+    /// A breakpoint was hit, see System::take_break
+    Break(u64),
+    /// The cycle budget was exhausted
+    Expired(u64),
+}
+
+impl Watchdog {
+    pub fn cycles(self) -> u64 {
+        match self {
+            Watchdog::Met(cycles) | Watchdog::Break(cycles) | Watchdog::Expired(cycles) => cycles,
+        }
+    }
+}
+
+/// Future returned by System::next_frame
+pub struct NextFrame<'a, T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion> {
+    system: &'a mut System<T, S, SO, AS, E>,
+}
+
+impl<'a, T: RomStorage,
+     S: Screen,
+     SO: SerialOutput,
+     AS: AudioSpeaker,
+     E: MemoryRegion> Future for NextFrame<'a, T, S, SO, AS, E> {
+    type Output = FrameProgress;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<FrameProgress> {
+        self.system.poll_frame(cx)
     }
 }
@@ -0,0 +1,383 @@
+//! Helpers to run test roms and compare runs
+//!
+//! Blargg's roms print their result on the serial port, some of them only write it in the cartridge ram,
+//! Mooneye's roms execute `LD B,B` once done and set the registers to a magic signature.
+//! Two systems, or a system and a recorded trace, can also be run in lockstep to find where they diverge.
+//! ```no_run
+//! use padme_core::{Rom, System};
+//! use padme_core::default::{NoScreen, NoSpeaker};
+//! use padme_core::testing::{run_blargg, SerialCapture, TestResult};
+//!
+//! let bin = std::fs::read("cpu_instrs.gb").unwrap();
+//! let rom = Rom::load(bin).unwrap();
+//! let mut emu = System::new(rom, NoScreen, SerialCapture::new(), NoSpeaker);
+//! let report = run_blargg(&mut emu, 60 * 4_194_304);
+//! assert_eq!(report.result, TestResult::Passed, "{}", emu.serial().as_str());
+//! ```
+use core::{fmt, str};
+
+use crate::{AudioSpeaker, BreakReason, CpuState, Instruction, MemoryRegion, RomStorage, Screen, SerialOutput, System};
+
+/// Number of bytes kept by SerialCapture
+pub const SERIAL_CAPTURE_SIZE: usize    = 512;
+/// Number of instructions disassembled around a divergence
+pub const DIVERGENCE_CONTEXT_SIZE: usize = 5;
+
+/// Bytes at 0xA001 once a Blargg rom reports its result in the cartridge ram
+const BLARGG_SIGNATURE: [u8; 3]         = [0xDE, 0xB0, 0x61];
+/// Result code at 0xA000 while the test runs
+const BLARGG_RUNNING: u8                = 0x80;
+/// Registers B, C, D, E, H, L when a Mooneye test passed (fibonacci numbers)
+const MOONEYE_PASS: [u8; 6]             = [3, 5, 8, 13, 21, 34];
+/// Registers B, C, D, E, H, L when a Mooneye test failed
+const MOONEYE_FAIL: [u8; 6]             = [0x42; 6];
+
+/// Outcome of a test rom
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestResult {
+    Passed,
+    Failed,
+    /// No result before the end of the cycle budget
+    Timeout,
+}
+
+/// Result of a test rom and the number of cycles it took
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestReport {
+    pub result: TestResult,
+    pub cycles: u64,
+}
+
+/// Serial output keeping the last bytes sent
+pub struct SerialCapture {
+    buf: [u8; SERIAL_CAPTURE_SIZE],
+    len: usize,
+    /// Number of bytes received, including the ones that were dropped
+    count: usize,
+}
+
+impl SerialCapture {
+    pub fn new() -> Self {
+        Self {
+            buf: [0u8; SERIAL_CAPTURE_SIZE],
+            len: 0,
+            count: 0,
+        }
+    }
+
+    /// Retrieve the last bytes received
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Retrieve the last bytes received as text, up to the first invalid utf-8 character
+    pub fn as_str(&self) -> &str {
+        let bytes = self.as_bytes();
+        match str::from_utf8(bytes) {
+            Ok(text) => text,
+            // A character may have been cut by the capture
+            Err(err) => str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
+        }
+    }
+
+    /// Checks whether the last bytes received contain a pattern
+    pub fn contains(&self, pattern: &str) -> bool {
+        let pattern = pattern.as_bytes();
+        !pattern.is_empty() && self.as_bytes().windows(pattern.len()).any(|w| w == pattern)
+    }
+
+    /// Number of bytes received since the creation
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for SerialCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SerialOutput for SerialCapture {
+    fn putchar(&mut self, ch: u8) {
+        if self.len == SERIAL_CAPTURE_SIZE {
+            self.buf.copy_within(1.., 0);
+            self.len -= 1;
+        }
+        self.buf[self.len] = ch;
+        self.len += 1;
+        self.count += 1;
+    }
+}
+
+/// Run a Blargg test rom until it prints "Passed" or "Failed" on the serial port
+pub fn run_blargg<T, S, AS, E>(emu: &mut System<T, S, SerialCapture, AS, E>, max_cycles: u64) -> TestReport
+    where T: RomStorage, S: Screen, AS: AudioSpeaker, E: MemoryRegion
+{
+    let mut count = emu.serial().count();
+    let mut result = TestResult::Timeout;
+
+    let outcome = emu.run_with_watchdog(max_cycles, |emu| {
+        // Only look for the result when something new has been printed
+        if emu.serial().count() == count {
+            return false;
+        }
+        count = emu.serial().count();
+        if emu.serial().contains("Passed") {
+            result = TestResult::Passed;
+        } else if emu.serial().contains("Failed") {
+            result = TestResult::Failed;
+        }
+        result != TestResult::Timeout
+    });
+    TestReport { result, cycles: outcome.cycles() }
+}
+
+/// Run a Blargg test rom until it writes its result code in the cartridge ram, like dmg_sound
+/// 0xA000 holds 0x80 while the test runs, then 0 if it passed
+///
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # use padme_core::testing::{run_blargg_ram, TestResult};
+/// #
+/// # let mut bin = vec![0u8; 32 * 1024];
+/// # bin[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+/// # // MBC1+RAM+BATTERY, 8 KiB
+/// # bin[0x147] = 0x03;
+/// # bin[0x149] = 0x02;
+/// // Enable the ram, write the signature, then 0 for a success
+/// bin[0x150..0x16A].copy_from_slice(&[
+///     0x3E, 0x0A, 0xEA, 0x00, 0x00,
+///     0x3E, 0xDE, 0xEA, 0x01, 0xA0,
+///     0x3E, 0xB0, 0xEA, 0x02, 0xA0,
+///     0x3E, 0x61, 0xEA, 0x03, 0xA0,
+///     0xAF, 0xEA, 0x00, 0xA0,
+///     0x18, 0xFE,
+/// ]);
+/// let rom = Rom::load(bin).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// assert_eq!(run_blargg_ram(&mut emu, 1000).result, TestResult::Passed);
+/// ```
+pub fn run_blargg_ram<T, S, SO, AS, E>(emu: &mut System<T, S, SO, AS, E>, max_cycles: u64) -> TestReport
+    where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion
+{
+    let mut result = TestResult::Timeout;
+
+    let outcome = emu.run_with_watchdog(max_cycles, |emu| {
+        let signature = [emu.peek(0xA001), emu.peek(0xA002), emu.peek(0xA003)];
+        if signature != BLARGG_SIGNATURE {
+            return false;
+        }
+        result = match emu.peek(0xA000) {
+            BLARGG_RUNNING => TestResult::Timeout,
+            0 => TestResult::Passed,
+            _ => TestResult::Failed,
+        };
+        result != TestResult::Timeout
+    });
+    TestReport { result, cycles: outcome.cycles() }
+}
+
+/// Run a Mooneye test rom until it executes `LD B,B` and check the registers signature
+/// Software breakpoints are enabled while the rom runs
+pub fn run_mooneye<T, S, SO, AS, E>(emu: &mut System<T, S, SO, AS, E>, max_cycles: u64) -> TestReport
+    where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion
+{
+    let mut cycles = 0u64;
+    let mut result = TestResult::Timeout;
+
+    emu.set_software_breakpoints(true);
+    while cycles < max_cycles {
+        cycles += emu.step() as u64;
+        if let Some(BreakReason::Software(_)) = emu.take_break() {
+            let cpu = emu.cpu_state();
+            let regs = [cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l];
+            if regs == MOONEYE_PASS {
+                result = TestResult::Passed;
+                break;
+            }
+            if regs == MOONEYE_FAIL {
+                result = TestResult::Failed;
+                break;
+            }
+        }
+    }
+    emu.set_software_breakpoints(false);
+    TestReport { result, cycles }
+}
+
+/// CPU state before an instruction, displayed as a [Gameboy Doctor](https://github.com/robert/gameboy-doctor) log line
+///
+/// `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`
+///
+/// The reference logs are produced with LY always reading 0x90,
+/// so they only match up to the first read of LY.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DoctorLine {
+    pub cpu: CpuState,
+    /// Bytes at PC, PC+1, PC+2 and PC+3
+    pub pcmem: [u8; 4],
+}
+
+impl DoctorLine {
+    /// Capture the current state of the system
+    pub fn capture<T, S, SO, AS, E>(emu: &System<T, S, SO, AS, E>) -> Self
+        where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion
+    {
+        let cpu = emu.cpu_state();
+        Self {
+            cpu,
+            pcmem: core::array::from_fn(|i| emu.peek(cpu.pc.wrapping_add(i as u16))),
+        }
+    }
+}
+
+impl fmt::Display for DoctorLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cpu = &self.cpu;
+        write!(f, "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+               cpu.a, cpu.f, cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l, cpu.sp, cpu.pc,
+               self.pcmem[0], self.pcmem[1], self.pcmem[2], self.pcmem[3])
+    }
+}
+
+/// Run for max_cycles, calling log with the state before each executed instruction
+/// Returns the number of cycles executed
+///
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # use padme_core::testing::trace_doctor;
+/// #
+/// # let bin = [0u8; 0x150];
+/// # let rom = Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap();
+/// let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+/// let mut lines = Vec::new();
+/// trace_doctor(&mut emu, 8, |line| lines.push(line.to_string()));
+///
+/// assert_eq!(lines[0], "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,00,00,00");
+/// assert_eq!(lines[1], "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101 PCMEM:00,00,00,00");
+/// ```
+pub fn trace_doctor<T, S, SO, AS, E, F>(emu: &mut System<T, S, SO, AS, E>, max_cycles: u64, mut log: F) -> u64
+    where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion, F: FnMut(&DoctorLine)
+{
+    let mut cycles = 0u64;
+
+    while cycles < max_cycles {
+        // No instruction is executed while the CPU waits
+        if !emu.is_halted() && !emu.is_stopped() {
+            log(&DoctorLine::capture(emu));
+        }
+        cycles += emu.step() as u64;
+    }
+    cycles
+}
+
+/// First point where two runs stopped matching
+#[derive(Clone, Copy)]
+pub struct Divergence {
+    /// Number of instructions executed before the divergence
+    pub instruction: usize,
+    pub left: CpuState,
+    pub right: CpuState,
+    /// Disassembly of the left system from the PC of the last matching instruction
+    pub context: [Instruction; DIVERGENCE_CONTEXT_SIZE],
+}
+
+impl Divergence {
+    fn new<T, S, SO, AS, E>(instruction: usize, emu: &System<T, S, SO, AS, E>, right: CpuState, pc: u16) -> Self
+        where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion
+    {
+        let mut address = pc;
+        let context = core::array::from_fn(|_| {
+            let ins = emu.instruction_at(address);
+            address = address.wrapping_add(ins.len() as u16);
+            ins
+        });
+        Self { instruction, left: emu.cpu_state(), right, context }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "divergence after {} instructions", self.instruction)?;
+        writeln!(f, "left:  {:?}", self.left)?;
+        writeln!(f, "right: {:?}", self.right)?;
+        for ins in self.context.iter() {
+            writeln!(f, "  {:04X}: {}", ins.address(), ins)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Run both systems one instruction at a time and compare their states
+/// CPU registers are compared after each instruction and the whole state hash
+/// is compared every hash_period instructions
+///
+/// ```
+/// # use padme_core::*;
+/// # use padme_core::default::*;
+/// # use padme_core::testing::run_lockstep;
+/// #
+/// # let bin = [0u8; 0x150];
+/// let mut left = System::new(Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap(), NoScreen, NoSerial, NoSpeaker);
+/// let mut right = System::new(Rom::load_raw(&bin[..], CartridgeType::RomOnly).unwrap(), NoScreen, NoSerial, NoSpeaker);
+/// assert!(run_lockstep(&mut left, &mut right, 100, 10).is_ok());
+///
+/// // An extra step on one side is enough to desync both runs
+/// right.step();
+/// let divergence = run_lockstep(&mut left, &mut right, 100, 10).unwrap_err();
+/// assert_eq!(divergence.instruction, 0);
+/// ```
+pub fn run_lockstep<T1, S1, SO1, AS1, E1, T2, S2, SO2, AS2, E2>(
+    left: &mut System<T1, S1, SO1, AS1, E1>,
+    right: &mut System<T2, S2, SO2, AS2, E2>,
+    max_instructions: usize,
+    hash_period: usize,
+) -> Result<(), Divergence>
+    where T1: RomStorage, S1: Screen, SO1: SerialOutput, AS1: AudioSpeaker, E1: MemoryRegion,
+          T2: RomStorage, S2: Screen, SO2: SerialOutput, AS2: AudioSpeaker, E2: MemoryRegion
+{
+    let hash_period = hash_period.max(1);
+
+    for instruction in 0..max_instructions {
+        let pc = left.cpu_state().pc;
+
+        left.step();
+        right.step();
+
+        let diverged = left.cpu_state() != right.cpu_state()
+            || (instruction % hash_period == 0 && left.state_hash() != right.state_hash());
+
+        if diverged {
+            return Err(Divergence::new(instruction, left, right.cpu_state(), pc));
+        }
+    }
+    Ok(())
+}
+
+/// Same as run_lockstep, but against a previously recorded trace of the CPU state after each instruction
+pub fn compare_with_trace<T, S, SO, AS, E>(emu: &mut System<T, S, SO, AS, E>, trace: &[CpuState]) -> Result<(), Divergence>
+    where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker, E: MemoryRegion
+{
+    for (instruction, expected) in trace.iter().enumerate() {
+        let pc = emu.cpu_state().pc;
+
+        emu.step();
+        if emu.cpu_state() != *expected {
+            return Err(Divergence::new(instruction, emu, *expected, pc));
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,8 @@
 use log::trace;
 
-use crate::cpu::CLOCK_SPEED;
 use crate::interrupt::{InterruptHandler, InterruptFlag};
 use crate::region::*;
 
-const DIV_PERIOD: u32 = CLOCK_SPEED / 16384;
-
 // Default DMG register values
 const DEFAULT_REG_DIV: u8       = 0x18;
 const DEFAULT_REG_TIMA: u8      = 0x00;
@@ -22,86 +19,131 @@ const INPUT_CLOCK_SEL_16: u8    = 0x01;
 const INPUT_CLOCK_SEL_64: u8    = 0x02;
 const INPUT_CLOCK_SEL_256: u8   = 0x03;
 
+/// TIMA reads 0x00 for one M-cycle after an overflow before it is reloaded
+const RELOAD_DELAY: u8          = 4;
+
+/// Snapshot of the timer registers and internal counter, see System::timer_state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerState {
+    /// Internal counter incremented every cycle, DIV is its upper byte
+    pub divider: u16,
+    /// Timer counter
+    pub tima: u8,
+    /// Timer modulo
+    pub tma: u8,
+    /// Timer control
+    pub tac: u8,
+    /// Cycles before TIMA is incremented, None when the timer is disabled
+    pub cycles_to_increment: Option<u16>,
+}
+
 pub struct Timer {
-    /// Divider
-    reg_div: u8,
+    /// Internal counter incremented every cycle, DIV is its upper byte
+    counter: u16,
     /// Timer counter
     reg_tima: u8,
     /// Timer modulo
     reg_tma: u8,
     /// Timer control
     reg_tac: u8,
-    /// divider counter cycles (max = 255 + max(CPU_ticks))
-    div_cycles: u16,
-    /// tma counter of cycles
-    tima_cycles: u16,
-    /// keep track of the clock period
-    tima_period: u16,
+    /// Cycles before TIMA is reloaded with TMA after an overflow
+    reload_delay: u8,
+    /// Cycles left in the M-cycle TIMA is reloaded, TIMA writes are ignored
+    /// and TMA writes go through to TIMA
+    reloading: u8,
 }
 
 impl Timer {
     pub fn new() -> Self {
         Self {
-            reg_div: DEFAULT_REG_DIV,
+            counter: (DEFAULT_REG_DIV as u16) << 8,
             reg_tima: DEFAULT_REG_TIMA,
             reg_tma: DEFAULT_REG_TMA,
             reg_tac: DEFAULT_REG_TAC,
-            div_cycles: 0,
-            tima_cycles: 0,
-            tima_period: Timer::period_from_tac(DEFAULT_REG_TAC),
+            reload_delay: 0,
+            reloading: 0,
         }
     }
 
     /// Reset all registers and state
     pub fn reset(&mut self) {
-        self.reg_div = DEFAULT_REG_DIV;
+        self.counter = (DEFAULT_REG_DIV as u16) << 8;
         self.reg_tima = DEFAULT_REG_TIMA;
         self.reg_tma = DEFAULT_REG_TMA;
         self.reg_tac = DEFAULT_REG_TAC;
-        self.div_cycles = 0;
-        self.tima_cycles = 0;
-        self.tima_period = Timer::period_from_tac(DEFAULT_REG_TAC);
+        self.reload_delay = 0;
+        self.reloading = 0;
     }
 
-    /// Determine how many ticks to wait
-    fn period_from_tac(tac: u8) -> u16 {
+    /// Determine which counter bit clocks TIMA
+    fn bit_from_tac(tac: u8) -> u16 {
         match tac & FLAG_INPUT_CLOCK_SEL {
-            INPUT_CLOCK_SEL_1024 => 1024,
-            INPUT_CLOCK_SEL_16 => 16,
-            INPUT_CLOCK_SEL_64 => 64,
-            INPUT_CLOCK_SEL_256 => 256,
+            INPUT_CLOCK_SEL_1024 => 1 << 9,
+            INPUT_CLOCK_SEL_16 => 1 << 3,
+            INPUT_CLOCK_SEL_64 => 1 << 5,
+            INPUT_CLOCK_SEL_256 => 1 << 7,
             _ => unreachable!(),
         }
     }
 
+    pub fn state(&self) -> TimerState {
+        let cycles_to_increment = if is_set!(self.reg_tac, FLAG_TIMER_ENABLED) {
+            // The selected bit falls when the bits below it wrap around
+            let period = Timer::bit_from_tac(self.reg_tac) << 1;
+            Some(period - (self.counter & (period - 1)))
+        } else {
+            None
+        };
+
+        TimerState {
+            divider: self.counter,
+            tima: self.reg_tima,
+            tma: self.reg_tma,
+            tac: self.reg_tac,
+            cycles_to_increment,
+        }
+    }
+
+    /// TIMA is incremented on a falling edge of this signal
+    fn signal(&self) -> bool {
+        is_set!(self.reg_tac, FLAG_TIMER_ENABLED) && (self.counter & Timer::bit_from_tac(self.reg_tac)) != 0
+    }
+
+    fn increment_tima(&mut self) {
+        self.reg_tima = self.reg_tima.wrapping_add(1);
+        if self.reg_tima == 0x00 {
+            self.reload_delay = RELOAD_DELAY;
+        }
+    }
+
     /// Single timer step for each cpu T-cycle
     pub fn step(&mut self, ir: &mut InterruptHandler) {
-        self.div_cycles += 1;
+        if self.reloading > 0 {
+            self.reloading -= 1;
+        }
+        if self.reload_delay > 0 {
+            self.reload_delay -= 1;
+            if self.reload_delay == 0 {
+                trace!("timer overflow, reset to 0x{:02X}", self.reg_tma);
+                self.reg_tima = self.reg_tma;
+                self.reloading = RELOAD_DELAY;
+                ir.request(InterruptFlag::TimerOverflow);
+            }
+        }
 
-        if self.div_cycles > DIV_PERIOD as u16 {
-            self.reg_div = self.reg_div.wrapping_add(1);
-            self.div_cycles = 0;
+        let signal = self.signal();
+        self.counter = self.counter.wrapping_add(1);
+        if signal && !self.signal() {
+            self.increment_tima();
         }
+    }
 
-        let new_tima_period = Timer::period_from_tac(self.reg_tac);
-
-        if new_tima_period != self.tima_period {
-            // period changed
-            self.tima_period = new_tima_period;
-            self.tima_cycles = 0;
-        } else if (self.reg_tac & FLAG_TIMER_ENABLED) == FLAG_TIMER_ENABLED {
-            self.tima_cycles += 1;
-
-            if self.tima_cycles >= self.tima_period {
-                // Reached cycles limit, increment tima
-                self.reg_tima = self.reg_tima.wrapping_add(1);
-                self.tima_cycles = 0;
-                if self.reg_tima == 0xFF {
-                    trace!("timer overflow, reset to 0x{:02X}", self.reg_tma);
-                    self.reg_tima = self.reg_tma;
-                    ir.request(InterruptFlag::TimerOverflow);
-                }
-            }
+    /// Update the counter or TAC, a falling edge caused by the write increments TIMA
+    fn write_edge(&mut self, write: impl FnOnce(&mut Self)) {
+        let signal = self.signal();
+        write(self);
+        if signal && !self.signal() {
+            self.increment_tima();
         }
     }
 }
@@ -109,7 +151,7 @@ impl Timer {
 impl MemoryRegion for Timer {
     fn read(&self, address: u16) -> u8 {
         match address {
-            REG_DIV_ADDR => self.reg_div,
+            REG_DIV_ADDR => (self.counter >> 8) as u8,
             REG_TIMA_ADDR => self.reg_tima,
             REG_TMA_ADDR => self.reg_tma,
             REG_TAC_ADDR => self.reg_tac,
@@ -119,10 +161,19 @@ impl MemoryRegion for Timer {
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
-            REG_DIV_ADDR => self.reg_div = 0,
-            REG_TIMA_ADDR => self.reg_tima = value,
-            REG_TMA_ADDR => self.reg_tma = value,
-            REG_TAC_ADDR => self.reg_tac = value,
+            REG_DIV_ADDR => self.write_edge(|timer| timer.counter = 0),
+            // Writing TIMA before the reload cancels it, the reload wins on the reload cycle
+            REG_TIMA_ADDR => if self.reloading == 0 {
+                self.reg_tima = value;
+                self.reload_delay = 0;
+            },
+            REG_TMA_ADDR => {
+                self.reg_tma = value;
+                if self.reloading > 0 {
+                    self.reg_tima = value;
+                }
+            },
+            REG_TAC_ADDR => self.write_edge(|timer| timer.reg_tac = value),
             _ => unreachable!(),
         }
     }
@@ -0,0 +1,60 @@
+use std::fs;
+use padme_core::*;
+use padme_core::default::{NoSerial, NoSpeaker};
+
+/// Screen keeping the shade (0 - 3) of each pixel of the last frame
+struct ShadeScreen {
+    shades: Vec<u8>,
+}
+
+impl Screen for ShadeScreen {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        let shade = palette::GRAYSCALE.iter().position(|color| color == px).unwrap();
+        self.shades[y as usize * FRAME_WIDTH + x as usize] = shade as u8;
+    }
+
+    fn update(&mut self) {
+    }
+}
+
+fn read_file(name: &str) -> Vec<u8> {
+    fs::read(format!("tests/roms/dmg-acid2/{}", name)).unwrap()
+}
+
+/// Read the shades of a binary PPM (P6) image, like the ones written by FrameDumper
+fn read_ppm_shades(data: &[u8]) -> Vec<u8> {
+    // Skip the magic number, width, height and max value
+    let mut fields = 0;
+    let mut start = 0;
+    while fields < 4 {
+        while data[start].is_ascii_whitespace() {
+            start += 1;
+        }
+        while !data[start].is_ascii_whitespace() {
+            start += 1;
+        }
+        fields += 1;
+    }
+    // White, light gray, dark gray, black: 0xFF, 0xAA, 0x55, 0x00
+    data[(start + 1)..].chunks(3).map(|rgb| 3 - rgb[0] / 0x55).collect()
+}
+
+/// dmg-acid2 executes LD B,B once the frame is drawn,
+/// the screen must then match the reference image converted to PPM
+#[test]
+#[ignore]
+fn dmg_acid2() {
+    let bin = read_file("dmg-acid2.gb");
+    let reference = read_ppm_shades(&read_file("reference-dmg.ppm"));
+    let rom = Rom::load(bin).unwrap();
+    let screen = ShadeScreen { shades: vec![0u8; FRAME_WIDTH * FRAME_HEIGHT] };
+    let mut emu = System::new(rom, screen, NoSerial, NoSpeaker);
+
+    emu.set_software_breakpoints(true);
+    let outcome = emu.run_with_watchdog(60 * CLOCK_SPEED as u64, |_| false);
+    assert!(matches!(outcome, Watchdog::Break(_)), "{:?}", outcome);
+    emu.set_software_breakpoints(false);
+    emu.update_frame();
+
+    assert!(emu.screen().shades == reference);
+}
@@ -0,0 +1,71 @@
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial};
+
+/// Speaker keeping every sample with the cycle it was produced at
+struct RecordSpeaker {
+    samples: Vec<(f32, f32, u64)>,
+}
+
+impl AudioSpeaker for RecordSpeaker {
+    fn set_samples(&mut self, _left: f32, _right: f32) {
+        unreachable!();
+    }
+
+    fn set_samples_at(&mut self, left: f32, right: f32, cycle: u64) {
+        self.samples.push((left, right, cycle));
+    }
+}
+
+/// Headerless rom playing a tone on channel 2 and changing its frequency a few times per frame
+/// The sound registers are written through LDH (n), LD (HL) and LD (C), NR52 is read into D
+fn tone_rom() -> Vec<u8> {
+    let code = [
+        0x3E, 0xFF,                       // LD A,$FF
+        0xE0, 0x25,                       // LDH (NR51),A
+        0x3E, 0xF0,                       // LD A,$F0
+        0xE0, 0x17,                       // LDH (NR22),A
+        0x3E, 0x80,                       // LD A,$80
+        0xE0, 0x16,                       // LDH (NR21),A
+        0x21, 0x18, 0xFF,                 // LD HL,NR23
+        0x0E, 0x19,                       // LD C,$19
+        // Wait about 4000 cycles
+        0x06, 0x00,                       // LD B,0
+        0x05,                             // DEC B
+        0x20, 0xFD,                       // JR NZ,-3
+        // Next frequency, with a length of 64 / 256 s
+        0x1C,                             // INC E
+        0x7B,                             // LD A,E
+        0x77,                             // LD (HL),A
+        0x3E, 0xC7,                       // LD A,$C7
+        0xE2,                             // LD (C),A
+        0xF0, 0x26,                       // LDH A,(NR52)
+        0x57,                             // LD D,A
+        0x18, 0xF0,                       // JR -16
+    ];
+    let mut bin = vec![0u8; 0x400];
+    bin[0x0100..0x0103].copy_from_slice(&[0xC3, 0x00, 0x02]);
+    bin[0x0200..(0x0200 + code.len())].copy_from_slice(&code);
+    bin
+}
+
+fn record(mode: AudioMode, frames: usize) -> (Vec<(f32, f32, u64)>, u8) {
+    let rom = Rom::load_raw(tone_rom(), CartridgeType::RomOnly).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, RecordSpeaker { samples: Vec::new() });
+    emu.set_audio_mode(mode);
+    for _ in 0..frames {
+        emu.update_frame();
+    }
+    (std::mem::take(&mut emu.speaker().samples), emu.cpu_state().d)
+}
+
+#[test]
+fn it_synthesizes_the_same_samples_in_fast_mode_with_writes_mid_frame() {
+    let (samples, nr52) = record(AudioMode::Accurate, 4);
+    let (fast_samples, fast_nr52) = record(AudioMode::Fast, 4);
+
+    assert!(samples.iter().any(|(left, _, _)| *left != samples[0].0));
+    assert_eq!(samples.len(), fast_samples.len());
+    assert!(samples == fast_samples);
+    assert_eq!(nr52, 0xF2);
+    assert_eq!(fast_nr52, nr52);
+}
@@ -0,0 +1,28 @@
+#![cfg(feature = "std")]
+
+use std::fs;
+
+use padme_core::compat::{scan, ScanConfig};
+
+#[test]
+fn it_scans_a_rom_that_prints_its_results() {
+    let bin = fs::read("tests/roms/cpu_instrs.gb").unwrap();
+    let report = scan(bin, ScanConfig { frames: 120, stable_frames: 30 }).unwrap();
+
+    assert!(report.booted);
+    assert!(!report.locked);
+    assert_eq!(report.diagnostics.illegal_opcodes, 0);
+}
+
+#[test]
+fn it_stops_when_the_cpu_locks_up() {
+    let mut bin = vec![0u8; 32 * 1024];
+    // Unknown op code at the entry point
+    bin[0x100] = 0xD3;
+    let report = scan(bin, ScanConfig::default()).unwrap();
+
+    assert!(report.locked);
+    assert!(!report.booted);
+    assert_eq!(report.frames, 0);
+    assert_eq!(report.diagnostics.illegal_opcodes, 1);
+}
@@ -1,99 +1,83 @@
 use std::fs;
 use padme_core::*;
 use padme_core::default::{NoScreen, NoSpeaker};
-
-struct SerialBuffer {
-    pub data: String,
-}
-
-impl SerialOutput for SerialBuffer {
-    fn putchar(&mut self, c: u8) {
-        self.data += &String::from(c as char);
-    }
-}
+use padme_core::testing::{run_blargg, SerialCapture, TestResult};
 
 fn get_bin(name: &str) -> Vec<u8> {
     fs::read(format!("tests/roms/cpu_instrs/{}.gb", name)).unwrap()
 }
 
-fn check_output(bin_name: &str, max_ticks: usize) -> bool {
+fn check_output(bin_name: &str, max_ticks: u64) -> bool {
     let bin = get_bin(bin_name);
     let rom = Rom::load(bin).unwrap();
-    let mut emu = System::new(rom, NoScreen, SerialBuffer { data: "".to_owned() }, NoSpeaker);
-    let mut ticks: usize = 0;
-
-    loop {
-        ticks += emu.step() as usize;
-        if ticks >= max_ticks {
-            break;
-        }
-    }
+    let mut emu = System::new(rom, NoScreen, SerialCapture::new(), NoSpeaker);
 
-    return emu.serial().data.contains(&format!("{}\n\n\nPassed", bin_name));
+    run_blargg(&mut emu, max_ticks).result == TestResult::Passed
+        && emu.serial().contains(&format!("{}\n\n\nPassed", bin_name))
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_special() {
-    assert!(check_output("01-special", 9747268));
+    assert!(check_output("01-special", 9791316));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_interrupts() {
-    assert!(check_output("02-interrupts", 1741744));
+    assert!(check_output("02-interrupts", 1785784));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_op_sp_hl() {
-    assert!(check_output("03-op sp,hl", 9747028));
+    assert!(check_output("03-op sp,hl", 9791076));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_op_r_imm() {
-    assert!(check_output("04-op r,imm", 11432356));
+    assert!(check_output("04-op r,imm", 11476348));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_op_rp() {
-    assert!(check_output("05-op rp", 15646524));
+    assert!(check_output("05-op rp", 15690516));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_ld_r_r() {
-    assert!(check_output("06-ld r,r", 2373928));
+    assert!(check_output("06-ld r,r", 2417976));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_jr_jp_call_ret_rst() {
-    assert!(check_output("07-jr,jp,call,ret,rst", 2935484));
+    assert!(check_output("07-jr,jp,call,ret,rst", 2979532));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_misc() {
-    assert!(check_output("08-misc instrs", 2233692));
+    assert!(check_output("08-misc instrs", 2277684));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_op_r_r() {
-    assert!(check_output("09-op r,r", 38117856));
+    assert!(check_output("09-op r,r", 38161848));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_bitops() {
-    assert!(check_output("10-bit ops", 57921392));
+    assert!(check_output("10-bit ops", 57965384));
 }
 
 #[test]
 #[ignore]
 fn cpu_instrs_op_a_hl() {
-    assert!(check_output("11-op a,(hl)", 73370352));
+    assert!(check_output("11-op a,(hl)", 73484568));
 }
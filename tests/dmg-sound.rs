@@ -0,0 +1,61 @@
+use std::fs;
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+use padme_core::testing::{run_blargg_ram, TestResult};
+
+/// Every rom reports its result in less than 30 seconds
+const MAX_CYCLES: u64 = 30 * CLOCK_SPEED as u64;
+
+fn get_bin(name: &str) -> Vec<u8> {
+    fs::read(format!("tests/roms/dmg_sound/{}.gb", name)).unwrap()
+}
+
+fn check_output(bin_name: &str) -> bool {
+    let bin = get_bin(bin_name);
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+
+    run_blargg_ram(&mut emu, MAX_CYCLES).result == TestResult::Passed
+}
+
+/// Enabling the length in NRx4 or triggering a channel in the first half of a length period clocks it once more
+#[test]
+#[ignore]
+fn dmg_sound_trigger() {
+    assert!(check_output("03-trigger"));
+}
+
+/// Sweep calculations and overflow checks of the square channel 1
+#[test]
+#[ignore]
+fn dmg_sound_sweep() {
+    assert!(check_output("04-sweep"));
+}
+
+/// Powering off keeps the length counters, NRx1 still loads them while the APU is off
+#[test]
+#[ignore]
+fn dmg_sound_len_ctr_during_power() {
+    assert!(check_output("08-len ctr during power"));
+}
+
+/// While the wave channel plays, the wave ram reads the byte being played or 0xFF outside of its access window
+#[test]
+#[ignore]
+fn dmg_sound_wave_read_while_on() {
+    assert!(check_output("09-wave read while on"));
+}
+
+/// Triggering the wave channel while it plays corrupts the first bytes of the wave ram on DMG
+#[test]
+#[ignore]
+fn dmg_sound_wave_trigger_while_on() {
+    assert!(check_output("10-wave trigger while on"));
+}
+
+/// While the wave channel plays, the wave ram writes go to the byte being played or are ignored outside of its access window
+#[test]
+#[ignore]
+fn dmg_sound_wave_write_while_on() {
+    assert!(check_output("12-wave write while on"));
+}
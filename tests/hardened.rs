@@ -0,0 +1,59 @@
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+
+/// Rom sizes, some of them don't match the header or a whole number of banks
+const ROM_SIZES: [usize; 4] = [32 * 1024, 40 * 1024, 64 * 1024, 128 * 1024];
+/// Cartridge type offset in the header
+const HEADER_CARTRIDGE_TYPE: usize = 0x0147;
+
+/// xorshift64*, good enough to generate garbage
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+}
+
+fn random_rom(seed: u64) -> Vec<u8> {
+    let mut random = Random(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1);
+    let size = ROM_SIZES[random.next() as usize % ROM_SIZES.len()];
+    let mut bin: Vec<u8> = (0..size).map(|_| random.next() as u8).collect();
+    // Mostly use the supported MBCs to go past the rom loading
+    if !seed.is_multiple_of(4) {
+        bin[HEADER_CARTRIDGE_TYPE] = [0x00, 0x01, 0x02, 0x03, 0x0F, 0x10, 0x11, 0x12, 0x13][seed as usize % 9];
+    }
+    bin
+}
+
+fn run_random_roms(seeds: std::ops::Range<u64>, frames: usize) {
+    for seed in seeds {
+        let rom = match Rom::load(random_rom(seed)) {
+            Ok(rom) => rom,
+            Err(_) => continue,
+        };
+        let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+        if !seed.is_multiple_of(2) {
+            emu.set_render_mode(RenderMode::Fast);
+            emu.set_tile_cache(true);
+            emu.set_audio_mode(AudioMode::Fast);
+        }
+        for _ in 0..frames {
+            emu.update_frame();
+        }
+    }
+}
+
+#[test]
+fn it_runs_random_roms_without_panicking() {
+    run_random_roms(0..32, 2);
+}
+
+#[test]
+#[ignore]
+fn it_runs_many_random_roms_without_panicking() {
+    run_random_roms(0..2048, 10);
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "hooks")]
+
+use std::fs;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+
+static INSTRUCTIONS: AtomicUsize = AtomicUsize::new(0);
+static LAST_PC: AtomicU32 = AtomicU32::new(0);
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+fn on_instruction(pc: u16, _opcode: u8) {
+    INSTRUCTIONS.fetch_add(1, Ordering::Relaxed);
+    LAST_PC.store(pc as u32, Ordering::Relaxed);
+}
+
+fn on_write(_address: u16, _value: u8) {
+    WRITES.fetch_add(1, Ordering::Relaxed);
+}
+
+#[test]
+fn it_calls_hooks() {
+    let bin = fs::read("tests/roms/cpu_instrs.gb").unwrap();
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    emu.set_hooks(Hooks {
+        instruction: Some(on_instruction),
+        write: Some(on_write),
+        ..Hooks::default()
+    });
+
+    for _ in 0..1000 {
+        let pc = emu.cpu_state().pc;
+        emu.step();
+        assert_eq!(LAST_PC.load(Ordering::Relaxed), pc as u32);
+    }
+
+    assert_eq!(INSTRUCTIONS.load(Ordering::Relaxed), 1000);
+    assert!(WRITES.load(Ordering::Relaxed) > 0);
+}
+
+static VIDEO_CHANGES: AtomicUsize = AtomicUsize::new(0);
+static LAST_VIDEO: AtomicU32 = AtomicU32::new(0);
+
+fn on_video(address: u16, value: u8) {
+    VIDEO_CHANGES.fetch_add(1, Ordering::Relaxed);
+    LAST_VIDEO.store(((address as u32) << 8) | value as u32, Ordering::Relaxed);
+}
+
+#[test]
+fn it_calls_the_video_hook_on_changes_only() {
+    let mut bin = vec![0u8; 32 * 1024];
+    bin[0x100..0x10A].copy_from_slice(&[
+        0x3E, 0x12,         // LD A, 0x12
+        0xEA, 0x00, 0x80,   // LD (0x8000), A
+        0xEA, 0x00, 0x80,   // LD (0x8000), A
+        0x18, 0xFE,         // JR -2
+    ]);
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    emu.set_hooks(Hooks { video: Some(on_video), ..Hooks::default() });
+
+    for _ in 0..4 {
+        emu.step();
+    }
+
+    // The second write doesn't change the value
+    assert_eq!(VIDEO_CHANGES.load(Ordering::Relaxed), 1);
+    assert_eq!(LAST_VIDEO.load(Ordering::Relaxed), 0x800012);
+}
+
+static ILLEGAL_PC: AtomicU32 = AtomicU32::new(0);
+
+fn on_illegal(pc: u16, opcode: u8) {
+    ILLEGAL_PC.store(((pc as u32) << 8) | opcode as u32, Ordering::Relaxed);
+}
+
+#[test]
+fn it_calls_the_illegal_hook_when_the_cpu_locks() {
+    let mut bin = vec![0u8; 32 * 1024];
+    bin[0x101] = 0xFD;
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    emu.set_hooks(Hooks { illegal: Some(on_illegal), ..Hooks::default() });
+
+    emu.step();
+    assert!(!emu.is_locked());
+    emu.step();
+    assert!(emu.is_locked());
+    assert_eq!(ILLEGAL_PC.load(Ordering::Relaxed), 0x0101FD);
+}
+
+static READS: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPT_READS: AtomicUsize = AtomicUsize::new(0);
+
+fn on_read(address: u16, _value: u8) {
+    READS.fetch_add(1, Ordering::Relaxed);
+    if address == 0xFFFF || address == 0xFF0F {
+        INTERRUPT_READS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn it_calls_the_read_hook_on_bus_cycles_only() {
+    let mut bin = vec![0u8; 32 * 1024];
+    bin[0x100..0x102].copy_from_slice(&[
+        0x18, 0xFE,         // JR -2
+    ]);
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    emu.set_hooks(Hooks { read: Some(on_read), ..Hooks::default() });
+
+    for _ in 0..100 {
+        emu.step();
+    }
+
+    // Op code and operand, the interrupt polling and the trace don't go through the bus
+    assert_eq!(READS.load(Ordering::Relaxed), 200);
+    assert_eq!(INTERRUPT_READS.load(Ordering::Relaxed), 0);
+}
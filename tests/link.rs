@@ -0,0 +1,116 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use padme_core::*;
+use padme_core::default::{Loopback, NoScreen, NoSpeaker, NullModem, NullModemChannel};
+
+/// Build a rom sending data with the given control value,
+/// then copying the received byte in B once the transfer is done
+fn exchange(data: u8, control: u8) -> Vec<u8> {
+    let mut bin = vec![0u8; 0x400];
+    bin[0x0100..0x0112].copy_from_slice(&[
+        0xF3,                   // DI
+        0x3E, data,             // LD A,data
+        0xE0, 0x01,             // LDH (SB),A
+        0x3E, control,          // LD A,control
+        0xE0, 0x02,             // LDH (SC),A
+        0xF0, 0x02,             // LDH A,(SC)
+        0xCB, 0x7F,             // BIT 7,A
+        0x20, 0xFA,             // JR NZ,-6
+        0xF0, 0x01,             // LDH A,(SB)
+        0x47,                   // LD B,A
+    ]);
+    bin[0x0112..0x0114].copy_from_slice(&[0x18, 0xFE]); // JR -2
+    bin
+}
+
+fn load(bin: Vec<u8>) -> Rom<Vec<u8>> {
+    Rom::load_raw(bin, CartridgeType::RomOnly).unwrap()
+}
+
+#[test]
+fn it_receives_transmitted_byte_with_loopback() {
+    let mut emu = System::new(load(exchange(0x42, 0x81)), NoScreen, Loopback, NoSpeaker);
+    emu.update_frame();
+    assert_eq!(emu.cpu_state().b, 0x42);
+}
+
+#[test]
+fn it_exchanges_bytes_over_null_modem() {
+    let channel = NullModemChannel::new();
+    let (master_link, slave_link) = NullModem::pair(&channel);
+    let mut master = System::new(load(exchange(0x5A, 0x81)), NoScreen, master_link, NoSpeaker);
+    let mut slave = System::new(load(exchange(0xA5, 0x80)), NoScreen, slave_link, NoSpeaker);
+
+    for _ in 0..2000 {
+        slave.step();
+        master.step();
+    }
+    assert_eq!(master.cpu_state().b, 0xA5);
+    assert_eq!(slave.cpu_state().b, 0x5A);
+}
+
+/// One direction of a connection, bytes arrive after a few polls
+#[derive(Default)]
+struct Wire {
+    queue: VecDeque<(u32, u8)>,
+    now: u32,
+}
+
+/// Transport over two wires with some latency
+struct Socket {
+    tx: Rc<RefCell<Wire>>,
+    rx: Rc<RefCell<Wire>>,
+}
+
+const LATENCY: u32 = 50;
+
+impl LinkTransport for Socket {
+    fn send(&mut self, byte: u8) {
+        let mut tx = self.tx.borrow_mut();
+        let at = tx.now + LATENCY;
+        tx.queue.push_back((at, byte));
+    }
+
+    fn recv(&mut self) -> Option<u8> {
+        let mut rx = self.rx.borrow_mut();
+        rx.now += 1;
+        match rx.queue.front() {
+            Some(&(at, byte)) if at <= rx.now => {
+                rx.queue.pop_front();
+                Some(byte)
+            },
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn it_exchanges_bytes_over_a_slow_transport() {
+    let a = Rc::new(RefCell::new(Wire::default()));
+    let b = Rc::new(RefCell::new(Wire::default()));
+    let master_link = NetworkLink::new(Socket { tx: a.clone(), rx: b.clone() });
+    let slave_link = NetworkLink::new(Socket { tx: b, rx: a });
+
+    // The slave starts first and waits for the transfer
+    let mut slave = System::new(load(exchange(0xA5, 0x80)), NoScreen, slave_link, NoSpeaker);
+    for _ in 0..200 {
+        slave.step();
+    }
+    // Let the announced byte reach the master before it clocks the transfer
+    let mut master = System::new(load(exchange(0x5A, 0x81)), NoScreen, master_link, NoSpeaker);
+    for _ in 0..200 {
+        slave.step();
+        master.serial().poll();
+    }
+    for _ in 0..2000 {
+        master.step();
+        slave.step();
+    }
+
+    assert!(master.serial().is_connected());
+    assert!(slave.serial().is_connected());
+    assert_eq!(master.cpu_state().b, 0xA5);
+    assert_eq!(slave.cpu_state().b, 0x5A);
+}
@@ -0,0 +1,57 @@
+use std::fs;
+
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+use padme_core::testing::{compare_with_trace, run_lockstep, DIVERGENCE_CONTEXT_SIZE};
+
+static TEST_ROM_1: &str = "cpu_instrs";
+
+fn get_rom_bin(name: &str) -> Vec<u8> {
+    fs::read(format!("tests/roms/{}.gb", name)).unwrap()
+}
+
+/// Record the CPU state after each instruction
+fn record_trace<T, S, SO, AS>(emu: &mut System<T, S, SO, AS>, instructions: usize) -> Vec<CpuState>
+where T: RomStorage, S: Screen, SO: SerialOutput, AS: AudioSpeaker
+{
+    (0..instructions).map(|_| { emu.step(); emu.cpu_state() }).collect()
+}
+
+fn new_system() -> System<Vec<u8>, NoScreen, NoSerial, NoSpeaker> {
+    let rom = Rom::load(get_rom_bin(TEST_ROM_1)).unwrap();
+    System::new(rom, NoScreen, NoSerial, NoSpeaker)
+}
+
+#[test]
+fn it_runs_identical_systems_in_lockstep() {
+    let mut left = new_system();
+    let mut right = new_system();
+
+    run_lockstep(&mut left, &mut right, 20_000, 1_000).unwrap();
+}
+
+#[test]
+fn it_reports_the_first_divergence() {
+    let mut left = new_system();
+    let mut right = new_system();
+
+    run_lockstep(&mut left, &mut right, 1_000, 100).unwrap();
+    // an extra step on one side is enough to desync both runs
+    right.step();
+
+    let divergence = run_lockstep(&mut left, &mut right, 1_000, 100).unwrap_err();
+
+    assert_eq!(divergence.instruction, 0);
+    assert_eq!(divergence.context.len(), DIVERGENCE_CONTEXT_SIZE);
+}
+
+#[test]
+fn it_compares_a_system_with_a_recorded_trace() {
+    let trace = record_trace(&mut new_system(), 5_000);
+
+    compare_with_trace(&mut new_system(), &trace).unwrap();
+
+    let mut emu = new_system();
+    emu.step();
+    assert!(compare_with_trace(&mut emu, &trace).is_err());
+}
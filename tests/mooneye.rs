@@ -0,0 +1,136 @@
+use std::fs;
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+use padme_core::testing::{run_mooneye, TestResult};
+
+/// Every acceptance rom ends with LD B,B in less than 10 seconds
+const MAX_CYCLES: u64 = 10 * CLOCK_SPEED as u64;
+
+/// Run an acceptance rom built into tests/roms/mooneye
+fn check_acceptance(name: &str) -> TestResult {
+    let bin = fs::read(format!("tests/roms/mooneye/acceptance/{}.gb", name)).unwrap();
+    let rom = Rom::load(bin).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+
+    run_mooneye(&mut emu, MAX_CYCLES).result
+}
+
+macro_rules! acceptance {
+    ($($test:ident => $name:expr),* $(,)?) => {
+        $(
+            #[test]
+            #[ignore]
+            fn $test() {
+                assert_eq!(check_acceptance($name), TestResult::Passed);
+            }
+        )*
+    };
+}
+
+/// Roms that don't pass yet, mostly because the components are only stepped after a whole instruction
+/// while these roms check the timing of each memory access
+macro_rules! known_failures {
+    ($($test:ident => $name:expr),* $(,)?) => {
+        $(
+            #[test]
+            #[ignore]
+            fn $test() {
+                assert_ne!(check_acceptance($name), TestResult::Passed, "{} passes now, move it to acceptance!", $name);
+            }
+        )*
+    };
+}
+
+// CPU, interrupts and boot state
+acceptance! {
+    boot_regs => "boot_regs-dmgABC",
+    div_timing => "div_timing",
+    ei_sequence => "ei_sequence",
+    ei_timing => "ei_timing",
+    halt_ime0_ei => "halt_ime0_ei",
+    halt_ime1_timing => "halt_ime1_timing",
+    if_ie_registers => "if_ie_registers",
+    rapid_di_ei => "rapid_di_ei",
+    bits_mem_oam => "bits/mem_oam",
+    bits_reg_f => "bits/reg_f",
+    instr_daa => "instr/daa",
+    interrupts_ie_push => "interrupts/ie_push",
+}
+
+known_failures! {
+    add_sp_e_timing => "add_sp_e_timing",
+    boot_div => "boot_div-dmgABCmgb",
+    boot_hwio => "boot_hwio-dmgABCmgb",
+    call_cc_timing => "call_cc_timing",
+    call_cc_timing2 => "call_cc_timing2",
+    call_timing => "call_timing",
+    call_timing2 => "call_timing2",
+    di_timing => "di_timing-GS",
+    halt_ime0_nointr_timing => "halt_ime0_nointr_timing",
+    halt_ime1_timing2 => "halt_ime1_timing2-GS",
+    intr_timing => "intr_timing",
+    jp_cc_timing => "jp_cc_timing",
+    jp_timing => "jp_timing",
+    ld_hl_sp_e_timing => "ld_hl_sp_e_timing",
+    pop_timing => "pop_timing",
+    push_timing => "push_timing",
+    ret_cc_timing => "ret_cc_timing",
+    ret_timing => "ret_timing",
+    reti_intr_timing => "reti_intr_timing",
+    reti_timing => "reti_timing",
+    rst_timing => "rst_timing",
+    bits_unused_hwio => "bits/unused_hwio-GS",
+    serial_boot_sclk_align => "serial/boot_sclk_align-dmgABCmgb",
+}
+
+// Timer
+acceptance! {
+    timer_div_write => "timer/div_write",
+    timer_rapid_toggle => "timer/rapid_toggle",
+    timer_tim00 => "timer/tim00",
+    timer_tim00_div_trigger => "timer/tim00_div_trigger",
+    timer_tim01 => "timer/tim01",
+    timer_tim01_div_trigger => "timer/tim01_div_trigger",
+    timer_tim10 => "timer/tim10",
+    timer_tim10_div_trigger => "timer/tim10_div_trigger",
+    timer_tim11 => "timer/tim11",
+    timer_tim11_div_trigger => "timer/tim11_div_trigger",
+}
+
+known_failures! {
+    timer_tima_reload => "timer/tima_reload",
+    timer_tima_write_reloading => "timer/tima_write_reloading",
+    timer_tma_write_reloading => "timer/tma_write_reloading",
+}
+
+// OAM DMA
+acceptance! {
+    oam_dma_basic => "oam_dma/basic",
+    oam_dma_reg_read => "oam_dma/reg_read",
+    oam_dma_sources => "oam_dma/sources-GS",
+}
+
+known_failures! {
+    oam_dma_restart => "oam_dma_restart",
+    oam_dma_start => "oam_dma_start",
+    oam_dma_timing => "oam_dma_timing",
+}
+
+// PPU
+acceptance! {
+    ppu_stat_irq_blocking => "ppu/stat_irq_blocking",
+    ppu_stat_lyc_onoff => "ppu/stat_lyc_onoff",
+}
+
+known_failures! {
+    ppu_hblank_ly_scx_timing => "ppu/hblank_ly_scx_timing-GS",
+    ppu_intr_1_2_timing => "ppu/intr_1_2_timing-GS",
+    ppu_intr_2_0_timing => "ppu/intr_2_0_timing",
+    ppu_intr_2_mode0_timing => "ppu/intr_2_mode0_timing",
+    ppu_intr_2_mode0_timing_sprites => "ppu/intr_2_mode0_timing_sprites",
+    ppu_intr_2_mode3_timing => "ppu/intr_2_mode3_timing",
+    ppu_intr_2_oam_ok_timing => "ppu/intr_2_oam_ok_timing",
+    ppu_lcdon_timing => "ppu/lcdon_timing-GS",
+    ppu_lcdon_write_timing => "ppu/lcdon_write_timing-GS",
+    ppu_vblank_stat_intr => "ppu/vblank_stat_intr-GS",
+}
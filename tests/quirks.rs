@@ -0,0 +1,189 @@
+use padme_core::*;
+use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+
+/// Build a headerless rom: a JP to 0x0200 at the entry point,
+/// the given handlers at their vector and the program at 0x0200
+fn program(handlers: &[(usize, &[u8])], code: &[u8]) -> Vec<u8> {
+    let mut bin = vec![0u8; 0x400];
+    for (vector, handler) in handlers {
+        bin[*vector..(vector + handler.len())].copy_from_slice(handler);
+    }
+    bin[0x0100..0x0103].copy_from_slice(&[0xC3, 0x00, 0x02]);
+    bin[0x0200..(0x0200 + code.len())].copy_from_slice(code);
+    bin
+}
+
+fn run(bin: Vec<u8>, frames: usize) -> CpuState {
+    let rom = Rom::load_raw(bin, CartridgeType::RomOnly).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    for _ in 0..frames {
+        emu.update_frame();
+    }
+    emu.cpu_state()
+}
+
+#[test]
+fn it_cancels_dispatch_when_push_overwrites_ie() {
+    // LD B,$AA ; JR -2
+    let reset = [0x06, 0xAA, 0x18, 0xFE];
+    // LD B,$BB ; JR -2
+    let vblank = [0x06, 0xBB, 0x18, 0xFE];
+    let code = |sp: u16| vec![
+        0xF3,                             // DI
+        0x31, sp as u8, (sp >> 8) as u8,  // LD SP,sp
+        0x3E, 0x01,                       // LD A,$01
+        0xE0, 0xFF,                       // LDH (IE),A
+        0xAF,                             // XOR A
+        0xE0, 0x0F,                       // LDH (IF),A
+        0xFB,                             // EI
+        0x76,                             // HALT
+        0x00,                             // NOP
+        0x18, 0xFE,                       // JR -2
+    ];
+
+    // PC high byte 0x02 lands in IE which disables VBlank: the dispatch jumps to 0x0000
+    let state = run(program(&[(0x0000, &reset), (0x0040, &vblank)], &code(0x0000)), 2);
+    assert_eq!(state.b, 0xAA);
+
+    // Same program with a regular stack
+    let state = run(program(&[(0x0000, &reset), (0x0040, &vblank)], &code(0xDFFE)), 2);
+    assert_eq!(state.b, 0xBB);
+}
+
+#[test]
+fn it_services_one_interrupt_at_a_time() {
+    // LD A,$04 ; LDH (IF),A ; LD C,B ; RETI
+    let vblank = [0x3E, 0x04, 0xE0, 0x0F, 0x48, 0xD9];
+    // INC B ; RETI
+    let timer = [0x04, 0xD9];
+    let code = [
+        0xF3,                             // DI
+        0x31, 0xFE, 0xDF,                 // LD SP,$DFFE
+        0x06, 0x00,                       // LD B,$00
+        0x3E, 0x05,                       // LD A,$05
+        0xE0, 0xFF,                       // LDH (IE),A
+        0xAF,                             // XOR A
+        0xE0, 0x0F,                       // LDH (IF),A
+        0xFB,                             // EI
+        0x76,                             // HALT
+        0xF3,                             // DI
+        0x18, 0xFE,                       // JR -2
+    ];
+
+    // The timer interrupt requested by the VBlank handler waits for RETI
+    let state = run(program(&[(0x0040, &vblank), (0x0050, &timer)], &code), 2);
+    assert_eq!(state.c, 0x00);
+    assert_eq!(state.b, 0x01);
+    assert!(!state.ime);
+}
+
+
+#[test]
+fn it_increments_tima_when_tac_write_clears_the_selected_bit() {
+    let code = |wait: u8| vec![
+        0xF3,                             // DI
+        0x3E, 0x04,                       // LD A,$04
+        0xE0, 0x07,                       // LDH (TAC),A
+        0xE0, 0x04,                       // LDH (DIV),A
+        0xAF,                             // XOR A
+        0xE0, 0x05,                       // LDH (TIMA),A
+        0x0E, wait,                       // LD C,wait
+        0x0D,                             // DEC C
+        0x20, 0xFD,                       // JR NZ,-3
+        0xE0, 0x07,                       // LDH (TAC),A
+        0xF0, 0x05,                       // LDH A,(TIMA)
+        0x47,                             // LD B,A
+        0x18, 0xFE,                       // JR -2
+    ];
+
+    // Counter bit 9 is high when the timer gets disabled: falling edge
+    let state = run(program(&[], &code(0x30)), 1);
+    assert_eq!(state.b, 0x01);
+
+    // Counter bit 9 is still low
+    let state = run(program(&[], &code(0x08)), 1);
+    assert_eq!(state.b, 0x00);
+}
+
+#[test]
+fn it_reloads_tima_one_cycle_after_the_overflow() {
+    let code = [
+        0xF3,                             // DI
+        0x3E, 0x42,                       // LD A,$42
+        0xE0, 0x06,                       // LDH (TMA),A
+        0x3E, 0xFE,                       // LD A,$FE
+        0xE0, 0x05,                       // LDH (TIMA),A
+        0x3E, 0x05,                       // LD A,$05
+        0xE0, 0x07,                       // LDH (TAC),A
+    ];
+    let rom = Rom::load_raw(program(&[], &code), CartridgeType::RomOnly).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+
+    // TIMA reads 0x00 until the reload, the interrupt is requested with it
+    let mut reads = Vec::new();
+    for _ in 0..32 {
+        emu.step();
+        let requested = emu.pending_interrupts().contains(InterruptFlag::TimerOverflow);
+        reads.push((emu.timer_state().tima, requested));
+    }
+    let overflow = reads.windows(2).position(|w| w[0].0 == 0xFF && w[1].0 == 0x00).unwrap() + 1;
+    assert!(!reads[overflow].1);
+    assert_eq!(reads[overflow + 1], (0x42, true));
+}
+
+#[test]
+fn it_locks_oam_during_dma() {
+    let code = [
+        0xF3,                             // DI
+        0x3E, 0x5A,                       // LD A,$5A
+        0xEA, 0x00, 0xC0,                 // LD ($C000),A
+        0x3E, 0xC0,                       // LD A,$C0
+        0xE0, 0x46,                       // LDH (DMA),A
+        0xFA, 0x00, 0xFE,                 // LD A,($FE00)
+        0x47,                             // LD B,A
+        0x0E, 0x40,                       // LD C,$40
+        0x0D,                             // DEC C
+        0x20, 0xFD,                       // JR NZ,-3
+        0xFA, 0x00, 0xFE,                 // LD A,($FE00)
+        0x4F,                             // LD C,A
+        0x18, 0xFE,                       // JR -2
+    ];
+
+    // The transfer takes 160 M-cycles
+    let state = run(program(&[], &code), 1);
+    assert_eq!(state.b, 0xFF);
+    assert_eq!(state.c, 0x5A);
+}
+
+#[test]
+fn it_requests_joypad_interrupt_on_selected_lines_only() {
+    let code = [
+        0xF3,                             // DI
+        0x3E, 0x30,                       // LD A,$30
+        0xE0, 0x00,                       // LDH (P1),A
+        0xAF,                             // XOR A
+        0xE0, 0x0F,                       // LDH (IF),A
+        0x06, 0xFF,                       // LD B,$FF
+        0x05,                             // DEC B
+        0x20, 0xFD,                       // JR NZ,-3
+        0x3E, 0x10,                       // LD A,$10
+        0xE0, 0x00,                       // LDH (P1),A
+        0x18, 0xFE,                       // JR -2
+    ];
+    let rom = Rom::load_raw(program(&[], &code), CartridgeType::RomOnly).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    for _ in 0..7 {
+        emu.step();
+    }
+
+    // No group is selected
+    emu.set_button(Button::A, true);
+    emu.step();
+    assert!(!emu.pending_interrupts().contains(InterruptFlag::Joypad));
+
+    // Selecting the action buttons pulls P10 low
+    for _ in 0..1024 {
+        emu.step();
+    }
+    assert!(emu.pending_interrupts().contains(InterruptFlag::Joypad));
+}
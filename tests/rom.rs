@@ -29,7 +29,7 @@ fn it_loads_checks_rom_sgb() {
     let bin = get_rom_bin(TEST_ROM_1);
     let rom = Rom::load(bin).unwrap();
 
-    assert_eq!(rom.is_sgb(), false);
+    assert!(!rom.is_sgb());
 }
 
 #[test]
@@ -62,7 +62,7 @@ fn it_checks_rom_japanese_mode() {
     let bin = get_rom_bin(TEST_ROM_1);
     let rom = Rom::load(bin).unwrap();
 
-    assert_eq!(rom.is_jp(), true);
+    assert!(rom.is_jp());
 }
 
 #[test]
@@ -86,5 +86,141 @@ fn it_checks_rom_checksum() {
     let bin = get_rom_bin(TEST_ROM_1);
     let rom = Rom::load(bin).unwrap();
 
-    assert_eq!(rom.verify_header_checksum(), true);
+    assert!(rom.verify_header_checksum());
+}
+
+struct BankReader(Vec<u8>);
+
+impl BankedStorage for BankReader {
+    fn read_bank(&mut self, bank: usize, buf: &mut [u8; ROM_BANK_SIZE]) {
+        let start = bank * ROM_BANK_SIZE;
+        buf.copy_from_slice(&self.0[start..(start + ROM_BANK_SIZE)]);
+    }
+}
+
+#[test]
+fn it_streams_rom_banks() {
+    use padme_core::default::{NoScreen, NoSpeaker};
+    use padme_core::testing::SerialCapture;
+
+    let bin = get_rom_bin(TEST_ROM_1);
+    let streamed = Rom::load(StreamedRom::<_, 1>::new(BankReader(bin.clone()))).unwrap();
+
+    assert_eq!(streamed.title().unwrap(), "CPU_INSTRS");
+    assert_eq!((streamed.size() as usize) * 1024, bin.len());
+
+    let mut expected = System::new(Rom::load(bin).unwrap(), NoScreen, SerialCapture::new(), NoSpeaker);
+    let mut emu = System::new(streamed, NoScreen, SerialCapture::new(), NoSpeaker);
+    for _ in 0..120 {
+        expected.update_frame();
+        emu.update_frame();
+    }
+
+    assert_eq!(emu.cpu_state(), expected.cpu_state());
+    assert_eq!(emu.serial().as_str(), expected.serial().as_str());
+}
+
+#[test]
+fn it_loads_rom_strictly() {
+    let mut bin = get_rom_bin(TEST_ROM_1);
+    let checks = StrictChecks { global_checksum: true, logo: true };
+
+    // The test rom does not bother updating its global checksum
+    assert!(Rom::load_strict(&bin[..], StrictChecks { global_checksum: false, ..checks }).is_ok());
+    assert!(matches!(Rom::load_strict(&bin[..], checks), Err(Error::InvalidGlobalChecksum(0xF530, 0xB171))));
+    bin[0x014E] = 0xB1;
+    bin[0x014F] = 0x71;
+    assert!(Rom::load_strict(&bin[..], checks).is_ok());
+
+    let mut corrupted = bin.clone();
+    corrupted[0x4000] ^= 0xFF;
+    assert!(Rom::load_strict(&corrupted[..], StrictChecks::default()).is_ok());
+    assert!(matches!(Rom::load_strict(&corrupted[..], checks), Err(Error::InvalidGlobalChecksum(_, _))));
+
+    let mut corrupted = bin.clone();
+    corrupted[0x0134] ^= 0xFF;
+    assert!(matches!(Rom::load_strict(&corrupted[..], checks), Err(Error::InvalidHeaderChecksum(_, _))));
+}
+
+#[test]
+fn it_resets_cartridge_registers() {
+    use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+
+    let mut bin = vec![0u8; 0x10000];
+    bin[0x0100..0x0111].copy_from_slice(&[
+        0x3E, 0x0A,             // LD A,$0A
+        0xEA, 0x00, 0x00,       // LD ($0000),A
+        0xEA, 0x00, 0xA0,       // LD ($A000),A
+        0x3E, 0x03,             // LD A,$03
+        0xEA, 0x00, 0x20,       // LD ($2000),A
+        0xEA, 0x00, 0xC0,       // LD ($C000),A
+        0x76,                   // HALT
+    ]);
+    let rom = Rom::load_raw(bin, CartridgeType::Mbc1RamBattery).unwrap();
+    let mut emu = System::new(rom, NoScreen, NoSerial, NoSpeaker);
+    let mut memory = [0u8; 0x10000];
+    emu.update_frame();
+    emu.dump_memory(&mut memory);
+    assert_eq!(emu.rom().rom_bank(), 3);
+    assert_eq!(memory[0xC000], 0x03);
+
+    emu.reset();
+    emu.dump_memory(&mut memory);
+    assert_eq!(emu.rom().rom_bank(), 1);
+    assert_eq!(emu.rom().ram()[0], 0x0A);
+    assert_eq!(memory[0xC000], 0x00);
+    // The ram is disabled again
+    assert_eq!(memory[0xA000], 0xFF);
+
+    emu.update_frame();
+    emu.reset_to(Model::Dmg, true);
+    assert_eq!(emu.rom().ram()[0], 0x00);
+}
+
+#[test]
+fn it_advances_the_cartridge_clock() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use padme_core::default::{NoScreen, NoSerial, NoSpeaker};
+
+    static NOW: AtomicU64 = AtomicU64::new(1000);
+
+    fn now() -> u64 {
+        NOW.load(Ordering::Relaxed)
+    }
+
+    let mut bin = vec![0u8; 0x8000];
+    bin[0x0100..0x0118].copy_from_slice(&[
+        0x3E, 0x0A,             // LD A,$0A
+        0xEA, 0x00, 0x00,       // LD ($0000),A
+        0x3E, 0x08,             // LD A,$08
+        0xEA, 0x00, 0x40,       // LD ($4000),A
+        0xAF,                   // XOR A
+        0xEA, 0x00, 0x60,       // LD ($6000),A
+        0x3C,                   // INC A
+        0xEA, 0x00, 0x60,       // LD ($6000),A
+        0xFA, 0x00, 0xA0,       // LD A,($A000)
+        0x47,                   // LD B,A
+        0x18, 0xF2,             // JR -14
+    ]);
+    let load = || Rom::load_raw(bin.clone(), CartridgeType::Mbc3TimerBattery).unwrap();
+
+    // 3 emulated seconds
+    let mut emu = System::new(load(), NoScreen, NoSerial, NoSpeaker);
+    emu.run_with_watchdog(3 * CLOCK_SPEED as u64 + 1000, |_| false);
+    assert_eq!(emu.cpu_state().b, 3);
+
+    // 42 seconds on the host clock during the first emulated second
+    let mut emu = System::new(load(), NoScreen, NoSerial, NoSpeaker);
+    emu.set_time_source(TimeSource::Wall(now));
+    NOW.store(1042, Ordering::Relaxed);
+    emu.run_with_watchdog(CLOCK_SPEED as u64 + 1000, |_| false);
+    assert_eq!(emu.cpu_state().b, 42);
+
+    // The host clock is ignored in deterministic mode
+    let mut emu = System::new(load(), NoScreen, NoSerial, NoSpeaker);
+    emu.set_time_source(TimeSource::Wall(now));
+    emu.set_deterministic(true);
+    NOW.store(2000, Ordering::Relaxed);
+    emu.run_with_watchdog(CLOCK_SPEED as u64 + 1000, |_| false);
+    assert_eq!(emu.cpu_state().b, 1);
 }
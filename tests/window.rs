@@ -0,0 +1,162 @@
+use padme_core::*;
+use padme_core::default::{NoSerial, NoSpeaker};
+
+/// Screen keeping the shade (0 - 3) of each pixel of the last frame
+struct ShadeScreen {
+    shades: Vec<u8>,
+}
+
+impl Screen for ShadeScreen {
+    fn set_pixel(&mut self, px: &Pixel, x: u8, y: u8) {
+        let shade = palette::GRAYSCALE.iter().position(|color| color == px).unwrap();
+        self.shades[y as usize * FRAME_WIDTH + x as usize] = shade as u8;
+    }
+
+    fn update(&mut self) {
+    }
+}
+
+/// Headerless rom drawing a window over a background scrolled by SCX = 3
+///
+/// Background: columns of 8 pixels alternating white (tile 0) and black (tile 1)
+/// Window at WX = 27, WY = 8: a light gray row (tile 2), a dark gray row (tile 3) then white rows
+/// The window is hidden with WX = 200 on lines 16 to 23
+fn window_rom() -> Vec<u8> {
+    let code = [
+        0xF3,                             // DI
+        0xF0, 0x44,                       // LDH A,(LY)
+        0xFE, 0x90,                       // CP $90
+        0x20, 0xFA,                       // JR NZ,-6
+        0xAF,                             // XOR A
+        0xE0, 0x40,                       // LDH (LCDC),A
+        // Clear the vram
+        0x21, 0x00, 0x80,                 // LD HL,$8000
+        0xAF,                             // XOR A
+        0x22,                             // LD (HL+),A
+        0x7C,                             // LD A,H
+        0xFE, 0xA0,                       // CP $A0
+        0x20, 0xF9,                       // JR NZ,-7
+        // Tile 1: color 3
+        0x21, 0x10, 0x80,                 // LD HL,$8010
+        0x06, 0x10,                       // LD B,16
+        0x3E, 0xFF,                       // LD A,$FF
+        0x22,                             // LD (HL+),A
+        0x05,                             // DEC B
+        0x20, 0xFC,                       // JR NZ,-4
+        // Tile 2: color 1
+        0x06, 0x08,                       // LD B,8
+        0x3E, 0xFF,                       // LD A,$FF
+        0x22,                             // LD (HL+),A
+        0xAF,                             // XOR A
+        0x22,                             // LD (HL+),A
+        0x05,                             // DEC B
+        0x20, 0xF8,                       // JR NZ,-8
+        // Tile 3: color 2
+        0x06, 0x08,                       // LD B,8
+        0xAF,                             // XOR A
+        0x22,                             // LD (HL+),A
+        0x3E, 0xFF,                       // LD A,$FF
+        0x22,                             // LD (HL+),A
+        0x05,                             // DEC B
+        0x20, 0xF8,                       // JR NZ,-8
+        // Background map: tiles 0 and 1 on even and odd columns
+        0x21, 0x00, 0x98,                 // LD HL,$9800
+        0x7D,                             // LD A,L
+        0xE6, 0x01,                       // AND 1
+        0x22,                             // LD (HL+),A
+        0x7C,                             // LD A,H
+        0xFE, 0x9C,                       // CP $9C
+        0x20, 0xF7,                       // JR NZ,-9
+        // Window map: a row of tile 2, then a row of tile 3
+        0x06, 0x20,                       // LD B,32
+        0x3E, 0x02,                       // LD A,2
+        0x22,                             // LD (HL+),A
+        0x05,                             // DEC B
+        0x20, 0xFC,                       // JR NZ,-4
+        0x06, 0x20,                       // LD B,32
+        0x3E, 0x03,                       // LD A,3
+        0x22,                             // LD (HL+),A
+        0x05,                             // DEC B
+        0x20, 0xFC,                       // JR NZ,-4
+        0x3E, 0xE4,                       // LD A,$E4
+        0xE0, 0x47,                       // LDH (BGP),A
+        0x3E, 0x03,                       // LD A,3
+        0xE0, 0x43,                       // LDH (SCX),A
+        0x3E, 0x08,                       // LD A,8
+        0xE0, 0x4A,                       // LDH (WY),A
+        0x3E, 0x1B,                       // LD A,27
+        0xE0, 0x4B,                       // LDH (WX),A
+        // LCD on, window map 0x9C00, window on, tile data 0x8000, background map 0x9800
+        0x3E, 0xF1,                       // LD A,$F1
+        0xE0, 0x40,                       // LDH (LCDC),A
+        // Hide the window from the HBlank of line 15
+        0xF0, 0x44,                       // LDH A,(LY)
+        0xFE, 0x0F,                       // CP 15
+        0x20, 0xFA,                       // JR NZ,-6
+        0xF0, 0x41,                       // LDH A,(STAT)
+        0xE6, 0x03,                       // AND 3
+        0x20, 0xFA,                       // JR NZ,-6
+        0x3E, 0xC8,                       // LD A,200
+        0xE0, 0x4B,                       // LDH (WX),A
+        // Show it again from the HBlank of line 23
+        0xF0, 0x44,                       // LDH A,(LY)
+        0xFE, 0x17,                       // CP 23
+        0x20, 0xFA,                       // JR NZ,-6
+        0xF0, 0x41,                       // LDH A,(STAT)
+        0xE6, 0x03,                       // AND 3
+        0x20, 0xFA,                       // JR NZ,-6
+        0x3E, 0x1B,                       // LD A,27
+        0xE0, 0x4B,                       // LDH (WX),A
+        // Wait for the next frame
+        0xF0, 0x44,                       // LDH A,(LY)
+        0xFE, 0x90,                       // CP $90
+        0x20, 0xFA,                       // JR NZ,-6
+        0x18, 0xD8,                       // JR -40
+    ];
+    let mut bin = vec![0u8; 0x400];
+    bin[0x0100..0x0103].copy_from_slice(&[0xC3, 0x00, 0x02]);
+    bin[0x0200..(0x0200 + code.len())].copy_from_slice(&code);
+    bin
+}
+
+/// Shade of each pixel of the frame drawn by window_rom
+fn reference_frame() -> Vec<u8> {
+    let mut shades = vec![0u8; FRAME_WIDTH * FRAME_HEIGHT];
+    for y in 0..FRAME_HEIGHT {
+        // The window line counter does not move on the hidden lines
+        let win_ly = match y {
+            8..=15 => Some(y - 8),
+            24.. => Some(y - 16),
+            _ => None,
+        };
+        for x in 0..FRAME_WIDTH {
+            let shade = match win_ly {
+                // The window starts at WX - 7 whatever SCX is
+                Some(win_ly) if x >= 20 => [1, 2].get(win_ly / 8).copied().unwrap_or(0),
+                _ => if (x + 3) / 8 % 2 == 1 { 3 } else { 0 },
+            };
+            shades[y * FRAME_WIDTH + x] = shade;
+        }
+    }
+    shades
+}
+
+#[test]
+fn it_draws_the_window_over_a_scrolled_background() {
+    for mode in [RenderMode::Accurate, RenderMode::Fast] {
+        let rom = Rom::load_raw(window_rom(), CartridgeType::RomOnly).unwrap();
+        let screen = ShadeScreen { shades: vec![0xFF; FRAME_WIDTH * FRAME_HEIGHT] };
+        let mut emu = System::new(rom, screen, NoSerial, NoSpeaker);
+        emu.set_render_mode(mode);
+
+        // Clearing the vram takes a few frames
+        for _ in 0..8 {
+            emu.update_frame();
+        }
+
+        let reference = reference_frame();
+        for (y, (line, expected)) in emu.screen().shades.chunks(FRAME_WIDTH).zip(reference.chunks(FRAME_WIDTH)).enumerate() {
+            assert_eq!(line, expected, "{:?} line {}", mode, y);
+        }
+    }
+}